@@ -0,0 +1,70 @@
+#ifndef GPUTHROUGHPUT_H
+#define GPUTHROUGHPUT_H
+
+/* Generated by cbindgen from src/ffi.rs, do not edit. */
+
+#include <stddef.h>
+
+/**
+ * What to measure, see `gt_benchmark`.
+ */
+typedef struct gt_config {
+  /**
+   * Size of each transfer in MiB, 2^20 bytes, as before sizes had a choice of units.
+   */
+  size_t size_mb;
+  /**
+   * Transfers per direction, at least one.
+   */
+  size_t iterations;
+  /**
+   * Non-zero to allocate a new host buffer for every iteration.
+   */
+  int fresh_host_buffer;
+} gt_config;
+
+/**
+ * The outcome of `gt_benchmark`, averaged over all iterations.
+ */
+typedef struct gt_result {
+  double h2d_gbps;
+  double d2h_gbps;
+  double h2d_seconds;
+  double d2h_seconds;
+} gt_result;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * Number of OpenCL GPU devices.
+ */
+size_t gt_device_count(void);
+
+/**
+ * Copies the NUL-terminated name of device `index` into `buffer`, truncated to fit `length`
+ * bytes. Returns the length the name needs including the terminator, or 0 if there is no
+ * such device, so that callers can size `buffer` with a first call passing `length` 0.
+ *
+ * # Safety
+ *
+ * `buffer` must be valid for writes of `length` bytes, or null if `length` is 0.
+ */
+size_t gt_device_name(size_t index, char *buffer, size_t length);
+
+/**
+ * Measures device `index` with `config` and stores the throughput in `result`.
+ *
+ * # Safety
+ *
+ * `config` must point to a valid `gt_config` and `result` to writable memory for a
+ * `gt_result`.
+ */
+int gt_benchmark(size_t index, const struct gt_config *config, struct gt_result *result);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* GPUTHROUGHPUT_H */
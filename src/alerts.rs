@@ -0,0 +1,275 @@
+//! Notifications from a continuous run when the link degrades, so that a machine left
+//! measuring can report a failing cable or riser without anyone watching it. Alerts go to a
+//! webhook as JSON, to an SMTP relay as plain-text mail, or both. Webhooks need the community
+//! feature, which brings the HTTP client.
+//!
+//! The SMTP client speaks unauthenticated, unencrypted SMTP, as a relay on the local network
+//! accepts; mail to an outside provider has to go through such a relay.
+
+use crate::error::BenchError;
+#[cfg(feature = "community")]
+use serde::Serialize;
+use std::fmt;
+use std::io::{ self, BufRead, BufReader, Write };
+use std::net::TcpStream;
+use std::thread;
+use std::time::Duration;
+
+/// How long to wait for a webhook or mail relay before giving up on an alert.
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Where alerts go and what raises them.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct AlertConfig {
+    /// URL that receives a JSON POST per alert.
+    pub webhook: Option<String>,
+    pub email: Option<Email>,
+    /// Rolling throughput in GB/s below which a direction raises an alert.
+    pub min_throughput: Option<f64>,
+}
+
+/// Mail delivery through an SMTP relay.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Email {
+    /// `host:port` of the relay.
+    pub relay: String,
+    pub from: String,
+    pub to: String,
+}
+
+impl AlertConfig {
+    /// Whether any alert has somewhere to go.
+    pub fn is_enabled(&self) -> bool {
+        self.webhook.is_some() || self.email.is_some()
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Event {
+    /// The rolling throughput of a direction fell below the threshold.
+    BelowThreshold {
+        direction: &'static str,
+        measured: f64,
+        threshold: f64,
+    },
+    /// It recovered after an earlier `BelowThreshold`.
+    Recovered {
+        direction: &'static str,
+        measured: f64,
+    },
+    /// The run stopped with an error, e.g. data that did not survive the round trip.
+    Failed(String),
+}
+
+impl Event {
+    pub fn failed(error: &BenchError) -> Event {
+        Event::Failed(error.to_string())
+    }
+
+    #[cfg(feature = "community")]
+    fn kind(&self) -> &'static str {
+        match self {
+            Event::BelowThreshold { .. } => "below_threshold",
+            Event::Recovered { .. } => "recovered",
+            Event::Failed(_) => "failed",
+        }
+    }
+}
+
+impl fmt::Display for Event {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Event::BelowThreshold { direction, measured, threshold } =>
+                write!(
+                    f,
+                    "{} throughput dropped to {:.2} GB/s, below the {:.2} GB/s threshold",
+                    direction,
+                    measured,
+                    threshold
+                ),
+            Event::Recovered { direction, measured } =>
+                write!(f, "{} throughput recovered to {:.2} GB/s", direction, measured),
+            Event::Failed(error) => write!(f, "Measurement stopped: {}", error),
+        }
+    }
+}
+
+/// Body of a webhook POST. `text` carries the whole message, which is what chat services
+/// such as Slack, Mattermost or Discord through its Slack-compatible endpoint display.
+#[cfg(feature = "community")]
+#[derive(Serialize)]
+struct Payload<'a> {
+    text: String,
+    device: &'a str,
+    event: &'static str,
+    measured: Option<f64>,
+    threshold: Option<f64>,
+}
+
+/// Raises the alerts of one continuous run on `device`. Each is sent from its own thread,
+/// so that a slow webhook or relay never holds up the measurement.
+pub struct Alerter {
+    config: AlertConfig,
+    device: String,
+    watch: Option<Watch>,
+}
+
+impl Alerter {
+    pub fn new(config: AlertConfig, device: String) -> Alerter {
+        let watch = config.min_throughput.map(Watch::new);
+        Alerter { config, device, watch }
+    }
+
+    /// Checks the rolling H2D and D2H throughput against the threshold, see `Watch::check`.
+    pub fn on_rolling(&mut self, h2d: f64, d2h: f64) {
+        if let Some(ref mut watch) = self.watch {
+            for event in watch.check(h2d, d2h) {
+                self.raise(event);
+            }
+        }
+    }
+
+    pub fn on_error(&self, error: &BenchError) {
+        self.raise(Event::failed(error));
+    }
+
+    fn raise(&self, event: Event) {
+        let config = self.config.clone();
+        let device = self.device.clone();
+        thread::spawn(move || {
+            for failure in send(&config, &device, &event) {
+                eprintln!("Warning: could not send an alert to {}", failure);
+            }
+        });
+    }
+}
+
+/// Sends `event` about `device` to every configured channel, returning the failures.
+pub fn send(config: &AlertConfig, device: &str, event: &Event) -> Vec<String> {
+    let message = format!("{}: {}", device, event);
+    let mut failures = Vec::new();
+    #[cfg(feature = "community")]
+    if let Some(ref url) = config.webhook {
+        let (measured, threshold) = match *event {
+            Event::BelowThreshold { measured, threshold, .. } =>
+                (Some(measured), Some(threshold)),
+            Event::Recovered { measured, .. } => (Some(measured), None),
+            Event::Failed(_) => (None, None),
+        };
+        let payload = Payload {
+            text: message.clone(),
+            device,
+            event: event.kind(),
+            measured,
+            threshold,
+        };
+        let response = ureq
+            ::post(url)
+            .timeout(TIMEOUT)
+            .send_json(payload);
+        if let Err(e) = response {
+            failures.push(format!("webhook {}: {}", url, e));
+        }
+    }
+    #[cfg(not(feature = "community"))]
+    if let Some(ref url) = config.webhook {
+        failures.push(
+            format!("webhook {}: this build has no HTTP client, see the community feature", url)
+        );
+    }
+    if let Some(ref email) = config.email {
+        if let Err(e) = send_mail(email, &format!("gputhroughput: {}", event), &message) {
+            failures.push(format!("mail relay {}: {}", email.relay, e));
+        }
+    }
+    failures
+}
+
+/// Delivers one message through `email.relay`.
+fn send_mail(email: &Email, subject: &str, body: &str) -> io::Result<()> {
+    let stream = TcpStream::connect(&email.relay)?;
+    stream.set_read_timeout(Some(TIMEOUT))?;
+    stream.set_write_timeout(Some(TIMEOUT))?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut writer = stream;
+
+    expect(&mut reader, '2')?;
+    for (command, reply) in [
+        ("HELO gputhroughput".to_string(), '2'),
+        (format!("MAIL FROM:<{}>", email.from), '2'),
+        (format!("RCPT TO:<{}>", email.to), '2'),
+        ("DATA".to_string(), '3'),
+    ] {
+        write!(writer, "{}\r\n", command)?;
+        expect(&mut reader, reply)?;
+    }
+    write!(
+        writer,
+        "From: <{}>\r\nTo: <{}>\r\nSubject: {}\r\n\r\n{}\r\n.\r\n",
+        email.from,
+        email.to,
+        subject,
+        // A line holding a single dot would end the message early
+        body.replace("\n.", "\n..")
+    )?;
+    expect(&mut reader, '2')?;
+    write!(writer, "QUIT\r\n")
+}
+
+/// Reads one, possibly multi-line, reply and fails unless its code starts with `class`.
+fn expect(reader: &mut impl BufRead, class: char) -> io::Result<()> {
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        // "250-..." continues a reply, "250 ..." ends it
+        if line.as_bytes().get(3) == Some(&b'-') {
+            continue;
+        }
+        if !line.starts_with(class) {
+            return Err(io::Error::other(format!("relay replied {}", line.trim_end())));
+        }
+        return Ok(());
+    }
+}
+
+/// Turns the rolling throughput of a run into alerts, raising one when a direction falls
+/// below the threshold and one when it recovers, rather than one per sample.
+#[derive(Clone, Debug)]
+pub struct Watch {
+    threshold: f64,
+    /// Whether H2D and D2H are currently below the threshold.
+    low: [bool; 2],
+}
+
+impl Watch {
+    pub fn new(threshold: f64) -> Watch {
+        Watch { threshold, low: [false; 2] }
+    }
+
+    /// The events the latest rolling H2D and D2H throughput raise; D2H is NaN in runs that
+    /// do not measure it.
+    pub fn check(&mut self, h2d: f64, d2h: f64) -> Vec<Event> {
+        let mut events = Vec::new();
+        for ((direction, measured), low) in [("Host to device", h2d), ("Device to host", d2h)]
+            .into_iter()
+            .zip(&mut self.low) {
+            if measured.is_nan() {
+                continue;
+            }
+            if !*low && measured < self.threshold {
+                *low = true;
+                events.push(Event::BelowThreshold {
+                    direction,
+                    measured,
+                    threshold: self.threshold,
+                });
+            } else if *low && measured >= self.threshold {
+                *low = false;
+                events.push(Event::Recovered { direction, measured });
+            }
+        }
+        events
+    }
+}
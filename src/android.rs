@@ -0,0 +1,13 @@
+//! The Android build: this library as a NativeActivity, starting the same GUI as the desktop
+//! binary with larger controls for touch. Package it with `cargo apk build --lib --release`;
+//! OpenCL comes from the phone's vendor driver, so linking needs a `libOpenCL.so` pulled from a
+//! device (or a stub) on the library path, and the app only finds a GPU where the vendor
+//! exposes OpenCL to apps.
+
+use crate::config::Config;
+use winit::platform::android::activity::AndroidApp;
+
+#[no_mangle]
+fn android_main(app: AndroidApp) {
+    crate::gui::run_android(Config::load(), app);
+}
@@ -0,0 +1,493 @@
+//! One entry point for running a complete measurement, shared by the GUI and the headless
+//! mode. `run_benchmark` is the async form: it runs on its own worker thread and can be
+//! awaited from any executor, or driven with `block_on` where there is none.
+
+use crate::bursts::{ self, BurstResult, SizeRange };
+use crate::completion::{ self, CompletionResult };
+use crate::concurrency::{ self, ScalingResult };
+use crate::contention::{ self, ContentionResult };
+use crate::dmabuf::{ self, DmaBufResult };
+use crate::error::BenchError;
+use crate::inflight::{ self, InflightResult };
+use crate::latency::{ self, LatencyResult };
+use crate::linkspeed;
+use crate::numa::{ self, MappingResult };
+use crate::offsets::{ self, OffsetResult, Offsets };
+use crate::overhead::{ self, Overhead };
+use crate::partition::{ self, Partition };
+use crate::patterns::{ self, PatternResult };
+use crate::peer::{ self, PeerResult };
+use crate::ramp::{ self, RampResult };
+use crate::roundtrip::{ self, RoundTripResult };
+use crate::scatter::{ self, ScatterResult };
+use crate::streaming::{ self, StreamResult };
+use crate::{ MeasureConfig, MyDevice, Throughput };
+use std::fmt;
+use std::future::Future;
+use std::ops::ControlFlow;
+use std::panic::{ self, AssertUnwindSafe };
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::sync::{ Arc, Mutex };
+use std::task::{ Context, Poll, Wake, Waker };
+use std::thread::{ self, Thread };
+use std::time::{ Duration, Instant, SystemTime };
+
+/// Everything needed to measure one device.
+#[derive(Clone)]
+pub struct BenchmarkRequest {
+    pub device: MyDevice,
+    pub partition: Partition,
+    pub sub_device: usize,
+    /// PCIe generation to retrain the link to for the run, see `linkspeed`.
+    pub link_gen: Option<u8>,
+    pub config: MeasureConfig,
+    /// Also compare this many submitting threads against one, see `concurrency`.
+    pub threads: Option<usize>,
+    /// Also compare submitting from the thread that allocated the host memory with threads
+    /// elsewhere, see `numa`.
+    pub thread_mapping: bool,
+    /// Also compare throughput across data patterns, see `patterns`.
+    pub patterns: bool,
+    /// Also time single-float round trips one by one, see `latency`.
+    pub latency: bool,
+    /// Also compare waiting for uploads with flush and an event against finish, see
+    /// `completion`.
+    pub completion: bool,
+    /// Also double the transfer size until a transfer takes this long, see `ramp`.
+    pub ramp: Option<Duration>,
+    /// Also time transfers at these offsets into the device's largest buffer, see `offsets`.
+    pub offsets: Option<Offsets>,
+    /// Also upload this many small buffers in one batch against one large buffer, see
+    /// `scatter`.
+    pub scatter: Option<usize>,
+    /// Also find how many chunked uploads must be in flight to hide latency, see `inflight`.
+    pub inflight: bool,
+    /// Also measure transfers against a background kernel of rising memory intensity, see
+    /// `contention`.
+    pub contention: bool,
+    /// Also time uploads followed straight away by downloads, see `roundtrip`.
+    pub round_trip: bool,
+    /// Also transfer sizes drawn at random from this range, see `bursts`.
+    pub random_sizes: Option<SizeRange>,
+    /// Also copy directly between the device and this one, see `peer`.
+    pub peer: Option<MyDevice>,
+    /// Also stream this file onto the device, see `streaming`.
+    pub stream: Option<PathBuf>,
+    /// Also measure transfers into a dma-buf allocated from this DMA heap, see `dmabuf`.
+    pub dma_buf: Option<PathBuf>,
+    /// Also time the harness's own enqueue and wait costs on the device's platform, see
+    /// `overhead`.
+    pub overhead: bool,
+}
+
+/// The outcome of a `BenchmarkRequest`.
+pub struct MeasurementRecord {
+    pub device: String,
+    pub config: MeasureConfig,
+    /// Wall-clock time the run started, for ordering results merged from several machines.
+    pub started: SystemTime,
+    /// Wall-clock time the run finished. Durations come from `elapsed` instead, as the wall
+    /// clock may be adjusted mid-run.
+    pub finished: SystemTime,
+    /// Monotonic time from start to finish, like every duration and sample of the run.
+    pub elapsed: Duration,
+    /// Compute units of the measured (sub-)device.
+    pub compute_units: u32,
+    /// Link speed reported after retraining, when `link_gen` was set.
+    pub link_speed: Option<String>,
+    pub throughput: Throughput,
+    pub scaling: Option<ScalingResult>,
+    pub mapping: Option<MappingResult>,
+    pub patterns: Option<PatternResult>,
+    pub latency: Option<LatencyResult>,
+    pub completion: Option<CompletionResult>,
+    pub ramp: Option<RampResult>,
+    pub offsets: Option<OffsetResult>,
+    pub scatter: Option<ScatterResult>,
+    pub inflight: Option<InflightResult>,
+    pub contention: Option<ContentionResult>,
+    pub round_trip: Option<RoundTripResult>,
+    pub random_sizes: Option<BurstResult>,
+    pub peer: Option<PeerResult>,
+    pub streaming: Option<StreamResult>,
+    pub dma_buf: Option<DmaBufResult>,
+    pub overhead: Option<Overhead>,
+}
+
+/// What a run is doing, as reported to `ProgressSink::on_phase_change`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Phase {
+    RetrainingLink,
+    Partitioning,
+    Measuring,
+    /// The device was reset and the measurement started over.
+    Retrying,
+    /// The system was suspended during the run and the context is being checked before the
+    /// run carries on, see `suspend`.
+    Resuming,
+    /// Waiting between iterations for the GPU to cool down, see `Pacing`.
+    CoolingDown,
+    /// Running the warm-up kernel or the discarded iterations, see `MeasureConfig::warm_up`
+    /// and `MeasureConfig::discard`.
+    WarmingUp,
+    ThreadScaling,
+    ThreadMapping,
+    Patterns,
+    Latency,
+    Completion,
+    Ramp,
+    Offsets,
+    Scatter,
+    Inflight,
+    Contention,
+    RoundTrip,
+    RandomSizes,
+    PeerCopy,
+    Streaming,
+    DmaBuf,
+    Overhead,
+}
+
+impl fmt::Display for Phase {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Phase::RetrainingLink => write!(f, "Retraining link"),
+            Phase::Partitioning => write!(f, "Partitioning device"),
+            Phase::Measuring => write!(f, "Measuring"),
+            Phase::Retrying => write!(f, "Retrying after a device reset"),
+            Phase::Resuming => write!(f, "Checking the device after a system resume"),
+            Phase::CoolingDown => write!(f, "Waiting for the GPU to cool down"),
+            Phase::WarmingUp => write!(f, "Warming up the GPU"),
+            Phase::ThreadScaling => write!(f, "Measuring thread scaling"),
+            Phase::ThreadMapping => write!(f, "Measuring thread mappings"),
+            Phase::Patterns => write!(f, "Measuring data patterns"),
+            Phase::Latency => write!(f, "Measuring round-trip latency"),
+            Phase::Completion => write!(f, "Comparing flush and finish"),
+            Phase::Ramp => write!(f, "Doubling the transfer size"),
+            Phase::Offsets => write!(f, "Moving the transfer through the buffer"),
+            Phase::Scatter => write!(f, "Measuring batched small uploads"),
+            Phase::Inflight => write!(f, "Finding the in-flight depth"),
+            Phase::Contention => write!(f, "Measuring transfers against a busy kernel"),
+            Phase::RoundTrip => write!(f, "Measuring round trips"),
+            Phase::RandomSizes => write!(f, "Measuring random transfer sizes"),
+            Phase::PeerCopy => write!(f, "Measuring peer copies"),
+            Phase::Streaming => write!(f, "Streaming from disk"),
+            Phase::DmaBuf => write!(f, "Measuring dma-buf import"),
+            Phase::Overhead => write!(f, "Measuring the harness overhead"),
+        }
+    }
+}
+
+/// Receives structured progress from a running benchmark, on the thread that runs it.
+/// Every method does nothing by default.
+pub trait ProgressSink {
+    /// One H2D and one D2H sample in GB/s, the latter NaN in upload-only runs. Returning
+    /// `ControlFlow::Break` ends the run after this iteration, which is the only way a
+    /// `RunLength::Continuous` run stops.
+    fn on_sample(&mut self, _h2d: f64, _d2h: f64) -> ControlFlow<()> {
+        ControlFlow::Continue(())
+    }
+
+    fn on_phase_change(&mut self, _phase: Phase) {}
+
+    /// Called with the finished record, before it is returned.
+    fn on_complete(&mut self, _record: &MeasurementRecord) {}
+}
+
+/// Ignores all progress.
+impl ProgressSink for () {}
+
+/// Runs `request` on the calling thread, reporting to `progress` as it goes. What the run
+/// does is also logged through the `log` crate, which goes nowhere unless a logger is set.
+pub fn execute(
+    request: &BenchmarkRequest,
+    progress: &mut dyn ProgressSink
+) -> Result<MeasurementRecord, BenchError> {
+    let name = request.device.name();
+    log::info!(
+        "{}: {} floats per transfer, {}",
+        name,
+        request.config.data_size,
+        request.config.length
+    );
+    let outcome = measure(request, &mut Logged { device: name, sink: progress });
+    match outcome {
+        Ok(ref record) => {
+            log::info!("{}: done, {:.2} GB/s mean", name, record.throughput.mean_throughput());
+            if let Some(ref track) = record.throughput.harness_memory {
+                for warning in track.warnings() {
+                    log::warn!("{}: {}", name, warning);
+                }
+            }
+        }
+        Err(ref e) => log::error!("{}: {}", name, e),
+    }
+    outcome
+}
+
+/// Logs the progress of a run on its way to the caller's sink.
+struct Logged<'a> {
+    device: &'a str,
+    sink: &'a mut dyn ProgressSink,
+}
+
+impl ProgressSink for Logged<'_> {
+    fn on_sample(&mut self, h2d: f64, d2h: f64) -> ControlFlow<()> {
+        if d2h.is_nan() {
+            log::debug!("{}: {:.2} GB/s H2D", self.device, h2d);
+        } else {
+            log::debug!("{}: {:.2} GB/s H2D, {:.2} GB/s D2H", self.device, h2d, d2h);
+        }
+        self.sink.on_sample(h2d, d2h)
+    }
+
+    fn on_phase_change(&mut self, phase: Phase) {
+        if phase == Phase::Retrying || phase == Phase::Resuming {
+            log::warn!("{}: {}", self.device, phase);
+        } else {
+            log::info!("{}: {}", self.device, phase);
+        }
+        self.sink.on_phase_change(phase);
+    }
+
+    fn on_complete(&mut self, record: &MeasurementRecord) {
+        self.sink.on_complete(record);
+    }
+}
+
+fn measure(
+    request: &BenchmarkRequest,
+    progress: &mut dyn ProgressSink
+) -> Result<MeasurementRecord, BenchError> {
+    let started = SystemTime::now();
+    let clock = Instant::now();
+    let device = request.device.get_device();
+    let link_guard = match request.link_gen {
+        Some(generation) => {
+            progress.on_phase_change(Phase::RetrainingLink);
+            Some(linkspeed::retrain(device, generation)?)
+        }
+        None => None,
+    };
+    if request.partition != Partition::None {
+        progress.on_phase_change(Phase::Partitioning);
+    }
+    let target = partition::select_target(device, request.partition, request.sub_device)?;
+
+    progress.on_phase_change(Phase::Measuring);
+    let mut throughput = Throughput::new();
+    throughput.measure_observed(&request.config, target.device(), progress)?;
+    let scaling = match request.threads {
+        Some(threads) => {
+            progress.on_phase_change(Phase::ThreadScaling);
+            Some(concurrency::measure_scaling(&request.config, target.device(), threads)?)
+        }
+        None => None,
+    };
+    let mapping = if request.thread_mapping {
+        progress.on_phase_change(Phase::ThreadMapping);
+        Some(numa::measure_mapping(&request.config, target.device())?)
+    } else {
+        None
+    };
+    let patterns = if request.patterns {
+        progress.on_phase_change(Phase::Patterns);
+        Some(patterns::measure_patterns(&request.config, target.device())?)
+    } else {
+        None
+    };
+    let latency = if request.latency {
+        progress.on_phase_change(Phase::Latency);
+        Some(latency::measure_latency(target.device())?)
+    } else {
+        None
+    };
+    let completion = if request.completion {
+        progress.on_phase_change(Phase::Completion);
+        Some(completion::measure_completion(&request.config, target.device())?)
+    } else {
+        None
+    };
+    let ramp = match request.ramp {
+        Some(budget) => {
+            progress.on_phase_change(Phase::Ramp);
+            Some(ramp::measure_ramp(target.device(), budget)?)
+        }
+        None => None,
+    };
+    let offsets = match request.offsets {
+        Some(ref offsets) => {
+            progress.on_phase_change(Phase::Offsets);
+            Some(offsets::measure_offsets(target.device(), offsets)?)
+        }
+        None => None,
+    };
+    let scatter = match request.scatter {
+        Some(buffers) => {
+            progress.on_phase_change(Phase::Scatter);
+            Some(scatter::measure_scatter(&request.config, target.device(), buffers)?)
+        }
+        None => None,
+    };
+    let inflight = if request.inflight {
+        progress.on_phase_change(Phase::Inflight);
+        Some(inflight::measure_inflight(&request.config, target.device())?)
+    } else {
+        None
+    };
+    let contention = if request.contention {
+        progress.on_phase_change(Phase::Contention);
+        Some(contention::measure_contention(&request.config, target.device())?)
+    } else {
+        None
+    };
+    let round_trip = if request.round_trip {
+        progress.on_phase_change(Phase::RoundTrip);
+        Some(roundtrip::measure_round_trip(&request.config, target.device())?)
+    } else {
+        None
+    };
+    let random_sizes = match request.random_sizes {
+        Some(range) => {
+            progress.on_phase_change(Phase::RandomSizes);
+            Some(bursts::measure_bursts(&request.config, target.device(), range)?)
+        }
+        None => None,
+    };
+    let peer = match request.peer {
+        Some(ref peer) => {
+            progress.on_phase_change(Phase::PeerCopy);
+            Some(peer::measure_peer(&request.config, &request.device, peer)?)
+        }
+        None => None,
+    };
+    let streaming = match request.stream {
+        Some(ref path) => {
+            progress.on_phase_change(Phase::Streaming);
+            let chunk_size = request.config.data_size * std::mem::size_of::<f32>();
+            Some(streaming::measure_streaming(path, chunk_size, target.device())?)
+        }
+        None => None,
+    };
+    let dma_buf = match request.dma_buf {
+        Some(ref heap) => {
+            progress.on_phase_change(Phase::DmaBuf);
+            let bytes = request.config.data_size * std::mem::size_of::<f32>();
+            let iterations = request.config.length.fixed_iterations();
+            Some(dmabuf::measure_dma_buf(heap, bytes, iterations, target.device())?)
+        }
+        None => None,
+    };
+    let overhead = if request.overhead {
+        progress.on_phase_change(Phase::Overhead);
+        Some(overhead::measure_overhead(target.device())?)
+    } else {
+        None
+    };
+    let record = MeasurementRecord {
+        device: request.device.name().to_string(),
+        config: request.config,
+        started,
+        finished: SystemTime::now(),
+        elapsed: clock.elapsed(),
+        compute_units: target.device().max_compute_units().unwrap_or_default(),
+        link_speed: link_guard.as_ref().and_then(|guard| guard.current_speed()),
+        throughput,
+        scaling,
+        mapping,
+        patterns,
+        latency,
+        completion,
+        ramp,
+        offsets,
+        scatter,
+        inflight,
+        contention,
+        round_trip,
+        random_sizes,
+        peer,
+        streaming,
+        dma_buf,
+        overhead,
+    };
+    progress.on_complete(&record);
+    Ok(record)
+}
+
+/// Runs `request` to completion on a worker thread, which also calls `progress`.
+pub async fn run_benchmark(
+    request: BenchmarkRequest,
+    mut progress: impl ProgressSink + Send + 'static
+) -> Result<MeasurementRecord, BenchError> {
+    Worker::spawn(move || execute(&request, &mut progress)).await
+}
+
+struct Slot<T> {
+    result: Option<thread::Result<T>>,
+    waker: Option<Waker>,
+}
+
+/// A future for the result of a closure running on its own thread.
+struct Worker<T> {
+    slot: Arc<Mutex<Slot<T>>>,
+}
+
+impl<T: Send + 'static> Worker<T> {
+    fn spawn(job: impl FnOnce() -> T + Send + 'static) -> Worker<T> {
+        let slot = Arc::new(Mutex::new(Slot { result: None, waker: None }));
+        thread::spawn({
+            let slot = Arc::clone(&slot);
+            move || {
+                // A panic is handed over too, so that the awaiting side does not wait forever
+                let result = panic::catch_unwind(AssertUnwindSafe(job));
+                let mut slot = slot.lock().unwrap();
+                slot.result = Some(result);
+                if let Some(waker) = slot.waker.take() {
+                    waker.wake();
+                }
+            }
+        });
+        Worker { slot }
+    }
+}
+
+impl<T> Future for Worker<T> {
+    type Output = T;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<T> {
+        let mut slot = self.slot.lock().unwrap();
+        match slot.result.take() {
+            Some(Ok(result)) => Poll::Ready(result),
+            Some(Err(payload)) => panic::resume_unwind(payload),
+            None => {
+                slot.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+/// Wakes a parked thread.
+struct Unpark(Thread);
+
+impl Wake for Unpark {
+    fn wake(self: Arc<Self>) {
+        self.0.unpark();
+    }
+}
+
+/// Drives `future` to completion on the calling thread, for callers without an executor.
+pub fn block_on<F: Future>(future: F) -> F::Output {
+    let waker = Waker::from(Arc::new(Unpark(thread::current())));
+    let mut cx = Context::from_waker(&waker);
+    let mut future = std::pin::pin!(future);
+    loop {
+        match future.as_mut().poll(&mut cx) {
+            Poll::Ready(output) => {
+                return output;
+            }
+            Poll::Pending => thread::park(),
+        }
+    }
+}
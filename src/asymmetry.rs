@@ -0,0 +1,160 @@
+//! Uploads and downloads of the same size differing by more than a fifth, the result users
+//! most often ask about. The link itself is symmetric, so a gap that wide comes from how each
+//! direction is driven; the likely reasons are listed with it rather than left to guesswork.
+
+use crate::telemetry::LinkStatus;
+use crate::trace::Direction;
+use crate::Throughput;
+
+/// Difference between the directions, as a fraction of the faster, above which it is flagged.
+pub const THRESHOLD: f64 = 0.2;
+
+/// Links this narrow or narrower are typical of slots wired to the chipset rather than the CPU.
+const CHIPSET_WIDTH: u32 = 4;
+
+/// Throughput of both directions of a run that differ by more than `THRESHOLD`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Asymmetry {
+    /// GB/s.
+    pub h2d: f64,
+    pub d2h: f64,
+    /// The link the run used, where it could be read.
+    pub link: Option<LinkStatus>,
+}
+
+impl Asymmetry {
+    /// The asymmetry of `throughput`, or `None` unless both directions were measured and
+    /// differ by more than `THRESHOLD`.
+    pub fn of(throughput: &Throughput, link: Option<LinkStatus>) -> Option<Asymmetry> {
+        if !throughput.has_d2h() {
+            return None;
+        }
+        let asymmetry = Asymmetry {
+            h2d: throughput.h2d_throughput,
+            d2h: throughput.d2h_throughput,
+            link,
+        };
+        (asymmetry.difference() > THRESHOLD).then_some(asymmetry)
+    }
+
+    /// How much slower the slower direction is, as a fraction of the faster.
+    pub fn difference(&self) -> f64 {
+        let (fast, slow) = (self.h2d.max(self.d2h), self.h2d.min(self.d2h));
+        if fast > 0.0 { (fast - slow) / fast } else { 0.0 }
+    }
+
+    pub fn slower(&self) -> Direction {
+        if self.d2h < self.h2d { Direction::DeviceToHost } else { Direction::HostToDevice }
+    }
+
+    /// The likely causes, most likely first.
+    pub fn explanations(&self) -> Vec<&'static str> {
+        let mut explanations = Vec::new();
+        match self.slower() {
+            Direction::DeviceToHost => {
+                explanations.push(
+                    "Readback is usually the slower path: the GPU's writes into host memory wait \
+                     on the host bridge, and many drivers give downloads fewer copy engines than \
+                     uploads."
+                );
+                if cfg!(windows) {
+                    explanations.push(
+                        "On Windows, WDDM schedules transfers as DMA packets; downloads often go \
+                         through a paging queue submitted one packet at a time, and hardware-\
+                         accelerated GPU scheduling, where available, can narrow the gap."
+                    );
+                }
+            }
+            Direction::HostToDevice => {
+                explanations.push(
+                    "Uploads from pageable memory are first copied by the CPU into a staging \
+                     buffer, so a slow host memcpy shows up on the upload side; pinned memory \
+                     (--memory host-ptr) avoids the copy."
+                );
+                if cfg!(windows) {
+                    explanations.push(
+                        "On Windows, WDDM may page the destination buffer into VRAM on its \
+                         first use, which is charged to the upload."
+                    );
+                }
+            }
+        }
+        if self.link.is_some_and(|link| link.width <= CHIPSET_WIDTH) {
+            explanations.push(
+                "The link is x4 or narrower, as slots wired to the chipset are; the chipset's \
+                 uplink to the CPU is shared with storage and USB and is often busier in one \
+                 direction."
+            );
+        }
+        explanations
+    }
+
+    /// The finding, then one line per explanation.
+    pub fn summary(&self) -> Vec<String> {
+        let slower = match self.slower() {
+            Direction::HostToDevice => "Host to device",
+            Direction::DeviceToHost => "Device to host",
+        };
+        let mut lines = vec![
+            format!(
+                "{} is {:.0}% slower than the other direction ({:.2} vs {:.2} GB/s)",
+                slower,
+                self.difference() * 100.0,
+                self.h2d.min(self.d2h),
+                self.h2d.max(self.d2h)
+            )
+        ];
+        lines.extend(self.explanations().into_iter().map(str::to_string));
+        lines
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn throughput(h2d: f64, d2h: Option<f64>) -> Throughput {
+        Throughput {
+            h2d_throughput: h2d,
+            d2h_throughput: d2h.unwrap_or(0.0),
+            h2d_samples: vec![h2d],
+            d2h_samples: d2h.into_iter().collect(),
+            ..Throughput::new()
+        }
+    }
+
+    #[test]
+    fn flags_a_gap_above_the_threshold() {
+        let asymmetry = Asymmetry::of(&throughput(25.0, Some(13.0)), None).unwrap();
+        assert!((asymmetry.difference() - 0.48).abs() < 1e-12);
+        assert_eq!(asymmetry.slower(), Direction::DeviceToHost);
+        assert!(asymmetry.summary()[0].starts_with("Device to host is 48% slower"));
+    }
+
+    #[test]
+    fn ignores_a_small_gap_and_upload_only_runs() {
+        assert!(Asymmetry::of(&throughput(25.0, Some(21.0)), None).is_none());
+        assert!(Asymmetry::of(&throughput(25.0, None), None).is_none());
+    }
+
+    #[test]
+    fn slower_uploads() {
+        let asymmetry = Asymmetry::of(&throughput(10.0, Some(20.0)), None).unwrap();
+        assert_eq!(asymmetry.slower(), Direction::HostToDevice);
+        assert_eq!(asymmetry.difference(), 0.5);
+    }
+
+    #[test]
+    fn narrow_links_mention_the_chipset() {
+        let chipset = |width| {
+            let link = Some(LinkStatus { speed: 16.0, width });
+            Asymmetry::of(&throughput(25.0, Some(13.0)), link)
+                .unwrap()
+                .explanations()
+                .iter()
+                .any(|explanation| explanation.contains("chipset"))
+        };
+        assert!(chipset(4));
+        assert!(!chipset(16));
+    }
+}
@@ -0,0 +1,1009 @@
+//! Running a measurement: its configuration, `MeasureConfig`, and the timed transfers
+//! themselves, `Throughput::measure_observed`, which fills in a `Throughput`.
+
+use crate::api::{ Phase, ProgressSink };
+use crate::checksum::{ self, Checksum };
+use crate::device::default_queue;
+use crate::error::BenchError;
+use crate::leaks::{ LeakWatch, MemoryTrack };
+use crate::memory::{ DeviceMemory, Memory };
+use crate::precision::Measurement;
+use crate::registry::DeviceRegistry;
+use crate::simulate;
+use crate::suspend::{ Suspend, SuspendWatch };
+use crate::telemetry::{ self, GpuState, Monitor, PciAddress, Sensors, Telemetry, Thermometer };
+use crate::trace::{ Direction, TransferEvent };
+use crate::warmup;
+use opencl3::command_queue::{ CommandQueue, CL_QUEUE_PROFILING_ENABLE };
+use opencl3::context::Context;
+use opencl3::device::Device;
+use opencl3::memory::{ Buffer, CL_MEM_READ_WRITE };
+use opencl3::types::CL_BLOCKING;
+use std::time::{ Duration, Instant, SystemTime };
+
+/// Device resets a soak run starts over after before giving up; other runs retry once.
+pub const SOAK_RESET_LIMIT: usize = 10;
+/// Latest iterations whose samples, timeline and trace a soak run keeps at least; older ones
+/// are dropped, in batches of as many again, so that memory stays bounded however long the run
+/// goes on.
+pub const SOAK_KEPT_ITERATIONS: usize = 4096;
+
+/// Whether the host side of the transfer reuses one allocation or gets a new one per iteration.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum HostBuffer {
+    Reuse,
+    /// A new allocation per iteration, timed with the transfer: H2D includes allocating the
+    /// upload and writing the pattern into it, which faults its pages in, and D2H lands in
+    /// untouched pages. Both also pay the driver registering the new memory.
+    Fresh,
+}
+
+impl std::fmt::Display for HostBuffer {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            HostBuffer::Reuse => write!(f, "Reused"),
+            HostBuffer::Fresh => write!(f, "Fresh per iteration"),
+        }
+    }
+}
+
+impl std::str::FromStr for HostBuffer {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "reuse" => Ok(HostBuffer::Reuse),
+            "fresh" => Ok(HostBuffer::Fresh),
+            _ => Err(format!("unknown host buffer mode '{}'", s)),
+        }
+    }
+}
+
+/// When a measurement stops. Every run does at least one iteration.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RunLength {
+    /// A fixed number of transfers per direction.
+    Iterations(usize),
+    /// Until this many bytes have moved, counting every direction measured.
+    TotalBytes(u64),
+    /// Until this much time has passed.
+    Time(Duration),
+    /// Until the caller stops it, see `Throughput::measure_observed`.
+    Continuous,
+}
+
+impl RunLength {
+    /// Whether the run is a stability test, going on for a time or until stopped, which
+    /// keeps going through device resets.
+    pub fn is_soak(&self) -> bool {
+        matches!(self, RunLength::Time(_) | RunLength::Continuous)
+    }
+
+    pub fn is_done(&self, iterations: usize, bytes: u64, elapsed: Duration) -> bool {
+        match *self {
+            RunLength::Iterations(count) => iterations >= count,
+            RunLength::TotalBytes(total) => bytes >= total,
+            RunLength::Time(limit) => elapsed >= limit,
+            RunLength::Continuous => false,
+        }
+    }
+
+    /// Iterations for passes that cannot stop adaptively, such as the thread scaling runs:
+    /// the configured count, or a single pass for byte and time budgets.
+    pub fn fixed_iterations(&self) -> usize {
+        match *self {
+            RunLength::Iterations(count) => count.max(1),
+            _ => 1,
+        }
+    }
+
+    /// Bytes a run will move when every iteration moves `iteration_bytes`, when that is
+    /// known up front.
+    pub fn planned_bytes(&self, iteration_bytes: u64) -> Option<u64> {
+        match *self {
+            RunLength::Iterations(count) => Some(iteration_bytes * (count.max(1) as u64)),
+            RunLength::TotalBytes(total) => {
+                Some(total.max(1).div_ceil(iteration_bytes) * iteration_bytes)
+            }
+            RunLength::Time(_) | RunLength::Continuous => None,
+        }
+    }
+
+    /// The inverse of `from_str`, for saving a run length as text.
+    pub fn spec(&self) -> String {
+        match self {
+            RunLength::Iterations(count) => format!("iterations:{}", count),
+            RunLength::TotalBytes(total) => format!("total:{}", total),
+            RunLength::Time(limit) => format!("time:{}", limit.as_secs_f64()),
+            RunLength::Continuous => "continuous".to_string(),
+        }
+    }
+}
+
+impl std::str::FromStr for RunLength {
+    type Err = String;
+
+    /// Parses `iterations:<n>`, `total:<bytes>`, `time:<seconds>` or `continuous`.
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let invalid = || format!("invalid run length '{}'", s);
+        match s.split_once(':') {
+            None if s == "continuous" => Ok(RunLength::Continuous),
+            Some(("iterations", count)) =>
+                count.parse().map(RunLength::Iterations).map_err(|_| invalid()),
+            Some(("total", total)) =>
+                total.parse().map(RunLength::TotalBytes).map_err(|_| invalid()),
+            Some(("time", seconds)) =>
+                seconds
+                    .parse()
+                    .ok()
+                    .and_then(|seconds| Duration::try_from_secs_f64(seconds).ok())
+                    .map(RunLength::Time)
+                    .ok_or_else(invalid),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+impl std::fmt::Display for RunLength {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            RunLength::Iterations(count) => write!(f, "{} iterations per direction", count),
+            RunLength::TotalBytes(total) =>
+                write!(f, "until {:.2} GB have moved", (*total as f64) / 1e9),
+            RunLength::Time(limit) => write!(f, "for {:.1} s", limit.as_secs_f64()),
+            RunLength::Continuous => write!(f, "until stopped"),
+        }
+    }
+}
+
+/// How transferred data is checked.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Verification {
+    /// Compare the data read back from the device with what was written.
+    ReadBack,
+    /// Checksum each upload on the device and skip the device-to-host transfers, for
+    /// upload-only runs, see `checksum`.
+    Checksum,
+}
+
+impl std::fmt::Display for Verification {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Verification::ReadBack => write!(f, "Read back"),
+            Verification::Checksum => write!(f, "Checksum on the device, upload only"),
+        }
+    }
+}
+
+impl std::str::FromStr for Verification {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "readback" => Ok(Verification::ReadBack),
+            "checksum" => Ok(Verification::Checksum),
+            _ => Err(format!("unknown verification mode '{}'", s)),
+        }
+    }
+}
+
+/// What happens between iterations, for thermally neutral numbers in long runs.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Pacing {
+    /// Pause after every iteration.
+    pub delay: Duration,
+    /// After the pause, also wait until the GPU is cooler than this many degrees Celsius.
+    pub max_temperature: Option<f64>,
+    /// Gentle mode: the share of the time spent transferring, above 0 and below 1. Every
+    /// transfer is followed by an idle period in proportion, so that a GPU driving the
+    /// display keeps up with the desktop during long runs.
+    pub duty: Option<f64>,
+}
+
+impl std::fmt::Display for Pacing {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self.max_temperature {
+            None if self.delay.is_zero() && self.duty.is_none() => write!(f, "none")?,
+            None if self.delay.is_zero() => (),
+            None => write!(f, "{} ms pause", self.delay.as_millis())?,
+            Some(limit) => {
+                let delay = self.delay.as_millis();
+                write!(f, "{} ms pause, then until below {:.0} °C", delay, limit)?;
+            }
+        }
+        if let Some(duty) = self.duty {
+            if !self.delay.is_zero() || self.max_temperature.is_some() {
+                write!(f, ", ")?;
+            }
+            write!(f, "gentle mode at {:.0}% duty", duty * 100.0)?;
+        }
+        Ok(())
+    }
+}
+
+/// What a megabyte and a gigabyte are: powers of ten, as link speeds and throughput are
+/// quoted, or powers of two, as memory is sized. Configured sizes are converted to bytes in
+/// these units. Throughput is kept in GB/s of 10^9 bytes whatever the units, so that results
+/// stay comparable, and converted with `rate` where it is shown.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SizeUnits {
+    /// MB and GB/s, 10^6 and 10^9 bytes.
+    Decimal,
+    /// MiB and GiB/s, 2^20 and 2^30 bytes.
+    Binary,
+}
+
+impl SizeUnits {
+    pub const ALL: [SizeUnits; 2] = [SizeUnits::Decimal, SizeUnits::Binary];
+
+    /// Bytes in one unit of a configured size.
+    pub fn megabyte(&self) -> usize {
+        match self {
+            SizeUnits::Decimal => 1_000_000,
+            SizeUnits::Binary => 1 << 20,
+        }
+    }
+
+    pub fn size_unit(&self) -> &'static str {
+        match self {
+            SizeUnits::Decimal => "MB",
+            SizeUnits::Binary => "MiB",
+        }
+    }
+
+    pub fn rate_unit(&self) -> &'static str {
+        match self {
+            SizeUnits::Decimal => "GB/s",
+            SizeUnits::Binary => "GiB/s",
+        }
+    }
+
+    /// Factor from GB/s, as throughput is kept, to `rate_unit`.
+    pub fn rate_factor(&self) -> f64 {
+        match self {
+            SizeUnits::Decimal => 1.0,
+            SizeUnits::Binary => 1e9 / ((1u64 << 30) as f64),
+        }
+    }
+
+    /// `gbps` in `rate_unit`.
+    pub fn rate(&self, gbps: f64) -> f64 {
+        gbps * self.rate_factor()
+    }
+
+    /// `bytes` as a size in `size_unit`.
+    pub fn size(&self, bytes: u64) -> f64 {
+        (bytes as f64) / (self.megabyte() as f64)
+    }
+
+    /// The name `from_str` accepts.
+    pub fn key(&self) -> &'static str {
+        match self {
+            SizeUnits::Decimal => "decimal",
+            SizeUnits::Binary => "binary",
+        }
+    }
+}
+
+impl Default for SizeUnits {
+    /// Decimal, as the PCIe specification and vendors quote link bandwidth.
+    fn default() -> Self {
+        SizeUnits::Decimal
+    }
+}
+
+impl std::fmt::Display for SizeUnits {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{} and {}", self.size_unit(), self.rate_unit())
+    }
+}
+
+impl std::str::FromStr for SizeUnits {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "decimal" => Ok(SizeUnits::Decimal),
+            "binary" => Ok(SizeUnits::Binary),
+            _ => Err(format!("unknown units '{}', expected decimal or binary", s)),
+        }
+    }
+}
+
+/// Number of f32 elements in a transfer of `megabytes` in `units`, rejecting sizes whose byte
+/// count does not fit in `usize`, as anything from 4 GB up does on a 32-bit platform.
+pub fn elements_in(megabytes: usize, units: SizeUnits) -> Result<usize, BenchError> {
+    megabytes
+        .checked_mul(units.megabyte())
+        .map(|bytes| bytes / std::mem::size_of::<f32>())
+        .ok_or_else(||
+            BenchError::Unsupported(
+                format!(
+                    "a {} {} transfer does not fit in this platform's {}-bit address space",
+                    megabytes,
+                    units.size_unit(),
+                    usize::BITS
+                )
+            )
+        )
+}
+
+/// Parses a transfer size as people write one into megabytes of `units`, the unit sizes are
+/// kept in. A bare number is already in them; `KiB` to `TiB` are binary and `KB` to `TB`
+/// decimal whatever `units` is, while a bare `k`, `m`, `g` or `t` follows `units`, so `2g` is
+/// 2 GiB with binary units. Sizes that are not a whole number of megabytes are rejected
+/// rather than rounded, e.g. `512MiB` with decimal units.
+pub fn parse_size(text: &str, units: SizeUnits) -> Result<usize, String> {
+    let bytes = parse_bytes(text, units)?;
+    let megabytes = (bytes as f64) / (units.megabyte() as f64);
+    let whole = megabytes.round();
+    if (megabytes - whole).abs() > 1e-6 {
+        return Err(
+            format!(
+                "{} is {} {}, not a whole number of them{}",
+                text,
+                megabytes,
+                units.size_unit(),
+                if units == SizeUnits::Decimal { "; binary units take it as is" } else { "" }
+            )
+        );
+    }
+    if whole >= (usize::MAX as f64) {
+        return Err(format!("{} is too large", text));
+    }
+    Ok(whole as usize)
+}
+
+/// Parses a size into bytes with the units of `parse_size`, for sizes that need not be whole
+/// megabytes; a `B` suffix is bytes, e.g. `4096B`.
+pub fn parse_bytes(text: &str, units: SizeUnits) -> Result<u64, String> {
+    let invalid = || format!("invalid size '{}', expected e.g. 512, 512MiB or 2g", text);
+    let split = text
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(text.len());
+    let (number, suffix) = text.split_at(split);
+    let number: f64 = number.parse().map_err(|_| invalid())?;
+    let suffix = suffix.trim().to_ascii_lowercase();
+    let bytes = if suffix.is_empty() {
+        number * (units.megabyte() as f64)
+    } else if suffix == "b" {
+        number
+    } else {
+        let mut chars = suffix.chars();
+        let power = match chars.next() {
+            Some('k') => 1,
+            Some('m') => 2,
+            Some('g') => 3,
+            Some('t') => 4,
+            _ => {
+                return Err(invalid());
+            }
+        };
+        let base: f64 = match chars.as_str() {
+            "" if units == SizeUnits::Binary => 1024.0,
+            "" | "b" => 1000.0,
+            "ib" => 1024.0,
+            _ => {
+                return Err(invalid());
+            }
+        };
+        number * base.powi(power)
+    };
+    let whole = bytes.round();
+    if (bytes - whole).abs() > 1e-3 {
+        return Err(format!("{} is not a whole number of bytes", text));
+    }
+    if whole >= (u64::MAX as f64) {
+        return Err(format!("{} is too large", text));
+    }
+    Ok(whole as u64)
+}
+
+#[derive(Clone, Copy)]
+pub struct MeasureConfig {
+    /// Number of f32 elements per transfer.
+    pub data_size: usize,
+    pub length: RunLength,
+    pub host_buffer: HostBuffer,
+    pub memory: Memory,
+    pub pacing: Pacing,
+    pub verification: Verification,
+    /// How long to keep the GPU busy with a kernel before measuring, zero for no warm-up.
+    pub warm_up: Duration,
+    /// Iterations run before the measured ones and left out of the results, as the first
+    /// transfers pay for pinning, mapping and waking the link.
+    pub discard: usize,
+    /// Telemetry sampled alongside the measurement.
+    pub sensors: Sensors,
+    /// How the transfer size was given, and how results are shown.
+    pub units: SizeUnits,
+}
+
+impl MeasureConfig {
+    /// Bytes moved by one transfer, counted in 64 bits.
+    pub fn transfer_bytes(&self) -> u64 {
+        (self.data_size as u64) * (std::mem::size_of::<f32>() as u64)
+    }
+
+    /// Bytes moved by one iteration: a transfer each way, or only the upload when verifying
+    /// by checksum.
+    pub fn iteration_bytes(&self) -> u64 {
+        match self.verification {
+            Verification::ReadBack => self.transfer_bytes() * 2,
+            Verification::Checksum => self.transfer_bytes(),
+        }
+    }
+}
+
+pub struct Throughput {
+    pub h2d_throughput: f64,
+    pub d2h_throughput: f64,
+    pub h2d_duration: f64,
+    pub d2h_duration: f64,
+    /// Per-iteration throughput in GB/s.
+    pub h2d_samples: Vec<f64>,
+    pub d2h_samples: Vec<f64>,
+    /// Iterations run and left out before the samples, see `MeasureConfig::discard`.
+    pub discarded: usize,
+    /// Iterations of a soak run dropped from the front of the samples, the timeline and the
+    /// trace, see `SOAK_KEPT_ITERATIONS`. The mean throughput still covers them.
+    pub trimmed: usize,
+    /// When the device was reset during the run, each time followed by starting over, so
+    /// the results come from the attempt after the last one.
+    pub resets: Vec<SystemTime>,
+    /// Driver-side readings taken during the run, where the platform exposes them.
+    pub telemetry: Telemetry,
+    /// Device timestamps of every transfer, if the driver reports them.
+    pub trace: Vec<TransferEvent>,
+    /// Clock state of the GPU as the first transfer started, where the driver exposes it.
+    pub start_state: Option<GpuState>,
+    /// Median time of a single-float blocking write, i.e. the fixed cost every transfer pays.
+    pub latency: Option<Duration>,
+    /// Share of the time spent transferring rather than idling, in gentle mode.
+    pub duty_cycle: Option<f64>,
+    /// When each iteration started and ended, to line driver readings up with the samples.
+    pub timeline: Vec<(Instant, Instant)>,
+    /// Memory use of the harness itself over a soak run, see `leaks`.
+    pub harness_memory: Option<MemoryTrack>,
+    /// Suspends of the system the run carried on through, see `suspend`.
+    pub suspends: Vec<Suspend>,
+}
+
+impl Default for Throughput {
+    fn default() -> Self {
+        Throughput::new()
+    }
+}
+
+impl Throughput {
+    pub fn new() -> Self {
+        Throughput {
+            h2d_throughput: 0.0,
+            d2h_throughput: 0.0,
+            h2d_duration: 0.0,
+            d2h_duration: 0.0,
+            h2d_samples: Vec::new(),
+            d2h_samples: Vec::new(),
+            discarded: 0,
+            trimmed: 0,
+            resets: Vec::new(),
+            telemetry: Telemetry::default(),
+            trace: Vec::new(),
+            start_state: None,
+            latency: None,
+            duty_cycle: None,
+            timeline: Vec::new(),
+            harness_memory: None,
+            suspends: Vec::new(),
+        }
+    }
+
+    /// Measures both directions, reporting every H2D and D2H sample pair to `progress` as it
+    /// is taken.
+    pub fn measure_observed(
+        &mut self,
+        config: &MeasureConfig,
+        device: &Device,
+        progress: &mut dyn ProgressSink
+    ) -> Result<(), BenchError> {
+        let monitor = Monitor::start(device, config.sensors);
+        let leaks = config.length.is_soak().then(|| LeakWatch::start(device));
+        let result = self.measure_with_retry(config, device, progress);
+        self.telemetry = monitor.finish(self);
+        self.harness_memory = leaks.map(LeakWatch::finish);
+        result
+    }
+
+    fn measure_with_retry(
+        &mut self,
+        config: &MeasureConfig,
+        device: &Device,
+        progress: &mut dyn ProgressSink
+    ) -> Result<(), BenchError> {
+        self.resets.clear();
+        let limit = if config.length.is_soak() { SOAK_RESET_LIMIT } else { 1 };
+        loop {
+            match self.measure_once(config, device, progress) {
+                Err(BenchError::DeviceReset(e)) => {
+                    // The queue and buffers of the failed attempt are dropped by now; forgetting
+                    // its context too makes the retry, or whatever runs next, start from a
+                    // freshly created one
+                    DeviceRegistry::global().forget_context(device);
+                    if self.resets.len() >= limit {
+                        return Err(BenchError::DeviceReset(e));
+                    }
+                    self.resets.push(SystemTime::now());
+                    progress.on_phase_change(Phase::Retrying);
+                }
+                result => {
+                    return result;
+                }
+            }
+        }
+    }
+
+    fn measure_once(
+        &mut self,
+        config: &MeasureConfig,
+        device: &Device,
+        progress: &mut dyn ProgressSink
+    ) -> Result<(), BenchError> {
+        simulate::check()?;
+        let data_size = config.data_size;
+        // Checked here because drivers report an oversized buffer as a generic failure
+        let max_alloc = device.max_mem_alloc_size()?;
+        if config.transfer_bytes() > max_alloc {
+            return Err(
+                BenchError::Unsupported(
+                    format!(
+                        "a {:.0} {unit} transfer exceeds the device's largest allocation of \
+                         {:.0} {unit}",
+                        config.units.size(config.transfer_bytes()),
+                        config.units.size(max_alloc),
+                        unit = config.units.size_unit()
+                    )
+                )
+            );
+        }
+        let context = DeviceRegistry::global().context(device)?;
+        let queue = default_queue(&context, CL_QUEUE_PROFILING_ENABLE)?;
+        if !config.warm_up.is_zero() {
+            progress.on_phase_change(Phase::WarmingUp);
+            warmup::warm_up(&context, &queue, config.warm_up)?;
+            progress.on_phase_change(Phase::Measuring);
+        }
+        self.start_state = PciAddress::of(device).and_then(telemetry::gpu_state);
+        self.latency = Some(write_latency(&context, &queue)?);
+
+        let mut d_data = DeviceMemory::create(config.memory, &context, device, data_size)?;
+        let checksum = match config.verification {
+            Verification::Checksum => {
+                if d_data.buffer().is_none() {
+                    return Err(
+                        BenchError::Unsupported(
+                            "checksum verification needs an OpenCL buffer, not USM".to_string()
+                        )
+                    );
+                }
+                let expected = checksum::of_host((0..data_size).map(pattern_value));
+                Some((Checksum::new(&context)?, expected))
+            }
+            Verification::ReadBack => None,
+        };
+        let mut thermometer = match config.pacing.max_temperature {
+            Some(_) =>
+                Some(
+                    PciAddress::of(device)
+                        .and_then(telemetry::thermometer)
+                        .ok_or_else(|| {
+                            BenchError::Unsupported(
+                                "no temperature sensor was found for this device".to_string()
+                            )
+                        })?
+                ),
+            None => None,
+        };
+
+        if config.discard > 0 {
+            // As the measured iterations move data, but neither timed nor verified
+            progress.on_phase_change(Phase::WarmingUp);
+            let mut h_data: Vec<f32> = (0..data_size).map(pattern_value).collect();
+            for _ in 0..config.discard {
+                d_data.write(&queue, &h_data)?;
+                if checksum.is_none() {
+                    d_data.read(&queue, &mut h_data)?;
+                }
+            }
+            queue.finish()?;
+            progress.on_phase_change(Phase::Measuring);
+        }
+        self.discarded = config.discard;
+
+        let bytes = config.transfer_bytes() as f64;
+        let mut h2d_total = 0.0;
+        let mut d2h_total = 0.0;
+        self.h2d_samples.clear();
+        self.d2h_samples.clear();
+        self.trace.clear();
+        self.timeline.clear();
+        self.suspends.clear();
+        self.trimmed = 0;
+
+        let mut idle_total = 0.0;
+        let mut idle = |duration: f64| {
+            if let Some(duty) = config.pacing.duty {
+                let pause = (duration * (1.0 - duty)) / duty;
+                std::thread::sleep(Duration::from_secs_f64(pause));
+                idle_total += pause;
+            }
+        };
+
+        let run_start = Instant::now();
+        let mut moved: u64 = 0;
+        let mut reused = Vec::new();
+        let mut suspend = SuspendWatch::start();
+        loop {
+            // Slept while pacing or verifying, which no sample includes
+            if let Some(slept) = suspend.check() {
+                let (iteration, trace_len) = (self.iterations(), self.trace.len());
+                self.resume(iteration, trace_len, slept, &context, &queue, progress)?;
+            }
+            // Filled once, before the first timer, when reused
+            let reused_data = (config.host_buffer == HostBuffer::Reuse).then(|| {
+                if reused.is_empty() {
+                    (0..data_size).map(pattern_value).collect()
+                } else {
+                    std::mem::take(&mut reused)
+                }
+            });
+
+            let iteration = self.iterations();
+            let trace_len = self.trace.len();
+            let (h2d_before, d2h_before) = (h2d_total, d2h_total);
+            let iteration_start = Instant::now();
+            let start = Instant::now();
+            // A fresh upload is allocated and filled inside the timed region, so that H2D
+            // pays its first-touch page faults, see `HostBuffer::Fresh`
+            let h_data: Vec<f32> = reused_data.unwrap_or_else(|| {
+                (0..data_size).map(pattern_value).collect()
+            });
+            let event = d_data.write(&queue, &h_data)?;
+            queue.finish()?;
+            let duration = start.elapsed().as_secs_f64();
+            self.trace.extend(
+                TransferEvent::from_event(&event, Direction::HostToDevice, iteration)
+            );
+            h2d_total += duration;
+            self.h2d_samples.push(bytes / duration / 1e9);
+            idle(duration);
+
+            if let Some((ref kernel, expected)) = checksum {
+                // Untimed, and only the partial sums come back rather than the data
+                let actual = kernel.of_buffer(&queue, d_data.buffer().unwrap(), data_size)?;
+                if actual != expected {
+                    return Err(BenchError::ChecksumMismatch { expected, actual });
+                }
+                if config.host_buffer == HostBuffer::Reuse {
+                    reused = h_data;
+                }
+                if let Some(slept) = suspend.check() {
+                    h2d_total = h2d_before;
+                    self.resume(iteration, trace_len, slept, &context, &queue, progress)?;
+                    continue;
+                }
+                self.timeline.push((iteration_start, Instant::now()));
+                moved += bytes as u64;
+                let h2d = self.h2d_samples[self.h2d_samples.len() - 1];
+                self.trim(config.length);
+                if
+                    progress.on_sample(h2d, f64::NAN).is_break() ||
+                    config.length.is_done(self.iterations(), moved, run_start.elapsed())
+                {
+                    break;
+                }
+                pace(&config.pacing, thermometer.as_mut(), progress)?;
+                continue;
+            }
+
+            let mut h_data = match config.host_buffer {
+                HostBuffer::Reuse => {
+                    // Clear the host copy so the read-back below can be verified
+                    let mut h_data = h_data;
+                    h_data.fill(0.0);
+                    h_data
+                }
+                HostBuffer::Fresh => {
+                    // Zeroed but untouched pages, so first-touch faults land in the timed read
+                    drop(h_data);
+                    vec![0.0f32; data_size]
+                }
+            };
+
+            let start = Instant::now();
+            let event = d_data.read(&queue, &mut h_data)?;
+            queue.finish()?;
+            let duration = start.elapsed().as_secs_f64();
+            self.trace.extend(
+                TransferEvent::from_event(&event, Direction::DeviceToHost, iteration)
+            );
+            d2h_total += duration;
+            self.d2h_samples.push(bytes / duration / 1e9);
+            idle(duration);
+
+            verify(&h_data)?;
+            if config.host_buffer == HostBuffer::Reuse {
+                reused = h_data;
+            }
+            if let Some(slept) = suspend.check() {
+                (h2d_total, d2h_total) = (h2d_before, d2h_before);
+                self.resume(iteration, trace_len, slept, &context, &queue, progress)?;
+                continue;
+            }
+
+            self.timeline.push((iteration_start, Instant::now()));
+            moved += (bytes as u64) * 2;
+            let h2d = self.h2d_samples[self.h2d_samples.len() - 1];
+            let d2h = self.d2h_samples[self.d2h_samples.len() - 1];
+            self.trim(config.length);
+            if
+                progress.on_sample(h2d, d2h).is_break() ||
+                config.length.is_done(self.iterations(), moved, run_start.elapsed())
+            {
+                break;
+            }
+            pace(&config.pacing, thermometer.as_mut(), progress)?;
+        }
+
+        let iterations = self.iterations() as f64;
+        self.h2d_duration = h2d_total / iterations;
+        self.h2d_throughput = (bytes * iterations) / h2d_total / 1e9;
+        if self.has_d2h() {
+            self.d2h_duration = d2h_total / iterations;
+            self.d2h_throughput = (bytes * iterations) / d2h_total / 1e9;
+        } else {
+            self.d2h_duration = 0.0;
+            self.d2h_throughput = 0.0;
+        }
+        self.duty_cycle = config.pacing.duty.map(|_| {
+            let active = h2d_total + d2h_total;
+            active / (active + idle_total)
+        });
+
+        Ok(())
+    }
+
+    /// Records a suspend of `slept` noticed at `iteration`, dropping what the iteration measured
+    /// so far, from `trace_len` trace events on, and checks that the context survived the
+    /// sleep before the run carries on.
+    fn resume(
+        &mut self,
+        iteration: usize,
+        trace_len: usize,
+        slept: Duration,
+        context: &Context,
+        queue: &CommandQueue,
+        progress: &mut dyn ProgressSink
+    ) -> Result<(), BenchError> {
+        let kept = iteration - self.trimmed;
+        self.h2d_samples.truncate(kept);
+        self.d2h_samples.truncate(kept);
+        self.timeline.truncate(kept);
+        self.trace.truncate(trace_len);
+        self.suspends.push(Suspend { resumed: SystemTime::now(), slept, iteration });
+        progress.on_phase_change(Phase::Resuming);
+        // Any failure here means the context was lost with the sleep, which is handled like a
+        // reset: the run starts over on a new one
+        write_latency(context, queue).map_err(|error| match error {
+            BenchError::OpenCl(error) => BenchError::DeviceReset(error),
+            error => error,
+        })?;
+        progress.on_phase_change(Phase::Measuring);
+        Ok(())
+    }
+
+    /// Iterations measured, including any `trimmed` from the samples.
+    pub fn iterations(&self) -> usize {
+        self.trimmed + self.h2d_samples.len()
+    }
+
+    /// Drops the oldest iterations of a soak run once twice `SOAK_KEPT_ITERATIONS` are kept.
+    fn trim(&mut self, length: RunLength) {
+        if !length.is_soak() || self.h2d_samples.len() < 2 * SOAK_KEPT_ITERATIONS {
+            return;
+        }
+        let dropped = self.h2d_samples.len() - SOAK_KEPT_ITERATIONS;
+        self.h2d_samples.drain(..dropped);
+        self.d2h_samples.drain(..dropped.min(self.d2h_samples.len()));
+        self.timeline.drain(..dropped.min(self.timeline.len()));
+        self.trimmed += dropped;
+        let first = self.trimmed;
+        self.trace.retain(|event| event.iteration >= first);
+    }
+
+    /// Whether device-to-host was measured, which upload-only runs skip.
+    pub fn has_d2h(&self) -> bool {
+        !self.d2h_samples.is_empty()
+    }
+
+    /// Whether the device was reset at least once during the run.
+    pub fn device_reset(&self) -> bool {
+        !self.resets.is_empty()
+    }
+
+    /// Mean throughput of the directions measured.
+    pub fn mean_throughput(&self) -> f64 {
+        if self.has_d2h() {
+            (self.h2d_throughput + self.d2h_throughput) / 2.0
+        } else {
+            self.h2d_throughput
+        }
+    }
+
+    /// Host to device throughput with the uncertainty its per-iteration samples support.
+    pub fn h2d(&self) -> Measurement {
+        Measurement::of_samples(self.h2d_throughput, &self.h2d_samples)
+    }
+
+    pub fn d2h(&self) -> Measurement {
+        Measurement::of_samples(self.d2h_throughput, &self.d2h_samples)
+    }
+
+    /// Throughput of the slower direction measured.
+    pub fn slowest_throughput(&self) -> f64 {
+        if self.has_d2h() {
+            self.h2d_throughput.min(self.d2h_throughput)
+        } else {
+            self.h2d_throughput
+        }
+    }
+}
+
+/// Value written to element `index` of the transfer buffer, checked again after read-back.
+fn pattern_value(index: usize) -> f32 {
+    (index % 4096) as f32
+}
+
+/// Median of `LATENCY_PROBES` blocking writes of a single float, too small for bandwidth to
+/// matter.
+fn write_latency(context: &Context, queue: &CommandQueue) -> Result<Duration, BenchError> {
+    const LATENCY_PROBES: usize = 64;
+    let mut buffer = unsafe {
+        Buffer::<f32>::create(context, CL_MEM_READ_WRITE, 1, std::ptr::null_mut())?
+    };
+    let mut times = Vec::with_capacity(LATENCY_PROBES);
+    for _ in 0..LATENCY_PROBES {
+        let start = Instant::now();
+        unsafe {
+            queue.enqueue_write_buffer(&mut buffer, CL_BLOCKING, 0, &[0.0f32], &[])?;
+        }
+        times.push(start.elapsed());
+    }
+    times.sort();
+    Ok(times[LATENCY_PROBES / 2])
+}
+
+fn verify(data: &[f32]) -> Result<(), BenchError> {
+    match
+        data
+            .iter()
+            .enumerate()
+            .find(|&(i, &v)| v != pattern_value(i))
+    {
+        Some((index, &actual)) =>
+            Err(BenchError::Verification {
+                index,
+                expected: pattern_value(index),
+                actual,
+            }),
+        None => Ok(()),
+    }
+}
+
+/// Sleeps for the pacing delay, then polls `thermometer` until the GPU is below the limit.
+fn pace(
+    pacing: &Pacing,
+    thermometer: Option<&mut Box<dyn Thermometer>>,
+    progress: &mut dyn ProgressSink
+) -> Result<(), BenchError> {
+    std::thread::sleep(pacing.delay);
+    let (Some(limit), Some(thermometer)) = (pacing.max_temperature, thermometer) else {
+        return Ok(());
+    };
+    let read = |thermometer: &mut Box<dyn Thermometer>| {
+        thermometer.celsius().ok_or_else(|| {
+            BenchError::Unsupported("the temperature sensor stopped responding".to_string())
+        })
+    };
+    if read(thermometer)? < limit {
+        return Ok(());
+    }
+    progress.on_phase_change(Phase::CoolingDown);
+    while read(thermometer)? >= limit {
+        std::thread::sleep(Duration::from_millis(500));
+    }
+    progress.on_phase_change(Phase::Measuring);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn soak_runs_keep_the_latest_iterations() {
+        let count = 2 * SOAK_KEPT_ITERATIONS;
+        let now = Instant::now();
+        let event = |iteration| TransferEvent {
+            direction: Direction::HostToDevice,
+            iteration,
+            queued: 0,
+            submitted: 0,
+            start: 0,
+            end: 0,
+        };
+        let mut throughput = Throughput {
+            h2d_samples: (0..count).map(|sample| sample as f64).collect(),
+            d2h_samples: vec![1.0; count],
+            timeline: vec![(now, now); count],
+            trace: (0..count).map(event).collect(),
+            ..Throughput::new()
+        };
+        throughput.trim(RunLength::Iterations(count));
+        assert_eq!(throughput.h2d_samples.len(), count);
+
+        throughput.trim(RunLength::Continuous);
+        assert_eq!(throughput.iterations(), count);
+        assert_eq!(throughput.trimmed, SOAK_KEPT_ITERATIONS);
+        assert_eq!(throughput.h2d_samples[0], SOAK_KEPT_ITERATIONS as f64);
+        assert_eq!(throughput.d2h_samples.len(), SOAK_KEPT_ITERATIONS);
+        assert_eq!(throughput.timeline.len(), SOAK_KEPT_ITERATIONS);
+        assert_eq!(throughput.trace.len(), SOAK_KEPT_ITERATIONS);
+        assert_eq!(throughput.trace[0].iteration, SOAK_KEPT_ITERATIONS);
+    }
+
+    #[test]
+    fn parse_size_takes_units() {
+        assert_eq!(parse_size("512MiB", SizeUnits::Binary), Ok(512));
+        assert_eq!(parse_size("512MB", SizeUnits::Decimal), Ok(512));
+        assert_eq!(parse_size("1.5GB", SizeUnits::Decimal), Ok(1500));
+        assert_eq!(parse_size("1GiB", SizeUnits::Binary), Ok(1024));
+        assert_eq!(parse_size("1024", SizeUnits::Decimal), Ok(1024));
+    }
+
+    #[test]
+    fn bare_suffix_follows_the_units() {
+        assert_eq!(parse_size("2g", SizeUnits::Binary), Ok(2048));
+        assert_eq!(parse_size("2g", SizeUnits::Decimal), Ok(2000));
+        assert_eq!(parse_bytes("2g", SizeUnits::Binary), Ok(2 << 30));
+        assert_eq!(parse_bytes("2g", SizeUnits::Decimal), Ok(2_000_000_000));
+    }
+
+    #[test]
+    fn suffixed_units_ignore_the_units() {
+        assert_eq!(parse_bytes("1MB", SizeUnits::Binary), Ok(1_000_000));
+        assert_eq!(parse_bytes("1MiB", SizeUnits::Decimal), Ok(1 << 20));
+        assert_eq!(parse_bytes("4096B", SizeUnits::Decimal), Ok(4096));
+        assert_eq!(parse_bytes("4 KiB", SizeUnits::Decimal), Ok(4096));
+    }
+
+    #[test]
+    fn parse_size_rejects_partial_megabytes() {
+        assert!(parse_size("512MiB", SizeUnits::Decimal).is_err());
+        assert!(parse_size("1000KB", SizeUnits::Binary).is_err());
+        assert!(parse_bytes("0.5B", SizeUnits::Decimal).is_err());
+    }
+
+    #[test]
+    fn parse_rejects_overflow() {
+        assert!(parse_bytes("100000000T", SizeUnits::Decimal).is_err());
+        assert!(parse_size("20000000TiB", SizeUnits::Binary).is_err());
+    }
+
+    #[test]
+    fn parse_rejects_empty_and_garbage() {
+        for text in ["", " ", "MiB", "abc", "12x", "1.2.3", "-5", "5 GiBs", "5 mb extra"] {
+            assert!(parse_bytes(text, SizeUnits::Decimal).is_err(), "accepted '{}'", text);
+            assert!(parse_size(text, SizeUnits::Binary).is_err(), "accepted '{}'", text);
+        }
+    }
+}
@@ -0,0 +1,198 @@
+//! Transfers of a random size each, within a range, as bursty workloads move data rather than
+//! in one size over and over: a renderer streams textures of every size, an inference server
+//! batches what has arrived. Sizes are drawn evenly across the orders of magnitude of the range
+//! and the results are grouped into power-of-two buckets, so that each size class gets its
+//! own throughput while the driver never settles into one.
+
+use crate::device::default_queue;
+use crate::error::BenchError;
+use crate::registry::DeviceRegistry;
+use crate::{ parse_bytes, patterns, ramp, MeasureConfig, SizeUnits };
+use opencl3::device::Device;
+use opencl3::memory::{ Buffer, CL_MEM_READ_WRITE };
+use opencl3::types::CL_BLOCKING;
+use std::fmt;
+use std::ptr;
+use std::time::{ Duration, Instant };
+
+/// Transfers each way with fixed run lengths of fewer iterations, and with byte and time
+/// budgets.
+pub const MIN_TRANSFERS: usize = 256;
+/// Smallest size a range may start at, one f32.
+pub const MIN_BYTES: u64 = 4;
+
+/// Sizes to draw from, in bytes, both ends included.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SizeRange {
+    pub min: u64,
+    pub max: u64,
+}
+
+impl SizeRange {
+    /// Parses `MIN..MAX` with each end a size as `parse_bytes` takes it, e.g. `4KiB..256MiB`.
+    pub fn parse(text: &str, units: SizeUnits) -> Result<SizeRange, String> {
+        let (min, max) = text
+            .split_once("..")
+            .ok_or_else(|| format!("invalid size range '{}', expected e.g. 4KiB..256MiB", text))?;
+        let range = SizeRange {
+            min: parse_bytes(min.trim(), units)?,
+            max: parse_bytes(max.trim(), units)?,
+        };
+        if range.min < MIN_BYTES || range.min > range.max {
+            return Err(
+                format!(
+                    "invalid size range '{}', expected from {} bytes up to a larger size",
+                    text,
+                    MIN_BYTES
+                )
+            );
+        }
+        Ok(range)
+    }
+}
+
+impl fmt::Display for SizeRange {
+    /// Writes each end in the largest binary unit that divides it, e.g. `4KiB..256MiB`, which
+    /// `parse` reads back whatever the units.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let exact = |bytes: u64| match bytes {
+            bytes if bytes % (1 << 20) == 0 => format!("{}MiB", bytes >> 20),
+            bytes if bytes % (1 << 10) == 0 => format!("{}KiB", bytes >> 10),
+            bytes => format!("{}B", bytes),
+        };
+        write!(f, "{}..{}", exact(self.min), exact(self.max))
+    }
+}
+
+/// One transfer each way of a drawn size.
+#[derive(Clone, Copy, Debug)]
+pub struct BurstTransfer {
+    pub bytes: u64,
+    pub h2d: Duration,
+    pub d2h: Duration,
+}
+
+/// The transfers of a power-of-two size class, see `BurstResult::buckets`.
+#[derive(Clone, Copy, Debug)]
+pub struct Bucket {
+    /// Sizes from `low` up to but excluding twice it.
+    pub low: u64,
+    pub transfers: usize,
+    /// GB/s over every transfer of the bucket together.
+    pub h2d: f64,
+    pub d2h: f64,
+}
+
+/// Every transfer in the order made, see `measure_bursts`.
+#[derive(Clone, Debug)]
+pub struct BurstResult {
+    pub range: SizeRange,
+    pub transfers: Vec<BurstTransfer>,
+}
+
+impl BurstResult {
+    /// The transfers grouped by the power of two at or below their size, smallest first.
+    pub fn buckets(&self) -> Vec<Bucket> {
+        let mut buckets: Vec<(u64, Vec<&BurstTransfer>)> = Vec::new();
+        for transfer in &self.transfers {
+            let low = 1 << transfer.bytes.ilog2();
+            match buckets.iter_mut().find(|(bucket, _)| *bucket == low) {
+                Some((_, members)) => members.push(transfer),
+                None => buckets.push((low, vec![transfer])),
+            }
+        }
+        buckets.sort_by_key(|&(low, _)| low);
+        buckets
+            .into_iter()
+            .map(|(low, members)| {
+                let (h2d, d2h) = throughput(members.iter().copied());
+                Bucket { low, transfers: members.len(), h2d, d2h }
+            })
+            .collect()
+    }
+
+    /// Throughput over every transfer, then a line per bucket.
+    pub fn summary(&self) -> Vec<String> {
+        let (h2d, d2h) = throughput(self.transfers.iter());
+        let mut lines = vec![
+            format!(
+                "{} transfers from {} to {}: {:.2} GB/s H2D, {:.2} GB/s D2H overall",
+                self.transfers.len(),
+                ramp::size_label(self.range.min),
+                ramp::size_label(self.range.max),
+                h2d,
+                d2h
+            )
+        ];
+        lines.extend(
+            self
+                .buckets()
+                .iter()
+                .map(|bucket| {
+                    format!(
+                        "{} to {}: {} transfers, {:.2} GB/s H2D, {:.2} GB/s D2H",
+                        ramp::size_label(bucket.low),
+                        ramp::size_label(bucket.low * 2),
+                        bucket.transfers,
+                        bucket.h2d,
+                        bucket.d2h
+                    )
+                })
+        );
+        lines
+    }
+}
+
+/// GB/s each way over `transfers` together.
+fn throughput<'a>(transfers: impl Iterator<Item = &'a BurstTransfer>) -> (f64, f64) {
+    let (bytes, h2d, d2h) = transfers.fold((0, Duration::ZERO, Duration::ZERO), |sum, transfer| {
+        (sum.0 + transfer.bytes, sum.1 + transfer.h2d, sum.2 + transfer.d2h)
+    });
+    let gbps = |time: Duration| (bytes as f64) / time.as_secs_f64() / 1e9;
+    (gbps(h2d), gbps(d2h))
+}
+
+/// Uploads and downloads sizes drawn from `range`, log-uniformly and rounded down to whole
+/// f32s, one blocking transfer each way per size. Fixed run lengths give the number of sizes,
+/// at least `MIN_TRANSFERS`. The sizes come from a fixed seed, so every run draws the same.
+///
+/// Data is not verified in this mode; it only looks at throughput by size.
+pub fn measure_bursts(
+    config: &MeasureConfig,
+    device: &Device,
+    range: SizeRange
+) -> Result<BurstResult, BenchError> {
+    let context = DeviceRegistry::global().context(device)?;
+    let queue = default_queue(&context, 0)?;
+    let mut buffer = unsafe {
+        Buffer::<u8>::create(&context, CL_MEM_READ_WRITE, range.max as usize, ptr::null_mut())?
+    };
+    let mut host: Vec<u8> = (0..range.max).map(|index| index as u8).collect();
+
+    let count = config.length.fixed_iterations().max(MIN_TRANSFERS);
+    let (low, high) = ((range.min as f64).ln(), (range.max as f64).ln());
+    // xorshift32, as for the random pattern
+    let mut state = patterns::PATTERN_SEED;
+    let mut transfers = Vec::with_capacity(count);
+    for _ in 0..count {
+        state ^= state << 13;
+        state ^= state >> 17;
+        state ^= state << 5;
+        let share = (state as f64) / (u32::MAX as f64);
+        let drawn = (low + (high - low) * share).exp() as u64;
+        let bytes = (drawn.clamp(range.min, range.max) / 4 * 4).max(MIN_BYTES);
+        let chunk = &mut host[..bytes as usize];
+
+        let start = Instant::now();
+        unsafe {
+            queue.enqueue_write_buffer(&mut buffer, CL_BLOCKING, 0, chunk, &[])?;
+        }
+        let h2d = start.elapsed();
+        let start = Instant::now();
+        unsafe {
+            queue.enqueue_read_buffer(&buffer, CL_BLOCKING, 0, chunk, &[])?;
+        }
+        transfers.push(BurstTransfer { bytes, h2d, d2h: start.elapsed() });
+    }
+    Ok(BurstResult { range, transfers })
+}
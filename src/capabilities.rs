@@ -0,0 +1,164 @@
+//! OpenCL extensions that matter for transfer benchmarking, and what each one enables here.
+
+use crate::MyDevice;
+
+pub struct Capability {
+    /// Any one of these extensions provides the capability.
+    pub extensions: &'static [&'static str],
+    pub description: &'static str,
+    /// What the benchmark does with it.
+    pub enables: &'static str,
+}
+
+pub const PCI_ADDRESS: Capability = Capability {
+    extensions: &[
+        "cl_khr_pci_bus_info",
+        "cl_nv_device_attribute_query",
+        "cl_amd_device_attribute_query",
+    ],
+    description: "PCI bus address",
+    enables: "Link retraining, driver link counters, power telemetry",
+};
+
+pub const DEVICE_FISSION: Capability = Capability {
+    extensions: &["cl_ext_device_fission"],
+    description: "Device fission",
+    enables: "Partitioning on OpenCL 1.1 drivers (core from 1.2)",
+};
+
+pub const USM: Capability = Capability {
+    extensions: &["cl_intel_unified_shared_memory"],
+    description: "Intel unified shared memory",
+    enables: "USM host, device and shared memory modes",
+};
+
+pub const HOST_VISIBLE_VRAM: Capability = Capability {
+    extensions: &["cl_amd_device_attribute_query"],
+    description: "Host-visible VRAM (AMD persistent memory)",
+    enables: "Direct CPU writes into VRAM through the BAR, faster with Resizable BAR",
+};
+
+pub const GL_SHARING: Capability = Capability {
+    extensions: &["cl_khr_gl_sharing"],
+    description: "OpenGL sharing",
+    enables: "Transfers into shared GL buffers, from the GUI",
+};
+
+pub const PEER_TO_PEER: Capability = Capability {
+    extensions: &["cl_amd_copy_buffer_p2p", "cl_nv_peer_to_peer"],
+    description: "Peer-to-peer copies",
+    enables: "Direct GPU-to-GPU copies (--peer) with cl_amd_copy_buffer_p2p",
+};
+
+pub const CAPABILITIES: &[Capability] = &[
+    PCI_ADDRESS,
+    DEVICE_FISSION,
+    USM,
+    HOST_VISIBLE_VRAM,
+    GL_SHARING,
+    PEER_TO_PEER,
+    Capability {
+        extensions: &["cl_khr_external_memory_dma_buf"],
+        description: "dma-buf import",
+        enables: "Transfers into imported dma-bufs on Linux (--dma-buf)",
+    },
+    Capability {
+        extensions: &["cl_khr_fp16"],
+        description: "Half precision",
+        enables: "Nothing yet; transfers use f32 data",
+    },
+];
+
+impl Capability {
+    pub fn supported_by(&self, device: &MyDevice) -> bool {
+        self.extensions.iter().any(|extension| device.has_extension(extension))
+    }
+}
+
+/// The parts of a device's capability report a driver update can change, compared across
+/// sessions so that a change in measured throughput can be put down to the driver.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Snapshot {
+    pub driver_version: String,
+    pub opencl_version: String,
+    /// Largest single allocation in bytes.
+    pub max_alloc: u64,
+    pub global_memory: u64,
+    pub compute_units: u32,
+    pub extensions: Vec<String>,
+}
+
+impl Snapshot {
+    pub fn of(device: &MyDevice) -> Snapshot {
+        let cl = device.get_device();
+        Snapshot {
+            driver_version: cl.driver_version().unwrap_or_default(),
+            opencl_version: cl.version().unwrap_or_default(),
+            max_alloc: cl.max_mem_alloc_size().unwrap_or_default(),
+            global_memory: cl.global_mem_size().unwrap_or_default(),
+            compute_units: cl.max_compute_units().unwrap_or_default(),
+            extensions: device.extensions().to_vec(),
+        }
+    }
+
+    /// What changed since `previous`, one sentence each, e.g. "max alloc grew from 4 GB to
+    /// 8 GB".
+    pub fn changes_since(&self, previous: &Snapshot) -> Vec<String> {
+        let mut changes = Vec::new();
+        if self.driver_version != previous.driver_version {
+            changes.push(
+                format!(
+                    "driver changed from {} to {}",
+                    previous.driver_version,
+                    self.driver_version
+                )
+            );
+        }
+        if self.opencl_version != previous.opencl_version {
+            changes.push(
+                format!(
+                    "OpenCL changed from {} to {}",
+                    previous.opencl_version,
+                    self.opencl_version
+                )
+            );
+        }
+        for (what, before, after) in [
+            ("max alloc", previous.max_alloc, self.max_alloc),
+            ("global memory", previous.global_memory, self.global_memory),
+        ] {
+            if before != after {
+                let verb = if after > before { "grew" } else { "shrank" };
+                changes.push(
+                    format!("{} {} from {} to {}", what, verb, gigabytes(before), gigabytes(after))
+                );
+            }
+        }
+        if self.compute_units != previous.compute_units {
+            changes.push(
+                format!(
+                    "compute units changed from {} to {}",
+                    previous.compute_units,
+                    self.compute_units
+                )
+            );
+        }
+        for extension in &self.extensions {
+            if !previous.extensions.contains(extension) {
+                changes.push(format!("gained {}", extension));
+            }
+        }
+        for extension in &previous.extensions {
+            if !self.extensions.contains(extension) {
+                changes.push(format!("lost {}", extension));
+            }
+        }
+        changes
+    }
+}
+
+/// `bytes` in binary gigabytes, without a fraction when it is whole.
+fn gigabytes(bytes: u64) -> String {
+    let gb = (bytes as f64) / ((1u64 << 30) as f64);
+    if gb.fract() == 0.0 { format!("{} GB", gb) } else { format!("{:.2} GB", gb) }
+}
@@ -0,0 +1,60 @@
+//! The capabilities tab of the GUI.
+
+use crate::snapshots::DriverUpdate;
+use eframe::egui;
+use gputhroughput::capabilities::CAPABILITIES;
+use gputhroughput::MyDevice;
+
+/// What changed with each driver update since the last session, then a matrix of every
+/// capability against every device, followed by each device's full extension list.
+pub fn show(ui: &mut egui::Ui, devices: &[MyDevice], driver_updates: &[DriverUpdate]) {
+    ui.heading("Capabilities");
+    for update in driver_updates {
+        ui.group(|ui| {
+            ui.strong(format!("{}: driver updated since the last session", update.device));
+            for change in &update.changes {
+                ui.label(format!("• {}", change));
+            }
+        });
+    }
+    if devices.is_empty() {
+        ui.label("No OpenCL GPU devices were found.");
+        return;
+    }
+
+    egui::ScrollArea::both().show(ui, |ui| {
+        egui::Grid
+            ::new("capabilities")
+            .striped(true)
+            .show(ui, |ui| {
+                ui.strong("Capability");
+                for device in devices {
+                    ui.strong(device.name());
+                }
+                ui.strong("Enables");
+                ui.end_row();
+
+                for capability in CAPABILITIES {
+                    ui.label(capability.description)
+                        .on_hover_text(capability.extensions.join(", "));
+                    for device in devices {
+                        if capability.supported_by(device) {
+                            ui.colored_label(egui::Color32::GREEN, "✔");
+                        } else {
+                            ui.colored_label(ui.visuals().weak_text_color(), "✘");
+                        }
+                    }
+                    ui.label(capability.enables);
+                    ui.end_row();
+                }
+            });
+
+        for device in devices {
+            ui.collapsing(format!("All extensions of {}", device.name()), |ui| {
+                for extension in device.extensions() {
+                    ui.monospace(extension);
+                }
+            });
+        }
+    });
+}
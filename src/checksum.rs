@@ -0,0 +1,85 @@
+//! Verifies uploads on the device: a kernel checksums the buffer and only the checksum comes
+//! back, so upload-only runs can be verified without reading every byte back.
+//!
+//! The checksum is the wrapping sum of every 32-bit word multiplied by its one-based
+//! position, which also catches data that landed at the wrong offset.
+
+use crate::error::BenchError;
+use opencl3::command_queue::CommandQueue;
+use opencl3::context::Context;
+use opencl3::kernel::Kernel;
+use opencl3::memory::{ Buffer, ClMem, CL_MEM_WRITE_ONLY };
+use opencl3::program::Program;
+use opencl3::types::{ cl_uint, cl_ulong, CL_BLOCKING };
+use std::ptr;
+
+const SOURCE: &str =
+    "\
+__kernel void checksum(__global const uint *data, ulong count, __global uint *partial) {
+    size_t id = get_global_id(0);
+    size_t stride = get_global_size(0);
+    uint sum = 0;
+    for (ulong i = id; i < count; i += stride) {
+        sum += data[i] * (uint)(i + 1);
+    }
+    partial[id] = sum;
+}
+";
+
+/// Work-items, each summing a strided slice; their partial sums are what is read back.
+const WORK_ITEMS: usize = 16384;
+
+/// The checksum kernel, built for one context.
+pub struct Checksum {
+    kernel: Kernel,
+    partial: Buffer<cl_uint>,
+    // Kept alive for the kernel
+    _program: Program,
+}
+
+impl Checksum {
+    pub fn new(context: &Context) -> Result<Checksum, BenchError> {
+        let program = Program::create_and_build_from_source(context, SOURCE, "").map_err(|log| {
+            BenchError::Unsupported(format!("the checksum kernel failed to build: {}", log))
+        })?;
+        let kernel = Kernel::create(&program, "checksum")?;
+        let partial = unsafe {
+            Buffer::<cl_uint>::create(context, CL_MEM_WRITE_ONLY, WORK_ITEMS, ptr::null_mut())?
+        };
+        Ok(Checksum { kernel, partial, _program: program })
+    }
+
+    /// Checksums the first `count` f32 elements of `buffer` on the device.
+    pub fn of_buffer(
+        &self,
+        queue: &CommandQueue,
+        buffer: &Buffer<f32>,
+        count: usize
+    ) -> Result<u32, BenchError> {
+        let mut partial = vec![0; WORK_ITEMS];
+        unsafe {
+            self.kernel.set_arg(0, &buffer.get())?;
+            self.kernel.set_arg(1, &(count as cl_ulong))?;
+            self.kernel.set_arg(2, &self.partial.get())?;
+            queue.enqueue_nd_range_kernel(
+                self.kernel.get(),
+                1,
+                ptr::null(),
+                [WORK_ITEMS].as_ptr(),
+                ptr::null(),
+                &[]
+            )?;
+            queue.enqueue_read_buffer(&self.partial, CL_BLOCKING, 0, &mut partial, &[])?;
+        }
+        Ok(partial.iter().fold(0, |sum: u32, part| sum.wrapping_add(*part)))
+    }
+}
+
+/// The checksum of `data` computed on the host, to compare against `Checksum::of_buffer`.
+pub fn of_host(data: impl IntoIterator<Item = f32>) -> u32 {
+    data.into_iter()
+        .enumerate()
+        .fold(0, |sum: u32, (i, value)| {
+            sum.wrapping_add(value.to_bits().wrapping_mul((i as u32).wrapping_add(1)))
+        })
+}
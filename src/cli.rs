@@ -0,0 +1,1672 @@
+use crate::config::{ Config, DeviceRule, Storage };
+use crate::elevation::{ self, Privileged };
+use crate::locale::NumberFormat;
+use crate::plan::Plan;
+use crate::progress::{ CliProgress, MatrixProgress };
+use crate::table::{ self, Output, Table };
+use gputhroughput::api::{ self, BenchmarkRequest, MeasurementRecord };
+use gputhroughput::asymmetry::Asymmetry;
+use gputhroughput::bursts::{ self, SizeRange };
+use gputhroughput::coldstart::{ ColdStart, ColdWarm };
+#[cfg(feature = "community")]
+use gputhroughput::community::{ self, Submission };
+use gputhroughput::contention;
+use gputhroughput::ecc;
+use gputhroughput::error::{ BenchError, EXIT_USAGE };
+use gputhroughput::health::HealthScore;
+use gputhroughput::hooks::Hook;
+use gputhroughput::hybrid::Hybrid;
+use gputhroughput::inflight;
+use gputhroughput::latency;
+use gputhroughput::leaks::MemoryTrack;
+use gputhroughput::matrix::{ self, Matrix };
+use gputhroughput::memory::{ self, Memory };
+use gputhroughput::numa;
+use gputhroughput::offsets::{ self, Offsets };
+use gputhroughput::overhead;
+use gputhroughput::partition::Partition;
+use gputhroughput::patterns::Pattern;
+use gputhroughput::peer;
+use gputhroughput::precision::{ significant, Measurement };
+use gputhroughput::ramp;
+use gputhroughput::reproducibility::Conditions;
+use gputhroughput::roundtrip;
+use gputhroughput::scatter;
+use gputhroughput::simulate::{ self, Failure };
+use gputhroughput::statistics::Statistics;
+use gputhroughput::store::StoredResult;
+use gputhroughput::telemetry::{ self, LinkStatus, PciAddress, Sensors };
+use gputhroughput::theoretical::Maximums;
+use gputhroughput::trace;
+use gputhroughput::unified::UnifiedMemory;
+use gputhroughput::virtualization::Environment;
+use gputhroughput::{
+    elements_in,
+    enumerate_devices,
+    HostBuffer,
+    MeasureConfig,
+    MyDevice,
+    Pacing,
+    parse_size,
+    RunLength,
+    SizeUnits,
+    Throughput,
+    Verification,
+};
+use std::io;
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::time::Duration;
+
+pub const USAGE: &str =
+    "\
+Usage: gputhroughput [OPTIONS]
+       gputhroughput diff [--output <FORMAT>] [--fail-on-regression] <BEFORE> <AFTER>
+
+Without --headless the graphical interface is started, in builds that have one. Defaults
+for both are read from gputhroughput.toml in the working directory or the user's
+gputhroughput config directory; the options below override them.
+
+diff compares two result files, each as printed by --output json or a JSON result store
+whose latest result is taken: the change in throughput per direction, whether it exceeds
+the spread of the iterations, and the settings that differ. --output json prints the
+comparison as JSON; --fail-on-regression exits with 6 if either direction got
+significantly slower, e.g. to compare driver versions in CI.
+
+Options:
+  --headless               Run one measurement, print the results and exit
+  --dry-run                Print what a headless run would do and exit
+  --quiet                  No progress bar, for scripts; it is also left out when
+                           stderr is not a terminal
+  --output <FORMAT>        text: one line per figure in fixed units; table: the main
+                           figures aligned in a table, in readable units and the
+                           locale's number format; json: the result as one JSON
+                           object, as the result store keeps it; csv: a header and
+                           one row, with each direction in GB/s, Gbit/s and per
+                           PCIe lane [default: text]
+  --list-devices           Print the GPU devices found, by the index --device takes,
+                           and exit
+  --device <INDEX>         Index of the GPU device to measure [default: 0, or the
+                           [device] rule of the config file]
+  --size <SIZE>            Transfer size, e.g. 512MiB, 2g or 1.5GB; a plain number
+                           is in MB, or MiB with --units binary [default: 1024]
+  --units <UNITS>          Sizes and throughput in decimal MB and GB/s or binary
+                           MiB and GiB/s: decimal, binary [default: decimal]
+  --iterations <N>         Transfers per direction, results are averaged [default: 1]
+  --total <GB>             Instead of --iterations, repeat until this much data has
+                           moved in both directions together
+  --duration <SECONDS>     Instead of --iterations, repeat for this long
+  --host-buffer <MODE>     reuse one host allocation or allocate a fresh one per
+                           iteration: reuse, fresh [default: reuse]; fresh buffers
+                           are allocated and first touched inside the timed region
+  --memory <KIND>          Device allocation to transfer with: buffer, host-ptr
+                           (CL_MEM_USE_HOST_PTR over page-aligned memory, mapped),
+                           or on Intel usm-host, usm-device, usm-shared, or on AMD
+                           vram (host-visible VRAM written by the CPU through the
+                           BAR, mapped), or on GPUs sharing DRAM with the CPU such as
+                           Jetson boards unified (CL_MEM_ALLOC_HOST_PTR, mapped
+                           zero-copy) [default: buffer]
+  --verify <MODE>          readback: compare the data read back with what was written;
+                           checksum: checksum each upload on the device instead and
+                           skip the device-to-host transfers [default: readback]
+  --delay <MS>             Pause this long after every iteration [default: 0]
+  --max-temp <CELSIUS>     After the pause, also wait until the GPU is cooler than
+                           this; needs NVML or a hwmon temperature sensor
+  --gentle <PERCENT>       Transfer only this share of the time, idling after every
+                           transfer, so that a GPU driving the display keeps the
+                           desktop smooth; sustained throughput is reported as well
+  --warm-up <MS>           Keep the GPU busy with a kernel this long before measuring,
+                           so it leaves its idle clocks first [default: 0]
+  --discard <N>            Run N iterations before the measured ones and leave them
+                           out of the results [default: 0]
+  --threads <N>            Also compare N submitting host threads, each with its
+                           own queue, against a single thread
+  --thread-mapping         Linux: also compare submitting from the thread that allocated
+                           the host memory with another thread on its NUMA node and
+                           with threads on every other node
+  --patterns               Also move zeros, a constant, a ramp and random data and
+                           flag throughput that depends on the data, as it does when
+                           the link or driver compresses in transit
+  --latency                Also time single-float round trips one by one and report
+                           their jitter (p99 - p50), to reveal power-management stalls
+  --completion             Also time uploads waited on with flush and an event against
+                           finish, and explain a difference between the two
+  --ramp <MS>              Also double the transfer size from 4 KB until a transfer
+                           takes at least MS milliseconds, and report every size
+  --offsets <sweep|KB,..>  Also time 4 MB transfers at offsets into the device's largest
+                           buffer, every 3 MB or at these KB, for BAR window cliffs
+  --scatter <N>            Also upload --size split into N buffers as one batch and
+                           compare it with one buffer, for the cost per transfer
+  --inflight               Also predict from latency and bandwidth how many chunked
+                           uploads must be in flight to hide latency, and measure it
+  --contention             Also measure transfers while a kernel of rising memory
+                           intensity runs alongside, for the contention curve
+  --round-trip             Also time each upload followed straight away by its download,
+                           for the bandwidth a readback-modify workflow gets
+  --random-sizes <RANGE>   Also transfer sizes drawn at random from RANGE, e.g.
+                           4KiB..256MiB, with throughput by power-of-two size
+  --peer <INDEX>           Also copy directly between the device and this one, without
+                           staging in host memory; needs cl_amd_copy_buffer_p2p on both
+  --stream <FILE>          Also read FILE from disk while uploading it in --size
+                           chunks, for end-to-end streaming throughput
+  --dma-buf <HEAP>         Linux: also import a dma-buf allocated from this DMA heap,
+                           e.g. /dev/dma_heap/system, and measure uploads and device
+                           copies into it; needs cl_khr_external_memory_dma_buf
+  --overhead               Also time the harness's own enqueue and event wait costs and
+                           the timer resolution on the device's OpenCL platform, kept
+                           with the result to compare platforms net of them
+  --matrix-memory <KINDS>  Measure every combination of these comma-separated --memory
+                           kinds, --matrix-sizes and --matrix-queues instead of one
+                           run, and print a table and the best combination; a
+                           dimension left out takes the single run's value
+  --matrix-sizes <SIZES>   Transfer sizes of the matrix, each as for --size
+  --matrix-queues <N,..>   Queue counts of the matrix, each queue fed by its own thread
+                           [default: 1]
+  --plan <FILE>            Run the size, run length, memory, experiments and matrix
+                           saved in FILE; options after it override the plan
+  --save-plan <FILE>       Save the options given so far as a plan and exit, instead of
+                           running it; a relative FILE goes into the export directory
+  --reproduce <FILE>       Run the configuration of the result in FILE, as printed by
+                           --output json or the latest in a JSON result store, on a
+                           device of the same name, and warn about power settings and
+                           CPU affinity that differ from the run's; options after it
+                           override the result's
+  --link-gen <GEN>         Linux, as root: retrain the PCIe link to this generation
+                           for the run and restore it afterwards
+  --submit                 Share the results anonymously with the community database
+                           and print their percentile among the same GPU model's;
+                           needs [community] endpoint in the config file
+  --min-throughput <GB/S>  Fail if either direction is slower than this
+  --partition <MODE>       Split the device first: none, numa or equally:<CUs>
+                           [default: none]
+  --sub-device <INDEX>     Sub-device to measure when partitioning [default: 0]
+  --trace <FILE>           Write the device timestamps of every transfer as a Chrome
+                           trace, for Perfetto or chrome://tracing; a relative FILE
+                           goes into the config file's export directory
+  -h, --help               Print this help
+
+Exit codes:
+  0  Success
+  1  Unexpected OpenCL or runtime error
+  2  Invalid command-line usage
+  3  No GPU device found, or the device index is out of range
+  4  Device or host buffer allocation failed
+  5  Data read back from the device did not match what was written
+  6  Measured throughput is below --min-throughput
+  7  The device was reset during measurement and did not recover";
+
+pub struct Cli {
+    pub headless: bool,
+    pub dry_run: bool,
+    pub quiet: bool,
+    pub output: Output,
+    pub device: DeviceRule,
+    pub size: usize,
+    pub units: SizeUnits,
+    pub length: RunLength,
+    pub host_buffer: HostBuffer,
+    pub memory: Memory,
+    pub pacing: Pacing,
+    pub verification: Verification,
+    pub warm_up: Duration,
+    pub discard: usize,
+    pub threads: Option<usize>,
+    pub thread_mapping: bool,
+    pub patterns: bool,
+    pub latency: bool,
+    pub completion: bool,
+    /// Time per transfer to double the transfer size up to, see `ramp`.
+    pub ramp: Option<Duration>,
+    /// Offsets into the largest buffer to time transfers at, see `offsets`.
+    pub offsets: Option<Offsets>,
+    /// Small buffers to compare with one large one, see `scatter`.
+    pub scatter: Option<usize>,
+    pub inflight: bool,
+    pub contention: bool,
+    pub round_trip: bool,
+    /// Sizes to draw transfers from, see `bursts`.
+    pub random_sizes: Option<SizeRange>,
+    /// Index of the device to measure peer copies with.
+    pub peer: Option<usize>,
+    pub stream: Option<PathBuf>,
+    pub dma_buf: Option<PathBuf>,
+    /// Time the harness overhead, see `overhead`.
+    pub overhead: bool,
+    /// Measure this matrix instead of a single configuration, see `matrix`.
+    pub matrix: Option<Matrix>,
+    /// Save the options as a plan here instead of running them, see `plan`.
+    pub save_plan: Option<PathBuf>,
+    /// Conditions of the result being reproduced, to compare with, see `reproducibility`.
+    pub reproduce: Option<Conditions>,
+    pub link_gen: Option<u8>,
+    pub min_throughput: Option<f64>,
+    pub partition: Partition,
+    pub sub_device: usize,
+    pub trace: Option<PathBuf>,
+    pub sensors: Sensors,
+    /// The community database to submit the results to, see `community`.
+    pub submit_to: Option<String>,
+    /// Where the result is kept after the run, see `store`.
+    pub storage: Storage,
+    /// Run with the result after the run, see `hooks`.
+    pub hook: Option<Hook>,
+    /// Left out of `--help`, see `simulate`.
+    pub simulate_failure: Option<Failure>,
+}
+
+pub enum Command {
+    Run(Box<Cli>),
+    Diff(Diff),
+    Help,
+    ListDevices,
+}
+
+/// `gputhroughput diff`, see `diff`.
+pub struct Diff {
+    pub before: PathBuf,
+    pub after: PathBuf,
+    pub output: Output,
+    pub fail_on_regression: bool,
+}
+
+impl Diff {
+    /// Parses the arguments after `diff`.
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Command, String> {
+        let mut output = Output::Text;
+        let mut fail_on_regression = false;
+        let mut paths = Vec::new();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "-h" | "--help" => {
+                    return Ok(Command::Help);
+                }
+                "--output" => {
+                    output = parse_value(&arg, args.next())?;
+                }
+                "--fail-on-regression" => {
+                    fail_on_regression = true;
+                }
+                _ if arg.starts_with('-') => {
+                    return Err(format!("unexpected argument '{}' for diff", arg));
+                }
+                _ => paths.push(PathBuf::from(arg)),
+            }
+        }
+        if matches!(output, Output::Table | Output::Csv) {
+            return Err(format!("diff prints text or json, not {}", output));
+        }
+        let [before, after] = <[PathBuf; 2]>::try_from(paths).map_err(|_| {
+            "diff takes two result files, before and after".to_string()
+        })?;
+        Ok(Command::Diff(Diff { before, after, output, fail_on_regression }))
+    }
+}
+
+impl Cli {
+    /// Parses `args` over the defaults of `config`.
+    pub fn parse(
+        args: impl Iterator<Item = String>,
+        config: &Config
+    ) -> Result<Command, String> {
+        let mut args = args.peekable();
+        if args.next_if(|arg| arg == "diff").is_some() {
+            return Diff::parse(args);
+        }
+        let defaults = config.defaults;
+        let mut cli = Cli {
+            headless: !cfg!(feature = "gui"),
+            dry_run: false,
+            quiet: false,
+            output: Output::Text,
+            device: config.device.clone(),
+            size: defaults.size,
+            units: defaults.units,
+            length: defaults.length,
+            host_buffer: defaults.host_buffer,
+            memory: defaults.memory,
+            pacing: Pacing::default(),
+            verification: defaults.verification,
+            warm_up: defaults.warm_up,
+            discard: 0,
+            threads: None,
+            thread_mapping: false,
+            patterns: false,
+            latency: false,
+            completion: false,
+            ramp: None,
+            offsets: None,
+            scatter: None,
+            inflight: false,
+            contention: false,
+            round_trip: false,
+            random_sizes: None,
+            peer: None,
+            stream: None,
+            dma_buf: None,
+            overhead: false,
+            matrix: None,
+            save_plan: None,
+            reproduce: None,
+            link_gen: None,
+            min_throughput: None,
+            partition: Partition::None,
+            sub_device: 0,
+            trace: None,
+            sensors: config.sensors,
+            submit_to: None,
+            storage: config.storage.clone(),
+            hook: config.hook.clone(),
+            simulate_failure: None,
+        };
+        // Builds without the community feature ignore community.submit and refuse --submit
+        let mut submit = config.submit && cfg!(feature = "community");
+        let mut matrix_memories = None;
+        let mut matrix_sizes = None;
+        let mut matrix_queues = None;
+        let mut length_flag: Option<String> = None;
+        // Read once the loop is done, as a size like 2g depends on --units given after it
+        let mut size_arg: Option<String> = None;
+        let mut matrix_size_arg: Option<String> = None;
+        let mut random_sizes_arg: Option<String> = None;
+
+        while let Some(arg) = args.next() {
+            if matches!(arg.as_str(), "--iterations" | "--total" | "--duration") {
+                if let Some(previous) = length_flag.filter(|previous| *previous != arg) {
+                    return Err(format!("{} cannot be combined with {}", arg, previous));
+                }
+                length_flag = Some(arg.clone());
+            }
+            match arg.as_str() {
+                "-h" | "--help" => {
+                    return Ok(Command::Help);
+                }
+                "--list-devices" => {
+                    return Ok(Command::ListDevices);
+                }
+                "--headless" => {
+                    cli.headless = true;
+                }
+                "--quiet" => {
+                    cli.quiet = true;
+                }
+                "--output" => {
+                    cli.output = parse_value(&arg, args.next())?;
+                }
+                "--dry-run" => {
+                    cli.dry_run = true;
+                }
+                "--device" => {
+                    cli.device = DeviceRule::Index(parse_value(&arg, args.next())?);
+                }
+                "--size" => {
+                    size_arg = Some(parse_value(&arg, args.next())?);
+                }
+                "--units" => {
+                    cli.units = parse_value(&arg, args.next())?;
+                }
+                "--iterations" => {
+                    cli.length = RunLength::Iterations(parse_value(&arg, args.next())?);
+                }
+                "--total" => {
+                    let gigabytes: f64 = parse_value(&arg, args.next())?;
+                    cli.length = RunLength::TotalBytes((gigabytes * 1e9) as u64);
+                }
+                "--duration" => {
+                    let seconds: f64 = parse_value(&arg, args.next())?;
+                    cli.length = RunLength::Time(
+                        Duration::try_from_secs_f64(seconds).map_err(|e| e.to_string())?
+                    );
+                }
+                "--host-buffer" => {
+                    cli.host_buffer = parse_value(&arg, args.next())?;
+                }
+                "--memory" => {
+                    cli.memory = parse_value(&arg, args.next())?;
+                }
+                "--verify" => {
+                    cli.verification = parse_value(&arg, args.next())?;
+                }
+                "--delay" => {
+                    cli.pacing.delay = Duration::from_millis(parse_value(&arg, args.next())?);
+                }
+                "--max-temp" => {
+                    cli.pacing.max_temperature = Some(parse_value(&arg, args.next())?);
+                }
+                "--gentle" => {
+                    let percent: u32 = parse_value(&arg, args.next())?;
+                    if !(1..=99).contains(&percent) {
+                        return Err("--gentle must be a percentage from 1 to 99".to_string());
+                    }
+                    cli.pacing.duty = Some((percent as f64) / 100.0);
+                }
+                "--warm-up" => {
+                    cli.warm_up = Duration::from_millis(parse_value(&arg, args.next())?);
+                }
+                "--discard" => {
+                    cli.discard = parse_value(&arg, args.next())?;
+                }
+                "--threads" => {
+                    cli.threads = Some(parse_value(&arg, args.next())?);
+                }
+                "--thread-mapping" => {
+                    cli.thread_mapping = true;
+                }
+                "--patterns" => {
+                    cli.patterns = true;
+                }
+                "--latency" => {
+                    cli.latency = true;
+                }
+                "--completion" => {
+                    cli.completion = true;
+                }
+                "--offsets" => {
+                    cli.offsets = Some(parse_value(&arg, args.next())?);
+                }
+                "--ramp" => {
+                    let millis: u64 = parse_value(&arg, args.next())?;
+                    if millis == 0 {
+                        return Err("--ramp needs a time above 0 ms".to_string());
+                    }
+                    cli.ramp = Some(Duration::from_millis(millis));
+                }
+                "--scatter" => {
+                    let buffers: usize = parse_value(&arg, args.next())?;
+                    if !(1..=scatter::MAX_BUFFERS).contains(&buffers) {
+                        return Err(
+                            format!("--scatter must be from 1 to {} buffers", scatter::MAX_BUFFERS)
+                        );
+                    }
+                    cli.scatter = Some(buffers);
+                }
+                "--inflight" => {
+                    cli.inflight = true;
+                }
+                "--contention" => {
+                    cli.contention = true;
+                }
+                "--round-trip" => {
+                    cli.round_trip = true;
+                }
+                "--random-sizes" => {
+                    random_sizes_arg = Some(parse_value(&arg, args.next())?);
+                }
+                "--peer" => {
+                    cli.peer = Some(parse_value(&arg, args.next())?);
+                }
+                "--stream" => {
+                    cli.stream = Some(parse_value(&arg, args.next())?);
+                }
+                "--dma-buf" => {
+                    cli.dma_buf = Some(parse_value(&arg, args.next())?);
+                }
+                "--overhead" => {
+                    cli.overhead = true;
+                }
+                "--matrix-memory" => {
+                    matrix_memories = Some(parse_list(&arg, args.next())?);
+                }
+                "--matrix-sizes" => {
+                    matrix_size_arg = Some(parse_value(&arg, args.next())?);
+                }
+                "--matrix-queues" => {
+                    matrix_queues = Some(parse_list(&arg, args.next())?);
+                }
+                "--plan" | "--reproduce" => {
+                    let path: PathBuf = parse_value(&arg, args.next())?;
+                    let plan = if arg == "--plan" {
+                        Plan::load(&path)?
+                    } else {
+                        let result = StoredResult::load(&path)?;
+                        let plan = Plan::of_result(&result).map_err(|e| {
+                            format!("{}: {}", path.display(), e)
+                        })?;
+                        cli.device = DeviceRule::Name(result.device);
+                        cli.reproduce = result.reproducibility.map(|block| block.conditions);
+                        plan
+                    };
+                    cli.size = plan.size;
+                    size_arg = None;
+                    cli.units = plan.units;
+                    cli.length = plan.length;
+                    cli.host_buffer = plan.host_buffer;
+                    cli.memory = plan.memory;
+                    cli.verification = plan.verification;
+                    cli.warm_up = plan.warm_up;
+                    cli.discard = plan.discard;
+                    cli.pacing = plan.pacing;
+                    cli.threads = plan.threads;
+                    cli.thread_mapping = plan.thread_mapping;
+                    cli.patterns = plan.patterns;
+                    cli.latency = plan.latency;
+                    cli.completion = plan.completion;
+                    cli.ramp = plan.ramp;
+                    cli.offsets = plan.offsets;
+                    cli.scatter = plan.scatter;
+                    cli.inflight = plan.inflight;
+                    cli.contention = plan.contention;
+                    cli.round_trip = plan.round_trip;
+                    cli.random_sizes = plan.random_sizes;
+                    random_sizes_arg = None;
+                    if let Some(matrix) = plan.matrix {
+                        matrix_memories = Some(matrix.memories);
+                        matrix_sizes = Some(matrix.sizes);
+                        matrix_size_arg = None;
+                        matrix_queues = Some(matrix.queues);
+                    }
+                }
+                "--save-plan" => {
+                    cli.save_plan = Some(parse_value(&arg, args.next())?);
+                }
+                "--link-gen" => {
+                    cli.link_gen = Some(parse_value(&arg, args.next())?);
+                }
+                "--submit" => {
+                    if !cfg!(feature = "community") {
+                        return Err(
+                            "this build cannot submit results, see the community feature".into()
+                        );
+                    }
+                    submit = true;
+                }
+                "--min-throughput" => {
+                    cli.min_throughput = Some(parse_value(&arg, args.next())?);
+                }
+                "--partition" => {
+                    cli.partition = parse_value(&arg, args.next())?;
+                }
+                "--sub-device" => {
+                    cli.sub_device = parse_value(&arg, args.next())?;
+                }
+                "--trace" => {
+                    cli.trace = Some(parse_value(&arg, args.next())?);
+                }
+                "--simulate-failure" => {
+                    cli.simulate_failure = Some(parse_value(&arg, args.next())?);
+                }
+                _ => {
+                    return Err(format!("unexpected argument '{}'", arg));
+                }
+            }
+        }
+
+        if let Some(size) = size_arg {
+            cli.size = parse_size(&size, cli.units).map_err(|e| format!("--size: {}", e))?;
+        }
+        if let Some(sizes) = matrix_size_arg {
+            let sizes = sizes
+                .split(',')
+                .map(|size| parse_size(size.trim(), cli.units))
+                .collect::<Result<Vec<usize>, String>>()
+                .map_err(|e| format!("--matrix-sizes: {}", e))?;
+            matrix_sizes = Some(sizes);
+        }
+        if let Some(range) = random_sizes_arg {
+            cli.random_sizes = Some(
+                SizeRange::parse(&range, cli.units).map_err(|e| format!("--random-sizes: {}", e))?
+            );
+        }
+        if cli.size == 0 {
+            return Err(format!("--size must be at least 1 {}", cli.units.size_unit()));
+        }
+        match cli.length {
+            RunLength::Iterations(0) => {
+                return Err("--iterations must be at least 1".to_string());
+            }
+            RunLength::TotalBytes(0) => {
+                return Err("--total must be more than 0 GB".to_string());
+            }
+            RunLength::Time(limit) if limit.is_zero() => {
+                return Err("--duration must be more than 0 seconds".to_string());
+            }
+            RunLength::Continuous if cli.headless || cli.dry_run => {
+                return Err(
+                    "a continuous run length from the config file or plan needs the graphical \
+                     interface; pass --iterations, --total or --duration".to_string()
+                );
+            }
+            _ => {}
+        }
+        if cli.pacing.max_temperature.is_some_and(|limit: f64| !limit.is_finite()) {
+            return Err("--max-temp must be a number of degrees Celsius".to_string());
+        }
+        if cli.threads == Some(0) {
+            return Err("--threads must be at least 1".to_string());
+        }
+        elements_in(cli.size, cli.units).map_err(|e| e.to_string())?;
+        if matrix_memories.is_some() || matrix_sizes.is_some() || matrix_queues.is_some() {
+            let matrix = Matrix {
+                memories: matrix_memories.unwrap_or_else(|| vec![cli.memory]),
+                sizes: matrix_sizes.unwrap_or_else(|| vec![cli.size]),
+                queues: matrix_queues.unwrap_or_else(|| vec![1]),
+            };
+            matrix.validate(cli.units)?;
+            let single_run = [
+                ("--partition", cli.partition != Partition::None),
+                ("--link-gen", cli.link_gen.is_some()),
+                ("--trace", cli.trace.is_some()),
+                ("--threads", cli.threads.is_some()),
+                ("--thread-mapping", cli.thread_mapping),
+                ("--patterns", cli.patterns),
+                ("--latency", cli.latency),
+                ("--completion", cli.completion),
+                ("--ramp", cli.ramp.is_some()),
+                ("--offsets", cli.offsets.is_some()),
+                ("--scatter", cli.scatter.is_some()),
+                ("--inflight", cli.inflight),
+                ("--contention", cli.contention),
+                ("--round-trip", cli.round_trip),
+                ("--random-sizes", cli.random_sizes.is_some()),
+                ("--peer", cli.peer.is_some()),
+                ("--stream", cli.stream.is_some()),
+                ("--dma-buf", cli.dma_buf.is_some()),
+                ("--overhead", cli.overhead),
+                ("--submit", submit),
+                ("--min-throughput", cli.min_throughput.is_some()),
+                ("--output table", cli.output == Output::Table),
+                ("--output json", cli.output == Output::Json),
+                ("--output csv", cli.output == Output::Csv),
+            ];
+            if let Some((flag, _)) = single_run.iter().find(|(_, set)| *set) {
+                return Err(format!("{} applies to a single run, not to a matrix", flag));
+            }
+            cli.matrix = Some(matrix);
+        }
+        cli.trace = cli.trace.map(|path| config.export_path(&path));
+        cli.save_plan = cli.save_plan.map(|path| config.export_path(&path));
+        if submit {
+            cli.submit_to = Some(
+                config.endpoint
+                    .clone()
+                    .ok_or("--submit needs a [community] endpoint in gputhroughput.toml")?
+            );
+        }
+
+        Ok(Command::Run(Box::new(cli)))
+    }
+
+    /// The options as a plan another machine can run, see `plan`.
+    fn plan(&self) -> Plan {
+        Plan {
+            size: self.size,
+            units: self.units,
+            length: self.length,
+            host_buffer: self.host_buffer,
+            memory: self.memory,
+            verification: self.verification,
+            warm_up: self.warm_up,
+            discard: self.discard,
+            pacing: self.pacing,
+            threads: self.threads,
+            thread_mapping: self.thread_mapping,
+            patterns: self.patterns,
+            latency: self.latency,
+            completion: self.completion,
+            ramp: self.ramp,
+            offsets: self.offsets.clone(),
+            scatter: self.scatter,
+            inflight: self.inflight,
+            contention: self.contention,
+            round_trip: self.round_trip,
+            random_sizes: self.random_sizes,
+            matrix: self.matrix.clone(),
+        }
+    }
+
+    fn measure_config(&self) -> MeasureConfig {
+        MeasureConfig {
+            data_size: elements_in(self.size, self.units).expect("--size is checked when parsing"),
+            length: self.length,
+            host_buffer: self.host_buffer,
+            memory: self.memory,
+            pacing: self.pacing,
+            verification: self.verification,
+            warm_up: self.warm_up,
+            discard: self.discard,
+            sensors: self.sensors,
+            units: self.units,
+        }
+    }
+}
+
+fn parse_value<T: std::str::FromStr>(flag: &str, value: Option<String>) -> Result<T, String> {
+    let value = value.ok_or_else(|| format!("{} requires a value", flag))?;
+    value.parse().map_err(|_| format!("invalid value '{}' for {}", value, flag))
+}
+
+/// Parses a comma-separated list of values for `flag`.
+fn parse_list<T: std::str::FromStr>(flag: &str, value: Option<String>) -> Result<Vec<T>, String> {
+    let value = value.ok_or_else(|| format!("{} requires a value", flag))?;
+    value
+        .split(',')
+        .map(|item| {
+            item.trim().parse().map_err(|_| format!("invalid value '{}' for {}", item, flag))
+        })
+        .collect()
+}
+
+pub fn usage_error(msg: &str) -> ExitCode {
+    eprintln!("error: {}\n\nFor more information, try '--help'.", msg);
+    ExitCode::from(EXIT_USAGE)
+}
+
+/// Compares two result files and prints the comparison, failing with `BelowThreshold` on a
+/// significant regression when asked to.
+pub fn diff(diff: &Diff) -> Result<(), BenchError> {
+    let load = |path: &PathBuf| StoredResult::load(path).map_err(io::Error::other);
+    let comparison = gputhroughput::diff::compare(&load(&diff.before)?, &load(&diff.after)?);
+    match diff.output {
+        Output::Json => {
+            let json = serde_json::to_string_pretty(&comparison).map_err(io::Error::from)?;
+            println!("{}", json);
+        }
+        _ => {
+            for line in comparison.summary() {
+                println!("{}", line);
+            }
+        }
+    }
+    let worst = comparison.regressions().min_by(|a, b| a.relative.total_cmp(&b.relative));
+    match worst {
+        Some(regression) if diff.fail_on_regression =>
+            Err(BenchError::BelowThreshold {
+                measured: regression.after,
+                threshold: regression.before,
+            }),
+        _ => Ok(()),
+    }
+}
+
+/// Prints every device by the index `--device` and `--peer` take, the same the GUI shows.
+pub fn list_devices() {
+    let devices = enumerate_devices();
+    if devices.is_empty() {
+        println!("No OpenCL GPU devices were found");
+    }
+    for (index, device) in devices.iter().enumerate() {
+        println!(
+            "{:>3}  {} ({}, {:.1} GB, {})",
+            index,
+            device.name(),
+            device.vendor(),
+            (device.global_memory() as f64) / 1e9,
+            device.platform()
+        );
+    }
+}
+
+pub fn run(cli: &Cli) -> Result<(), BenchError> {
+    if let Some(ref path) = cli.save_plan {
+        cli.plan().save(path)?;
+        println!("Plan saved to {}", path.display());
+        return Ok(());
+    }
+    simulate::set(cli.simulate_failure);
+    let devices = enumerate_devices();
+    if devices.is_empty() {
+        return Err(BenchError::NoDevice("no OpenCL GPU devices were found".to_string()));
+    }
+    let index = cli.device.select(&devices).map_err(BenchError::NoDevice)?;
+    let device = &devices[index];
+    if let Some(ref conditions) = cli.reproduce {
+        for difference in conditions.differences(&Conditions::detect()) {
+            eprintln!("Warning: unlike the run being reproduced, {}", difference);
+        }
+    }
+    let peer = match cli.peer {
+        Some(peer) =>
+            Some(
+                devices
+                    .get(peer)
+                    .cloned()
+                    .ok_or_else(|| {
+                        BenchError::NoDevice(
+                            format!(
+                                "peer index {} is out of range ({} devices found)",
+                                peer,
+                                devices.len()
+                            )
+                        )
+                    })?
+            ),
+        None => None,
+    };
+
+    if cli.dry_run {
+        match cli.matrix {
+            Some(ref matrix) => print_matrix_plan(cli, matrix, index, device),
+            None => print_plan(cli, index, device, peer.as_ref()),
+        }
+        return Ok(());
+    }
+
+    let denied = elevation::denied(device);
+    if cli.link_gen.is_some() && denied.contains(&Privileged::LinkRetraining) {
+        return Err(
+            BenchError::Unsupported(
+                format!("retraining the link needs elevated privileges; {}", elevation::hint())
+            )
+        );
+    }
+    let sensors: Vec<String> = denied
+        .iter()
+        .filter(|privileged| privileged.is_sensor_in(cli.sensors))
+        .map(Privileged::to_string)
+        .collect();
+    if !sensors.is_empty() {
+        eprintln!(
+            "Warning: reading {} needs elevated privileges, so they stay empty; {}",
+            sensors.join(", "),
+            elevation::hint()
+        );
+    }
+
+    if let Some(ref matrix) = cli.matrix {
+        return run_matrix(cli, matrix, device);
+    }
+
+    let request = BenchmarkRequest {
+        device: device.clone(),
+        partition: cli.partition,
+        sub_device: cli.sub_device,
+        link_gen: cli.link_gen,
+        config: cli.measure_config(),
+        threads: cli.threads,
+        thread_mapping: cli.thread_mapping,
+        patterns: cli.patterns,
+        latency: cli.latency,
+        completion: cli.completion,
+        ramp: cli.ramp,
+        offsets: cli.offsets.clone(),
+        scatter: cli.scatter,
+        inflight: cli.inflight,
+        contention: cli.contention,
+        round_trip: cli.round_trip,
+        random_sizes: cli.random_sizes,
+        peer,
+        stream: cli.stream.clone(),
+        dma_buf: cli.dma_buf.clone(),
+        overhead: cli.overhead,
+    };
+    let record = if cli.quiet {
+        api::block_on(api::run_benchmark(request, ()))?
+    } else {
+        let progress = CliProgress::new(&request.config);
+        api::block_on(api::run_benchmark(request, progress))?
+    };
+    let config = record.config;
+    let throughput = &record.throughput;
+    if let Some(resets) = throughput.reset_summary() {
+        eprintln!("Warning: {}", resets);
+    }
+    if let Some(suspends) = throughput.suspend_summary() {
+        eprintln!("Warning: {}", suspends);
+    }
+    if let Some(ref track) = throughput.harness_memory {
+        for warning in track.warnings() {
+            eprintln!("Warning: {}", warning);
+        }
+    }
+    if let Some(ref path) = cli.trace {
+        if throughput.trace.is_empty() {
+            eprintln!("Warning: the driver reported no profiling timestamps, no trace written");
+        } else {
+            trace::write_chrome_trace(path, &throughput.trace)?;
+        }
+    }
+
+    let stored = StoredResult::new(device, &record);
+    match cli.output {
+        Output::Json => {
+            println!("{}", serde_json::to_string_pretty(&stored).map_err(io::Error::from)?);
+        }
+        Output::Csv => {
+            println!("{}", StoredResult::CSV_HEADER);
+            println!("{}", stored.csv_row());
+        }
+        Output::Text | Output::Table => print_record(cli, device, &record),
+    }
+
+    #[cfg(feature = "community")]
+    if let Some(ref endpoint) = cli.submit_to {
+        match community::submit(endpoint, &Submission::new(device, &record)) {
+            Ok(_) if cli.output.is_data() => {}
+            Ok(ranking) => {
+                for line in ranking.summary() {
+                    println!("{}", line);
+                }
+            }
+            Err(e) => eprintln!("Warning: could not submit the results: {}", e),
+        }
+    }
+    if let Some(mut store) = cli.storage.open() {
+        if let Err(e) = store.save(&stored) {
+            eprintln!("Warning: could not keep the results: {}", e);
+        }
+        if throughput.telemetry.ecc.is_some() && !cli.output.is_data() {
+            match store.recent(&device.settings_key(), ecc::COMPARED_RUNS) {
+                Ok(history) => {
+                    if let Some(comparison) = ecc::compare(&history, config.transfer_bytes()) {
+                        println!("{}", comparison.summary());
+                    }
+                }
+                Err(e) => eprintln!("Warning: could not read the stored results: {}", e),
+            }
+        }
+    }
+    if let Some(ref hook) = cli.hook {
+        if let Err(e) = hook.run(&stored) {
+            eprintln!("Warning: the after_run hook failed: {}", e);
+        }
+    }
+
+    if let Some(threshold) = cli.min_throughput {
+        let measured = throughput.slowest_throughput();
+        if measured < threshold {
+            return Err(BenchError::BelowThreshold { measured, threshold });
+        }
+    }
+
+    Ok(())
+}
+
+/// Prints the results of a single run, as text or a table.
+fn print_record(cli: &Cli, device: &MyDevice, record: &MeasurementRecord) {
+    let config = record.config;
+    let throughput = &record.throughput;
+    println!("Device: {}", record.device);
+    if let Some(annotation) = Environment::detect(device).annotation() {
+        println!("Environment: {}", annotation);
+    }
+    if let Some(hybrid) = Hybrid::detect(device) {
+        println!("{}", hybrid.annotation());
+        for hint in hybrid.hints(throughput) {
+            println!("Hint: {}", hint);
+        }
+    }
+    if let Some(unified) = UnifiedMemory::detect(device) {
+        println!("{}", unified.annotation());
+        for line in unified.interpretation(config.memory.key()) {
+            println!("Note: {}", line);
+        }
+    }
+    if cli.partition != Partition::None {
+        println!(
+            "Sub-device: {} of partition {} ({} compute units)",
+            cli.sub_device,
+            cli.partition,
+            record.compute_units
+        );
+    }
+    if let Some(generation) = cli.link_gen {
+        println!(
+            "Link retrained to PCIe gen {} ({})",
+            generation,
+            record.link_speed.as_deref().unwrap_or("speed unknown")
+        );
+    }
+    println!(
+        "Data Size: {} floats (~{} {})",
+        config.data_size,
+        cli.size,
+        config.units.size_unit()
+    );
+    println!(
+        "Iterations: {} ({}, host buffer: {}, memory: {})",
+        throughput.iterations(),
+        config.length,
+        config.host_buffer,
+        config.memory
+    );
+    if config.discard > 0 {
+        println!("Discarded: {} iterations before measuring", config.discard);
+    }
+    if config.pacing != Pacing::default() {
+        println!("Between iterations: {}", config.pacing);
+    }
+    if let Some(state) = throughput.start_state {
+        if !state.is_idle() {
+            println!("GPU at start: {}", state);
+        } else if config.warm_up.is_zero() {
+            println!(
+                "GPU at start: {}, still idle; the first iterations may read low, see --warm-up",
+                state
+            );
+        } else {
+            println!("GPU at start: {}, still idle after the warm-up", state);
+        }
+    }
+    if cli.output == Output::Table {
+        print_table(throughput, config.units);
+    } else {
+        let units = config.units;
+        println!(
+            "Host to Device Throughput: {} {} (Duration: {} s)",
+            throughput.h2d().scaled(units.rate_factor()),
+            units.rate_unit(),
+            significant(throughput.h2d_duration, 3)
+        );
+        if throughput.has_d2h() {
+            println!(
+                "Device to Host Throughput: {} {} (Duration: {} s)",
+                throughput.d2h().scaled(units.rate_factor()),
+                units.rate_unit(),
+                significant(throughput.d2h_duration, 3)
+            );
+        } else {
+            println!("Device to Host Throughput: skipped, uploads verified by checksum");
+        }
+        if let Some(statistics) = throughput.h2d_statistics() {
+            println!("H2D per iteration: {}", statistics.summary(units));
+        }
+        if let Some(statistics) = throughput.d2h_statistics() {
+            println!("D2H per iteration: {}", statistics.summary(units));
+        }
+        if let Some(cold_start) = ColdStart::of(throughput) {
+            for line in cold_start.summary() {
+                println!("{}", line);
+            }
+        }
+        if let Some(link) = throughput.telemetry.link {
+            println!("Driver-reported peak: {:.2} GB/s H2D, {:.2} GB/s D2H", link.rx, link.tx);
+        }
+        if let Some(watts) = throughput.telemetry.power {
+            println!(
+                "Efficiency: {:.3} GB/s/W H2D, {:.3} GB/s/W D2H (avg {:.1} W)",
+                throughput.h2d_throughput / watts,
+                throughput.d2h_throughput / watts,
+                watts
+            );
+        }
+    }
+    if let Some(cpu) = throughput.telemetry.cpu {
+        println!("Host CPU: {}", cpu);
+    }
+    if let Some(paging) = throughput.telemetry.paging {
+        for line in paging.summary() {
+            println!("{}", line);
+        }
+    }
+    if let Some(enabled) = throughput.telemetry.ecc {
+        println!("ECC: {}", if enabled { "on" } else { "off" });
+    }
+    if let Some(vendor) = throughput.telemetry.vendor {
+        println!("{}", vendor.summary());
+    }
+    if let Some(summary) = throughput.harness_memory.as_ref().and_then(MemoryTrack::summary) {
+        println!("{}", summary);
+    }
+    if let Some(duty) = throughput.duty_cycle {
+        print!(
+            "Gentle mode: transferring {:.0}% of the time, sustained {:.2} GB/s H2D",
+            duty * 100.0,
+            throughput.h2d_throughput * duty
+        );
+        if throughput.has_d2h() {
+            print!(", {:.2} GB/s D2H", throughput.d2h_throughput * duty);
+        }
+        println!();
+    }
+    let maximums = Maximums::of(device.get_device());
+    if !maximums.is_empty() {
+        println!("Theoretical maximum:");
+        for line in maximums.summary(throughput) {
+            println!("  {}", line);
+        }
+    }
+    let link = PciAddress::of(device.get_device()).and_then(LinkStatus::current);
+    if let Some(health) = HealthScore::of(throughput, link) {
+        let mut lines = health.summary().into_iter();
+        println!();
+        println!("{}", lines.next().unwrap_or_default());
+        for line in lines {
+            println!("  {}", line);
+        }
+    }
+    if let Some(asymmetry) = Asymmetry::of(throughput, link) {
+        let mut lines = asymmetry.summary().into_iter();
+        println!("{}", lines.next().unwrap_or_default());
+        for line in lines {
+            println!("  {}", line);
+        }
+    }
+
+    if let Some(ref scaling) = record.scaling {
+        println!("Submission from {} threads:", scaling.threads);
+        for line in scaling.summary() {
+            println!("  {}", line);
+        }
+    }
+    if let Some(ref mapping) = record.mapping {
+        println!("Thread to NUMA node mapping:");
+        for line in mapping.summary() {
+            println!("  {}", line);
+        }
+    }
+    if let Some(ref patterns) = record.patterns {
+        println!("Data patterns:");
+        for line in patterns.summary() {
+            println!("  {}", line);
+        }
+    }
+    if let Some(ref latency) = record.latency {
+        println!("Latency:");
+        for line in latency.summary() {
+            println!("  {}", line);
+        }
+    }
+    if let Some(completion) = record.completion {
+        println!("Flush vs finish:");
+        for line in completion.summary() {
+            println!("  {}", line);
+        }
+    }
+    if let Some(ref ramp) = record.ramp {
+        println!("Size ramp:");
+        for line in ramp.summary() {
+            println!("  {}", line);
+        }
+    }
+    if let Some(ref offsets) = record.offsets {
+        println!("Copy offsets:");
+        for line in offsets.summary() {
+            println!("  {}", line);
+        }
+    }
+    if let Some(scatter) = record.scatter {
+        println!("Batched small uploads:");
+        for line in scatter.summary() {
+            println!("  {}", line);
+        }
+    }
+    if let Some(ref inflight) = record.inflight {
+        println!("In-flight depth:");
+        for line in inflight.summary() {
+            println!("  {}", line);
+        }
+    }
+    if let Some(ref contention) = record.contention {
+        println!("Contention curve:");
+        for line in contention.summary() {
+            println!("  {}", line);
+        }
+    }
+    if let Some(ref round_trip) = record.round_trip {
+        println!("Round trip:");
+        for line in round_trip.summary() {
+            println!("  {}", line);
+        }
+    }
+    if let Some(ref random_sizes) = record.random_sizes {
+        println!("Random sizes:");
+        for line in random_sizes.summary() {
+            println!("  {}", line);
+        }
+    }
+    if let Some(peer) = record.peer {
+        println!("Peer copies: {}", peer.summary());
+    }
+    if let (Some(path), Some(streaming)) = (&cli.stream, record.streaming) {
+        println!("Streaming from {}: {}", path.display(), streaming.summary());
+    }
+    if let Some(dma_buf) = record.dma_buf {
+        println!("dma-buf: {}", dma_buf.summary());
+    }
+    if let Some(ref overhead) = record.overhead {
+        println!("Harness overhead:");
+        for line in overhead.summary() {
+            println!("  {}", line);
+        }
+        let bytes = record.config.transfer_bytes();
+        let throughput = &record.throughput;
+        println!(
+            "  Net of it: {:.2} GB/s H2D",
+            overhead.normalize(throughput.h2d_throughput, bytes)
+        );
+        if throughput.has_d2h() {
+            println!(
+                "  Net of it: {:.2} GB/s D2H",
+                overhead.normalize(throughput.d2h_throughput, bytes)
+            );
+        }
+    }
+}
+
+/// Measures `matrix` in place of a single run and prints the pivoted results.
+fn run_matrix(cli: &Cli, matrix: &Matrix, device: &MyDevice) -> Result<(), BenchError> {
+    let config = cli.measure_config();
+    let cells = matrix.cells(cli.units).len();
+    let progress = MatrixProgress::new(cells, cli.quiet);
+    let result = matrix::run_matrix(matrix, &config, device, &mut |cell| {
+        progress.advance(cell.cell.to_string());
+    });
+    progress.finish();
+
+    println!("Device: {}", device.name());
+    println!("Matrix: {} combinations, {} each", cells, config.length);
+    for line in result.table() {
+        println!("{}", line);
+    }
+    for cell in &result.cells {
+        if let Err(ref reason) = cell.outcome {
+            println!("Failed: {}: {}", cell.cell, reason);
+        }
+    }
+    let Some(best) = result.best() else {
+        return Err(BenchError::Unsupported("every combination of the matrix failed".into()));
+    };
+    let (h2d, d2h) = best.outcome.clone().unwrap_or_default();
+    let unit = cli.units.rate_unit();
+    println!(
+        "Best: {} ({:.2} {} H2D, {:.2} {} D2H)",
+        best.cell,
+        cli.units.rate(h2d),
+        unit,
+        cli.units.rate(d2h),
+        unit
+    );
+    Ok(())
+}
+
+/// The throughput of both directions and what was read alongside it, as a table.
+fn print_table(throughput: &Throughput, units: SizeUnits) {
+    let numbers = NumberFormat::System;
+    let measured = throughput.has_d2h();
+    let skipped = || "skipped".to_string();
+    let mut results = Table::new(&["", "Host to device", "Device to host"]);
+    results.row(
+        vec![
+            "Throughput".to_string(),
+            table::throughput(throughput.h2d(), units, numbers),
+            if measured {
+                table::throughput(throughput.d2h(), units, numbers)
+            } else {
+                skipped()
+            }
+        ]
+    );
+    results.row(
+        vec![
+            "Duration".to_string(),
+            table::duration(throughput.h2d_duration, numbers),
+            if measured { table::duration(throughput.d2h_duration, numbers) } else { skipped() }
+        ]
+    );
+    if let Some(h2d) = throughput.h2d_statistics() {
+        let d2h = throughput.d2h_statistics();
+        let figure = |value: f64| table::throughput(Measurement::exact(value), units, numbers);
+        let labels = [
+            "Mean per iteration",
+            "Median per iteration",
+            "Slowest iteration",
+            "Fastest iteration",
+            "Standard deviation",
+        ];
+        let values = |statistics: &Statistics| {
+            [statistics.mean, statistics.median, statistics.min, statistics.max, statistics.std_dev]
+        };
+        let d2h_values = d2h.as_ref().map(values);
+        for (row, label) in labels.into_iter().enumerate() {
+            results.row(
+                vec![
+                    label.to_string(),
+                    figure(values(&h2d)[row]),
+                    d2h_values.map_or_else(skipped, |d2h| figure(d2h[row]))
+                ]
+            );
+        }
+        let spread = |statistics: &Statistics| {
+            format!("{}%", numbers.float(statistics.relative_spread() * 100.0, 1))
+        };
+        results.row(
+            vec![
+                "Relative spread".to_string(),
+                spread(&h2d),
+                d2h.as_ref().map_or_else(skipped, spread)
+            ]
+        );
+    }
+    if let Some(cold_start) = ColdStart::of(throughput) {
+        let figure = |value: f64| table::throughput(Measurement::exact(value), units, numbers);
+        let penalty = |figures: &ColdWarm| {
+            format!("{}%", numbers.float(figures.penalty() * 100.0, 0))
+        };
+        let d2h = cold_start.d2h.as_ref();
+        results.row(
+            vec![
+                "First transfer (cold)".to_string(),
+                figure(cold_start.h2d.cold),
+                d2h.map_or_else(skipped, |d2h| figure(d2h.cold))
+            ]
+        );
+        results.row(
+            vec![
+                "After it (warm)".to_string(),
+                figure(cold_start.h2d.warm),
+                d2h.map_or_else(skipped, |d2h| figure(d2h.warm))
+            ]
+        );
+        results.row(
+            vec![
+                "Cold slower by".to_string(),
+                penalty(&cold_start.h2d),
+                d2h.map_or_else(skipped, penalty)
+            ]
+        );
+    }
+    if let Some(link) = throughput.telemetry.link {
+        results.row(
+            vec![
+                "Driver-reported peak".to_string(),
+                table::throughput(Measurement::exact(link.rx), units, numbers),
+                table::throughput(Measurement::exact(link.tx), units, numbers)
+            ]
+        );
+    }
+    if let Some(watts) = throughput.telemetry.power {
+        let efficiency = |gbps: f64| format!("{} GB/s/W", numbers.float(gbps / watts, 3));
+        results.row(
+            vec![
+                format!("Efficiency at {} W", numbers.float(watts, 1)),
+                efficiency(throughput.h2d_throughput),
+                if measured { efficiency(throughput.d2h_throughput) } else { skipped() }
+            ]
+        );
+    }
+    for line in results.render() {
+        println!("{}", line);
+    }
+}
+
+/// Describes the matrix `cli` asks for without creating a context or touching the device.
+fn print_matrix_plan(cli: &Cli, matrix: &Matrix, index: usize, device: &MyDevice) {
+    let config = cli.measure_config();
+    let iterations = config.length.fixed_iterations() as u64;
+    let cells = matrix.cells(cli.units);
+    // A pass each way per combination
+    let bytes: u64 = cells
+        .iter()
+        .map(|cell| ((cell.size * cli.units.megabyte()) as u64) * 2 * iterations)
+        .sum();
+
+    println!("Dry run, nothing will be transferred.");
+    println!("Device: [{}] {}", index, device.name());
+    let list = |values: Vec<String>| values.join(", ");
+    println!(
+        "Matrix: {} combinations of {} x {} {} x {} queues",
+        cells.len(),
+        list(matrix.memories.iter().map(Memory::to_string).collect()),
+        list(matrix.sizes.iter().map(usize::to_string).collect()),
+        cli.units.size_unit(),
+        list(matrix.queues.iter().map(usize::to_string).collect())
+    );
+    for cell in cells.iter().filter(|cell| !cell.memory.supported_by(device)) {
+        println!("Unsupported, will fail: {}", cell);
+    }
+    println!("Per combination: {} iterations each way, data not verified", iterations);
+    println!("Total transferred: {:.2} GB", (bytes as f64) / 1e9);
+}
+
+/// Describes the run `cli` asks for without creating a context or touching the device.
+fn print_plan(cli: &Cli, index: usize, device: &MyDevice, peer: Option<&MyDevice>) {
+    let config = cli.measure_config();
+    let transfer_bytes = config.transfer_bytes();
+    // Single- and multi-thread passes per direction when scaling
+    let scaling_bytes = match cli.threads {
+        Some(_) => transfer_bytes * 4 * (config.length.fixed_iterations() as u64),
+        None => 0,
+    };
+    // A pass each way for every pairing
+    let pairings = if cli.thread_mapping { numa::pairings(&numa::nodes()).len() } else { 0 };
+    let mapping_bytes = transfer_bytes * 2 * (pairings * config.length.fixed_iterations()) as u64;
+    // A pass each way for every pattern
+    let patterns = if cli.patterns { Pattern::ALL.len() } else { 0 };
+    let pattern_bytes = transfer_bytes * 2 * (patterns * config.length.fixed_iterations()) as u64;
+    let stream_bytes = cli.stream
+        .as_ref()
+        .and_then(|path| std::fs::metadata(path).ok())
+        .map_or(0, |metadata| metadata.len());
+    // A copy each way
+    let peer_bytes = match peer {
+        Some(_) => transfer_bytes * 2 * (config.length.fixed_iterations() as u64),
+        None => 0,
+    };
+    // Uploads and device copies into the dma-buf
+    let dma_buf_bytes = match cli.dma_buf {
+        Some(_) => transfer_bytes * 2 * (config.length.fixed_iterations() as u64),
+        None => 0,
+    };
+    let extra_bytes =
+        scaling_bytes + mapping_bytes + pattern_bytes + peer_bytes + stream_bytes + dma_buf_bytes;
+    let link = PciAddress::of(device.get_device()).and_then(LinkStatus::current);
+    // Gentle mode idles in proportion to the transfers of the main measurement
+    let duty = config.pacing.duty.unwrap_or(1.0);
+
+    println!("Dry run, nothing will be transferred.");
+    println!("Device: [{}] {}", index, device.name());
+    if cli.partition != Partition::None {
+        println!("Partition: {}, sub-device {}", cli.partition, cli.sub_device);
+    }
+    if let Some(generation) = cli.link_gen {
+        println!("Link: retrained to PCIe gen {} for the run", generation);
+    }
+    let denied: Vec<String> = elevation
+        ::denied(device)
+        .iter()
+        .filter(|privileged| {
+            privileged.is_sensor_in(cli.sensors) ||
+                (**privileged == Privileged::LinkRetraining && cli.link_gen.is_some())
+        })
+        .map(Privileged::to_string)
+        .collect();
+    if !denied.is_empty() {
+        println!("Needs elevated privileges: {} ({})", denied.join(", "), elevation::hint());
+    }
+    println!(
+        "Transfer size: {} floats ({} bytes, ~{} {})",
+        config.data_size,
+        transfer_bytes,
+        cli.size,
+        cli.units.size_unit()
+    );
+    println!("Run length: {} (host buffer: {})", config.length, config.host_buffer);
+    println!("Verification: {}", config.verification);
+    match config.memory {
+        Memory::Buffer => println!("Device memory: buffer, CL_MEM_READ_WRITE, blocking transfers"),
+        Memory::HostPtr =>
+            println!(
+                "Device memory: CL_MEM_USE_HOST_PTR over {}-byte aligned host memory, \
+                 blocking map and unmap",
+                memory::PAGE_SIZE
+            ),
+        Memory::Vram =>
+            println!(
+                "Device memory: CL_MEM_USE_PERSISTENT_MEM_AMD, CPU writes into mapped VRAM"
+            ),
+        Memory::Unified =>
+            println!("Device memory: CL_MEM_ALLOC_HOST_PTR, mapped zero-copy in shared DRAM"),
+        memory => println!("Device memory: {}, blocking clEnqueueMemcpyINTEL", memory),
+    }
+    if config.pacing != Pacing::default() {
+        println!("Between iterations: {}", config.pacing);
+    }
+    let drives_display = PciAddress::of(device.get_device()).is_some_and(telemetry::drives_display);
+    if drives_display && config.pacing.duty.is_none() {
+        println!("Display: this GPU drives the display; --gentle keeps the desktop smooth");
+    }
+    if let Some(annotation) = Environment::detect(device).annotation() {
+        println!("Environment: {}", annotation);
+    }
+    if let Some(hybrid) = Hybrid::detect(device) {
+        println!("{}", hybrid.annotation());
+    }
+    if let Some(unified) = UnifiedMemory::detect(device) {
+        println!("{}", unified.annotation());
+    }
+    if !config.warm_up.is_zero() {
+        println!("Warm-up: {} ms of kernel work before measuring", config.warm_up.as_millis());
+    }
+    if config.discard > 0 {
+        println!("Discarded: {} iterations before measuring", config.discard);
+    }
+    if let Some(state) = PciAddress::of(device.get_device()).and_then(telemetry::gpu_state) {
+        println!("GPU now: {}{}", state, if state.is_idle() { ", idle" } else { "" });
+    }
+    if let Some(threads) = cli.threads {
+        println!("Thread scaling: 1 vs {} submitting threads", threads);
+    }
+    if cli.thread_mapping {
+        match pairings {
+            0 => println!("Thread mapping: unavailable, the NUMA topology could not be read"),
+            pairings => println!("Thread mapping: {} allocating and submitting pairings", pairings),
+        }
+    }
+    if cli.patterns {
+        let names: Vec<String> = Pattern::ALL.iter().map(Pattern::to_string).collect();
+        println!("Data patterns: {}", names.join(", "));
+    }
+    if cli.latency {
+        println!("Latency: {} single-float round trips", latency::ROUND_TRIPS);
+    }
+    if cli.completion {
+        println!(
+            "Flush vs finish: {} uploads each, waited on with flush and an event or finish",
+            config.length.fixed_iterations()
+        );
+    }
+    if let Some(budget) = cli.ramp {
+        println!(
+            "Size ramp: doubling from {} until a transfer takes {} ms, {} transfers each way \
+             per size",
+            ramp::size_label(ramp::START_BYTES),
+            budget.as_millis(),
+            ramp::SAMPLES_PER_SIZE
+        );
+    }
+    match cli.offsets {
+        Some(Offsets::Sweep) => println!(
+            "Copy offsets: {} transfers every {} through the largest buffer, {} each way per \
+             offset",
+            ramp::size_label(offsets::CHUNK_BYTES),
+            ramp::size_label(offsets::SWEEP_STEP),
+            offsets::SAMPLES_PER_OFFSET
+        ),
+        Some(Offsets::At(ref at)) => {
+            let labels: Vec<String> = at.iter().map(|&at| offsets::offset_label(at)).collect();
+            println!(
+                "Copy offsets: {} transfers at {} into the largest buffer, {} each way per offset",
+                ramp::size_label(offsets::CHUNK_BYTES),
+                labels.join(", "),
+                offsets::SAMPLES_PER_OFFSET
+            );
+        }
+        None => {}
+    }
+    if let Some(buffers) = cli.scatter {
+        println!(
+            "Batched small uploads: {} buffers of {} bytes against one, {} iterations each",
+            buffers,
+            (config.transfer_bytes() as usize) / buffers,
+            config.length.fixed_iterations()
+        );
+    }
+    if cli.inflight {
+        println!(
+            "In-flight depth: {} KB chunks, 1 to {} in flight, {} uploads each",
+            inflight::CHUNK_BYTES / 1024,
+            inflight::MAX_DEPTH,
+            config.length.fixed_iterations()
+        );
+    }
+    if cli.contention {
+        println!(
+            "Contention curve: without a kernel, then {} steps of memory intensity, {} iterations \
+             each",
+            contention::SHARE_STEPS + 1,
+            config.length.fixed_iterations().max(1)
+        );
+    }
+    if cli.round_trip {
+        println!(
+            "Round trip: {} uploads each followed by its download, then each way alone",
+            config.length.fixed_iterations().max(roundtrip::MIN_SAMPLES)
+        );
+    }
+    if let Some(range) = cli.random_sizes {
+        println!(
+            "Random sizes: {} transfers each way from {} to {}",
+            config.length.fixed_iterations().max(bursts::MIN_TRANSFERS),
+            ramp::size_label(range.min),
+            ramp::size_label(range.max)
+        );
+    }
+    if let Some(peer) = peer {
+        match peer::peer_access(device, peer) {
+            Ok(()) => println!("Peer copies: to and from {}", peer.name()),
+            Err(reason) => println!("Peer copies: unavailable, {}", reason),
+        }
+    }
+    if let Some(ref path) = cli.stream {
+        println!(
+            "Streaming: {} ({:.0} {unit}) from disk in {} {unit} chunks",
+            path.display(),
+            cli.units.size(stream_bytes),
+            cli.size,
+            unit = cli.units.size_unit()
+        );
+    }
+    if let Some(ref heap) = cli.dma_buf {
+        println!(
+            "dma-buf: {} {} allocated from {}",
+            cli.size,
+            cli.units.size_unit(),
+            heap.display()
+        );
+    }
+    if cli.overhead {
+        println!(
+            "Harness overhead: {} enqueues and event waits of one float",
+            overhead::PROBES
+        );
+    }
+    if let Some(ref endpoint) = cli.submit_to {
+        println!("Results: submitted anonymously to {}", endpoint);
+    }
+    if let Some(ref hook) = cli.hook {
+        println!("After the run: {} with the result as JSON", hook.program);
+    }
+
+    let Some(main_bytes) = config.length.planned_bytes(config.iteration_bytes()) else {
+        let RunLength::Time(limit) = config.length else {
+            println!("Total transferred: unbounded, the run lasts until stopped");
+            return;
+        };
+        println!(
+            "Total transferred: depends on throughput, the run lasts {:.1} s",
+            limit.as_secs_f64()
+        );
+        if let Some(link) = link {
+            println!(
+                "At most {:.2} GB at the link maximum of {:.2} GB/s",
+                (limit.as_secs_f64() * link.bandwidth() * duty + (extra_bytes as f64) / 1e9),
+                link.bandwidth()
+            );
+        }
+        return;
+    };
+
+    let total_bytes = ((main_bytes + extra_bytes) as f64) / 1e9;
+    println!("Total transferred: {:.2} GB", total_bytes);
+    let busy_bytes = ((main_bytes as f64) / duty + (extra_bytes as f64)) / 1e9;
+    match link {
+        Some(link) =>
+            println!(
+                "Estimated time: at least {:.1} s at the link maximum of {:.2} GB/s ({} GT/s x{})",
+                busy_bytes / link.bandwidth(),
+                link.bandwidth(),
+                link.speed,
+                link.width
+            ),
+        None => println!("Estimated time: unknown, the link speed could not be read"),
+    }
+}
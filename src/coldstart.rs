@@ -0,0 +1,111 @@
+//! The first transfer of a run against the ones after it. The first pays for pinning the host
+//! buffer, mapping it for the device and bringing the link and the GPU out of their idle power
+//! states, which an application transferring from idle pays as well, so it is reported as the
+//! cold figure beside the warm mean rather than only averaged in. Runs that discarded their
+//! leading iterations, or soak runs that trimmed them, have no cold transfer among their samples
+//! and are not reported.
+
+use crate::Throughput;
+
+/// One direction's first transfer and the mean of the rest, in GB/s.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ColdWarm {
+    pub cold: f64,
+    /// Over the transfers after the first, weighted by time as the run's mean is.
+    pub warm: f64,
+}
+
+impl ColdWarm {
+    /// `None` unless there is at least one transfer after the first.
+    fn of(samples: &[f64]) -> Option<ColdWarm> {
+        let (&cold, rest) = samples.split_first()?;
+        if rest.is_empty() {
+            return None;
+        }
+        let seconds_per_gb: f64 = rest
+            .iter()
+            .map(|sample| 1.0 / sample)
+            .sum();
+        Some(ColdWarm { cold, warm: (rest.len() as f64) / seconds_per_gb })
+    }
+
+    /// How much slower the first transfer was than the warm mean, as a fraction of it.
+    pub fn penalty(&self) -> f64 {
+        1.0 - self.cold / self.warm
+    }
+}
+
+/// Cold and warm throughput of a run, see `ColdStart::of`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ColdStart {
+    pub h2d: ColdWarm,
+    /// Left out when D2H was not measured.
+    pub d2h: Option<ColdWarm>,
+}
+
+impl ColdStart {
+    /// `None` for runs of a single iteration, which have no warm transfers to compare with,
+    /// and for runs that discarded or trimmed iterations, whose first sample is already warm.
+    pub fn of(throughput: &Throughput) -> Option<ColdStart> {
+        if throughput.discarded > 0 || throughput.trimmed > 0 {
+            return None;
+        }
+        Some(ColdStart {
+            h2d: ColdWarm::of(&throughput.h2d_samples)?,
+            d2h: ColdWarm::of(&throughput.d2h_samples),
+        })
+    }
+
+    /// One line per direction, e.g. "H2D cold: 9.81 GB/s, warm: 24.60 GB/s, first transfer
+    /// 60% slower".
+    pub fn summary(&self) -> Vec<String> {
+        let line = |direction: &str, figures: &ColdWarm| {
+            let penalty = figures.penalty();
+            format!(
+                "{} cold: {:.2} GB/s, warm: {:.2} GB/s, first transfer {:.0}% {}",
+                direction,
+                figures.cold,
+                figures.warm,
+                penalty.abs() * 100.0,
+                if penalty >= 0.0 { "slower" } else { "faster" }
+            )
+        };
+        let mut lines = vec![line("H2D", &self.h2d)];
+        lines.extend(self.d2h.iter().map(|d2h| line("D2H", d2h)));
+        lines
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn throughput(h2d: &[f64], d2h: &[f64], discarded: usize) -> Throughput {
+        Throughput {
+            h2d_samples: h2d.to_vec(),
+            d2h_samples: d2h.to_vec(),
+            discarded,
+            ..Throughput::new()
+        }
+    }
+
+    #[test]
+    fn compares_the_first_transfer_with_the_rest() {
+        let cold_start = ColdStart::of(&throughput(&[5.0, 10.0, 10.0], &[4.0, 8.0], 0)).unwrap();
+        assert_eq!(cold_start.h2d, ColdWarm { cold: 5.0, warm: 10.0 });
+        assert_eq!(cold_start.d2h, Some(ColdWarm { cold: 4.0, warm: 8.0 }));
+        assert!((cold_start.h2d.penalty() - 0.5).abs() < 1e-12);
+    }
+
+    #[test]
+    fn needs_a_warm_transfer() {
+        assert_eq!(ColdStart::of(&throughput(&[5.0], &[4.0], 0)), None);
+        let upload_only = ColdStart::of(&throughput(&[5.0, 10.0], &[], 0)).unwrap();
+        assert_eq!(upload_only.d2h, None);
+    }
+
+    #[test]
+    fn skipped_when_iterations_were_discarded() {
+        assert_eq!(ColdStart::of(&throughput(&[10.0, 10.0, 10.0], &[8.0, 8.0], 2)), None);
+    }
+}
@@ -0,0 +1,155 @@
+//! Opt-in sharing of results with a community database, which answers with where they rank
+//! among the results submitted for the same GPU model, so that users can tell whether their
+//! numbers are normal.
+//!
+//! Nothing is sent unless the user opts in. A submission is anonymous: it holds the device
+//! model, driver version, PCIe link, configuration and measured throughput, but no host
+//! name, serial number, PCI address or file path.
+
+use crate::api::MeasurementRecord;
+use crate::hybrid::{ Display, Hybrid };
+use crate::telemetry::{ LinkStatus, PciAddress };
+use crate::virtualization::Environment;
+use crate::{ HostBuffer, MyDevice, Verification };
+use serde::{ Deserialize, Serialize };
+use std::time::Duration;
+
+/// How long to wait for the database before giving up on a submission.
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// One anonymized result, as sent to the database.
+#[derive(Clone, Debug, Serialize)]
+pub struct Submission {
+    /// The OpenCL device name, e.g. "NVIDIA GeForce RTX 3080".
+    pub model: String,
+    pub vendor: String,
+    pub driver: String,
+    /// PCIe transfer rate per lane in GT/s, where it could be read.
+    pub link_speed: Option<f64>,
+    pub link_width: Option<u32>,
+    pub transfer_bytes: u64,
+    pub run_length: String,
+    pub host_buffer: &'static str,
+    pub memory: &'static str,
+    pub verification: &'static str,
+    /// Mean throughput in GB/s.
+    pub h2d: f64,
+    /// Left out when uploads were verified by checksum instead of read back.
+    pub d2h: Option<f64>,
+    /// How a virtualized GPU is reached, e.g. "SR-IOV virtual function", left out on bare
+    /// metal.
+    pub virtualization: Option<String>,
+    /// Which GPU drives the display where the measured one is half of a hybrid graphics
+    /// setup, e.g. "integrated GPU", left out otherwise or where it could not be told.
+    pub hybrid_display: Option<String>,
+    /// Whether ECC was on, where the driver says.
+    pub ecc: Option<bool>,
+}
+
+impl Submission {
+    pub fn new(device: &MyDevice, record: &MeasurementRecord) -> Submission {
+        let cl = device.get_device();
+        let link = PciAddress::of(cl).and_then(LinkStatus::current);
+        let config = &record.config;
+        let throughput = &record.throughput;
+        let environment = Environment::detect(device);
+        Submission {
+            model: cl.name().unwrap_or_default(),
+            vendor: cl.vendor().unwrap_or_default(),
+            driver: cl.driver_version().unwrap_or_default(),
+            link_speed: link.map(|link| link.speed),
+            link_width: link.map(|link| link.width),
+            transfer_bytes: config.transfer_bytes(),
+            run_length: config.length.spec(),
+            host_buffer: match config.host_buffer {
+                HostBuffer::Reuse => "reuse",
+                HostBuffer::Fresh => "fresh",
+            },
+            memory: config.memory.key(),
+            verification: match config.verification {
+                Verification::ReadBack => "readback",
+                Verification::Checksum => "checksum",
+            },
+            h2d: throughput.h2d_throughput,
+            d2h: throughput.has_d2h().then_some(throughput.d2h_throughput),
+            virtualization: environment
+                .is_virtualized()
+                .then(|| environment.access.to_string()),
+            hybrid_display: Hybrid::detect(device)
+                .map(|hybrid| hybrid.display)
+                .filter(|&display| display != Display::Unknown)
+                .map(|display| display.to_string()),
+            ecc: throughput.telemetry.ecc,
+        }
+    }
+}
+
+/// The database's answer: percentiles among results for the same model, transfer size and
+/// memory kind.
+#[derive(Clone, Debug, Deserialize)]
+struct Response {
+    h2d_percentile: f64,
+    d2h_percentile: Option<f64>,
+    /// Results the percentiles were computed over, this one included.
+    results: u64,
+}
+
+/// Where a submitted result ranks, see `submit`.
+#[derive(Clone, Debug)]
+pub struct Ranking {
+    pub model: String,
+    /// Throughput in GB/s and its percentile from 0 to 100, per direction.
+    pub h2d: (f64, f64),
+    pub d2h: Option<(f64, f64)>,
+    pub results: u64,
+}
+
+impl Ranking {
+    /// One line per direction, e.g. "Your 12.40 GB/s host to device is in the 63rd percentile
+    /// for NVIDIA GeForce RTX 3080 systems (812 results)".
+    pub fn summary(&self) -> Vec<String> {
+        let line = |direction: &str, (throughput, percentile): (f64, f64)| {
+            format!(
+                "Your {:.2} GB/s {} is in the {} percentile for {} systems ({} results)",
+                throughput,
+                direction,
+                ordinal(percentile.round().clamp(0.0, 100.0) as u32),
+                self.model,
+                self.results
+            )
+        };
+        let mut lines = vec![line("host to device", self.h2d)];
+        lines.extend(self.d2h.map(|d2h| line("device to host", d2h)));
+        lines
+    }
+}
+
+/// Sends `submission` to the database at `endpoint` and returns how it ranks.
+pub fn submit(endpoint: &str, submission: &Submission) -> Result<Ranking, String> {
+    let url = format!("{}/results", endpoint.trim_end_matches('/'));
+    let response: Response = ureq
+        ::post(&url)
+        .timeout(TIMEOUT)
+        .send_json(submission)
+        .map_err(|e| e.to_string())?
+        .into_json()
+        .map_err(|e| format!("unexpected response from {}: {}", url, e))?;
+    Ok(Ranking {
+        model: submission.model.clone(),
+        h2d: (submission.h2d, response.h2d_percentile),
+        d2h: submission.d2h.zip(response.d2h_percentile),
+        results: response.results,
+    })
+}
+
+/// `n` with its English ordinal suffix, e.g. "63rd".
+fn ordinal(n: u32) -> String {
+    let suffix = match (n % 10, n % 100) {
+        (_, 11..=13) => "th",
+        (1, _) => "st",
+        (2, _) => "nd",
+        (3, _) => "rd",
+        _ => "th",
+    };
+    format!("{}{}", n, suffix)
+}
@@ -0,0 +1,124 @@
+//! The same upload timed by two ways of waiting for it: flushing the queue and waiting on the
+//! transfer's event, or calling `finish` on the queue. Both should take as long, but drivers
+//! differ in how they wait (spinning or sleeping on an interrupt) and in when they submit a
+//! flushed command, so on some a measurement depends on which one the application uses.
+
+use crate::device::default_queue;
+use crate::error::BenchError;
+use crate::registry::DeviceRegistry;
+use crate::MeasureConfig;
+use opencl3::device::Device;
+use opencl3::memory::{ Buffer, CL_MEM_READ_WRITE };
+use opencl3::types::CL_NON_BLOCKING;
+use std::ptr;
+use std::time::{ Duration, Instant };
+
+/// Difference between the two, as a fraction of the faster, above which it is explained.
+pub const DISCREPANCY_THRESHOLD: f64 = 0.05;
+
+/// Upload times by the way of waiting for them, see `measure_completion`.
+#[derive(Clone, Copy, Debug)]
+pub struct CompletionResult {
+    pub bytes: u64,
+    /// Mean time with `clFlush` and `clWaitForEvents`.
+    pub event_wait: Duration,
+    /// Mean time with `clFinish`.
+    pub finish: Duration,
+}
+
+impl CompletionResult {
+    pub fn event_wait_throughput(&self) -> f64 {
+        (self.bytes as f64) / self.event_wait.as_secs_f64() / 1e9
+    }
+
+    pub fn finish_throughput(&self) -> f64 {
+        (self.bytes as f64) / self.finish.as_secs_f64() / 1e9
+    }
+
+    /// How much slower the slower way is, as a fraction of the faster.
+    pub fn discrepancy(&self) -> f64 {
+        let (fast, slow) = if self.event_wait < self.finish {
+            (self.event_wait, self.finish)
+        } else {
+            (self.finish, self.event_wait)
+        };
+        (slow.as_secs_f64() - fast.as_secs_f64()) / fast.as_secs_f64()
+    }
+
+    pub fn is_discrepant(&self) -> bool {
+        self.discrepancy() > DISCREPANCY_THRESHOLD
+    }
+
+    /// One line per way of waiting, then the verdict.
+    pub fn summary(&self) -> Vec<String> {
+        let millis = |duration: Duration| duration.as_secs_f64() * 1e3;
+        let mut lines = vec![
+            format!(
+                "Flush + event wait: {:.2} GB/s ({:.3} ms)",
+                self.event_wait_throughput(),
+                millis(self.event_wait)
+            ),
+            format!("Finish: {:.2} GB/s ({:.3} ms)", self.finish_throughput(), millis(self.finish))
+        ];
+        let percent = self.discrepancy() * 100.0;
+        lines.push(
+            if !self.is_discrepant() {
+                format!("No meaningful difference ({:.1}% apart)", percent)
+            } else if self.event_wait < self.finish {
+                format!(
+                    "Finish is {:.1}% slower; the driver likely waits on the whole queue by \
+                     sleeping until an interrupt, where the event wait returns as the transfer \
+                     completes",
+                    percent
+                )
+            } else {
+                format!(
+                    "Flush + event wait is {:.1}% slower; the driver likely submits flushed \
+                     commands lazily or wakes event waiters late, so event-based timing reads low",
+                    percent
+                )
+            }
+        );
+        lines
+    }
+}
+
+/// Uploads the configured transfer `config.length.fixed_iterations()` times each way of
+/// waiting, taking turns every iteration so that clock or thermal drift affects both alike.
+pub fn measure_completion(
+    config: &MeasureConfig,
+    device: &Device
+) -> Result<CompletionResult, BenchError> {
+    let context = DeviceRegistry::global().context(device)?;
+    let queue = default_queue(&context, 0)?;
+    let mut buffer = unsafe {
+        Buffer::<f32>::create(&context, CL_MEM_READ_WRITE, config.data_size, ptr::null_mut())?
+    };
+    let host: Vec<f32> = (0..config.data_size).map(|index| (index % 4096) as f32).collect();
+
+    let iterations = config.length.fixed_iterations().max(1);
+    let mut event_wait = Duration::ZERO;
+    let mut finish = Duration::ZERO;
+    for _ in 0..iterations {
+        let start = Instant::now();
+        let event = unsafe {
+            queue.enqueue_write_buffer(&mut buffer, CL_NON_BLOCKING, 0, &host, &[])?
+        };
+        queue.flush()?;
+        event.wait()?;
+        event_wait += start.elapsed();
+
+        let start = Instant::now();
+        unsafe {
+            queue.enqueue_write_buffer(&mut buffer, CL_NON_BLOCKING, 0, &host, &[])?;
+        }
+        queue.finish()?;
+        finish += start.elapsed();
+    }
+
+    Ok(CompletionResult {
+        bytes: config.transfer_bytes(),
+        event_wait: event_wait / (iterations as u32),
+        finish: finish / (iterations as u32),
+    })
+}
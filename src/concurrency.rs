@@ -0,0 +1,155 @@
+use crate::device::default_queue;
+use crate::error::BenchError;
+use crate::registry::DeviceRegistry;
+use crate::trace::Direction;
+use crate::MeasureConfig;
+use opencl3::context::Context;
+use opencl3::device::Device;
+use opencl3::memory::{ Buffer, CL_MEM_READ_WRITE };
+use opencl3::types::CL_BLOCKING;
+use std::ptr;
+use std::sync::Barrier;
+use std::thread;
+use std::time::Instant;
+
+/// Aggregate throughput of one submitting thread compared with several, each on its own queue.
+#[derive(Clone)]
+pub struct ScalingResult {
+    pub threads: usize,
+    pub h2d_single: f64,
+    pub h2d_aggregate: f64,
+    pub d2h_single: f64,
+    pub d2h_aggregate: f64,
+}
+
+impl ScalingResult {
+    pub fn h2d_speedup(&self) -> f64 {
+        self.h2d_aggregate / self.h2d_single
+    }
+
+    pub fn d2h_speedup(&self) -> f64 {
+        self.d2h_aggregate / self.d2h_single
+    }
+
+    /// Speedup as a fraction of perfect linear scaling with the thread count.
+    pub fn efficiency(&self, speedup: f64) -> f64 {
+        speedup / (self.threads as f64)
+    }
+
+    /// One human-readable line per direction.
+    pub fn summary(&self) -> [String; 2] {
+        [
+            ("Host to Device", self.h2d_single, self.h2d_aggregate, self.h2d_speedup()),
+            ("Device to Host", self.d2h_single, self.d2h_aggregate, self.d2h_speedup()),
+        ].map(|(label, single, aggregate, speedup)| {
+            format!(
+                "{}: {:.2} GB/s vs {:.2} GB/s on one thread ({:.2}x, {:.0}% efficiency)",
+                label,
+                aggregate,
+                single,
+                speedup,
+                self.efficiency(speedup) * 100.0
+            )
+        })
+    }
+}
+
+/// Splits the configured transfer between `threads` host threads that submit concurrently
+/// into one shared context, and compares that with a single thread moving the same data.
+///
+/// Data is not verified in this mode; it only looks at submission scaling.
+pub fn measure_scaling(
+    config: &MeasureConfig,
+    device: &Device,
+    threads: usize
+) -> Result<ScalingResult, BenchError> {
+    let context = DeviceRegistry::global().context(device)?;
+    let threads = threads.max(1);
+    let aggregate = |count, direction| {
+        let iterations = config.length.fixed_iterations();
+        aggregate_throughput(&context, config.data_size, iterations, count, direction)
+    };
+
+    Ok(ScalingResult {
+        threads,
+        h2d_single: aggregate(1, Direction::HostToDevice)?,
+        h2d_aggregate: aggregate(threads, Direction::HostToDevice)?,
+        d2h_single: aggregate(1, Direction::DeviceToHost)?,
+        d2h_aggregate: aggregate(threads, Direction::DeviceToHost)?,
+    })
+}
+
+/// GB/s moved by `threads` threads between the first start and the last finish.
+fn aggregate_throughput(
+    context: &Context,
+    data_size: usize,
+    iterations: usize,
+    threads: usize,
+    direction: Direction
+) -> Result<f64, BenchError> {
+    let share = data_size.div_ceil(threads);
+    let iterations = iterations.max(1);
+    let barrier = Barrier::new(threads);
+
+    let spans = thread::scope(|scope| {
+        let handles: Vec<_> = (0..threads)
+            .map(|_| scope.spawn(|| submit(context, share, iterations, direction, &barrier)))
+            .collect();
+
+        handles
+            .into_iter()
+            .map(|handle| handle.join().expect("submission thread panicked"))
+            .collect::<Result<Vec<_>, _>>()
+    })?;
+
+    let first_start = spans
+        .iter()
+        .map(|span| span.0)
+        .min()
+        .unwrap();
+    let last_end = spans
+        .iter()
+        .map(|span| span.1)
+        .max()
+        .unwrap();
+    // Counted in f64, as the product over many iterations can overflow a 32-bit usize
+    let bytes = ((share * threads * std::mem::size_of::<f32>()) as f64) * (iterations as f64);
+    Ok(bytes / (last_end - first_start).as_secs_f64() / 1e9)
+}
+
+/// One submitting thread: sets up its own queue and buffers, waits for the others, then
+/// transfers and reports when it started and finished.
+fn submit(
+    context: &Context,
+    size: usize,
+    iterations: usize,
+    direction: Direction,
+    barrier: &Barrier
+) -> Result<(Instant, Instant), BenchError> {
+    let setup = (|| -> Result<_, BenchError> {
+        let queue = default_queue(context, 0)?;
+        let buffer = unsafe {
+            Buffer::<f32>::create(context, CL_MEM_READ_WRITE, size, ptr::null_mut())?
+        };
+        Ok((queue, buffer, vec![0.0f32; size]))
+    })();
+    // Every thread reaches the barrier, even after a failed setup, so none are left waiting
+    barrier.wait();
+    let (queue, mut buffer, mut host) = setup?;
+
+    let start = Instant::now();
+    for _ in 0..iterations {
+        unsafe {
+            match direction {
+                Direction::HostToDevice => {
+                    queue.enqueue_write_buffer(&mut buffer, CL_BLOCKING, 0, &host, &[])?;
+                }
+                Direction::DeviceToHost => {
+                    queue.enqueue_read_buffer(&buffer, CL_BLOCKING, 0, &mut host, &[])?;
+                }
+            }
+        }
+    }
+    queue.finish()?;
+    Ok((start, Instant::now()))
+}
@@ -0,0 +1,404 @@
+//! Defaults read from `gputhroughput.toml`, looked up in the working directory first and
+//! then in the user's config directory next to `settings.toml`. Command-line flags override
+//! the file; entries that do not parse are reported and left at their built-in defaults.
+//!
+//! ```toml
+//! [device]
+//! name = "radeon"          # first device whose name contains this, ignoring case
+//! # index = 1
+//!
+//! [defaults]
+//! size_mb = 512            # or with a unit, e.g. "512MiB" or "2g"
+//! units = "binary"         # size_mb in MiB and results in GiB/s, or decimal (the default)
+//! run_length = "time:10"   # iterations:<n>, total:<bytes>, time:<seconds> or continuous
+//! host_buffer = "fresh"
+//! memory = "host-ptr"
+//! verify = "checksum"
+//! warm_up_ms = 500
+//!
+//! [export]
+//! directory = "results"
+//!
+//! [telemetry]
+//! link = true
+//! power = false
+//! cpu = true
+//! paging = true           # WDDM memory counters, Windows only
+//! vendor_tools = true     # nvidia-smi or rocm-smi where installed, off by default
+//!
+//! [community]
+//! endpoint = "https://example.org/gputhroughput"
+//! submit = true            # share every result, as --submit does for one run
+//!
+//! [alerts]                 # raised by continuous runs, see `alerts`
+//! min_throughput = 6.0     # GB/s of the rolling average
+//! webhook = "https://hooks.example.org/gpu"
+//! smtp_relay = "mail.lan:25"
+//! email_from = "gpu@example.org"
+//! email_to = "me@example.org"
+//!
+//! [storage]                # where finished results are kept, see `store`
+//! backend = "sqlite"       # json (the default), sqlite or none
+//! path = "/srv/gpu/results.db"
+//!
+//! [hooks]                  # see `hooks`
+//! after_run = ["python3", "dashboard.py"]   # then the path of the result as JSON
+//! ```
+
+use gputhroughput::alerts::{ AlertConfig, Email };
+use gputhroughput::hooks::Hook;
+use gputhroughput::memory::Memory;
+use gputhroughput::store::{ self, Backend, ResultStore };
+use gputhroughput::telemetry::Sensors;
+use gputhroughput::{
+    elements_in,
+    HostBuffer,
+    MyDevice,
+    parse_size,
+    RunLength,
+    SizeUnits,
+    Verification,
+};
+use std::path::{ Path, PathBuf };
+use std::str::FromStr;
+use std::time::Duration;
+use toml_edit::{ Document, Item };
+
+const FILE_NAME: &str = "gputhroughput.toml";
+
+#[derive(Clone, Debug, Default)]
+pub struct Config {
+    pub device: DeviceRule,
+    pub defaults: Defaults,
+    /// Where exported files with a relative path are written, the working directory if unset.
+    pub export_dir: Option<PathBuf>,
+    pub sensors: Sensors,
+    /// The results database to submit to, see `community`.
+    pub endpoint: Option<String>,
+    /// Whether to submit every result without being asked on the command line.
+    pub submit: bool,
+    pub alerts: AlertConfig,
+    pub storage: Storage,
+    /// Run after each finished measurement, see `hooks`.
+    pub hook: Option<Hook>,
+}
+
+/// Where finished results are kept, see `store`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Storage {
+    /// `None` keeps no results.
+    pub backend: Option<Backend>,
+    /// The backend's default file in the config directory if unset.
+    pub path: Option<PathBuf>,
+}
+
+/// How the device to measure is chosen when none is picked explicitly.
+#[derive(Clone, Debug, PartialEq)]
+pub enum DeviceRule {
+    Index(usize),
+    /// The first device whose name contains this, ignoring case.
+    Name(String),
+}
+
+/// The configuration a run starts from before flags or per-device settings change it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Defaults {
+    /// Transfer size in `units`.
+    pub size: usize,
+    pub length: RunLength,
+    pub host_buffer: HostBuffer,
+    pub memory: Memory,
+    pub verification: Verification,
+    pub warm_up: Duration,
+    pub units: SizeUnits,
+}
+
+impl Default for DeviceRule {
+    fn default() -> Self {
+        DeviceRule::Index(0)
+    }
+}
+
+impl Default for Defaults {
+    fn default() -> Self {
+        Defaults {
+            size: 1024,
+            length: RunLength::Iterations(1),
+            host_buffer: HostBuffer::Reuse,
+            memory: Memory::Buffer,
+            verification: Verification::ReadBack,
+            warm_up: Duration::ZERO,
+            units: SizeUnits::default(),
+        }
+    }
+}
+
+impl Default for Storage {
+    fn default() -> Self {
+        Storage { backend: Some(Backend::Json), path: None }
+    }
+}
+
+impl Storage {
+    /// Opens the configured store, warning on stderr and keeping no results if it cannot.
+    pub fn open(&self) -> Option<Box<dyn ResultStore>> {
+        let backend = self.backend?;
+        let path = self.path
+            .clone()
+            .or_else(|| Some(config_dir()?.join(backend.default_file_name())))?;
+        match store::open(backend, &path) {
+            Ok(store) => Some(store),
+            Err(e) => {
+                eprintln!("Warning: not keeping results: {}", e);
+                None
+            }
+        }
+    }
+}
+
+impl DeviceRule {
+    /// Index of the device the rule picks from `devices`.
+    pub fn select(&self, devices: &[MyDevice]) -> Result<usize, String> {
+        match self {
+            DeviceRule::Index(index) if *index < devices.len() => Ok(*index),
+            DeviceRule::Index(index) =>
+                Err(format!("index {} is out of range ({} devices found)", index, devices.len())),
+            DeviceRule::Name(name) => {
+                let lowercase = name.to_lowercase();
+                devices
+                    .iter()
+                    .position(|device| device.name().to_lowercase().contains(&lowercase))
+                    .ok_or_else(|| format!("no device name contains '{}'", name))
+            }
+        }
+    }
+}
+
+impl Config {
+    /// Reads the first config file found, warning on stderr about anything ignored in it.
+    pub fn load() -> Config {
+        let Some(path) = config_path() else {
+            return Config::default();
+        };
+        let document = match
+            std::fs::read_to_string(&path).map_err(|e| e.to_string()).and_then(|text| {
+                text.parse::<Document>().map_err(|e| e.to_string())
+            })
+        {
+            Ok(document) => document,
+            Err(e) => {
+                eprintln!("Warning: ignoring {}: {}", path.display(), e);
+                return Config::default();
+            }
+        };
+        Reader { path: &path, document: &document }.config()
+    }
+
+    /// `path` inside the export directory, unless it is absolute.
+    pub fn export_path(&self, path: &Path) -> PathBuf {
+        match self.export_dir {
+            Some(ref dir) => dir.join(path),
+            None => path.to_path_buf(),
+        }
+    }
+}
+
+/// `gputhroughput.toml` in the working directory, or else in the config directory.
+fn config_path() -> Option<PathBuf> {
+    let local = PathBuf::from(FILE_NAME);
+    if local.is_file() {
+        return Some(local);
+    }
+    Some(config_dir()?.join(FILE_NAME)).filter(|path| path.is_file())
+}
+
+struct Reader<'a> {
+    path: &'a Path,
+    document: &'a Document,
+}
+
+impl Reader<'_> {
+    fn config(&self) -> Config {
+        let mut config = Config::default();
+
+        let index = |item: &Item| item.as_integer()?.try_into().ok();
+        if let Some(index) = self.value("device", "index", index) {
+            config.device = DeviceRule::Index(index);
+        }
+        let name = |item: &Item| item.as_str().filter(|name| !name.is_empty()).map(str::to_string);
+        if let Some(name) = self.value("device", "name", name) {
+            config.device = DeviceRule::Name(name);
+        }
+
+        let defaults = &mut config.defaults;
+        if let Some(units) = self.value("defaults", "units", parse) {
+            defaults.units = units;
+        }
+        let units = defaults.units;
+        let size = |item: &Item| {
+            let size: usize = match item.as_str() {
+                Some(text) => parse_size(text, units).ok()?,
+                None => item.as_integer()?.try_into().ok()?,
+            };
+            (size > 0 && elements_in(size, units).is_ok()).then_some(size)
+        };
+        if let Some(size) = self.value("defaults", "size_mb", size) {
+            defaults.size = size;
+        }
+        let length = |item: &Item| {
+            let length = item.as_str()?.parse().ok()?;
+            match length {
+                RunLength::Iterations(0) | RunLength::TotalBytes(0) => None,
+                RunLength::Time(limit) if limit.is_zero() => None,
+                length => Some(length),
+            }
+        };
+        if let Some(length) = self.value("defaults", "run_length", length) {
+            defaults.length = length;
+        }
+        if let Some(host_buffer) = self.value("defaults", "host_buffer", parse) {
+            defaults.host_buffer = host_buffer;
+        }
+        if let Some(memory) = self.value("defaults", "memory", parse) {
+            defaults.memory = memory;
+        }
+        if let Some(verification) = self.value("defaults", "verify", parse) {
+            defaults.verification = verification;
+        }
+        let millis = |item: &Item| {
+            Some(Duration::from_millis(item.as_integer()?.try_into().ok()?))
+        };
+        if let Some(warm_up) = self.value("defaults", "warm_up_ms", millis) {
+            defaults.warm_up = warm_up;
+        }
+
+        config.export_dir = self.value("export", "directory", |item| {
+            item.as_str().filter(|dir| !dir.is_empty()).map(PathBuf::from)
+        });
+
+        let sensors = &mut config.sensors;
+        for (key, enabled) in [
+            ("link", &mut sensors.link),
+            ("power", &mut sensors.power),
+            ("cpu", &mut sensors.cpu),
+            ("paging", &mut sensors.paging),
+            ("vendor_tools", &mut sensors.vendor_tools),
+        ] {
+            if let Some(value) = self.value("telemetry", key, Item::as_bool) {
+                *enabled = value;
+            }
+        }
+
+        config.endpoint = self.value("community", "endpoint", |item| {
+            item.as_str().filter(|url| url.starts_with("http")).map(str::to_string)
+        });
+        if let Some(submit) = self.value("community", "submit", Item::as_bool) {
+            if submit && config.endpoint.is_none() {
+                eprintln!(
+                    "Warning: {}: community.submit needs community.endpoint, not submitting",
+                    self.path.display()
+                );
+            }
+            config.submit = submit && config.endpoint.is_some();
+        }
+
+        let alerts = &mut config.alerts;
+        alerts.min_throughput = self.value("alerts", "min_throughput", |item| {
+            item
+                .as_float()
+                .or_else(|| item.as_integer().map(|gbs| gbs as f64))
+                .filter(|gbs| *gbs > 0.0)
+        });
+        alerts.webhook = self.value("alerts", "webhook", |item| {
+            item.as_str().filter(|url| url.starts_with("http")).map(str::to_string)
+        });
+        let text = |item: &Item| item.as_str().filter(|text| !text.is_empty()).map(str::to_string);
+        let relay = self.value("alerts", "smtp_relay", text);
+        let from = self.value("alerts", "email_from", text);
+        let to = self.value("alerts", "email_to", text);
+        match (relay, from, to) {
+            (Some(relay), Some(from), Some(to)) => {
+                alerts.email = Some(Email { relay, from, to });
+            }
+            (None, None, None) => (),
+            _ =>
+                eprintln!(
+                    "Warning: {}: alert mail needs alerts.smtp_relay, email_from and email_to, \
+                     not sending mail",
+                    self.path.display()
+                ),
+        }
+        if alerts.min_throughput.is_some() && !alerts.is_enabled() {
+            eprintln!(
+                "Warning: {}: alerts.min_throughput needs a webhook or mail relay to alert",
+                self.path.display()
+            );
+        }
+
+        let backend = |item: &Item| match item.as_str()? {
+            "none" => Some(None),
+            backend => backend.parse().ok().map(Some),
+        };
+        if let Some(backend) = self.value("storage", "backend", backend) {
+            config.storage.backend = backend;
+        }
+        config.storage.path = self.value("storage", "path", |item| {
+            item.as_str().filter(|path| !path.is_empty()).map(PathBuf::from)
+        });
+
+        // A program alone, or the program and its arguments
+        config.hook = self.value("hooks", "after_run", |item| {
+            let command: Vec<String> = match item.as_array() {
+                Some(array) => {
+                    array
+                        .iter()
+                        .map(|word| word.as_str().map(str::to_string))
+                        .collect::<Option<_>>()?
+                }
+                None => vec![item.as_str()?.to_string()],
+            };
+            let (program, args) = command.split_first()?;
+            (!program.is_empty()).then(|| Hook { program: program.clone(), args: args.to_vec() })
+        });
+        config
+    }
+
+    /// `key` in `table` converted by `read`, warning when it is present but does not convert.
+    fn value<T>(
+        &self,
+        table: &str,
+        key: &str,
+        read: impl FnOnce(&Item) -> Option<T>
+    ) -> Option<T> {
+        let item = self.document.get(table)?.get(key)?;
+        let value = read(item);
+        if value.is_none() {
+            eprintln!(
+                "Warning: {}: ignoring invalid {}.{} = {}",
+                self.path.display(),
+                table,
+                key,
+                item.to_string().trim()
+            );
+        }
+        value
+    }
+}
+
+fn parse<T: FromStr>(item: &Item) -> Option<T> {
+    item.as_str()?.parse().ok()
+}
+
+/// `gputhroughput` under the platform's per-user config directory.
+pub fn config_dir() -> Option<PathBuf> {
+    let config = if cfg!(windows) {
+        std::env::var_os("APPDATA").map(PathBuf::from)
+    } else if cfg!(target_os = "macos") {
+        std::env::var_os("HOME").map(|home| PathBuf::from(home).join("Library/Application Support"))
+    } else {
+        std::env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+    };
+    Some(config?.join("gputhroughput"))
+}
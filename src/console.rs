@@ -0,0 +1,156 @@
+//! The log console at the bottom of the window, showing what the measurement core logs, see
+//! `api::execute`, and what the libraries under the GUI log at info and above, so that a run
+//! can be followed without a terminal. Records beyond `MAX_PER_SECOND` are counted rather
+//! than kept, so that the per-sample lines of a fast run cannot flood the window.
+
+use eframe::egui;
+use log::{ Level, LevelFilter, Log, Metadata, Record };
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::{ Duration, Instant };
+
+/// Lines kept, the oldest dropped first.
+const CAPACITY: usize = 1000;
+/// Lines kept per second, those beyond only counted.
+const MAX_PER_SECOND: usize = 50;
+
+struct Line {
+    /// Since the console was installed.
+    time: Duration,
+    level: Level,
+    message: String,
+}
+
+struct State {
+    lines: VecDeque<Line>,
+    /// When the second that `kept` and `dropped` count started.
+    second: Instant,
+    kept: usize,
+    dropped: usize,
+}
+
+pub struct Console {
+    started: Instant,
+    state: Mutex<State>,
+}
+
+impl Console {
+    /// Installs the console as the logger of the process, where it stays for good.
+    pub fn install() -> &'static Console {
+        let console: &'static Console = Box::leak(
+            Box::new(Console {
+                started: Instant::now(),
+                state: Mutex::new(State {
+                    lines: VecDeque::new(),
+                    second: Instant::now(),
+                    kept: 0,
+                    dropped: 0,
+                }),
+            })
+        );
+        if log::set_logger(console).is_ok() {
+            log::set_max_level(LevelFilter::Debug);
+        }
+        console
+    }
+
+    /// The lines at `level` and more severe, oldest first, with a level picker and a way to
+    /// clear them.
+    pub fn show(&self, ui: &mut egui::Ui, level: &mut Level) {
+        ui.horizontal(|ui| {
+            egui::ComboBox
+                ::from_id_source("console_level")
+                .selected_text(level.to_string())
+                .show_ui(ui, |ui| {
+                    for option in [Level::Error, Level::Warn, Level::Info, Level::Debug] {
+                        ui.selectable_value(level, option, option.to_string());
+                    }
+                });
+            if ui.button("Clear").clicked() {
+                self.state.lock().unwrap().lines.clear();
+            }
+        });
+        // Copied out, as anything egui logs while drawing would otherwise wait on the lock
+        let lines: Vec<(Level, String)> = self.state
+            .lock()
+            .unwrap()
+            .lines.iter()
+            .filter(|line| line.level <= *level)
+            .map(|line| {
+                let text = format!(
+                    "{:>8.3} {:<5} {}",
+                    line.time.as_secs_f64(),
+                    line.level,
+                    line.message
+                );
+                (line.level, text)
+            })
+            .collect();
+        egui::ScrollArea
+            ::vertical()
+            .max_height(160.0)
+            .stick_to_bottom(true)
+            .auto_shrink([false, true])
+            .show(ui, |ui| {
+                for (level, text) in lines {
+                    let color = match level {
+                        Level::Error => ui.visuals().error_fg_color,
+                        Level::Warn => ui.visuals().warn_fg_color,
+                        Level::Info => ui.visuals().text_color(),
+                        Level::Debug | Level::Trace => ui.visuals().weak_text_color(),
+                    };
+                    ui.label(egui::RichText::new(text).monospace().color(color));
+                }
+            });
+    }
+
+    fn push(&self, level: Level, message: String) {
+        let mut state = self.state.lock().unwrap();
+        let now = Instant::now();
+        if now.duration_since(state.second) >= Duration::from_secs(1) {
+            if state.dropped > 0 {
+                let dropped = format!(
+                    "{} lines left out, over {} per second",
+                    state.dropped,
+                    MAX_PER_SECOND
+                );
+                state.lines.push_back(Line {
+                    time: now - self.started,
+                    level: Level::Warn,
+                    message: dropped,
+                });
+            }
+            state.second = now;
+            state.kept = 0;
+            state.dropped = 0;
+        }
+        if state.kept == MAX_PER_SECOND {
+            state.dropped += 1;
+            return;
+        }
+        state.kept += 1;
+        state.lines.push_back(Line { time: now - self.started, level, message });
+        while state.lines.len() > CAPACITY {
+            state.lines.pop_front();
+        }
+    }
+}
+
+impl Log for Console {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        // Debug output of the GUI's own libraries would crowd out the measurement's
+        if metadata.target().starts_with("gputhroughput") {
+            metadata.level() <= Level::Debug
+        } else {
+            metadata.level() <= Level::Info
+        }
+    }
+
+    fn log(&self, record: &Record) {
+        if self.enabled(record.metadata()) {
+            self.push(record.level(), record.args().to_string());
+        }
+    }
+
+    fn flush(&self) {}
+}
@@ -0,0 +1,205 @@
+//! Transfer throughput while a kernel competes for the GPU's memory, as on a GPU shared with
+//! other workloads. The background kernel runs back to back on a queue of its own while the
+//! transfers are measured, its memory intensity raised in steps: at each step a larger share
+//! of its work-items streams through device memory instead of spinning on arithmetic. The
+//! throughput at each step is the contention curve, how much of the link a transfer keeps
+//! as its neighbors get hungrier.
+
+use crate::device::default_queue;
+use crate::error::BenchError;
+use crate::registry::DeviceRegistry;
+use crate::MeasureConfig;
+use opencl3::context::Context;
+use opencl3::device::Device;
+use opencl3::kernel::Kernel;
+use opencl3::memory::{ Buffer, ClMem, CL_MEM_READ_WRITE };
+use opencl3::program::Program;
+use opencl3::types::{ cl_uint, cl_ulong, CL_BLOCKING };
+use std::ptr;
+use std::sync::atomic::{ AtomicBool, Ordering };
+use std::thread;
+use std::time::{ Duration, Instant };
+
+const SOURCE: &str =
+    "\
+__kernel void contend(
+    __global float4 *data,
+    ulong count,
+    uint memory_share,
+    uint share_steps,
+    uint rounds
+) {
+    size_t id = get_global_id(0);
+    if (id % share_steps < memory_share) {
+        size_t lane = (id / share_steps) * memory_share + id % share_steps;
+        size_t lanes = (get_global_size(0) / share_steps) * memory_share;
+        for (ulong i = lane; i < count; i += lanes) {
+            data[i] = data[i] * 0.5f + 1.0f;
+        }
+    } else {
+        float x = (float)id;
+        for (uint i = 0; i < rounds; i++) {
+            x = x * 0.999999f + 1.0f;
+        }
+        if (x == 0.0f) {
+            data[0] = (float4)(x);
+        }
+    }
+}
+";
+
+/// Steps of memory intensity above none, each a further share of the work-items.
+pub const SHARE_STEPS: cl_uint = 4;
+/// Bytes the memory work-items stream through on every launch.
+const BACKGROUND_BYTES: usize = 128 * 1024 * 1024;
+const WORK_ITEMS: usize = 65536;
+/// Loop rounds of the arithmetic work-items, about as long as the streaming ones take.
+const ROUNDS: cl_uint = 4096;
+
+/// Throughput at one step of the sweep.
+#[derive(Clone, Copy, Debug)]
+pub struct ContentionStep {
+    /// Share of the kernel's work-items streaming memory, `None` without a kernel.
+    pub intensity: Option<f64>,
+    /// GB/s of each direction.
+    pub h2d: f64,
+    pub d2h: f64,
+}
+
+/// The contention curve, see `measure_contention`.
+#[derive(Clone, Debug)]
+pub struct ContentionResult {
+    /// The run without a kernel first, then by rising intensity.
+    pub steps: Vec<ContentionStep>,
+}
+
+impl ContentionResult {
+    /// One line per step with the share of the throughput without a kernel it kept.
+    pub fn summary(&self) -> Vec<String> {
+        let Some(idle) = self.steps.first() else {
+            return Vec::new();
+        };
+        self.steps
+            .iter()
+            .map(|step| {
+                let label = match step.intensity {
+                    None => "No kernel".to_string(),
+                    Some(share) => format!("Kernel {:.0}% memory", share * 100.0),
+                };
+                format!(
+                    "{}: {:.2} GB/s H2D ({:.0}%), {:.2} GB/s D2H ({:.0}%)",
+                    label,
+                    step.h2d,
+                    (step.h2d / idle.h2d) * 100.0,
+                    step.d2h,
+                    (step.d2h / idle.d2h) * 100.0
+                )
+            })
+            .collect()
+    }
+}
+
+/// Measures uploads and downloads of the configured size without a background kernel, then
+/// with one running at each memory intensity from none to every work-item streaming, in
+/// `SHARE_STEPS` steps.
+///
+/// Data is not verified in this mode; it only looks at how the transfers slow down.
+pub fn measure_contention(
+    config: &MeasureConfig,
+    device: &Device
+) -> Result<ContentionResult, BenchError> {
+    let context = DeviceRegistry::global().context(device)?;
+    let program = Program::create_and_build_from_source(&context, SOURCE, "").map_err(|log| {
+        BenchError::Unsupported(format!("the contention kernel failed to build: {}", log))
+    })?;
+    let queue = default_queue(&context, 0)?;
+    let mut buffer = unsafe {
+        Buffer::<f32>::create(&context, CL_MEM_READ_WRITE, config.data_size, ptr::null_mut())?
+    };
+    let mut host: Vec<f32> = (0..config.data_size).map(|index| index as f32).collect();
+    let iterations = config.length.fixed_iterations().max(1);
+
+    let mut measure = || -> Result<(f64, f64), BenchError> {
+        let (mut upload, mut download) = (Duration::ZERO, Duration::ZERO);
+        for _ in 0..iterations {
+            let start = Instant::now();
+            unsafe {
+                queue.enqueue_write_buffer(&mut buffer, CL_BLOCKING, 0, &host, &[])?;
+            }
+            upload += start.elapsed();
+            let start = Instant::now();
+            unsafe {
+                queue.enqueue_read_buffer(&buffer, CL_BLOCKING, 0, &mut host, &[])?;
+            }
+            download += start.elapsed();
+        }
+        let bytes = (config.transfer_bytes() as f64) * (iterations as f64);
+        Ok((bytes / upload.as_secs_f64() / 1e9, bytes / download.as_secs_f64() / 1e9))
+    };
+
+    let (h2d, d2h) = measure()?;
+    let mut steps = vec![ContentionStep { intensity: None, h2d, d2h }];
+    for memory_share in 0..=SHARE_STEPS {
+        let stop = AtomicBool::new(false);
+        let running = AtomicBool::new(false);
+        let (measured, background) = thread::scope(|scope| {
+            let background = scope.spawn(|| {
+                let result = contend(&context, &program, memory_share, &running, &stop);
+                // Lets the measurement below go ahead should the kernel fail to start
+                running.store(true, Ordering::SeqCst);
+                result
+            });
+            while !running.load(Ordering::SeqCst) {
+                thread::yield_now();
+            }
+            let measured = measure();
+            stop.store(true, Ordering::SeqCst);
+            (measured, background.join())
+        });
+        background.unwrap_or_else(|panic| std::panic::resume_unwind(panic))?;
+        let (h2d, d2h) = measured?;
+        let intensity = Some((memory_share as f64) / (SHARE_STEPS as f64));
+        steps.push(ContentionStep { intensity, h2d, d2h });
+    }
+
+    Ok(ContentionResult { steps })
+}
+
+/// Launches the background kernel back to back on a queue of its own until `stop` is set,
+/// setting `running` once the first launch finished.
+fn contend(
+    context: &Context,
+    program: &Program,
+    memory_share: cl_uint,
+    running: &AtomicBool,
+    stop: &AtomicBool
+) -> Result<(), BenchError> {
+    let queue = default_queue(context, 0)?;
+    let kernel = Kernel::create(program, "contend")?;
+    let count = BACKGROUND_BYTES / (4 * std::mem::size_of::<f32>());
+    let data = unsafe {
+        Buffer::<f32>::create(context, CL_MEM_READ_WRITE, count * 4, ptr::null_mut())?
+    };
+    unsafe {
+        kernel.set_arg(0, &data.get())?;
+        kernel.set_arg(1, &(count as cl_ulong))?;
+        kernel.set_arg(2, &memory_share)?;
+        kernel.set_arg(3, &SHARE_STEPS)?;
+        kernel.set_arg(4, &ROUNDS)?;
+    }
+    while !stop.load(Ordering::SeqCst) {
+        unsafe {
+            queue.enqueue_nd_range_kernel(
+                kernel.get(),
+                1,
+                ptr::null(),
+                [WORK_ITEMS].as_ptr(),
+                ptr::null(),
+                &[]
+            )?;
+        }
+        queue.finish()?;
+        running.store(true, Ordering::SeqCst);
+    }
+    Ok(())
+}
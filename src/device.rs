@@ -0,0 +1,153 @@
+//! The devices a run can measure, found once per process through `registry`.
+
+use crate::registry::DeviceRegistry;
+use opencl3::command_queue::CommandQueue;
+use opencl3::context::Context;
+use opencl3::device::Device;
+use opencl3::error_codes::ClError;
+use opencl3::platform::Platform;
+use opencl3::types::{ cl_command_queue_properties, cl_device_id };
+
+/// Maker of a device, from the PCI vendor ID the driver reports.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Vendor {
+    Nvidia,
+    Amd,
+    Intel,
+    Apple,
+    Arm,
+    Qualcomm,
+    Other,
+}
+
+impl Vendor {
+    pub fn from_id(id: u32) -> Vendor {
+        match id {
+            0x10de => Vendor::Nvidia,
+            0x1002 | 0x1022 => Vendor::Amd,
+            0x8086 => Vendor::Intel,
+            // Apple reports its own ID rather than a PCI one
+            0x1027f00 | 0x106b => Vendor::Apple,
+            0x13b5 => Vendor::Arm,
+            0x5143 => Vendor::Qualcomm,
+            _ => Vendor::Other,
+        }
+    }
+}
+
+impl std::fmt::Display for Vendor {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Vendor::Nvidia => write!(f, "NVIDIA"),
+            Vendor::Amd => write!(f, "AMD"),
+            Vendor::Intel => write!(f, "Intel"),
+            Vendor::Apple => write!(f, "Apple"),
+            Vendor::Arm => write!(f, "Arm"),
+            Vendor::Qualcomm => write!(f, "Qualcomm"),
+            Vendor::Other => write!(f, "Other"),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct MyDevice {
+    device: Device,
+    name: String,
+    /// Name of the OpenCL platform, i.e. the installed driver (ICD), the device belongs to.
+    platform: String,
+    vendor: Vendor,
+    /// Bytes of device memory, see `global_memory`.
+    global_memory: u64,
+    max_sub_devices: u32,
+    extensions: Vec<String>,
+}
+
+impl PartialEq for MyDevice {
+    fn eq(&self, other: &Self) -> bool {
+        self.device.id() == other.device.id()
+    }
+}
+
+impl MyDevice {
+    pub fn new(id: cl_device_id) -> Self {
+        let device = Device::new(id);
+        let name = device.board_name_amd().unwrap_or_default();
+        let platform = device
+            .platform()
+            .and_then(|id| Platform::new(id).name())
+            .unwrap_or_default();
+        let vendor = Vendor::from_id(device.vendor_id().unwrap_or_default());
+        let global_memory = device.global_mem_size().unwrap_or_default();
+        // Devices without fission support report one (themselves) or fail the query
+        let max_sub_devices = device.partition_max_sub_devices().unwrap_or_default();
+        let extensions = device
+            .extensions()
+            .unwrap_or_default()
+            .split_whitespace()
+            .map(str::to_string)
+            .collect();
+        MyDevice { device, name, platform, vendor, global_memory, max_sub_devices, extensions }
+    }
+
+    pub fn get_device(&self) -> &Device {
+        &self.device
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn platform(&self) -> &str {
+        &self.platform
+    }
+
+    pub fn vendor(&self) -> Vendor {
+        self.vendor
+    }
+
+    /// Bytes of device memory: VRAM, or on an integrated GPU the share of system memory it
+    /// may allocate.
+    pub fn global_memory(&self) -> u64 {
+        self.global_memory
+    }
+
+    /// Stable across sessions, unlike `key`, so that saved settings find the device again.
+    /// Identical boards share an entry.
+    pub fn settings_key(&self) -> String {
+        self.device.name().unwrap_or_default()
+    }
+
+    /// Identifies the device for as long as the process runs.
+    pub fn key(&self) -> usize {
+        self.device.id() as usize
+    }
+
+    pub fn extensions(&self) -> &[String] {
+        &self.extensions
+    }
+
+    pub fn has_extension(&self, name: &str) -> bool {
+        self.extensions.iter().any(|extension| extension == name)
+    }
+
+    pub fn supports_partitioning(&self) -> bool {
+        self.max_sub_devices > 1
+    }
+}
+
+/// The GPUs on the system, enumerated on the first call only, see `registry`.
+pub fn enumerate_devices() -> Vec<MyDevice> {
+    DeviceRegistry::global().devices().to_vec()
+}
+
+/// An in-order queue on the device of `context`, with `properties` such as
+/// `CL_QUEUE_PROFILING_ENABLE`. Every measurement creates its queues here. The pre-2.0 entry
+/// point is kept on purpose, deprecated as it is, so that OpenCL 1.2 drivers, which lack its
+/// replacement, still work.
+pub fn default_queue(
+    context: &Context,
+    properties: cl_command_queue_properties
+) -> Result<CommandQueue, ClError> {
+    #[allow(deprecated)]
+    CommandQueue::create_default(context, properties)
+}
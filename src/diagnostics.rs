@@ -0,0 +1,148 @@
+//! Facts about a GPU read from its vendor's command-line tool, `nvidia-smi` or `rocm-smi`,
+//! where one is installed. A fallback for what NVML and sysfs leave unanswered, e.g. the link
+//! outside Linux or ECC on boards whose OpenCL driver does not say.
+//!
+//! Every query launches the tool, which can take the better part of a second, so it is only
+//! made with the `vendor_tools` sensor on.
+
+use crate::telemetry::{ LinkStatus, PciAddress };
+use serde_json::Value;
+use std::fmt;
+use std::process::Command;
+
+/// Transfer rate per lane in GT/s of PCIe generations 1 to 6.
+const GENERATION_SPEEDS: [f64; 6] = [2.5, 5.0, 8.0, 16.0, 32.0, 64.0];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Tool {
+    NvidiaSmi,
+    RocmSmi,
+}
+
+impl fmt::Display for Tool {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Tool::NvidiaSmi => "nvidia-smi",
+            Tool::RocmSmi => "rocm-smi",
+        })
+    }
+}
+
+/// What the vendor tool reported for one GPU.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct VendorDiagnostics {
+    pub tool: Tool,
+    /// The link as negotiated when queried.
+    pub link: Option<LinkStatus>,
+    /// Whether the driver stays loaded with no clients, which spares the first run its
+    /// initialisation; NVIDIA only.
+    pub persistence: Option<bool>,
+    pub ecc: Option<bool>,
+}
+
+impl VendorDiagnostics {
+    /// e.g. "nvidia-smi: PCIe gen 4 x16, persistence mode on, ECC off"
+    pub fn summary(&self) -> String {
+        let on_off = |enabled: bool| if enabled { "on" } else { "off" };
+        let mut facts = Vec::new();
+        if let Some(link) = self.link {
+            match link.generation() {
+                Some(generation) => facts.push(format!("PCIe gen {} x{}", generation, link.width)),
+                None => facts.push(format!("PCIe {:.1} GT/s x{}", link.speed, link.width)),
+            }
+        }
+        if let Some(persistence) = self.persistence {
+            facts.push(format!("persistence mode {}", on_off(persistence)));
+        }
+        if let Some(ecc) = self.ecc {
+            facts.push(format!("ECC {}", on_off(ecc)));
+        }
+        format!("{}: {}", self.tool, facts.join(", "))
+    }
+
+    fn is_empty(&self) -> bool {
+        self.link.is_none() && self.persistence.is_none() && self.ecc.is_none()
+    }
+}
+
+/// Asks whichever vendor tool knows the GPU at `address`, or `None` if neither is installed
+/// or neither reports anything for it.
+pub fn query(address: PciAddress) -> Option<VendorDiagnostics> {
+    nvidia_smi(address)
+        .or_else(|| rocm_smi(address))
+        .filter(|diagnostics| !diagnostics.is_empty())
+}
+
+fn run(program: &str, args: &[&str]) -> Option<String> {
+    let output = Command::new(program).args(args).output().ok()?;
+    output.status.success().then(|| String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+fn nvidia_smi(address: PciAddress) -> Option<VendorDiagnostics> {
+    let id = format!("--id={}", address);
+    let output = run(
+        "nvidia-smi",
+        &[
+            &id,
+            "--query-gpu=pcie.link.gen.current,pcie.link.width.current,persistence_mode,ecc.mode.current",
+            "--format=csv,noheader,nounits",
+        ]
+    )?;
+    parse_nvidia_smi(&output)
+}
+
+/// One CSV line such as "4, 16, Enabled, Disabled", with "[N/A]" or "[Not Supported]" for
+/// what the board does not report.
+fn parse_nvidia_smi(output: &str) -> Option<VendorDiagnostics> {
+    let fields: Vec<&str> = output.lines().next()?.split(',').map(str::trim).collect();
+    let [generation, width, persistence, ecc] = fields[..] else {
+        return None;
+    };
+    let enabled = |field: &str| {
+        match field {
+            "Enabled" => Some(true),
+            "Disabled" => Some(false),
+            _ => None,
+        }
+    };
+    let speed = generation
+        .parse::<usize>()
+        .ok()
+        .and_then(|generation| GENERATION_SPEEDS.get(generation.checked_sub(1)?).copied());
+    let link = match (speed, width.parse().ok()) {
+        (Some(speed), Some(width)) => Some(LinkStatus { speed, width }),
+        _ => None,
+    };
+    Some(VendorDiagnostics {
+        tool: Tool::NvidiaSmi,
+        link,
+        persistence: enabled(persistence),
+        ecc: enabled(ecc),
+    })
+}
+
+fn rocm_smi(address: PciAddress) -> Option<VendorDiagnostics> {
+    let output = run("rocm-smi", &["--showbus", "--showclocks", "--json"])?;
+    parse_rocm_smi(&output, address)
+}
+
+/// JSON keyed by card, e.g. `{"card0": {"PCI Bus": "0000:03:00.0", "pcie clock level":
+/// "1 (8.0GT/s x16)", ...}}`. rocm-smi has no persistence mode, and reports ECC only per RAS
+/// block, so only the link is read.
+fn parse_rocm_smi(output: &str, address: PciAddress) -> Option<VendorDiagnostics> {
+    let cards: Value = serde_json::from_str(output).ok()?;
+    let card = cards
+        .as_object()?
+        .values()
+        .find(|card| {
+            card["PCI Bus"].as_str().is_some_and(|bus| bus.eq_ignore_ascii_case(&address.to_string()))
+        })?;
+    // The level in use, then its rate and width in brackets
+    let level = card["pcie clock level"].as_str()?;
+    let (rate, width) = level.split_once('(')?.1.trim_end_matches(')').split_once(" x")?;
+    let link = LinkStatus {
+        speed: rate.trim_end_matches("GT/s").parse().ok()?,
+        width: width.trim().parse().ok()?,
+    };
+    Some(VendorDiagnostics { tool: Tool::RocmSmi, link: Some(link), persistence: None, ecc: None })
+}
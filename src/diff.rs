@@ -0,0 +1,227 @@
+//! Comparison of two results, e.g. of the same GPU before and after a driver update, for
+//! `gputhroughput diff`. Each direction's throughput is compared with a significance test
+//! where both results carry per-iteration statistics, and whatever differs in their
+//! configuration or conditions is listed, since a changed setting explains most deltas.
+
+use crate::statistics::Statistics;
+use crate::store::StoredResult;
+use serde::Serialize;
+
+/// Welch's t above which a difference counts as significant, about 95% confidence for the
+/// iteration counts runs use.
+const T_CRITICAL: f64 = 2.0;
+
+/// Whether a delta is more than the iterations' spread explains.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Significance {
+    Significant,
+    WithinNoise,
+    /// One of the results has no statistics, e.g. a single iteration.
+    Unknown,
+}
+
+/// One metric in both results, in GB/s.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct MetricDelta {
+    pub metric: &'static str,
+    pub before: f64,
+    pub after: f64,
+    pub delta: f64,
+    /// `delta` as a fraction of `before`.
+    pub relative: f64,
+    pub significance: Significance,
+}
+
+impl MetricDelta {
+    fn new(
+        metric: &'static str,
+        before: f64,
+        after: f64,
+        statistics: (Option<Statistics>, Option<Statistics>)
+    ) -> MetricDelta {
+        let delta = after - before;
+        MetricDelta {
+            metric,
+            before,
+            after,
+            delta,
+            relative: if before > 0.0 { delta / before } else { 0.0 },
+            significance: match statistics {
+                (Some(before), Some(after)) => significance(&before, &after),
+                _ => Significance::Unknown,
+            },
+        }
+    }
+
+    /// Whether throughput dropped by more than the noise.
+    pub fn is_regression(&self) -> bool {
+        self.delta < 0.0 && self.significance == Significance::Significant
+    }
+}
+
+/// A setting that differs between the results.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Change {
+    pub setting: &'static str,
+    pub before: String,
+    pub after: String,
+}
+
+/// What `compare` found.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Comparison {
+    pub metrics: Vec<MetricDelta>,
+    /// Configuration and conditions, see `reproducibility`.
+    pub changes: Vec<Change>,
+}
+
+impl Comparison {
+    pub fn regressions(&self) -> impl Iterator<Item = &MetricDelta> {
+        self.metrics.iter().filter(|metric| metric.is_regression())
+    }
+
+    /// One line per metric, e.g. "H2D: 24.61 -> 22.03 GB/s, -2.58 (-10.5%), significant",
+    /// then the changes.
+    pub fn summary(&self) -> Vec<String> {
+        let mut lines: Vec<String> = self.metrics
+            .iter()
+            .map(|metric| {
+                format!(
+                    "{}: {:.2} -> {:.2} GB/s, {:+.2} ({:+.1}%), {}",
+                    metric.metric,
+                    metric.before,
+                    metric.after,
+                    metric.delta,
+                    metric.relative * 100.0,
+                    match metric.significance {
+                        Significance::Significant => "significant",
+                        Significance::WithinNoise => "within noise",
+                        Significance::Unknown => "no per-iteration statistics to test",
+                    }
+                )
+            })
+            .collect();
+        for change in &self.changes {
+            lines.push(
+                format!("{} changed: {} -> {}", change.setting, change.before, change.after)
+            );
+        }
+        lines
+    }
+}
+
+/// Compares `after` with `before`.
+pub fn compare(before: &StoredResult, after: &StoredResult) -> Comparison {
+    let statistics = (before.h2d_statistics, after.h2d_statistics);
+    let mut metrics = vec![MetricDelta::new("H2D", before.h2d, after.h2d, statistics)];
+    if let (Some(d2h_before), Some(d2h_after)) = (before.d2h, after.d2h) {
+        metrics.push(
+            MetricDelta::new(
+                "D2H",
+                d2h_before,
+                d2h_after,
+                (before.d2h_statistics, after.d2h_statistics)
+            )
+        );
+    }
+
+    let mut changes = Vec::new();
+    let mut compare = |setting: &'static str, before: String, after: String| {
+        if before != after {
+            changes.push(Change { setting, before, after });
+        }
+    };
+    let optional = |value: Option<String>| value.unwrap_or_else(|| "unknown".to_string());
+    compare("Device", before.device.clone(), after.device.clone());
+    let bytes = |result: &StoredResult| result.transfer_bytes.to_string();
+    compare("Transfer bytes", bytes(before), bytes(after));
+    compare("Run length", before.run_length.clone(), after.run_length.clone());
+    compare("Host buffer", before.host_buffer.clone(), after.host_buffer.clone());
+    compare("Memory", before.memory.clone(), after.memory.clone());
+    compare("Verification", before.verification.clone(), after.verification.clone());
+    compare(
+        "Link width",
+        optional(before.lanes.map(|lanes| format!("x{}", lanes))),
+        optional(after.lanes.map(|lanes| format!("x{}", lanes)))
+    );
+    compare(
+        "ECC",
+        optional(before.ecc.map(|ecc| ecc.to_string())),
+        optional(after.ecc.map(|ecc| ecc.to_string()))
+    );
+
+    if let (Some(before), Some(after)) = (&before.overhead, &after.overhead) {
+        compare("Platform", before.platform.clone(), after.platform.clone());
+        compare(
+            "Platform version",
+            before.platform_version.clone(),
+            after.platform_version.clone()
+        );
+    }
+    if let (Some(before), Some(after)) = (&before.reproducibility, &after.reproducibility) {
+        compare("Units", before.units.clone(), after.units.clone());
+        compare("Warm-up ms", before.warm_up_ms.to_string(), after.warm_up_ms.to_string());
+        compare("Discarded iterations", before.discard.to_string(), after.discard.to_string());
+        compare("Allocator", before.allocator.clone(), after.allocator.clone());
+        for (setting, before, after) in before.conditions.changes(&after.conditions) {
+            compare(setting, before, after);
+        }
+    }
+    Comparison { metrics, changes }
+}
+
+/// Welch's t-test on the iteration means.
+fn significance(before: &Statistics, after: &Statistics) -> Significance {
+    let variance = |statistics: &Statistics| {
+        statistics.std_dev.powi(2) / (statistics.iterations as f64)
+    };
+    let error = (variance(before) + variance(after)).sqrt();
+    let difference = (after.mean - before.mean).abs();
+    let significant = if error > 0.0 { difference / error > T_CRITICAL } else { difference > 0.0 };
+    if significant { Significance::Significant } else { Significance::WithinNoise }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn statistics(mean: f64, std_dev: f64, iterations: usize) -> Statistics {
+        Statistics { iterations, mean, median: mean, min: mean, max: mean, std_dev }
+    }
+
+    #[test]
+    fn difference_beyond_the_spread_is_significant() {
+        let before = statistics(24.0, 0.5, 10);
+        let after = statistics(22.0, 0.5, 10);
+        assert_eq!(significance(&before, &after), Significance::Significant);
+        assert_eq!(significance(&after, &before), Significance::Significant);
+    }
+
+    #[test]
+    fn difference_within_the_spread_is_noise() {
+        let before = statistics(24.0, 2.0, 10);
+        let after = statistics(23.5, 2.0, 10);
+        assert_eq!(significance(&before, &after), Significance::WithinNoise);
+    }
+
+    #[test]
+    fn more_iterations_make_a_difference_significant() {
+        // t = 1 / sqrt(2 * 4 / n): 1.58 at 20 iterations, 2.5 at 50
+        assert_eq!(
+            significance(&statistics(24.0, 2.0, 20), &statistics(23.0, 2.0, 20)),
+            Significance::WithinNoise
+        );
+        assert_eq!(
+            significance(&statistics(24.0, 2.0, 50), &statistics(23.0, 2.0, 50)),
+            Significance::Significant
+        );
+    }
+
+    #[test]
+    fn without_spread_any_difference_is_significant() {
+        let before = statistics(24.0, 0.0, 5);
+        assert_eq!(significance(&before, &statistics(24.0, 0.0, 5)), Significance::WithinNoise);
+        assert_eq!(significance(&before, &statistics(23.9, 0.0, 5)), Significance::Significant);
+    }
+}
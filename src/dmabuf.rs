@@ -0,0 +1,235 @@
+//! Transfers into a dma-buf imported with `cl_khr_external_memory_dma_buf`, the zero-copy
+//! path compositors and video pipelines use to hand buffers between devices and APIs.
+//!
+//! Linux only. The dma-buf is allocated from a DMA heap such as `/dev/dma_heap/system`; one
+//! exported by GBM, V4L2 or a display driver is imported the same way.
+
+use crate::device::default_queue;
+use crate::error::BenchError;
+use crate::registry::DeviceRegistry;
+use cl3::ext::{
+    clEnqueueAcquireExternalMemObjectsKHR_fn,
+    clEnqueueReleaseExternalMemObjectsKHR_fn,
+    clGetExtensionFunctionAddressForPlatform,
+    cl_mem,
+    cl_mem_properties,
+    CL_EXTERNAL_MEMORY_HANDLE_DMA_BUF_KHR,
+};
+use opencl3::device::Device;
+use opencl3::error_codes::{ ClError, CL_SUCCESS };
+use opencl3::memory::{ Buffer, ClMem, CL_MEM_READ_ONLY, CL_MEM_READ_WRITE };
+use opencl3::types::{ cl_context, cl_int, cl_mem_flags, CL_BLOCKING };
+use std::ffi::c_void;
+use std::io;
+use std::path::Path;
+use std::ptr;
+use std::time::{ Duration, Instant };
+
+#[cfg(target_os = "linux")]
+use std::os::fd::{ AsRawFd, FromRawFd, OwnedFd };
+
+/// Where `--dma-buf` allocates from unless another heap is named.
+pub const DEFAULT_HEAP: &str = "/dev/dma_heap/system";
+
+/// Throughput into an imported dma-buf, see `measure_dma_buf`.
+#[derive(Clone, Copy, Debug)]
+pub struct DmaBufResult {
+    /// Host writes into the dma-buf, in GB/s.
+    pub upload: f64,
+    /// Device copies from a regular buffer into the dma-buf, in GB/s.
+    pub copy: f64,
+}
+
+impl DmaBufResult {
+    pub fn summary(&self) -> String {
+        format!(
+            "{:.2} GB/s uploaded into an imported dma-buf, {:.2} GB/s copied into it on the device",
+            self.upload,
+            self.copy
+        )
+    }
+}
+
+/// `clCreateBufferWithProperties`, core in OpenCL 3.0 and looked up at run time so that
+/// older loaders still work for everything else.
+type CreateBufferWithProperties = unsafe extern "C" fn(
+    cl_context,
+    *const cl_mem_properties,
+    cl_mem_flags,
+    usize,
+    *mut c_void,
+    *mut cl_int
+) -> cl_mem;
+
+/// Allocates a `bytes`-sized dma-buf from `heap`, imports it on `device` and measures host
+/// uploads and device copies into it, `iterations` times each.
+pub fn measure_dma_buf(
+    heap: &Path,
+    bytes: usize,
+    iterations: usize,
+    device: &Device
+) -> Result<DmaBufResult, BenchError> {
+    if !cfg!(target_os = "linux") {
+        return Err(BenchError::Unsupported("dma-buf import is only available on Linux".into()));
+    }
+    if !device.extensions().unwrap_or_default().contains("cl_khr_external_memory_dma_buf") {
+        return Err(
+            BenchError::Unsupported(
+                "the device does not support cl_khr_external_memory_dma_buf".into()
+            )
+        );
+    }
+    let unsupported = |e: io::Error| {
+        BenchError::Unsupported(format!("cannot allocate from {}: {}", heap.display(), e))
+    };
+    let dma_buf = allocate(heap, bytes).map_err(unsupported)?;
+
+    let context = DeviceRegistry::global().context(device)?;
+    let queue = default_queue(&context, 0)?;
+    let create = create_buffer_with_properties().ok_or_else(|| {
+        BenchError::Unsupported("the OpenCL loader lacks clCreateBufferWithProperties".into())
+    })?;
+    let properties = [
+        CL_EXTERNAL_MEMORY_HANDLE_DMA_BUF_KHR as cl_mem_properties,
+        raw_fd(&dma_buf) as cl_mem_properties,
+        0,
+    ];
+    let mut status = CL_SUCCESS;
+    let mem = unsafe {
+        create(
+            context.get(),
+            properties.as_ptr(),
+            CL_MEM_READ_WRITE,
+            bytes,
+            ptr::null_mut(),
+            &mut status
+        )
+    };
+    if status != CL_SUCCESS {
+        return Err(
+            BenchError::Unsupported(
+                format!("the driver could not import the dma-buf: {}", ClError(status))
+            )
+        );
+    }
+    let mut imported = Buffer::<u8>::new(mem);
+
+    // Ownership moves between APIs with explicit acquire and release commands
+    let platform = device.platform()?;
+    let (acquire, release) = unsafe {
+        (
+            std::mem::transmute::<*mut c_void, clEnqueueAcquireExternalMemObjectsKHR_fn>(
+                clGetExtensionFunctionAddressForPlatform(
+                    platform,
+                    c"clEnqueueAcquireExternalMemObjectsKHR".as_ptr()
+                )
+            ),
+            std::mem::transmute::<*mut c_void, clEnqueueReleaseExternalMemObjectsKHR_fn>(
+                clGetExtensionFunctionAddressForPlatform(
+                    platform,
+                    c"clEnqueueReleaseExternalMemObjectsKHR".as_ptr()
+                )
+            ),
+        )
+    };
+    let (Some(acquire), Some(release)) = (acquire, release) else {
+        return Err(
+            BenchError::Unsupported("the driver lacks the external memory entry points".into())
+        );
+    };
+    let objects = [imported.get()];
+    let owned = |f: unsafe extern "C" fn(_, _, _, _, _, _) -> cl_int| {
+        let status = unsafe {
+            f(queue.get(), 1, objects.as_ptr(), 0, ptr::null(), ptr::null_mut())
+        };
+        if status == CL_SUCCESS { Ok(()) } else { Err(BenchError::OpenCl(ClError(status))) }
+    };
+
+    let data = vec![1u8; bytes];
+    let source = unsafe {
+        Buffer::<u8>::create(&context, CL_MEM_READ_ONLY, bytes, ptr::null_mut())?
+    };
+    let mut upload = Duration::ZERO;
+    let mut copy = Duration::ZERO;
+    for _ in 0..iterations {
+        owned(acquire)?;
+        let start = Instant::now();
+        unsafe {
+            queue.enqueue_write_buffer(&mut imported, CL_BLOCKING, 0, &data, &[])?;
+        }
+        upload += start.elapsed();
+
+        let start = Instant::now();
+        unsafe {
+            queue.enqueue_copy_buffer(&source, &mut imported, 0, 0, bytes, &[])?;
+        }
+        queue.finish()?;
+        copy += start.elapsed();
+        owned(release)?;
+        queue.finish()?;
+    }
+
+    let total = (bytes as f64) * (iterations as f64);
+    Ok(DmaBufResult {
+        upload: total / upload.as_secs_f64() / 1e9,
+        copy: total / copy.as_secs_f64() / 1e9,
+    })
+}
+
+/// `struct dma_heap_allocation_data` from `linux/dma-heap.h`.
+#[cfg(target_os = "linux")]
+#[repr(C)]
+struct HeapAllocation {
+    len: u64,
+    fd: u32,
+    fd_flags: u32,
+    heap_flags: u64,
+}
+
+/// `_IOWR('H', 0x0, struct dma_heap_allocation_data)`
+#[cfg(target_os = "linux")]
+const DMA_HEAP_IOCTL_ALLOC: u64 = 0xc018_4800;
+
+#[cfg(target_os = "linux")]
+fn allocate(heap: &Path, bytes: usize) -> io::Result<OwnedFd> {
+    let heap = std::fs::File::open(heap)?;
+    let mut request = HeapAllocation {
+        len: bytes as u64,
+        fd: 0,
+        fd_flags: (libc::O_RDWR | libc::O_CLOEXEC) as u32,
+        heap_flags: 0,
+    };
+    if unsafe { libc::ioctl(heap.as_raw_fd(), DMA_HEAP_IOCTL_ALLOC as _, &mut request) } < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(unsafe { OwnedFd::from_raw_fd(request.fd as i32) })
+}
+
+#[cfg(target_os = "linux")]
+fn raw_fd(fd: &OwnedFd) -> i32 {
+    fd.as_raw_fd()
+}
+
+#[cfg(target_os = "linux")]
+fn create_buffer_with_properties() -> Option<CreateBufferWithProperties> {
+    // The OpenCL loader is already linked into the process
+    let process = libloading::os::unix::Library::this();
+    unsafe { process.get::<CreateBufferWithProperties>(b"clCreateBufferWithProperties\0") }
+        .ok()
+        .map(|symbol| *symbol)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn allocate(_: &Path, _: usize) -> io::Result<()> {
+    Err(io::ErrorKind::Unsupported.into())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn raw_fd(_: &()) -> i32 {
+    -1
+}
+
+#[cfg(not(target_os = "linux"))]
+fn create_buffer_with_properties() -> Option<CreateBufferWithProperties> {
+    None
+}
@@ -0,0 +1,60 @@
+//! How much ECC costs a device, from its stored results. ECC keeps check bits alongside the
+//! data in GPU memory, which on some boards takes a share of its bandwidth; once a device has
+//! been measured with ECC both on and off, the two sets of runs answer how much.
+
+use crate::store::StoredResult;
+
+/// Latest stored runs of a device that `compare` is given.
+pub const COMPARED_RUNS: usize = 200;
+
+/// Mean throughput of the runs with ECC in one state.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct EccRuns {
+    pub runs: usize,
+    /// GB/s, both directions averaged as in `StoredResult::mean_throughput`.
+    pub mean: f64,
+}
+
+/// Stored runs of one device and transfer size, by ECC state, see `compare`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct EccComparison {
+    pub enabled: EccRuns,
+    pub disabled: EccRuns,
+}
+
+impl EccComparison {
+    /// How much slower the runs with ECC on were, as a fraction of those with it off.
+    pub fn cost(&self) -> f64 {
+        1.0 - self.enabled.mean / self.disabled.mean
+    }
+
+    pub fn summary(&self) -> String {
+        format!(
+            "ECC on: {:.2} GB/s over {} runs, off: {:.2} GB/s over {} runs, ECC costs {:.1}%",
+            self.enabled.mean,
+            self.enabled.runs,
+            self.disabled.mean,
+            self.disabled.runs,
+            self.cost() * 100.0
+        )
+    }
+}
+
+/// Compares the results in `history` of `transfer_bytes` per transfer by ECC state, or `None`
+/// unless there are runs with ECC both on and off. Runs of other sizes are left out, as the
+/// size alone moves throughput more than ECC does.
+pub fn compare(history: &[StoredResult], transfer_bytes: u64) -> Option<EccComparison> {
+    let runs = |enabled: bool| {
+        let means: Vec<f64> = history
+            .iter()
+            .filter(|result| result.transfer_bytes == transfer_bytes)
+            .filter(|result| result.ecc == Some(enabled))
+            .map(StoredResult::mean_throughput)
+            .collect();
+        (!means.is_empty()).then(|| EccRuns {
+            runs: means.len(),
+            mean: means.iter().sum::<f64>() / (means.len() as f64),
+        })
+    };
+    Some(EccComparison { enabled: runs(true)?, disabled: runs(false)? })
+}
@@ -0,0 +1,140 @@
+//! Features that read or write files only privileged users may, and relaunching the app
+//! with those privileges, so that a user without them is told what is missing rather than
+//! shown empty telemetry. Relaunching goes through polkit's `pkexec` on Linux and a UAC
+//! prompt on Windows.
+
+use gputhroughput::telemetry::{ PciAddress, Sensors };
+use gputhroughput::MyDevice;
+use std::fmt;
+use std::fs::{ File, OpenOptions };
+use std::io;
+#[cfg(feature = "gui")]
+use std::path::Path;
+use std::path::PathBuf;
+#[cfg(feature = "gui")]
+use std::process::Command;
+
+/// A reading or action that needs more privileges than the user may have.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Privileged {
+    TrafficCounters,
+    ErrorCounters,
+    Power,
+    LinkRetraining,
+}
+
+impl Privileged {
+    /// Whether this is a sensor that `sensors` turns on.
+    pub fn is_sensor_in(&self, sensors: Sensors) -> bool {
+        match self {
+            Privileged::TrafficCounters | Privileged::ErrorCounters => sensors.link,
+            Privileged::Power => sensors.power,
+            Privileged::LinkRetraining => false,
+        }
+    }
+}
+
+impl fmt::Display for Privileged {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Privileged::TrafficCounters => write!(f, "PCIe traffic counters"),
+            Privileged::ErrorCounters => write!(f, "PCIe error counters"),
+            Privileged::Power => write!(f, "board power"),
+            Privileged::LinkRetraining => write!(f, "link retraining"),
+        }
+    }
+}
+
+/// What the current user is denied for `device`. Empty where the device has no PCI address
+/// or everything is accessible.
+pub fn denied(device: &MyDevice) -> Vec<Privileged> {
+    let Some(address) = PciAddress::of(device.get_device()) else {
+        return Vec::new();
+    };
+    let sysfs = PathBuf::from(format!("/sys/bus/pci/devices/{}", address));
+    let mut denied = Vec::new();
+    if is_denied(File::open(sysfs.join("pcie_bw"))) {
+        denied.push(Privileged::TrafficCounters);
+    }
+    if is_denied(File::open(sysfs.join("aer_dev_correctable"))) {
+        denied.push(Privileged::ErrorCounters);
+    }
+    let power = std::fs
+        ::read_dir(sysfs.join("hwmon"))
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path().join("power1_average"))
+        .find(|path| path.exists());
+    if power.is_some_and(|path| is_denied(File::open(path))) {
+        denied.push(Privileged::Power);
+    }
+    // Retraining writes the config space of the port above the GPU, which needs root
+    let upstream = std::fs
+        ::canonicalize(&sysfs)
+        .ok()
+        .and_then(|device| Some(device.parent()?.join("config")))
+        .filter(|config| config.exists());
+    if upstream.is_some_and(|config| is_denied(OpenOptions::new().write(true).open(config))) {
+        denied.push(Privileged::LinkRetraining);
+    }
+    denied
+}
+
+/// Whether opening a file failed for lack of permission, rather than succeeding or the file
+/// not being there.
+fn is_denied(opened: io::Result<File>) -> bool {
+    matches!(opened, Err(e) if e.kind() == io::ErrorKind::PermissionDenied)
+}
+
+/// How to get the privileges on this platform, for messages.
+pub fn hint() -> &'static str {
+    if cfg!(windows) {
+        "run gputhroughput as administrator"
+    } else {
+        "rerun with sudo, or relaunch through pkexec"
+    }
+}
+
+/// Starts another instance of the app with elevated privileges and the same arguments. The
+/// caller should exit once this succeeds.
+#[cfg(feature = "gui")]
+pub fn relaunch() -> io::Result<()> {
+    let exe = std::env::current_exe()?;
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let mut command = elevated_command(&exe, &args);
+    command.spawn().map(drop)
+}
+
+#[cfg(all(feature = "gui", windows))]
+fn elevated_command(exe: &Path, args: &[String]) -> Command {
+    // Start-Process -Verb RunAs shows the UAC prompt
+    let quoted: Vec<String> = args
+        .iter()
+        .map(|arg| format!("'{}'", arg.replace('\'', "''")))
+        .collect();
+    let mut script = format!(
+        "Start-Process -Verb RunAs -FilePath '{}'",
+        exe.display().to_string().replace('\'', "''")
+    );
+    if !quoted.is_empty() {
+        script.push_str(&format!(" -ArgumentList {}", quoted.join(",")));
+    }
+    let mut command = Command::new("powershell");
+    command.args(["-NoProfile", "-Command", &script]);
+    command
+}
+
+#[cfg(all(feature = "gui", not(windows)))]
+fn elevated_command(exe: &Path, args: &[String]) -> Command {
+    // pkexec clears the environment, so pass on what a window needs to reach the display
+    let mut command = Command::new("pkexec");
+    command.arg("env");
+    for name in ["DISPLAY", "XAUTHORITY", "WAYLAND_DISPLAY", "XDG_RUNTIME_DIR"] {
+        if let Ok(value) = std::env::var(name) {
+            command.arg(format!("{}={}", name, value));
+        }
+    }
+    command.arg(exe).args(args);
+    command
+}
@@ -0,0 +1,131 @@
+use opencl3::error_codes::{
+    ClError,
+    CL_CONTEXT_TERMINATED_KHR,
+    CL_DEVICE_NOT_AVAILABLE,
+    CL_INVALID_BUFFER_SIZE,
+    CL_MEM_OBJECT_ALLOCATION_FAILURE,
+    CL_OUT_OF_HOST_MEMORY,
+    CL_OUT_OF_RESOURCES,
+};
+use std::fmt;
+use std::io;
+use std::process::ExitCode;
+
+/// Everything that can stop a measurement, grouped so that each kind of
+/// failure maps onto its own process exit code.
+#[derive(Debug)]
+pub enum BenchError {
+    /// No usable device matched the request.
+    NoDevice(String),
+    /// The device or host could not provide the requested buffer.
+    Allocation(ClError),
+    /// Data read back from the device differs from what was written.
+    Verification {
+        index: usize,
+        expected: f32,
+        actual: f32,
+    },
+    /// The device-side checksum of an upload differs from the host's, see `checksum`.
+    ChecksumMismatch {
+        expected: u32,
+        actual: u32,
+    },
+    /// The run completed but did not reach the requested throughput.
+    BelowThreshold {
+        measured: f64,
+        threshold: f64,
+    },
+    /// A requested feature is not available on this system or needs more privileges.
+    Unsupported(String),
+    /// The driver reset the device or tore down the context (e.g. a Windows TDR).
+    DeviceReset(ClError),
+    /// Any other OpenCL failure.
+    OpenCl(ClError),
+    /// Writing an output file failed.
+    Io(io::Error),
+}
+
+/// Exit codes returned by the headless mode, as documented in `--help`.
+pub const EXIT_OTHER: u8 = 1;
+pub const EXIT_USAGE: u8 = 2;
+pub const EXIT_NO_DEVICE: u8 = 3;
+pub const EXIT_ALLOCATION: u8 = 4;
+pub const EXIT_VERIFICATION: u8 = 5;
+pub const EXIT_BELOW_THRESHOLD: u8 = 6;
+pub const EXIT_DEVICE_RESET: u8 = 7;
+
+impl BenchError {
+    pub fn exit_code(&self) -> ExitCode {
+        ExitCode::from(self.code())
+    }
+
+    /// The numeric exit code, also returned by the C ABI in `ffi`.
+    pub fn code(&self) -> u8 {
+        match self {
+            BenchError::NoDevice(_) => EXIT_NO_DEVICE,
+            BenchError::Allocation(_) => EXIT_ALLOCATION,
+            BenchError::Verification { .. } | BenchError::ChecksumMismatch { .. } =>
+                EXIT_VERIFICATION,
+            BenchError::BelowThreshold { .. } => EXIT_BELOW_THRESHOLD,
+            BenchError::DeviceReset(_) => EXIT_DEVICE_RESET,
+            BenchError::Unsupported(_) | BenchError::OpenCl(_) | BenchError::Io(_) => EXIT_OTHER,
+        }
+    }
+}
+
+impl From<ClError> for BenchError {
+    fn from(error: ClError) -> Self {
+        match error.0 {
+            | CL_MEM_OBJECT_ALLOCATION_FAILURE
+            | CL_OUT_OF_RESOURCES
+            | CL_OUT_OF_HOST_MEMORY
+            | CL_INVALID_BUFFER_SIZE => {
+                BenchError::Allocation(error)
+            }
+            CL_DEVICE_NOT_AVAILABLE | CL_CONTEXT_TERMINATED_KHR => BenchError::DeviceReset(error),
+            _ => BenchError::OpenCl(error),
+        }
+    }
+}
+
+impl From<io::Error> for BenchError {
+    fn from(error: io::Error) -> Self {
+        BenchError::Io(error)
+    }
+}
+
+impl fmt::Display for BenchError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BenchError::NoDevice(msg) => write!(f, "No device: {}", msg),
+            BenchError::Allocation(e) => write!(f, "Allocation failed: {}", e),
+            BenchError::Verification { index, expected, actual } =>
+                write!(
+                    f,
+                    "Verification failed at element {}: expected {}, read back {}",
+                    index,
+                    expected,
+                    actual
+                ),
+            BenchError::ChecksumMismatch { expected, actual } =>
+                write!(
+                    f,
+                    "Checksum verification failed: expected {:#010x}, the device computed {:#010x}",
+                    expected,
+                    actual
+                ),
+            BenchError::BelowThreshold { measured, threshold } =>
+                write!(
+                    f,
+                    "Throughput {:.2} GB/s is below the threshold of {:.2} GB/s",
+                    measured,
+                    threshold
+                ),
+            BenchError::Unsupported(msg) => write!(f, "Unsupported: {}", msg),
+            BenchError::DeviceReset(e) =>
+                write!(f, "The device was reset during measurement and did not recover: {}", e),
+            BenchError::OpenCl(e) => write!(f, "OpenCL error: {}", e),
+            BenchError::Io(e) => write!(f, "I/O error: {}", e),
+        }
+    }
+}
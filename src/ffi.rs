@@ -0,0 +1,161 @@
+//! A small C ABI over the measurement core, so that C and C++ test suites can embed it.
+//! `include/gputhroughput.h` declares these functions; it is generated with
+//! `cbindgen --config cbindgen.toml --output include/gputhroughput.h`.
+//!
+//! Every function returns 0 on success or one of the headless mode's exit codes, see
+//! `error`. Devices are addressed by their index in the order `gt_device_count` counts them.
+
+use crate::api::{ self, BenchmarkRequest };
+use crate::error::{ BenchError, EXIT_OTHER, EXIT_USAGE };
+use crate::memory::Memory;
+use crate::partition::Partition;
+use crate::telemetry::Sensors;
+use crate::{
+    elements_in,
+    enumerate_devices,
+    HostBuffer,
+    MeasureConfig,
+    MyDevice,
+    Pacing,
+    RunLength,
+    SizeUnits,
+    Verification,
+};
+use std::os::raw::{ c_char, c_int };
+use std::panic::{ self, AssertUnwindSafe };
+use std::time::Duration;
+
+/// What to measure, see `gt_benchmark`.
+#[repr(C)]
+pub struct gt_config {
+    /// Size of each transfer in MiB, 2^20 bytes, as before sizes had a choice of units.
+    pub size_mb: usize,
+    /// Transfers per direction, at least one.
+    pub iterations: usize,
+    /// Non-zero to allocate a new host buffer for every iteration.
+    pub fresh_host_buffer: c_int,
+}
+
+/// The outcome of `gt_benchmark`, averaged over all iterations.
+#[repr(C)]
+pub struct gt_result {
+    pub h2d_gbps: f64,
+    pub d2h_gbps: f64,
+    pub h2d_seconds: f64,
+    pub d2h_seconds: f64,
+}
+
+/// Number of OpenCL GPU devices.
+#[no_mangle]
+pub extern "C" fn gt_device_count() -> usize {
+    panic::catch_unwind(|| enumerate_devices().len()).unwrap_or_default()
+}
+
+/// Copies the NUL-terminated name of device `index` into `buffer`, truncated to fit `length`
+/// bytes. Returns the length the name needs including the terminator, or 0 if there is no
+/// such device, so that callers can size `buffer` with a first call passing `length` 0.
+///
+/// # Safety
+///
+/// `buffer` must be valid for writes of `length` bytes, or null if `length` is 0.
+#[no_mangle]
+pub unsafe extern "C" fn gt_device_name(index: usize, buffer: *mut c_char, length: usize) -> usize {
+    let Some(device) = device_at(index) else {
+        return 0;
+    };
+    let name = device.name().as_bytes();
+    if !buffer.is_null() && length > 0 {
+        let copied = name.len().min(length - 1);
+        std::ptr::copy_nonoverlapping(name.as_ptr().cast::<c_char>(), buffer, copied);
+        *buffer.add(copied) = 0;
+    }
+    name.len() + 1
+}
+
+/// Measures device `index` with `config` and stores the throughput in `result`.
+///
+/// # Safety
+///
+/// `config` must point to a valid `gt_config` and `result` to writable memory for a
+/// `gt_result`.
+#[no_mangle]
+pub unsafe extern "C" fn gt_benchmark(
+    index: usize,
+    config: *const gt_config,
+    result: *mut gt_result
+) -> c_int {
+    if config.is_null() || result.is_null() {
+        return EXIT_USAGE.into();
+    }
+    let config = &*config;
+    if config.size_mb == 0 || config.iterations == 0 {
+        return EXIT_USAGE.into();
+    }
+
+    // Unwinding into C is undefined behavior, so a panic becomes an error code
+    let outcome = panic::catch_unwind(AssertUnwindSafe(|| benchmark(index, config)));
+    match outcome {
+        Ok(Ok(measured)) => {
+            result.write(measured);
+            0
+        }
+        Ok(Err(e)) => e.code().into(),
+        Err(_) => EXIT_OTHER.into(),
+    }
+}
+
+fn benchmark(index: usize, config: &gt_config) -> Result<gt_result, BenchError> {
+    let device = device_at(index).ok_or_else(||
+        BenchError::NoDevice(format!("there is no device {}", index))
+    )?;
+    let request = BenchmarkRequest {
+        device,
+        partition: Partition::None,
+        sub_device: 0,
+        link_gen: None,
+        config: MeasureConfig {
+            data_size: elements_in(config.size_mb, SizeUnits::Binary)?,
+            length: RunLength::Iterations(config.iterations),
+            host_buffer: if config.fresh_host_buffer != 0 {
+                HostBuffer::Fresh
+            } else {
+                HostBuffer::Reuse
+            },
+            memory: Memory::Buffer,
+            pacing: Pacing::default(),
+            verification: Verification::ReadBack,
+            warm_up: Duration::ZERO,
+            discard: 0,
+            sensors: Sensors::default(),
+            units: SizeUnits::Binary,
+        },
+        threads: None,
+        thread_mapping: false,
+        patterns: false,
+        latency: false,
+        completion: false,
+        ramp: None,
+        offsets: None,
+        scatter: None,
+        inflight: false,
+        contention: false,
+        round_trip: false,
+        random_sizes: None,
+        peer: None,
+        stream: None,
+        dma_buf: None,
+        overhead: false,
+    };
+    let record = api::execute(&request, &mut ())?;
+    let throughput = record.throughput;
+    Ok(gt_result {
+        h2d_gbps: throughput.h2d_throughput,
+        d2h_gbps: throughput.d2h_throughput,
+        h2d_seconds: throughput.h2d_duration,
+        d2h_seconds: throughput.d2h_duration,
+    })
+}
+
+fn device_at(index: usize) -> Option<MyDevice> {
+    panic::catch_unwind(|| enumerate_devices().into_iter().nth(index)).ok().flatten()
+}
@@ -0,0 +1,3017 @@
+//! The graphical interface, started unless a headless run is asked for. Left out of builds
+//! without the `gui` feature, as are the modules only it uses.
+
+use crate::capabilities_tab;
+use crate::config::{ self, Config };
+use crate::console::Console;
+use crate::elevation::{ self, Privileged };
+use crate::locale::NumberFormat;
+use crate::plan::Plan;
+use crate::plot::{ self, Palette };
+use crate::resume::Checkpoint;
+use crate::settings::{ DeviceDefaults, Settings };
+use crate::snapshots::{ self, DriverUpdate };
+use crate::taskbar::{ Progress, Taskbar };
+use eframe::egui;
+use eframe::glow::{ self, HasContext };
+use gputhroughput::alerts::Alerter;
+use gputhroughput::api::{ self, BenchmarkRequest, MeasurementRecord, Phase, ProgressSink };
+use gputhroughput::asymmetry::Asymmetry;
+use gputhroughput::bursts::{ self, BurstResult, SizeRange };
+use gputhroughput::capabilities;
+use gputhroughput::coldstart::ColdStart;
+use gputhroughput::community::{ self, Ranking, Submission };
+use gputhroughput::completion::{ self, CompletionResult };
+use gputhroughput::concurrency::{ self, ScalingResult };
+use gputhroughput::contention::{ self, ContentionResult };
+use gputhroughput::dmabuf::{ self, DmaBufResult };
+use gputhroughput::ecc::{ self, EccComparison };
+use gputhroughput::error::{ self, BenchError };
+use gputhroughput::health::{ Grade, HealthScore };
+use gputhroughput::hooks::Hook;
+use gputhroughput::hybrid::Hybrid;
+use gputhroughput::inflight::{ self, InflightResult };
+use gputhroughput::interop::{ self, GlContext, InteropResult };
+use gputhroughput::latency::{ self, LatencyResult };
+use gputhroughput::live::{ self, LiveReadout };
+use gputhroughput::matrix::{ self, Matrix, MatrixResult };
+use gputhroughput::memory::Memory;
+use gputhroughput::metrics;
+use gputhroughput::modes::{ ModeResult, Registry };
+use gputhroughput::numa::{ self, MappingResult };
+use gputhroughput::offsets::{ self, OffsetResult, Offsets };
+use gputhroughput::partition::Partition;
+use gputhroughput::patterns::{ self, PatternResult };
+use gputhroughput::peer::{ self, PeerResult };
+use gputhroughput::precision::{ significant, Measurement };
+use gputhroughput::ramp::{ self, RampResult };
+use gputhroughput::reproducibility::Conditions;
+use gputhroughput::roundtrip::{ self, RoundTripResult };
+use gputhroughput::scatter::{ self, ScatterResult };
+use gputhroughput::simulate::{ self, Failure };
+use gputhroughput::store::{ ResultStore, StoredResult };
+use gputhroughput::streaming::{ self, StreamResult };
+use gputhroughput::sweep::{ self, SweepResult };
+use gputhroughput::telemetry::{ self, GpuState, LinkStatus, PciAddress, Sensors, Telemetry };
+use gputhroughput::theoretical::Maximums;
+use gputhroughput::trace;
+use gputhroughput::unified::UnifiedMemory;
+use gputhroughput::virtualization::Environment;
+use gputhroughput::{
+    enumerate_devices,
+    HostBuffer,
+    MeasureConfig,
+    MyDevice,
+    Pacing,
+    RunLength,
+    SizeUnits,
+    Throughput,
+    Vendor,
+    Verification,
+};
+use opencl3::types::cl_float;
+use std::collections::HashMap;
+use std::ops::ControlFlow;
+use std::process::ExitCode;
+use std::sync::atomic::{ AtomicBool, Ordering };
+use std::sync::{ Arc, Mutex };
+use std::time::Duration;
+#[cfg(target_os = "android")]
+use winit::platform::android::{ activity::AndroidApp, EventLoopBuilderExtAndroid };
+
+/// Where the GUI exports traces, relative to the configured export directory.
+const TRACE_FILE: &str = "gputhroughput-trace.json";
+/// Where "Save Plan" writes and "Load Plan" reads, inside the export directory.
+const PLAN_FILE: &str = "gputhroughput-plan.toml";
+
+/// The window title, followed by the run's progress or result while there is one.
+const APP_TITLE: &str = "GPU Throughput App";
+
+/// Throughput results kept per device for the sparklines in the selector.
+const HISTORY_LEN: usize = 20;
+
+/// Configurations kept for "Repeat Last Run" and the list of recent runs.
+const RECENT_RUNS: usize = 5;
+
+/// Result cards that can be pinned side by side at once.
+const PINNED_CARDS: usize = 4;
+
+/// Largest transfer the size slider offers, in MB or MiB. Kept below 4 GB on 32-bit platforms,
+/// where larger byte counts overflow `usize`.
+const MAX_DATA_SIZE: usize = if usize::BITS > 32 { 10000 } else { 4095 };
+
+/// Transfer sizes in MB or MiB and queue counts the matrix section offers.
+const MATRIX_SIZES: [usize; 5] = [16, 64, 256, 1024, 4095];
+const MATRIX_QUEUES: [usize; 4] = [1, 2, 4, 8];
+
+#[derive(Clone, Copy, PartialEq)]
+enum Tab {
+    Benchmark,
+    Capabilities,
+}
+
+/// Order of the devices in the device selector.
+#[derive(Clone, Copy, PartialEq)]
+enum DeviceSort {
+    /// As `--list-devices` and `--device` number them.
+    Index,
+    Name,
+    /// Largest device memory first.
+    Memory,
+}
+
+impl DeviceSort {
+    const ALL: [DeviceSort; 3] = [DeviceSort::Index, DeviceSort::Name, DeviceSort::Memory];
+
+    fn label(self) -> &'static str {
+        match self {
+            DeviceSort::Index => "Index",
+            DeviceSort::Name => "Name",
+            DeviceSort::Memory => "VRAM",
+        }
+    }
+}
+
+/// The latest single run on one device, kept while other devices are measured.
+#[derive(Clone)]
+struct SessionResult {
+    /// `MyDevice::key` of the device.
+    key: usize,
+    label: String,
+    /// Transfer size, run length and memory, e.g. "256 MiB, 10 iterations per direction,
+    /// buffer".
+    configuration: String,
+    h2d: f64,
+    d2h: f64,
+}
+
+/// A result copied out of the Results area, so that later runs do not replace it.
+struct PinnedCard {
+    title: String,
+    lines: Vec<String>,
+}
+
+/// A single run's device and configuration, for running it again with one click.
+struct RecentRun {
+    /// `MyDevice::key` of the device.
+    key: usize,
+    label: String,
+    plan: Plan,
+}
+
+struct App {
+    tab: Tab,
+    throughput: Arc<Mutex<Throughput>>,
+    data_size: usize,
+    units: SizeUnits,
+    h2d_throughput: Measurement,
+    d2h_throughput: Measurement,
+    h2d_duration: f64,
+    d2h_duration: f64,
+    /// False after an upload-only run.
+    d2h_measured: bool,
+    pcie_speed: (i32, Vec<&'static str>),
+    telemetry: Telemetry,
+    selected_device: Option<MyDevice>,
+    devices: Vec<MyDevice>,
+    /// Shows only devices whose name, vendor or platform contains this, ignoring case.
+    device_filter: String,
+    device_sort: DeviceSort,
+    /// Devices whose driver changed since the last session, and what changed with it.
+    driver_updates: Vec<DriverUpdate>,
+    partition: Partition,
+    sub_device: usize,
+    run_length: RunLength,
+    host_buffer: HostBuffer,
+    memory: Memory,
+    submit_threads: usize,
+    pacing: Pacing,
+    verification: Verification,
+    warm_up: Duration,
+    /// Iterations run before the measured ones, see `MeasureConfig::discard`.
+    discard: usize,
+    link_gen: Option<u8>,
+    sensors: Sensors,
+    /// What the user lacks the privileges for on the selected device.
+    denied: Vec<Privileged>,
+    /// Theoretical PCIe and VRAM bandwidth of the selected device, see `theoretical`.
+    maximums: Maximums,
+    /// Whether the selected device drives the display, where gentle mode is suggested.
+    drives_display: bool,
+    /// How the selected device is virtualized, see `virtualization`.
+    environment: Option<Environment>,
+    /// Whether the selected device is half of a hybrid graphics setup, see `hybrid`.
+    hybrid: Option<Hybrid>,
+    /// Whether the selected device shares DRAM with the CPU, see `unified`.
+    unified: Option<UnifiedMemory>,
+    /// Whether to share each result with the community database, see `community`.
+    submit: bool,
+    /// Where the last submitted result ranks, or why it could not be submitted.
+    ranking: Arc<Mutex<Option<Result<Ranking, String>>>>,
+    /// The selected device's stored runs by ECC state, updated after each run, see `ecc`.
+    ecc_comparison: Arc<Mutex<Option<EccComparison>>>,
+    settings: Settings,
+    config: Config,
+    /// Iteration annotated in the results chart.
+    pinned_sample: Option<usize>,
+    /// Last title sent to the window, see `show_status`.
+    title: String,
+    /// The window's taskbar button, on Windows.
+    taskbar: Option<Taskbar>,
+    trace_status: Option<String>,
+    scaling: Arc<Mutex<Option<ScalingResult>>>,
+    mapping: Arc<Mutex<Option<MappingResult>>>,
+    patterns: Arc<Mutex<Option<PatternResult>>>,
+    latency: Arc<Mutex<Option<LatencyResult>>>,
+    completion: Arc<Mutex<Option<CompletionResult>>>,
+    /// Time per transfer the size ramp stops at.
+    ramp_budget: Duration,
+    ramp: Arc<Mutex<Option<RampResult>>>,
+    /// Filled in size by size while the sweep runs.
+    sweep: Arc<Mutex<Option<SweepResult>>>,
+    /// KB offsets to time transfers at, a sweep when empty.
+    offset_list: String,
+    offsets: Arc<Mutex<Option<OffsetResult>>>,
+    /// Small buffers the batched upload splits the transfer into.
+    scatter_buffers: usize,
+    scatter: Arc<Mutex<Option<ScatterResult>>>,
+    inflight: Arc<Mutex<Option<InflightResult>>>,
+    contention: Arc<Mutex<Option<ContentionResult>>>,
+    round_trip: Arc<Mutex<Option<RoundTripResult>>>,
+    /// Sizes for the random size measurement, as typed, e.g. "4KiB..256MiB".
+    random_range: String,
+    random_sizes: Arc<Mutex<Option<BurstResult>>>,
+    /// Modes compiled in or loaded from plugins, see `modes`.
+    modes: Registry,
+    /// The result of the mode run last.
+    mode_result: Arc<Mutex<Option<ModeResult>>>,
+    /// The second GPU of peer copies, see `peer`.
+    peer_device: Option<MyDevice>,
+    peer: Arc<Mutex<Option<PeerResult>>>,
+    /// The combinations picked for a matrix run, see `matrix`.
+    matrix: Matrix,
+    /// Filled in combination by combination while the matrix runs.
+    matrix_result: Arc<Mutex<Option<MatrixResult>>>,
+    /// Whether saved plans include the matrix, which then replaces the single run.
+    plan_matrix: bool,
+    plan_status: Option<String>,
+    /// Device label and mean H2D and D2H throughput from the last all-devices run, fastest
+    /// first.
+    comparison: Arc<Mutex<Vec<(String, f64, f64)>>>,
+    /// The latest single run on each device this session, most recent last, kept when
+    /// switching devices.
+    session: Arc<Mutex<Vec<SessionResult>>>,
+    /// Configurations of this session's single runs, most recent first.
+    recent_runs: Vec<RecentRun>,
+    /// Results pinned side by side above the latest one, oldest first.
+    pinned: Vec<PinnedCard>,
+    /// An all-devices run an earlier session did not finish, offered for resuming.
+    resume: Option<Checkpoint>,
+    /// File for the end-to-end streaming measurement.
+    stream_path: String,
+    streaming: Arc<Mutex<Option<StreamResult>>>,
+    interop: Arc<Mutex<Option<InteropResult>>>,
+    dma_heap: String,
+    dma_buf: Arc<Mutex<Option<DmaBufResult>>>,
+    /// The GL buffer of the last interop run, deleted before the next one creates another.
+    gl_buffer: Option<glow::Buffer>,
+    /// Recent mean H2D/D2H throughput of each device, oldest first, keyed by device id.
+    history: Arc<Mutex<HashMap<usize, Vec<f64>>>>,
+    /// Where finished results are kept, see `store`; the history starts from it.
+    store: Option<Arc<Mutex<Box<dyn ResultStore>>>>,
+    live: Arc<Mutex<LiveReadout>>,
+    stop: Arc<AtomicBool>,
+    /// What the running measurement is doing, shown next to the spinner.
+    phase: Arc<Mutex<Option<Phase>>>,
+    measuring: Arc<AtomicBool>,
+    /// The hidden window for injecting failures, toggled with Ctrl+Shift+D.
+    debug_menu: bool,
+    console: &'static Console,
+    /// Least severe level the console shows.
+    console_level: log::Level,
+    error_message: Arc<Mutex<Option<String>>>,
+}
+
+impl App {
+    /// Starts from the defaults of `config`, on the device its rule picks if any matches.
+    fn new(config: Config, console: &'static Console) -> Self {
+        let devices = enumerate_devices();
+        let selected_device = config.device
+            .select(&devices)
+            .ok()
+            .map(|index| devices[index].clone());
+        let defaults = config.defaults;
+        let store = config.storage.open();
+        let history = store
+            .as_ref()
+            .map(|store| history_from(store.as_ref(), &devices))
+            .unwrap_or_default();
+        let mut app = Self {
+            tab: Tab::Benchmark,
+            throughput: Arc::new(Mutex::new(Throughput::new())),
+            data_size: defaults.size, // in `units`
+            units: defaults.units,
+            h2d_throughput: Measurement::exact(0.0),
+            d2h_throughput: Measurement::exact(0.0),
+            h2d_duration: 0.0,
+            d2h_duration: 0.0,
+            d2h_measured: true,
+            pcie_speed: (0, vec![]),
+            telemetry: Telemetry::default(),
+            selected_device,
+            driver_updates: snapshots::update(&devices),
+            devices,
+            device_filter: String::new(),
+            device_sort: DeviceSort::Index,
+            partition: Partition::None,
+            sub_device: 0,
+            run_length: defaults.length,
+            host_buffer: defaults.host_buffer,
+            memory: defaults.memory,
+            submit_threads: 4,
+            pacing: Pacing::default(),
+            warm_up: defaults.warm_up,
+            discard: 0,
+            verification: defaults.verification,
+            link_gen: None,
+            sensors: config.sensors,
+            denied: Vec::new(),
+            maximums: Maximums::default(),
+            drives_display: false,
+            environment: None,
+            hybrid: None,
+            unified: None,
+            submit: config.submit,
+            ranking: Arc::new(Mutex::new(None)),
+            ecc_comparison: Arc::new(Mutex::new(None)),
+            settings: Settings::load(),
+            config,
+            pinned_sample: None,
+            title: APP_TITLE.to_string(),
+            taskbar: None,
+            trace_status: None,
+            scaling: Arc::new(Mutex::new(None)),
+            mapping: Arc::new(Mutex::new(None)),
+            patterns: Arc::new(Mutex::new(None)),
+            latency: Arc::new(Mutex::new(None)),
+            completion: Arc::new(Mutex::new(None)),
+            ramp_budget: ramp::DEFAULT_BUDGET,
+            ramp: Arc::new(Mutex::new(None)),
+            sweep: Arc::new(Mutex::new(None)),
+            offset_list: String::new(),
+            offsets: Arc::new(Mutex::new(None)),
+            scatter_buffers: 256,
+            scatter: Arc::new(Mutex::new(None)),
+            inflight: Arc::new(Mutex::new(None)),
+            contention: Arc::new(Mutex::new(None)),
+            round_trip: Arc::new(Mutex::new(None)),
+            random_range: "4KiB..256MiB".to_string(),
+            random_sizes: Arc::new(Mutex::new(None)),
+            modes: load_modes(),
+            mode_result: Arc::new(Mutex::new(None)),
+            peer_device: None,
+            peer: Arc::new(Mutex::new(None)),
+            matrix: Matrix {
+                memories: vec![Memory::Buffer, Memory::HostPtr],
+                sizes: vec![64, 256, 1024],
+                queues: vec![1, 2],
+            },
+            matrix_result: Arc::new(Mutex::new(None)),
+            plan_matrix: false,
+            plan_status: None,
+            comparison: Arc::new(Mutex::new(Vec::new())),
+            session: Arc::new(Mutex::new(Vec::new())),
+            recent_runs: Vec::new(),
+            pinned: Vec::new(),
+            resume: Checkpoint::load(),
+            stream_path: String::new(),
+            streaming: Arc::new(Mutex::new(None)),
+            interop: Arc::new(Mutex::new(None)),
+            dma_heap: dmabuf::DEFAULT_HEAP.to_string(),
+            dma_buf: Arc::new(Mutex::new(None)),
+            gl_buffer: None,
+            history: Arc::new(Mutex::new(history)),
+            store: store.map(|store| Arc::new(Mutex::new(store))),
+            live: Arc::new(Mutex::new(LiveReadout::default())),
+            stop: Arc::new(AtomicBool::new(false)),
+            phase: Arc::new(Mutex::new(None)),
+            measuring: Arc::new(AtomicBool::new(false)),
+            debug_menu: false,
+            console,
+            console_level: log::Level::Info,
+            error_message: Arc::new(Mutex::new(None)),
+        };
+        if let Some(device) = app.selected_device.clone() {
+            app.restore_defaults(&device);
+            app.inspect_device(&device);
+        }
+        app
+    }
+
+    /// Restores what was last measured on `device`, if anything was.
+    fn restore_defaults(&mut self, device: &MyDevice) {
+        if let Some(defaults) = self.settings.devices.get(&device.settings_key()) {
+            self.data_size = defaults.data_size;
+            self.units = defaults.units;
+            self.run_length = defaults.run_length;
+            self.host_buffer = defaults.host_buffer;
+        }
+    }
+
+    /// Reads what the UI shows about a newly selected device.
+    fn inspect_device(&mut self, device: &MyDevice) {
+        self.denied = elevation::denied(device);
+        self.maximums = Maximums::of(device.get_device());
+        self.drives_display = PciAddress::of(device.get_device()).is_some_and(
+            telemetry::drives_display
+        );
+        self.environment = Some(Environment::detect(device));
+        self.hybrid = Hybrid::detect(device);
+        self.unified = UnifiedMemory::detect(device);
+    }
+
+    /// The current configuration as a plan, see `plan`.
+    fn plan(&self) -> Plan {
+        Plan {
+            size: self.data_size,
+            units: self.units,
+            length: self.run_length,
+            host_buffer: self.host_buffer,
+            memory: self.memory,
+            verification: self.verification,
+            warm_up: self.warm_up,
+            discard: self.discard,
+            pacing: self.pacing,
+            // Experiments are started by their own buttons here, so none are planned
+            threads: None,
+            thread_mapping: false,
+            patterns: false,
+            latency: false,
+            completion: false,
+            ramp: None,
+            offsets: None,
+            scatter: None,
+            inflight: false,
+            contention: false,
+            round_trip: false,
+            random_sizes: None,
+            matrix: self.plan_matrix.then(|| self.matrix.clone()),
+        }
+    }
+
+    /// Takes over the configuration of the selected device's latest kept result, returning
+    /// what happened and which conditions differ from the result's.
+    fn reproduce_last(&mut self) -> String {
+        let (Some(store), Some(device)) = (&self.store, &self.selected_device) else {
+            return "No result to reproduce".to_string();
+        };
+        let recent = store.lock().unwrap().recent(&device.settings_key(), 1);
+        let result = match recent {
+            Ok(mut results) =>
+                match results.pop() {
+                    Some(result) => result,
+                    None => {
+                        return "No result kept for this device yet".to_string();
+                    }
+                }
+            Err(e) => {
+                return format!("Failed to read the stored results: {}", e);
+            }
+        };
+        match Plan::of_result(&result) {
+            Ok(plan) => {
+                self.apply_plan(plan);
+                let mut lines = vec!["Configuration of the latest result restored".to_string()];
+                if let Some(block) = result.reproducibility {
+                    let differences = block.conditions.differences(&Conditions::detect());
+                    lines.extend(differences.into_iter().map(|line| format!("Changed: {}", line)));
+                }
+                lines.join("\n")
+            }
+            Err(e) => format!("Failed to reproduce the result: {}", e),
+        }
+    }
+
+    /// Takes over the configuration of `plan`. Its experiments stay with their buttons.
+    fn apply_plan(&mut self, plan: Plan) {
+        self.data_size = plan.size.min(MAX_DATA_SIZE);
+        self.units = plan.units;
+        self.run_length = plan.length;
+        self.host_buffer = plan.host_buffer;
+        self.memory = plan.memory;
+        self.verification = plan.verification;
+        self.warm_up = plan.warm_up;
+        self.discard = plan.discard;
+        self.pacing = plan.pacing;
+        if let Some(threads) = plan.threads {
+            self.submit_threads = threads;
+        }
+        if let Some(budget) = plan.ramp {
+            self.ramp_budget = budget;
+        }
+        match plan.offsets {
+            Some(Offsets::At(offsets)) => {
+                self.offset_list = Offsets::At(offsets).to_string();
+            }
+            Some(Offsets::Sweep) => self.offset_list.clear(),
+            None => {}
+        }
+        if let Some(buffers) = plan.scatter {
+            self.scatter_buffers = buffers;
+        }
+        if let Some(range) = plan.random_sizes {
+            self.random_range = range.to_string();
+        }
+        self.plan_matrix = plan.matrix.is_some();
+        if let Some(matrix) = plan.matrix {
+            self.matrix = matrix;
+        }
+    }
+
+    /// Puts the run's progress, or its result once done, in the window title and on the
+    /// taskbar button, for users who switched to another window meanwhile.
+    fn show_status(&mut self, ctx: &egui::Context) {
+        let (status, progress) = if self.measuring.load(Ordering::SeqCst) {
+            let phase = *self.phase.lock().unwrap();
+            let live = self.live.lock().unwrap();
+            let config = self.measure_config();
+            let planned = config.length
+                .planned_bytes(config.iteration_bytes())
+                .map(|bytes| bytes / config.iteration_bytes());
+            match (phase, planned) {
+                (Some(Phase::Measuring), Some(iterations)) => {
+                    let done = (live.h2d.samples as f64) / (iterations as f64);
+                    (format!("{:.0}% measured", done * 100.0), Progress::Fraction(done))
+                }
+                (Some(Phase::Measuring), None) if live.h2d.samples > 0 => {
+                    let rolling = format!(
+                        "{:.2} {} H2D",
+                        self.units.rate(live.h2d.rolling),
+                        self.units.rate_unit()
+                    );
+                    (format!("Measuring, {}", rolling), Progress::Indeterminate)
+                }
+                (phase, _) => {
+                    let phase = phase.map_or("Measuring".to_string(), |phase| phase.to_string());
+                    (phase, Progress::Indeterminate)
+                }
+            }
+        } else if self.error_message.lock().unwrap().is_some() {
+            ("Failed".to_string(), Progress::None)
+        } else {
+            let throughput = self.throughput.lock().unwrap();
+            let (units, unit) = (self.units, self.units.rate_unit());
+            let status = if throughput.h2d_samples.is_empty() {
+                String::new()
+            } else if throughput.has_d2h() {
+                format!(
+                    "{:.2} {} H2D, {:.2} {} D2H",
+                    units.rate(throughput.h2d_throughput),
+                    unit,
+                    units.rate(throughput.d2h_throughput),
+                    unit
+                )
+            } else {
+                format!("{:.2} {} H2D", units.rate(throughput.h2d_throughput), unit)
+            };
+            (status, Progress::None)
+        };
+        let title = if status.is_empty() {
+            APP_TITLE.to_string()
+        } else {
+            format!("{} - {}", status, APP_TITLE)
+        };
+        if title != self.title {
+            ctx.send_viewport_cmd(egui::ViewportCommand::Title(title.clone()));
+            self.title = title;
+        }
+        if let Some(ref mut taskbar) = self.taskbar {
+            taskbar.show(progress);
+        }
+    }
+
+    fn measure_config(&self) -> MeasureConfig {
+        MeasureConfig {
+            data_size: (self.data_size * self.units.megabyte()) / std::mem::size_of::<f32>(),
+            length: self.run_length,
+            host_buffer: self.host_buffer,
+            memory: self.memory,
+            pacing: self.pacing,
+            warm_up: self.warm_up,
+            discard: self.discard,
+            verification: self.verification,
+            sensors: self.sensors,
+            units: self.units,
+        }
+    }
+
+    /// Replaces the previous interop run's GL buffer, on the UI thread where GL is current.
+    fn create_gl_buffer(
+        &mut self,
+        gl: &glow::Context
+    ) -> Result<(GlContext, glow::Buffer), String> {
+        let share = GlContext::current().ok_or("Error: no current GL context was found")?;
+        let bytes = i32::try_from(self.data_size * self.units.megabyte()).map_err(|_| {
+            "Error: GL buffers are limited to 2 GB, lower the data size".to_string()
+        })?;
+        unsafe {
+            if let Some(previous) = self.gl_buffer.take() {
+                gl.delete_buffer(previous);
+            }
+            let buffer = gl.create_buffer().map_err(|e| format!("Error: {}", e))?;
+            gl.bind_buffer(glow::ARRAY_BUFFER, Some(buffer));
+            gl.buffer_data_size(glow::ARRAY_BUFFER, bytes, glow::DYNAMIC_DRAW);
+            gl.bind_buffer(glow::ARRAY_BUFFER, None);
+            // OpenCL may only acquire the buffer once GL is done with it
+            gl.finish();
+            self.gl_buffer = Some(buffer);
+            Ok((share, buffer))
+        }
+    }
+
+    /// Measures `device` with the current configuration, as "Measure Throughput" does, and
+    /// puts the configuration on top of the recent runs.
+    fn measure(&mut self, ctx: &egui::Context, device: MyDevice) {
+        let continuous = self.run_length == RunLength::Continuous;
+        let configuration = format!(
+            "{} {}, {}, {}",
+            self.data_size,
+            self.units.size_unit(),
+            self.run_length,
+            self.memory
+        );
+        let run = RecentRun {
+            key: device.key(),
+            label: format!("{}: {}", device.name(), configuration),
+            plan: Plan { matrix: None, ..self.plan() },
+        };
+        self.recent_runs.retain(|earlier| earlier.key != run.key || earlier.plan != run.plan);
+        self.recent_runs.insert(0, run);
+        self.recent_runs.truncate(RECENT_RUNS);
+        let defaults = DeviceDefaults {
+            data_size: self.data_size,
+            units: self.units,
+            run_length: self.run_length,
+            host_buffer: self.host_buffer,
+        };
+        if self.settings.devices.get(&device.settings_key()) != Some(&defaults) {
+            self.settings.devices.insert(device.settings_key(), defaults);
+            if let Err(e) = self.settings.save() {
+                eprintln!("Warning: failed to save settings: {}", e);
+            }
+        }
+
+        self.pinned_sample = None;
+        self.trace_status = None;
+        let request = BenchmarkRequest {
+            device: device.clone(),
+            partition: self.partition,
+            sub_device: self.sub_device,
+            link_gen: self.link_gen,
+            config: self.measure_config(),
+            threads: None,
+            thread_mapping: false,
+            patterns: false,
+            latency: false,
+            completion: false,
+            ramp: None,
+            offsets: None,
+            scatter: None,
+            inflight: false,
+            contention: false,
+            round_trip: false,
+            random_sizes: None,
+            peer: None,
+            stream: None,
+            dma_buf: None,
+            overhead: false,
+        };
+        let throughput = Arc::clone(&self.throughput);
+        let session = Arc::clone(&self.session);
+        let index = self.devices
+            .iter()
+            .position(|d| d.key() == device.key())
+            .unwrap_or_default();
+        let label = format!("[{}] {}", index, device.name());
+        let history = Arc::clone(&self.history);
+        let store = self.store.clone();
+        let hook = self.config.hook.clone();
+        let ranking = Arc::clone(&self.ranking);
+        let ecc_comparison = Arc::clone(&self.ecc_comparison);
+        let endpoint = self.config.endpoint.clone().filter(|_| self.submit);
+        *ranking.lock().unwrap() = None;
+        let mut progress = GuiProgress {
+            live: Arc::clone(&self.live),
+            stop: Arc::clone(&self.stop),
+            phase: Arc::clone(&self.phase),
+            repaint: ctx.clone(),
+            alerter: Some(self.config.alerts.clone())
+                .filter(|alerts| continuous && alerts.is_enabled())
+                .map(|alerts| Alerter::new(alerts, device.name().to_string())),
+        };
+        *self.live.lock().unwrap() = LiveReadout::default();
+        self.stop.store(false, Ordering::SeqCst);
+
+        self.spawn_job(ctx, move || {
+            // Measure into a local copy so the UI never waits on the lock
+            let outcome = api::execute(&request, &mut progress);
+            *progress.phase.lock().unwrap() = None;
+            match outcome {
+                Ok(record) => {
+                    if let (Some(endpoint), false) = (endpoint, continuous) {
+                        let submission = Submission::new(&request.device, &record);
+                        *ranking.lock().unwrap() = Some(
+                            community::submit(&endpoint, &submission)
+                        );
+                    }
+                    if let Some(ref store) = store {
+                        keep(store, &request.device, &record);
+                        *ecc_comparison.lock().unwrap() = compare_ecc(
+                            store,
+                            &request.device,
+                            &record
+                        );
+                    }
+                    if let Some(ref hook) = hook {
+                        run_hook(hook, &request.device, &record);
+                    }
+                    let result = record.throughput;
+                    {
+                        // Released before `throughput` is taken, which the UI holds while
+                        // it reads the session
+                        let key = request.device.key();
+                        let mut session = session.lock().unwrap();
+                        session.retain(|earlier| earlier.key != key);
+                        session.push(SessionResult {
+                            key,
+                            label,
+                            configuration,
+                            h2d: result.h2d_throughput,
+                            d2h: result.d2h_throughput,
+                        });
+                    }
+                    let mean = result.mean_throughput();
+                    let mut history = history.lock().unwrap();
+                    let values = history.entry(request.device.key()).or_default();
+                    values.push(mean);
+                    if values.len() > HISTORY_LEN {
+                        values.remove(0);
+                    }
+                    *throughput.lock().unwrap() = result;
+                    Ok(())
+                }
+                Err(e) => {
+                    if let Some(ref alerter) = progress.alerter {
+                        alerter.on_error(&e);
+                    }
+                    if let BenchError::DeviceReset(_) = e {
+                        // Results from before the reset no longer describe the
+                        // device
+                        *throughput.lock().unwrap() = Throughput::new();
+                        let key = request.device.key();
+                        session
+                            .lock()
+                            .unwrap()
+                            .retain(|earlier| earlier.key != key);
+                    }
+                    Err(e)
+                }
+            }
+        });
+    }
+
+    /// Selects the device of the recent run at `index` and measures it with that run's
+    /// configuration again. The matrix and the experiments are left as they are.
+    fn repeat(&mut self, ctx: &egui::Context, index: usize) {
+        let run = &self.recent_runs[index];
+        let Some(device) = self.devices.iter().find(|d| d.key() == run.key).cloned() else {
+            return;
+        };
+        let plan = run.plan.clone();
+        let plan_matrix = self.plan_matrix;
+        self.apply_plan(plan);
+        self.plan_matrix = plan_matrix;
+        if self.selected_device.as_ref().map(MyDevice::key) != Some(device.key()) {
+            self.inspect_device(&device);
+            self.selected_device = Some(device.clone());
+        }
+        self.measure(ctx, device);
+    }
+
+    /// Measures the pending devices of `checkpoint` in turn with the current configuration,
+    /// saving the checkpoint after each so that an interrupted run can be resumed.
+    fn measure_devices(&mut self, ctx: &egui::Context, mut checkpoint: Checkpoint) {
+        self.resume = None;
+        let devices = self.devices.clone();
+        let config = self.measure_config();
+        let comparison = Arc::clone(&self.comparison);
+        let store = self.store.clone();
+        let hook = self.config.hook.clone();
+        let mut progress = GuiProgress {
+            live: Arc::clone(&self.live),
+            stop: Arc::clone(&self.stop),
+            phase: Arc::clone(&self.phase),
+            repaint: ctx.clone(),
+            alerter: None,
+        };
+        self.stop.store(false, Ordering::SeqCst);
+
+        self.spawn_job(ctx, move || {
+            let save = |checkpoint: &Checkpoint| {
+                if let Err(e) = checkpoint.save() {
+                    eprintln!("Warning: failed to save the run's progress: {}", e);
+                }
+            };
+            save(&checkpoint);
+            let mut failure = None;
+            while let Some((index, name)) = checkpoint.pending.first().cloned() {
+                // Skipped if the device is gone or another one took its index since
+                let device = devices
+                    .get(index)
+                    .filter(|device| device.settings_key() == name)
+                    .cloned();
+                if let Some(device) = device {
+                    let label = format!("[{}] {}", index, device.name());
+                    let request = BenchmarkRequest {
+                        device,
+                        partition: Partition::None,
+                        sub_device: 0,
+                        link_gen: None,
+                        config,
+                        threads: None,
+                        thread_mapping: false,
+                        patterns: false,
+                        latency: false,
+                        completion: false,
+                        ramp: None,
+                        offsets: None,
+                        scatter: None,
+                        inflight: false,
+                        contention: false,
+                        round_trip: false,
+                        random_sizes: None,
+                        peer: None,
+                        stream: None,
+                        dma_buf: None,
+                        overhead: false,
+                    };
+                    // One failing device should not hide the others' results
+                    match api::execute(&request, &mut progress) {
+                        Ok(record) => {
+                            if let Some(ref store) = store {
+                                keep(store, &request.device, &record);
+                            }
+                            if let Some(ref hook) = hook {
+                                run_hook(hook, &request.device, &record);
+                            }
+                            let result = record.throughput;
+                            let (h2d, d2h) = (result.h2d_throughput, result.d2h_throughput);
+                            checkpoint.completed.push((label, h2d, d2h));
+                        }
+                        Err(e) => {
+                            failure.get_or_insert(e);
+                        }
+                    }
+                }
+                checkpoint.pending.remove(0);
+                save(&checkpoint);
+            }
+            if let Err(e) = Checkpoint::clear() {
+                eprintln!("Warning: failed to remove the run's progress: {}", e);
+            }
+            *progress.phase.lock().unwrap() = None;
+            let mut results = checkpoint.completed;
+            results.sort_by(|a, b| (b.1 + b.2).total_cmp(&(a.1 + a.2)));
+            *comparison.lock().unwrap() = results;
+            failure.map_or(Ok(()), Err)
+        });
+    }
+
+    /// Runs `job` on a worker thread, showing the spinner until it finishes and its error after.
+    fn spawn_job<F>(&self, ctx: &egui::Context, job: F)
+        where F: FnOnce() -> Result<(), BenchError> + Send + 'static
+    {
+        self.measuring.store(true, Ordering::SeqCst);
+        *self.error_message.lock().unwrap() = None;
+        let measuring = Arc::clone(&self.measuring);
+        let error_message = Arc::clone(&self.error_message);
+        let ctx = ctx.clone();
+
+        std::thread::spawn(move || {
+            if let Err(e) = job() {
+                *error_message.lock().unwrap() = Some(format!("Error: {}", e));
+            }
+            measuring.store(false, Ordering::SeqCst);
+            ctx.request_repaint();
+        });
+    }
+}
+
+/// Feeds the live readout and the phase label, and stops continuous runs on request.
+struct GuiProgress {
+    live: Arc<Mutex<LiveReadout>>,
+    stop: Arc<AtomicBool>,
+    phase: Arc<Mutex<Option<Phase>>>,
+    repaint: egui::Context,
+    /// Set for continuous runs with alerts configured.
+    alerter: Option<Alerter>,
+}
+
+impl ProgressSink for GuiProgress {
+    fn on_sample(&mut self, h2d: f64, d2h: f64) -> ControlFlow<()> {
+        let mut live = self.live.lock().unwrap();
+        live.h2d.push(h2d);
+        if !d2h.is_nan() {
+            live.d2h.push(d2h);
+        }
+        if let Some(ref mut alerter) = self.alerter {
+            // Only once the rolling average spans a full window, so one slow sample at the
+            // start does not raise an alert
+            if live.h2d.samples >= live::WINDOW {
+                let d2h = if live.d2h.samples > 0 { live.d2h.rolling } else { f64::NAN };
+                alerter.on_rolling(live.h2d.rolling, d2h);
+            }
+        }
+        self.repaint.request_repaint();
+        if self.stop.load(Ordering::SeqCst) {
+            ControlFlow::Break(())
+        } else {
+            ControlFlow::Continue(())
+        }
+    }
+
+    fn on_phase_change(&mut self, phase: Phase) {
+        *self.phase.lock().unwrap() = Some(phase);
+        self.repaint.request_repaint();
+    }
+}
+
+impl eframe::App for App {
+    fn update(&mut self, ctx: &egui::Context, frame: &mut eframe::Frame) {
+        let toggle = egui::KeyboardShortcut::new(
+            egui::Modifiers::CTRL | egui::Modifiers::SHIFT,
+            egui::Key::D
+        );
+        self.show_status(ctx);
+        if ctx.input_mut(|input| input.consume_shortcut(&toggle)) {
+            self.debug_menu = !self.debug_menu;
+        }
+        egui::Window
+            ::new("Debug")
+            .open(&mut self.debug_menu)
+            .show(ctx, |ui| {
+                ui.label("Make the next measurement fail with:");
+                let mut failure = simulate::current();
+                ui.radio_value(&mut failure, None, "Nothing");
+                for kind in Failure::ALL {
+                    ui.radio_value(&mut failure, Some(kind), kind.to_string());
+                }
+                if failure != simulate::current() {
+                    simulate::set(failure);
+                }
+            });
+
+        egui::TopBottomPanel::top("tabs").show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                ui.selectable_value(&mut self.tab, Tab::Benchmark, "Benchmark");
+                ui.selectable_value(&mut self.tab, Tab::Capabilities, "Capabilities");
+                if !self.driver_updates.is_empty() {
+                    ui.colored_label(
+                        ui.visuals().warn_fg_color,
+                        "Driver updated since the last session, see Capabilities"
+                    );
+                }
+            });
+        });
+
+        egui::TopBottomPanel::bottom("console").show(ctx, |ui| {
+            egui::CollapsingHeader::new("Log").show(ui, |ui| {
+                self.console.show(ui, &mut self.console_level);
+            });
+        });
+
+        let gl = frame.gl().cloned();
+        egui::CentralPanel::default().show(ctx, |ui| {
+            if self.tab == Tab::Capabilities {
+                capabilities_tab::show(ui, &self.devices, &self.driver_updates);
+                return;
+            }
+
+            ui.columns(2, |columns| {
+                let (config_ui, result_ui) = columns.split_at_mut(1);
+                let config_ui = &mut config_ui[0];
+                let result_ui = &mut result_ui[0];
+
+                config_ui.heading("Configuration");
+
+                if let Some(checkpoint) = self.resume.clone() {
+                    let measured = checkpoint.completed.len();
+                    let total = measured + checkpoint.pending.len();
+                    config_ui.colored_label(
+                        config_ui.visuals().warn_fg_color,
+                        format!(
+                            "An earlier Measure All Devices run was interrupted after {} of {} \
+                             devices",
+                            measured,
+                            total
+                        )
+                    );
+                    config_ui.horizontal(|ui| {
+                        let measuring = self.measuring.load(Ordering::SeqCst);
+                        if ui.add_enabled(!measuring, egui::Button::new("Resume")).clicked() {
+                            self.data_size = checkpoint.data_size;
+                            self.units = checkpoint.units;
+                            self.run_length = checkpoint.run_length;
+                            self.host_buffer = checkpoint.host_buffer;
+                            self.memory = checkpoint.memory;
+                            self.verification = checkpoint.verification;
+                            self.pacing = checkpoint.pacing;
+                            self.warm_up = checkpoint.warm_up;
+                            self.discard = checkpoint.discard;
+                            self.measure_devices(ctx, checkpoint);
+                        } else if ui.button("Discard").clicked() {
+                            self.resume = None;
+                            if let Err(e) = Checkpoint::clear() {
+                                eprintln!("Warning: failed to remove the run's progress: {}", e);
+                            }
+                        }
+                    });
+                }
+
+                config_ui.horizontal(|ui| {
+                    ui.add(
+                        egui::Slider
+                            ::new(&mut self.data_size, 1..=MAX_DATA_SIZE)
+                            .text(format!("Data Size ({})", self.units.size_unit()))
+                    );
+                    egui::ComboBox
+                        ::from_id_source("units")
+                        .selected_text(self.units.to_string())
+                        .show_ui(ui, |ui| {
+                            for units in SizeUnits::ALL {
+                                ui.selectable_value(&mut self.units, units, units.to_string());
+                            }
+                        })
+                        .response.on_hover_text(
+                            "Decimal units as link speeds are quoted, or binary units as \
+                             memory is sized; stored results stay in GB/s either way"
+                        );
+                });
+
+                config_ui.horizontal(|ui| {
+                    egui::ComboBox
+                        ::from_id_source("run_length")
+                        .selected_text(match self.run_length {
+                            RunLength::Iterations(_) => "Iterations",
+                            RunLength::TotalBytes(_) => "Total data",
+                            RunLength::Time(_) => "Time budget",
+                            RunLength::Continuous => "Continuous",
+                        })
+                        .show_ui(ui, |ui| {
+                            for (kind, label) in [
+                                (RunLength::Iterations(10), "Iterations"),
+                                (RunLength::TotalBytes(100_000_000_000), "Total data"),
+                                (RunLength::Time(Duration::from_secs(60)), "Time budget"),
+                                (RunLength::Continuous, "Continuous"),
+                            ] {
+                                let selected =
+                                    std::mem::discriminant(&self.run_length) ==
+                                    std::mem::discriminant(&kind);
+                                if ui.selectable_label(selected, label).clicked() && !selected {
+                                    self.run_length = kind;
+                                }
+                            }
+                        });
+                    match self.run_length {
+                        RunLength::Iterations(ref mut count) => {
+                            ui.add(egui::Slider::new(count, 1..=100).text("Iterations"));
+                        }
+                        RunLength::TotalBytes(ref mut total) => {
+                            let mut gigabytes = *total / 1_000_000_000;
+                            ui.add(egui::Slider::new(&mut gigabytes, 1..=1000).text("GB"));
+                            *total = gigabytes * 1_000_000_000;
+                        }
+                        RunLength::Time(ref mut limit) => {
+                            let mut seconds = limit.as_secs();
+                            ui.add(egui::Slider::new(&mut seconds, 1..=3600).text("Seconds"));
+                            *limit = Duration::from_secs(seconds);
+                        }
+                        RunLength::Continuous => {
+                            ui.label("until stopped");
+                        }
+                    }
+                });
+
+                let mut fresh = self.host_buffer == HostBuffer::Fresh;
+                config_ui
+                    .checkbox(&mut fresh, "Allocate a fresh host buffer each iteration")
+                    .on_hover_text(
+                        "Exposes allocator and first-touch page fault costs instead of \
+                         reusing warm memory"
+                    );
+                self.host_buffer = if fresh { HostBuffer::Fresh } else { HostBuffer::Reuse };
+
+                let mut upload_only = self.verification == Verification::Checksum;
+                config_ui
+                    .checkbox(&mut upload_only, "Upload only, verified by a checksum on the device")
+                    .on_hover_text(
+                        "Skips the device-to-host transfers that reading data back for \
+                         verification would need"
+                    );
+                self.verification = if upload_only {
+                    Verification::Checksum
+                } else {
+                    Verification::ReadBack
+                };
+
+                config_ui.label("Select GPU Device:");
+
+                let previous_device = self.selected_device.as_ref().map(MyDevice::key);
+                egui::ComboBox
+                    ::from_label("Device")
+                    .selected_text(self.selected_device.as_ref().map_or("None", |d| d.name()))
+                    .show_ui(config_ui, |ui| {
+                        let history = self.history.lock().unwrap();
+                        ui.horizontal(|ui| {
+                            ui.add(
+                                egui::TextEdit
+                                    ::singleline(&mut self.device_filter)
+                                    .hint_text("Filter")
+                                    .desired_width(120.0)
+                            );
+                            ui.label("Sort by:");
+                            for sort in DeviceSort::ALL {
+                                ui.selectable_value(&mut self.device_sort, sort, sort.label());
+                            }
+                        });
+                        let filter = self.device_filter.to_lowercase();
+                        let mut listed: Vec<(usize, &MyDevice)> = self.devices
+                            .iter()
+                            .enumerate()
+                            .filter(|(_, device)| {
+                                [device.name(), &device.vendor().to_string(), device.platform()]
+                                    .iter()
+                                    .any(|text| text.to_lowercase().contains(&filter))
+                            })
+                            .collect();
+                        match self.device_sort {
+                            DeviceSort::Index => {}
+                            DeviceSort::Name => listed.sort_by_key(|(_, device)| device.name()),
+                            DeviceSort::Memory =>
+                                listed.sort_by_key(|(_, device)| {
+                                    std::cmp::Reverse(device.global_memory())
+                                }),
+                        }
+                        if listed.is_empty() {
+                            ui.weak("No device matches the filter");
+                        }
+                        // Grouped by platform, in the order the platforms were found, so that
+                        // a machine with several drivers installed lists each one's devices
+                        let mut platforms: Vec<&str> = Vec::new();
+                        for (_, device) in &listed {
+                            if !platforms.contains(&device.platform()) {
+                                platforms.push(device.platform());
+                            }
+                        }
+                        for platform in platforms {
+                            let header = if platform.is_empty() {
+                                "Unknown platform"
+                            } else {
+                                platform
+                            };
+                            ui.label(egui::RichText::new(header).strong());
+                            let devices = listed
+                                .iter()
+                                .filter(|(_, device)| device.platform() == platform);
+                            for &(index, device) in devices {
+                                ui.horizontal(|ui| {
+                                    ui.monospace(format!("{:>2}", index)).on_hover_text(
+                                        "The index --device and --list-devices use"
+                                    );
+                                    ui.label(
+                                        egui::RichText
+                                            ::new(device.vendor().to_string())
+                                            .small()
+                                            .color(vendor_color(device.vendor()))
+                                    );
+                                    ui.selectable_value(
+                                        &mut self.selected_device,
+                                        Some(device.clone()),
+                                        device.name()
+                                    );
+                                    if let Some(values) = history.get(&device.key()) {
+                                        let color = ui.visuals().text_color();
+                                        let latest = self.settings.number_format.float(
+                                            values[values.len() - 1],
+                                            2
+                                        );
+                                        plot::sparkline(ui, values, color).on_hover_text(
+                                            format!(
+                                                "Last {} runs, latest {} GB/s",
+                                                values.len(),
+                                                latest
+                                            )
+                                        );
+                                    }
+                                });
+                            }
+                        }
+                    });
+
+                if let Some(device) = self.selected_device.clone() {
+                    if previous_device != Some(device.key()) {
+                        self.restore_defaults(&device);
+                        self.inspect_device(&device);
+                    }
+                }
+
+                let can_partition = self.selected_device
+                    .as_ref()
+                    .is_some_and(|d| d.supports_partitioning());
+                if !can_partition {
+                    self.partition = Partition::None;
+                }
+                config_ui.add_enabled_ui(can_partition, |ui| {
+                    egui::ComboBox
+                        ::from_label("Partition")
+                        .selected_text(self.partition.to_string())
+                        .show_ui(ui, |ui| {
+                            ui.selectable_value(&mut self.partition, Partition::None, "None");
+                            let equally = match self.partition {
+                                Partition::Equally(units) => Partition::Equally(units),
+                                _ => Partition::Equally(1),
+                            };
+                            ui.selectable_value(&mut self.partition, equally, "Equally");
+                            ui.selectable_value(
+                                &mut self.partition,
+                                Partition::Numa,
+                                "By NUMA node"
+                            );
+                        });
+                    if let Partition::Equally(ref mut units) = self.partition {
+                        ui.add(egui::DragValue::new(units).range(1..=1024).suffix(" CUs each"));
+                    }
+                    if self.partition != Partition::None {
+                        ui.add(egui::DragValue::new(&mut self.sub_device).prefix("Sub-device "));
+                    }
+                });
+
+                let selected = self.selected_device.as_ref();
+                if !selected.is_some_and(|d| self.memory.supported_by(d)) {
+                    self.memory = Memory::Buffer;
+                }
+                egui::ComboBox
+                    ::from_label("Memory")
+                    .selected_text(self.memory.to_string())
+                    .show_ui(config_ui, |ui| {
+                        for memory in Memory::ALL {
+                            let supported = selected.is_some_and(|d| memory.supported_by(d));
+                            ui.add_enabled_ui(supported, |ui| {
+                                ui.selectable_value(&mut self.memory, memory, memory.to_string());
+                            });
+                        }
+                    })
+                    .response.on_hover_text(
+                        "USM modes need cl_intel_unified_shared_memory, host-visible VRAM an \
+                         AMD driver and unified a GPU sharing DRAM with the CPU, see the \
+                         Capabilities tab"
+                    );
+
+                config_ui.collapsing("Advanced", |ui| {
+                    let has_pci_address = self.selected_device
+                        .as_ref()
+                        .is_some_and(|d| capabilities::PCI_ADDRESS.supported_by(d));
+                    if !has_pci_address {
+                        self.link_gen = None;
+                    }
+                    ui.add_enabled_ui(has_pci_address, |ui| {
+                        egui::ComboBox
+                            ::from_label("Retrain link to")
+                            .selected_text(
+                                self.link_gen.map_or("Unchanged".into(), |g| {
+                                    format!("PCIe gen {}", g)
+                                })
+                            )
+                            .show_ui(ui, |ui| {
+                                ui.selectable_value(&mut self.link_gen, None, "Unchanged");
+                                for generation in 1..=5 {
+                                    ui.selectable_value(
+                                        &mut self.link_gen,
+                                        Some(generation),
+                                        format!("PCIe gen {}", generation)
+                                    );
+                                }
+                            })
+                            .response.on_hover_text(
+                                "Linux, as root: lowers the link speed through the upstream \
+                                 port for the run and restores it afterwards"
+                            );
+                    });
+
+                    let denied: Vec<String> = self.denied
+                        .iter()
+                        .filter(|privileged| {
+                            privileged.is_sensor_in(self.sensors) ||
+                                (**privileged == Privileged::LinkRetraining &&
+                                    self.link_gen.is_some())
+                        })
+                        .map(Privileged::to_string)
+                        .collect();
+                    if !denied.is_empty() {
+                        ui.colored_label(
+                            ui.visuals().warn_fg_color,
+                            format!("Needs elevated privileges: {}", denied.join(", "))
+                        );
+                        let relaunch = if cfg!(windows) {
+                            "Relaunch as Administrator"
+                        } else {
+                            "Relaunch with pkexec"
+                        };
+                        if ui.button(relaunch).clicked() {
+                            match elevation::relaunch() {
+                                Ok(()) => ui.ctx().send_viewport_cmd(egui::ViewportCommand::Close),
+                                Err(e) => {
+                                    *self.error_message.lock().unwrap() = Some(
+                                        format!("Error: could not relaunch: {}", e)
+                                    );
+                                }
+                            }
+                        }
+                    }
+
+                    ui.horizontal(|ui| {
+                        let mut millis = self.pacing.delay.as_millis() as u64;
+                        ui.add(egui::DragValue::new(&mut millis).range(0..=60_000).suffix(" ms"));
+                        ui.label("Pause between iterations");
+                        self.pacing.delay = Duration::from_millis(millis);
+                    });
+                    ui.horizontal(|ui| {
+                        let mut gate = self.pacing.max_temperature.is_some();
+                        ui.checkbox(&mut gate, "Then wait until the GPU is below");
+                        let mut limit = self.pacing.max_temperature.unwrap_or(60.0);
+                        ui.add_enabled(
+                            gate,
+                            egui::DragValue::new(&mut limit).range(20.0..=110.0).suffix(" °C")
+                        );
+                        self.pacing.max_temperature = gate.then_some(limit);
+                    }).response.on_hover_text(
+                        "Thermally neutral numbers for long runs; needs NVML or a hwmon sensor"
+                    );
+                    ui.horizontal(|ui| {
+                        let mut gentle = self.pacing.duty.is_some();
+                        ui.checkbox(&mut gentle, "Gentle mode, transferring");
+                        let mut percent = self.pacing.duty.map_or(50.0, |duty| duty * 100.0);
+                        ui.add_enabled(
+                            gentle,
+                            egui::DragValue::new(&mut percent).range(1.0..=99.0).suffix(" %")
+                        );
+                        ui.label("of the time");
+                        self.pacing.duty = gentle.then_some(percent.round() / 100.0);
+                    }).response.on_hover_text(
+                        "Idles after every transfer so that the GPU driving the display keeps \
+                         the desktop smooth during long runs; per-transfer throughput is \
+                         unaffected and the sustained rate is shown alongside"
+                    );
+                    if self.drives_display && self.pacing.duty.is_none() {
+                        ui.weak("This GPU drives the display, where gentle mode avoids stutter");
+                    }
+                    ui.horizontal(|ui| {
+                        let mut millis = self.warm_up.as_millis() as u64;
+                        ui.add(egui::DragValue::new(&mut millis).range(0..=10_000).suffix(" ms"));
+                        ui.label("Warm-up before measuring");
+                        self.warm_up = Duration::from_millis(millis);
+                    }).response.on_hover_text(
+                        "Keeps the GPU busy with a kernel first, so power-managed GPUs leave \
+                         their idle clocks before the first transfer"
+                    );
+                    ui.horizontal(|ui| {
+                        ui.add(egui::DragValue::new(&mut self.discard).range(0..=100));
+                        ui.label("Iterations discarded before measuring");
+                    }).response.on_hover_text(
+                        "Runs these first and leaves them out of the results, as the first \
+                         transfers pay for pinning, mapping and waking the link"
+                    );
+                    ui.add_enabled(
+                        self.config.endpoint.is_some(),
+                        egui::Checkbox::new(&mut self.submit, "Share results anonymously")
+                    )
+                        .on_hover_text(
+                            "Sends the GPU model, driver, link, configuration and throughput, \
+                             nothing identifying, and shows how the result ranks for the model"
+                        )
+                        .on_disabled_hover_text(
+                            "Needs a [community] endpoint in gputhroughput.toml"
+                        );
+
+                    ui.horizontal(|ui| {
+                        let path = self.config.export_path(PLAN_FILE.as_ref());
+                        if ui.button("Save Plan").clicked() {
+                            self.plan_status = Some(match self.plan().save(&path) {
+                                Ok(()) => format!("Plan saved to {}", path.display()),
+                                Err(e) => format!("Failed to save the plan: {}", e),
+                            });
+                        }
+                        if ui.button("Load Plan").clicked() {
+                            self.plan_status = Some(match Plan::load(&path) {
+                                Ok(plan) => {
+                                    self.apply_plan(plan);
+                                    format!("Plan loaded from {}", path.display())
+                                }
+                                Err(e) => format!("Failed to load the plan: {}", e),
+                            });
+                        }
+                        ui.checkbox(&mut self.plan_matrix, "With the matrix");
+                    }).response.on_hover_text(
+                        format!(
+                            "The configuration and optionally the matrix as {} in the export \
+                             directory, to run elsewhere with --plan",
+                            PLAN_FILE
+                        )
+                    );
+                    let can_reproduce = self.store.is_some() && self.selected_device.is_some();
+                    let reproduce = ui
+                        .add_enabled(can_reproduce, egui::Button::new("Reproduce Last Result"))
+                        .on_hover_text(
+                            "Restores the configuration of the selected device's latest kept \
+                             result and lists power settings and CPU affinity that have changed \
+                             since"
+                        )
+                        .on_disabled_hover_text(
+                            "Needs a device and [storage] in gputhroughput.toml"
+                        );
+                    if reproduce.clicked() {
+                        self.plan_status = Some(self.reproduce_last());
+                    }
+                    if let Some(ref status) = self.plan_status {
+                        ui.label(status);
+                    }
+
+                    let previous = self.settings.clone();
+                    let palette = &mut self.settings.palette;
+                    egui::ComboBox
+                        ::from_label("Plot colors")
+                        .selected_text(palette.to_string())
+                        .show_ui(ui, |ui| {
+                            ui.selectable_value(palette, Palette::Classic, "Classic");
+                            ui.selectable_value(
+                                palette,
+                                Palette::ColorBlindSafe,
+                                "Color-blind safe"
+                            );
+                            let (h2d, d2h) = palette.colors();
+                            if !matches!(palette, Palette::Custom { .. }) {
+                                let custom = Palette::Custom { h2d, d2h };
+                                ui.selectable_value(palette, custom, "Custom");
+                            }
+                        });
+                    if let Palette::Custom { ref mut h2d, ref mut d2h } = palette {
+                        ui.horizontal(|ui| {
+                            ui.color_edit_button_srgba(h2d);
+                            ui.label("Host to Device");
+                            ui.color_edit_button_srgba(d2h);
+                            ui.label("Device to Host");
+                        });
+                    }
+                    let number_format = &mut self.settings.number_format;
+                    egui::ComboBox
+                        ::from_label("Number format")
+                        .selected_text(number_format.to_string())
+                        .show_ui(ui, |ui| {
+                            for format in NumberFormat::ALL {
+                                ui.selectable_value(number_format, format, format.to_string());
+                            }
+                        })
+                        .response.on_hover_text(
+                            "How results are written in this window; exported files always \
+                             use 1234.5"
+                        );
+                    if self.settings != previous {
+                        if let Err(e) = self.settings.save() {
+                            eprintln!("Warning: failed to save settings: {}", e);
+                        }
+                    }
+                });
+
+                let measuring = self.measuring.load(Ordering::SeqCst);
+                let continuous = self.run_length == RunLength::Continuous;
+                if measuring && continuous {
+                    if config_ui.button("Stop").clicked() {
+                        self.stop.store(true, Ordering::SeqCst);
+                    }
+                    if self.config.alerts.is_enabled() {
+                        config_ui.weak("Alerts from gputhroughput.toml are on for this run");
+                    }
+                } else if
+                    config_ui
+                        .add_enabled(!measuring, egui::Button::new("Measure Throughput"))
+                        .clicked()
+                {
+                    if let Some(device) = self.selected_device.clone() {
+                        self.measure(ctx, device);
+                    }
+                }
+
+                let last = self.recent_runs.first().map(|run| run.label.clone());
+                let button = config_ui
+                    .add_enabled(!measuring && last.is_some(), egui::Button::new("Repeat Last Run"))
+                    .on_hover_text(last.unwrap_or_else(|| "No run yet this session".to_string()));
+                if button.clicked() {
+                    self.repeat(ctx, 0);
+                }
+                if self.recent_runs.len() > 1 {
+                    config_ui.collapsing("Recent Runs", |ui| {
+                        let mut repeat = None;
+                        for (index, run) in self.recent_runs.iter().enumerate() {
+                            ui.horizontal(|ui| {
+                                if ui.add_enabled(!measuring, egui::Button::new("Run")).clicked() {
+                                    repeat = Some(index);
+                                }
+                                ui.label(&run.label);
+                            });
+                        }
+                        if let Some(index) = repeat {
+                            self.repeat(ctx, index);
+                        }
+                    });
+                }
+
+                let button = config_ui
+                    .add_enabled(
+                        !measuring && !continuous && self.devices.len() > 1,
+                        egui::Button::new("Measure All Devices")
+                    )
+                    .on_hover_text("Runs the configured measurement on every device in turn");
+                if button.clicked() {
+                    let checkpoint = Checkpoint {
+                        data_size: self.data_size,
+                        units: self.units,
+                        run_length: self.run_length,
+                        host_buffer: self.host_buffer,
+                        memory: self.memory,
+                        verification: self.verification,
+                        pacing: self.pacing,
+                        warm_up: self.warm_up,
+                        discard: self.discard,
+                        pending: self.devices
+                            .iter()
+                            .enumerate()
+                            .map(|(index, device)| (index, device.settings_key()))
+                            .collect(),
+                        completed: Vec::new(),
+                    };
+                    self.measure_devices(ctx, checkpoint);
+                }
+
+                config_ui.horizontal(|ui| {
+                    let button = ui.add_enabled(
+                        !measuring,
+                        egui::Button::new("Measure Thread Scaling")
+                    );
+                    ui.add(
+                        egui::DragValue
+                            ::new(&mut self.submit_threads)
+                            .range(2..=16)
+                            .suffix(" threads")
+                    );
+                    if button.clicked() {
+                        if let Some(ref device) = self.selected_device {
+                            let config = self.measure_config();
+                            let device_clone = device.clone();
+                            let threads = self.submit_threads;
+                            let scaling = Arc::clone(&self.scaling);
+
+                            self.spawn_job(ctx, move || {
+                                let result = concurrency::measure_scaling(
+                                    &config,
+                                    device_clone.get_device(),
+                                    threads
+                                )?;
+                                *scaling.lock().unwrap() = Some(result);
+                                Ok(())
+                            });
+                        }
+                    }
+                });
+
+                let button = config_ui
+                    .add_enabled(!measuring, egui::Button::new("Measure Data Patterns"))
+                    .on_hover_text(
+                        "Moves zeros, a constant, a ramp and random data in turn; throughput \
+                         that depends on the data means the link or driver compresses"
+                    );
+                if button.clicked() {
+                    if let Some(ref device) = self.selected_device {
+                        let config = self.measure_config();
+                        let device_clone = device.clone();
+                        let patterns = Arc::clone(&self.patterns);
+
+                        self.spawn_job(ctx, move || {
+                            let result = patterns::measure_patterns(
+                                &config,
+                                device_clone.get_device()
+                            )?;
+                            *patterns.lock().unwrap() = Some(result);
+                            Ok(())
+                        });
+                    }
+                }
+
+                let button = config_ui
+                    .add_enabled(!measuring, egui::Button::new("Measure Latency"))
+                    .on_hover_text(
+                        format!(
+                            "Times {} single-float round trips one by one; spikes well above \
+                             the median usually mean power management",
+                            latency::ROUND_TRIPS
+                        )
+                    );
+                if button.clicked() {
+                    if let Some(ref device) = self.selected_device {
+                        let device_clone = device.clone();
+                        let latency = Arc::clone(&self.latency);
+
+                        self.spawn_job(ctx, move || {
+                            let result = latency::measure_latency(device_clone.get_device())?;
+                            *latency.lock().unwrap() = Some(result);
+                            Ok(())
+                        });
+                    }
+                }
+
+                let button = config_ui
+                    .add_enabled(!measuring, egui::Button::new("Compare Flush and Finish"))
+                    .on_hover_text(
+                        "Times uploads waited on with flush and an event against finish; some \
+                         drivers wait differently in each"
+                    );
+                if button.clicked() {
+                    if let Some(ref device) = self.selected_device {
+                        let config = self.measure_config();
+                        let device_clone = device.clone();
+                        let completion = Arc::clone(&self.completion);
+
+                        self.spawn_job(ctx, move || {
+                            let result = completion::measure_completion(
+                                &config,
+                                device_clone.get_device()
+                            )?;
+                            *completion.lock().unwrap() = Some(result);
+                            Ok(())
+                        });
+                    }
+                }
+
+                let button = config_ui
+                    .horizontal(|ui| {
+                        let button = ui.add_enabled(!measuring, egui::Button::new("Ramp Size"));
+                        let mut millis = self.ramp_budget.as_millis() as u64;
+                        ui.add(egui::DragValue::new(&mut millis).range(1..=1000).suffix(" ms"));
+                        ui.label("per transfer");
+                        self.ramp_budget = Duration::from_millis(millis);
+                        button
+                    })
+                    .inner.on_hover_text(
+                        "Doubles the transfer size from 4 KB until a single transfer takes this \
+                         long, showing where small transfers stop being timer-bound"
+                    );
+                if button.clicked() {
+                    if let Some(ref device) = self.selected_device {
+                        let device_clone = device.clone();
+                        let ramp = Arc::clone(&self.ramp);
+                        let budget = self.ramp_budget;
+
+                        self.spawn_job(ctx, move || {
+                            let result = ramp::measure_ramp(device_clone.get_device(), budget)?;
+                            *ramp.lock().unwrap() = Some(result);
+                            Ok(())
+                        });
+                    }
+                }
+
+                let button = config_ui
+                    .add_enabled(!measuring, egui::Button::new("Sweep"))
+                    .on_hover_text(
+                        format!(
+                            "Runs the configured measurement at every power of two from {} to \
+                             {}, or the device's largest allocation, to show where throughput \
+                             saturates",
+                            ramp::size_label(sweep::START_BYTES),
+                            ramp::size_label(sweep::END_BYTES)
+                        )
+                    );
+                if button.clicked() {
+                    if let Some(ref device) = self.selected_device {
+                        let config = self.measure_config();
+                        let device_clone = device.clone();
+                        let result = Arc::clone(&self.sweep);
+                        let repaint = ctx.clone();
+                        *result.lock().unwrap() = Some(SweepResult::default());
+
+                        self.spawn_job(ctx, move || {
+                            sweep::measure_sweep(&config, device_clone.get_device(), &mut |point| {
+                                if let Some(ref mut partial) = *result.lock().unwrap() {
+                                    partial.points.push(*point);
+                                }
+                                repaint.request_repaint();
+                            })?;
+                            Ok(())
+                        });
+                    }
+                }
+
+                config_ui.horizontal(|ui| {
+                    let button = ui
+                        .add_enabled(!measuring, egui::Button::new("Sweep Copy Offsets"))
+                        .on_hover_text(
+                            "Times 4 MB transfers at offsets into the device's largest buffer, \
+                             every 3 MB or at the KB offsets given, for cliffs where transfers \
+                             cross BAR aperture boundaries"
+                        );
+                    ui.add(
+                        egui::TextEdit
+                            ::singleline(&mut self.offset_list)
+                            .hint_text("KB offsets, e.g. 261120,262144")
+                            .desired_width(200.0)
+                    );
+                    if button.clicked() {
+                        let list = self.offset_list.trim();
+                        let parsed = if list.is_empty() {
+                            Ok(Offsets::Sweep)
+                        } else {
+                            list.parse::<Offsets>()
+                        };
+                        match (parsed, &self.selected_device) {
+                            (Err(e), _) => {
+                                *self.error_message.lock().unwrap() = Some(format!("Error: {}", e));
+                            }
+                            (Ok(at), Some(device)) => {
+                                let device_clone = device.clone();
+                                let offsets = Arc::clone(&self.offsets);
+
+                                self.spawn_job(ctx, move || {
+                                    let result = offsets::measure_offsets(
+                                        device_clone.get_device(),
+                                        &at
+                                    )?;
+                                    *offsets.lock().unwrap() = Some(result);
+                                    Ok(())
+                                });
+                            }
+                            (Ok(_), None) => {}
+                        }
+                    }
+                });
+
+                config_ui.horizontal(|ui| {
+                    let button = ui
+                        .add_enabled(!measuring, egui::Button::new("Measure Batched Uploads"))
+                        .on_hover_text(
+                            "Uploads the transfer split into many small buffers as one batch \
+                             and compares it with one buffer, for the cost of every transfer"
+                        );
+                    ui.add(
+                        egui::DragValue
+                            ::new(&mut self.scatter_buffers)
+                            .range(2..=scatter::MAX_BUFFERS)
+                            .suffix(" buffers")
+                    );
+                    if button.clicked() {
+                        if let Some(ref device) = self.selected_device {
+                            let config = self.measure_config();
+                            let device_clone = device.clone();
+                            let buffers = self.scatter_buffers;
+                            let scatter = Arc::clone(&self.scatter);
+
+                            self.spawn_job(ctx, move || {
+                                let result = scatter::measure_scatter(
+                                    &config,
+                                    device_clone.get_device(),
+                                    buffers
+                                )?;
+                                *scatter.lock().unwrap() = Some(result);
+                                Ok(())
+                            });
+                        }
+                    }
+                });
+
+                let button = config_ui
+                    .add_enabled(!measuring, egui::Button::new("Find In-Flight Depth"))
+                    .on_hover_text(
+                        "Predicts from latency and bandwidth how many chunked uploads must be \
+                         in flight to hide latency, then measures it"
+                    );
+                if button.clicked() {
+                    if let Some(ref device) = self.selected_device {
+                        let config = self.measure_config();
+                        let device_clone = device.clone();
+                        let inflight = Arc::clone(&self.inflight);
+
+                        self.spawn_job(ctx, move || {
+                            let result = inflight::measure_inflight(
+                                &config,
+                                device_clone.get_device()
+                            )?;
+                            *inflight.lock().unwrap() = Some(result);
+                            Ok(())
+                        });
+                    }
+                }
+
+                let button = config_ui
+                    .add_enabled(!measuring, egui::Button::new("Measure Contention Curve"))
+                    .on_hover_text(
+                        "Measures transfers while a kernel of rising memory intensity runs \
+                         alongside, as on a GPU shared with other workloads"
+                    );
+                if button.clicked() {
+                    if let Some(ref device) = self.selected_device {
+                        let config = self.measure_config();
+                        let device_clone = device.clone();
+                        let contention = Arc::clone(&self.contention);
+
+                        self.spawn_job(ctx, move || {
+                            let result = contention::measure_contention(
+                                &config,
+                                device_clone.get_device()
+                            )?;
+                            *contention.lock().unwrap() = Some(result);
+                            Ok(())
+                        });
+                    }
+                }
+
+                let button = config_ui
+                    .add_enabled(!measuring, egui::Button::new("Measure Round Trip"))
+                    .on_hover_text(
+                        "Times each upload followed straight away by its download, the \
+                         bandwidth a readback-modify workflow such as a video filter gets"
+                    );
+                if button.clicked() {
+                    if let Some(ref device) = self.selected_device {
+                        let config = self.measure_config();
+                        let device_clone = device.clone();
+                        let round_trip = Arc::clone(&self.round_trip);
+
+                        self.spawn_job(ctx, move || {
+                            let result = roundtrip::measure_round_trip(
+                                &config,
+                                device_clone.get_device()
+                            )?;
+                            *round_trip.lock().unwrap() = Some(result);
+                            Ok(())
+                        });
+                    }
+                }
+
+                config_ui.horizontal(|ui| {
+                    let button = ui
+                        .add_enabled(!measuring, egui::Button::new("Measure Random Sizes"))
+                        .on_hover_text(
+                            "Transfers a size drawn at random from the range every time, as \
+                             bursty workloads do, with throughput by power-of-two size"
+                        );
+                    ui.add(
+                        egui::TextEdit
+                            ::singleline(&mut self.random_range)
+                            .hint_text("e.g. 4KiB..256MiB")
+                            .desired_width(200.0)
+                    );
+                    if button.clicked() {
+                        let parsed = SizeRange::parse(self.random_range.trim(), self.units);
+                        match (parsed, &self.selected_device) {
+                            (Err(e), _) => {
+                                *self.error_message.lock().unwrap() = Some(format!("Error: {}", e));
+                            }
+                            (Ok(range), Some(device)) => {
+                                let config = self.measure_config();
+                                let device_clone = device.clone();
+                                let random_sizes = Arc::clone(&self.random_sizes);
+
+                                self.spawn_job(ctx, move || {
+                                    let result = bursts::measure_bursts(
+                                        &config,
+                                        device_clone.get_device(),
+                                        range
+                                    )?;
+                                    *random_sizes.lock().unwrap() = Some(result);
+                                    Ok(())
+                                });
+                            }
+                            (Ok(_), None) => {}
+                        }
+                    }
+                });
+
+                let mut started = None;
+                config_ui.horizontal_wrapped(|ui| {
+                    for mode in self.modes.modes() {
+                        let supported = self.selected_device
+                            .as_ref()
+                            .is_some_and(|device| mode.supported_by(device));
+                        let button = ui
+                            .add_enabled(!measuring && supported, egui::Button::new(mode.name()))
+                            .on_hover_text(mode.description());
+                        if button.clicked() {
+                            started = Some(Arc::clone(mode));
+                        }
+                    }
+                });
+                if let (Some(mode), Some(device)) = (started, self.selected_device.clone()) {
+                    let config = self.measure_config();
+                    let mode_result = Arc::clone(&self.mode_result);
+
+                    self.spawn_job(ctx, move || {
+                        let lines = mode.run(&config, &device)?;
+                        *mode_result.lock().unwrap() = Some(ModeResult {
+                            mode: mode.name().to_string(),
+                            lines,
+                        });
+                        Ok(())
+                    });
+                }
+
+                config_ui.collapsing("Matrix", |ui| {
+                    let selected = self.selected_device.as_ref();
+                    ui.horizontal_wrapped(|ui| {
+                        for memory in Memory::ALL {
+                            let mut on = self.matrix.memories.contains(&memory);
+                            let supported = selected.is_some_and(|d| memory.supported_by(d));
+                            let checkbox = egui::Checkbox::new(&mut on, memory.to_string());
+                            let toggled = ui.add_enabled(supported, checkbox).changed();
+                            if toggled {
+                                self.matrix.memories.retain(|&m| m != memory);
+                                if on {
+                                    self.matrix.memories.push(memory);
+                                }
+                                self.matrix.memories.sort_by_key(|m| {
+                                    Memory::ALL.iter().position(|all| all == m)
+                                });
+                            }
+                        }
+                    });
+                    for (values, options, unit) in [
+                        (&mut self.matrix.sizes, &MATRIX_SIZES[..], self.units.size_unit()),
+                        (&mut self.matrix.queues, &MATRIX_QUEUES[..], "queues"),
+                    ] {
+                        ui.horizontal(|ui| {
+                            for &option in options {
+                                let mut on = values.contains(&option);
+                                if ui.checkbox(&mut on, option.to_string()).changed() {
+                                    values.retain(|&value| value != option);
+                                    if on {
+                                        values.push(option);
+                                    }
+                                    values.sort();
+                                }
+                            }
+                            ui.label(unit);
+                        });
+                    }
+
+                    let valid = self.matrix.validate(self.units);
+                    let cells = self.matrix.cells(self.units).len();
+                    let button = ui
+                        .add_enabled(
+                            !measuring && valid.is_ok(),
+                            egui::Button::new(format!("Measure {} Combinations", cells))
+                        )
+                        .on_hover_text(
+                            "Splits each transfer between the queues, one host thread each, \
+                             and tabulates aggregate throughput; data is not verified"
+                        );
+                    if let Err(reason) = valid {
+                        ui.weak(reason);
+                    }
+                    if button.clicked() {
+                        if let Some(ref device) = self.selected_device {
+                            let config = self.measure_config();
+                            let device_clone = device.clone();
+                            let plan = self.matrix.clone();
+                            let result = Arc::clone(&self.matrix_result);
+                            let repaint = ctx.clone();
+                            *result.lock().unwrap() = Some(MatrixResult::default());
+
+                            self.spawn_job(ctx, move || {
+                                matrix::run_matrix(&plan, &config, &device_clone, &mut |cell| {
+                                    if let Some(ref mut partial) = *result.lock().unwrap() {
+                                        partial.cells.push(cell.clone());
+                                    }
+                                    repaint.request_repaint();
+                                });
+                                Ok(())
+                            });
+                        }
+                    }
+                });
+
+                let access = match (&self.selected_device, &self.peer_device) {
+                    (Some(device), Some(peer)) => peer::peer_access(device, peer),
+                    _ => Err("pick a peer GPU".to_string()),
+                };
+                config_ui.add_enabled_ui(self.devices.len() > 1, |ui| {
+                    ui.horizontal(|ui| {
+                        let button = ui
+                            .add_enabled(
+                                !measuring && access.is_ok(),
+                                egui::Button::new("Measure Peer Copy")
+                            )
+                            .on_hover_text(
+                                "Copies directly between the selected GPU and the peer, without \
+                                 staging in host memory; needs cl_amd_copy_buffer_p2p"
+                            );
+                        egui::ComboBox
+                            ::from_label("Peer")
+                            .selected_text(self.peer_device.as_ref().map_or("None", |d| d.name()))
+                            .show_ui(ui, |ui| {
+                                for device in &self.devices {
+                                    ui.selectable_value(
+                                        &mut self.peer_device,
+                                        Some(device.clone()),
+                                        device.name()
+                                    );
+                                }
+                            });
+                        if button.clicked() {
+                            if let (Some(device), Some(peer)) = (
+                                self.selected_device.clone(),
+                                self.peer_device.clone(),
+                            ) {
+                                let config = self.measure_config();
+                                let result = Arc::clone(&self.peer);
+
+                                self.spawn_job(ctx, move || {
+                                    *result.lock().unwrap() = Some(
+                                        peer::measure_peer(&config, &device, &peer)?
+                                    );
+                                    Ok(())
+                                });
+                            }
+                        }
+                    });
+                }).response.on_disabled_hover_text("Peer copies need two GPUs");
+                if let (Err(reason), Some(_)) = (&access, &self.peer_device) {
+                    config_ui.label(
+                        egui::RichText
+                            ::new(format!("Peer copies unavailable: {}", reason))
+                            .weak()
+                    );
+                }
+
+                config_ui.horizontal(|ui| {
+                    let button = ui
+                        .add_enabled(
+                            !measuring && !self.stream_path.is_empty(),
+                            egui::Button::new("Measure Streaming")
+                        )
+                        .on_hover_text(
+                            "Reads the file from disk while uploading it in chunks of the data \
+                             size; cached files read at memory speed"
+                        );
+                    ui.add(
+                        egui::TextEdit::singleline(&mut self.stream_path).hint_text("File path")
+                    );
+                    if button.clicked() {
+                        if let Some(ref device) = self.selected_device {
+                            let path = std::path::PathBuf::from(&self.stream_path);
+                            let chunk_size = self.data_size * self.units.megabyte();
+                            let device_clone = device.clone();
+                            let streaming = Arc::clone(&self.streaming);
+
+                            self.spawn_job(ctx, move || {
+                                let result = streaming::measure_streaming(
+                                    &path,
+                                    chunk_size,
+                                    device_clone.get_device()
+                                )?;
+                                *streaming.lock().unwrap() = Some(result);
+                                Ok(())
+                            });
+                        }
+                    }
+                });
+
+                if cfg!(target_os = "linux") {
+                    config_ui.horizontal(|ui| {
+                        let button = ui
+                            .add_enabled(
+                                !measuring && !self.dma_heap.is_empty(),
+                                egui::Button::new("Measure dma-buf Import")
+                            )
+                            .on_hover_text(
+                                "Imports a dma-buf allocated from the DMA heap and measures \
+                                 uploads and device copies into it; needs \
+                                 cl_khr_external_memory_dma_buf"
+                            );
+                        ui.add(
+                            egui::TextEdit::singleline(&mut self.dma_heap).hint_text("DMA heap")
+                        );
+                        if button.clicked() {
+                            if let Some(ref device) = self.selected_device {
+                                let heap = std::path::PathBuf::from(&self.dma_heap);
+                                let config = self.measure_config();
+                                let device_clone = device.clone();
+                                let dma_buf = Arc::clone(&self.dma_buf);
+
+                                self.spawn_job(ctx, move || {
+                                    let result = dmabuf::measure_dma_buf(
+                                        &heap,
+                                        config.data_size * std::mem::size_of::<f32>(),
+                                        config.length.fixed_iterations(),
+                                        device_clone.get_device()
+                                    )?;
+                                    *dma_buf.lock().unwrap() = Some(result);
+                                    Ok(())
+                                });
+                            }
+                        }
+                    });
+
+                    let button = config_ui
+                        .add_enabled(!measuring, egui::Button::new("Measure Thread Mapping"))
+                        .on_hover_text(
+                            "Submits host memory from the thread that allocated it, another \
+                             thread on its NUMA node and threads on the other nodes"
+                        );
+                    if button.clicked() {
+                        if let Some(ref device) = self.selected_device {
+                            let config = self.measure_config();
+                            let device_clone = device.clone();
+                            let mapping = Arc::clone(&self.mapping);
+
+                            self.spawn_job(ctx, move || {
+                                let result = numa::measure_mapping(
+                                    &config,
+                                    device_clone.get_device()
+                                )?;
+                                *mapping.lock().unwrap() = Some(result);
+                                Ok(())
+                            });
+                        }
+                    }
+                }
+
+                let shareable = self.selected_device
+                    .as_ref()
+                    .is_some_and(|device| capabilities::GL_SHARING.supported_by(device));
+                let button = config_ui
+                    .add_enabled(
+                        !measuring && shareable && gl.is_some(),
+                        egui::Button::new("Measure GL Interop")
+                    )
+                    .on_hover_text(
+                        "Transfers into an OpenGL buffer shared with OpenCL, acquiring and \
+                         releasing it around every transfer; needs cl_khr_gl_sharing"
+                    );
+                if button.clicked() {
+                    if let (Some(device), Some(gl)) = (self.selected_device.clone(), &gl) {
+                        match self.create_gl_buffer(gl) {
+                            Ok((share, buffer)) => {
+                                let config = self.measure_config();
+                                let interop = Arc::clone(&self.interop);
+
+                                self.spawn_job(ctx, move || {
+                                    let result = interop::measure_gl_buffer(
+                                        &config,
+                                        device.get_device(),
+                                        share,
+                                        buffer.0.get()
+                                    )?;
+                                    *interop.lock().unwrap() = Some(result);
+                                    Ok(())
+                                });
+                            }
+                            Err(message) => {
+                                *self.error_message.lock().unwrap() = Some(message);
+                            }
+                        }
+                    }
+                }
+
+                if measuring {
+                    config_ui.horizontal(|ui| {
+                        ui.spinner();
+                        if let Some(phase) = *self.phase.lock().unwrap() {
+                            ui.label(phase.to_string());
+                        }
+                    });
+                }
+
+                if let Some(ref msg) = *self.error_message.lock().unwrap() {
+                    config_ui.colored_label(egui::Color32::RED, msg);
+                }
+
+                result_ui.heading("Results");
+                let numbers = self.settings.number_format;
+
+                if !self.pinned.is_empty() {
+                    let mut closed = None;
+                    result_ui.columns(self.pinned.len(), |columns| {
+                        let cards = columns.iter_mut().zip(&self.pinned).enumerate();
+                        for (index, (ui, card)) in cards {
+                            egui::Frame::group(ui.style()).show(ui, |ui| {
+                                ui.horizontal(|ui| {
+                                    ui.strong(&card.title);
+                                    if ui.small_button("Close").clicked() {
+                                        closed = Some(index);
+                                    }
+                                });
+                                for line in &card.lines {
+                                    ui.label(numbers.number(line));
+                                }
+                            });
+                        }
+                    });
+                    if let Some(index) = closed {
+                        self.pinned.remove(index);
+                    }
+                    result_ui.separator();
+                }
+
+                if continuous {
+                    let live = self.live.lock().unwrap();
+                    if live.h2d.samples > 0 {
+                        for (label, stats, metric) in [
+                            ("Host to Device", &live.h2d, metrics::H2D),
+                            ("Device to Host", &live.d2h, metrics::D2H),
+                        ] {
+                            if stats.samples == 0 {
+                                continue;
+                            }
+                            result_ui.label(label).on_hover_text(metric.description);
+                            result_ui.horizontal(|ui| {
+                                for (name, value) in [
+                                    ("now", stats.current),
+                                    ("avg", stats.rolling),
+                                    ("worst", stats.worst),
+                                ] {
+                                    ui.label(
+                                        egui::RichText
+                                            ::new(numbers.float(value, 2))
+                                            .size(32.0)
+                                            .strong()
+                                    );
+                                    ui.label(format!("GB/s {}", name));
+                                }
+                            }).response.on_hover_text(metrics::ROLLING.description);
+                        }
+                        result_ui
+                            .label(format!("{} samples", live.h2d.samples))
+                            .on_hover_text(metrics::SAMPLES.description);
+                        result_ui.separator();
+                    }
+                }
+
+                // Copied out first, as the worker holds `session` while it takes `throughput`
+                let latest = self.session.lock().unwrap().last().cloned();
+                // Lock to update the UI with the new throughput results
+                let (maximums, duty_cycle, hints, cold_start, statistics) = {
+                    let throughput = self.throughput.lock().unwrap();
+                    self.h2d_throughput = throughput.h2d();
+                    self.d2h_throughput = throughput.d2h();
+                    self.h2d_duration = throughput.h2d_duration;
+                    self.d2h_duration = throughput.d2h_duration;
+                    self.d2h_measured = throughput.has_d2h() || throughput.h2d_samples.is_empty();
+                    self.pcie_speed = throughput.approximate_link_speed();
+                    let link = self.selected_device
+                        .as_ref()
+                        .and_then(|device| PciAddress::of(device.get_device()))
+                        .and_then(LinkStatus::current);
+                    if let Some(latest) = latest.filter(|_| !throughput.h2d_samples.is_empty()) {
+                        result_ui.horizontal(|ui| {
+                            ui.weak(format!("Measured on {}", latest.label));
+                            if pin_button(ui, self.pinned.len()) {
+                                let health = HealthScore::of(&throughput, link);
+                                self.pinned.push(result_card(&latest, &throughput, health));
+                            }
+                        });
+                    }
+                    if let Some(health) = HealthScore::of(&throughput, link) {
+                        let color = match health.grade() {
+                            Grade::Healthy => egui::Color32::GREEN,
+                            Grade::Degraded => egui::Color32::YELLOW,
+                            Grade::Unhealthy => egui::Color32::RED,
+                        };
+                        let breakdown = format!(
+                            "{}\n\n{}",
+                            metrics::LINK_HEALTH.description,
+                            health.summary()[1..].join("\n")
+                        );
+                        result_ui
+                            .horizontal(|ui| {
+                                ui.label(
+                                    egui::RichText
+                                        ::new(health.value().to_string())
+                                        .size(40.0)
+                                        .strong()
+                                        .color(color)
+                                );
+                                ui.vertical(|ui| {
+                                    ui.label("Link health");
+                                    ui.colored_label(color, health.grade().to_string());
+                                });
+                            })
+                            .response.on_hover_text(breakdown);
+                    }
+                    if let Some(asymmetry) = Asymmetry::of(&throughput, link) {
+                        let lines = asymmetry.summary();
+                        result_ui
+                            .colored_label(egui::Color32::YELLOW, numbers.number(&lines[0]))
+                            .on_hover_text(metrics::ASYMMETRY.description);
+                        egui::CollapsingHeader
+                            ::new("Why the directions differ")
+                            .show(result_ui, |ui| {
+                                for line in &lines[1..] {
+                                    ui.label(line);
+                                }
+                            });
+                    }
+                    if let Some(resets) = throughput.reset_summary() {
+                        result_ui.colored_label(egui::Color32::YELLOW, resets);
+                    }
+                    if let Some(suspends) = throughput.suspend_summary() {
+                        result_ui.colored_label(egui::Color32::YELLOW, suspends);
+                    }
+                    if let Some(ref track) = throughput.harness_memory {
+                        for warning in track.warnings() {
+                            result_ui
+                                .colored_label(egui::Color32::YELLOW, warning)
+                                .on_hover_text(metrics::HARNESS_MEMORY.description);
+                        }
+                        if let Some(summary) = track.summary() {
+                            result_ui
+                                .weak(numbers.number(&summary))
+                                .on_hover_text(metrics::HARNESS_MEMORY.description);
+                        }
+                    }
+                    if let Some(state) = throughput.start_state.filter(GpuState::is_idle) {
+                        result_ui.colored_label(
+                            egui::Color32::YELLOW,
+                            format!(
+                                "The GPU was still idle ({}) when measuring started; the \
+                                 first iterations may read low, a warm-up helps.",
+                                state
+                            )
+                        );
+                    }
+                    self.telemetry = throughput.telemetry;
+                    if !throughput.h2d_samples.is_empty() {
+                        let gaps: Vec<usize> = throughput.suspends
+                            .iter()
+                            .filter_map(|suspend| suspend.iteration.checked_sub(throughput.trimmed))
+                            .collect();
+                        plot::samples_chart(
+                            result_ui,
+                            &throughput.h2d_samples,
+                            &throughput.d2h_samples,
+                            &gaps,
+                            self.settings.palette,
+                            &mut self.pinned_sample
+                        );
+                    }
+                    if !throughput.trace.is_empty() {
+                        let button = result_ui
+                            .button("Export Trace")
+                            .on_hover_text(
+                                "Chrome trace of every transfer, for Perfetto or chrome://tracing"
+                            );
+                        if button.clicked() {
+                            let path = self.config.export_path(TRACE_FILE.as_ref());
+                            self.trace_status = Some(
+                                match trace::write_chrome_trace(&path, &throughput.trace) {
+                                    Ok(()) => format!("Trace written to {}", path.display()),
+                                    Err(e) => format!("Failed to write trace: {}", e),
+                                }
+                            );
+                        }
+                    }
+                    if let Some(ref status) = self.trace_status {
+                        result_ui.label(status);
+                    }
+                    let hints = self.hybrid.as_ref().map(|hybrid| hybrid.hints(&throughput));
+                    (
+                        self.maximums.summary(&throughput),
+                        throughput.duty_cycle,
+                        hints,
+                        ColdStart::of(&throughput),
+                        [
+                            ("H2D", throughput.h2d_statistics()),
+                            ("D2H", throughput.d2h_statistics()),
+                        ],
+                    )
+                };
+
+                let floats = (self.data_size * self.units.megabyte()) /
+                    std::mem::size_of::<cl_float>();
+                result_ui
+                    .label(
+                        format!(
+                            "Data Size: {} floats (~{} {})",
+                            numbers.number(&floats.to_string()),
+                            numbers.number(&self.data_size.to_string()),
+                            self.units.size_unit()
+                        )
+                    )
+                    .on_hover_text(metrics::DATA_SIZE.description);
+                let with_duration = format!(
+                    "{}\n\n{}",
+                    metrics::H2D.description,
+                    metrics::DURATION.description
+                );
+                result_ui
+                    .label(
+                        format!(
+                            "Host to Device Throughput: {} {} (Duration: {} s)",
+                            numbers.number(
+                                &self.h2d_throughput.scaled(self.units.rate_factor()).to_string()
+                            ),
+                            self.units.rate_unit(),
+                            numbers.number(&significant(self.h2d_duration, 3))
+                        )
+                    )
+                    .on_hover_text(with_duration);
+                if self.d2h_measured {
+                    let with_duration = format!(
+                        "{}\n\n{}",
+                        metrics::D2H.description,
+                        metrics::DURATION.description
+                    );
+                    result_ui
+                        .label(
+                            format!(
+                                "Device to Host Throughput: {} {} (Duration: {} s)",
+                                numbers.number(
+                                    &self.d2h_throughput
+                                        .scaled(self.units.rate_factor())
+                                        .to_string()
+                                ),
+                                self.units.rate_unit(),
+                                numbers.number(&significant(self.d2h_duration, 3))
+                            )
+                        )
+                        .on_hover_text(with_duration);
+                } else {
+                    result_ui
+                        .label("Device to Host Throughput: skipped, uploads verified by checksum")
+                        .on_hover_text(metrics::D2H.description);
+                }
+                if statistics.iter().any(|(_, statistics)| statistics.is_some()) {
+                    let factor = self.units.rate_factor();
+                    let cell = |ui: &mut egui::Ui, value: f64| {
+                        ui.label(numbers.number(&format!("{:.2}", value * factor)));
+                    };
+                    result_ui
+                        .label(format!("Per iteration, {}:", self.units.rate_unit()))
+                        .on_hover_text(metrics::STATISTICS.description);
+                    egui::Grid
+                        ::new("iteration_statistics")
+                        .striped(true)
+                        .show(result_ui, |ui| {
+                            for heading in ["", "Mean", "Median", "Min", "Max", "Std dev"] {
+                                ui.strong(heading);
+                            }
+                            ui.end_row();
+                            for (direction, statistics) in statistics {
+                                let Some(statistics) = statistics else {
+                                    continue;
+                                };
+                                ui.label(direction);
+                                cell(ui, statistics.mean);
+                                cell(ui, statistics.median);
+                                cell(ui, statistics.min);
+                                cell(ui, statistics.max);
+                                cell(ui, statistics.std_dev);
+                                ui.end_row();
+                            }
+                        });
+                }
+                if let Some(cold_start) = cold_start {
+                    for line in cold_start.summary() {
+                        result_ui
+                            .label(numbers.number(&line))
+                            .on_hover_text(metrics::COLD_START.description);
+                    }
+                }
+
+                if let Some(link) = self.telemetry.link {
+                    result_ui
+                        .label(
+                            format!(
+                                "Driver-reported peak: {} GB/s H2D, {} GB/s D2H",
+                                numbers.float(link.rx, 2),
+                                numbers.float(link.tx, 2)
+                            )
+                        )
+                        .on_hover_text(metrics::DRIVER_PEAK.description);
+                }
+                if let Some(watts) = self.telemetry.power {
+                    result_ui
+                        .label(
+                            format!(
+                                "Efficiency: {} GB/s/W H2D, {} GB/s/W D2H (avg {} W)",
+                                numbers.float(self.h2d_throughput.value / watts, 3),
+                                numbers.float(self.d2h_throughput.value / watts, 3),
+                                numbers.float(watts, 1)
+                            )
+                        )
+                        .on_hover_text(metrics::EFFICIENCY.description);
+                }
+                if let Some(cpu) = self.telemetry.cpu {
+                    result_ui
+                        .label(format!("Host CPU: {}", cpu))
+                        .on_hover_text(metrics::HOST_CPU.description);
+                }
+                if let Some(paging) = self.telemetry.paging {
+                    let color = if paging.explains_dips() {
+                        result_ui.visuals().warn_fg_color
+                    } else {
+                        result_ui.visuals().text_color()
+                    };
+                    for line in paging.summary() {
+                        result_ui
+                            .colored_label(color, numbers.number(&line))
+                            .on_hover_text(metrics::PAGING.description);
+                    }
+                }
+                if let Some(enabled) = self.telemetry.ecc {
+                    result_ui
+                        .label(format!("ECC: {}", if enabled { "on" } else { "off" }))
+                        .on_hover_text(metrics::ECC.description);
+                    if let Some(comparison) = *self.ecc_comparison.lock().unwrap() {
+                        result_ui
+                            .label(numbers.number(&comparison.summary()))
+                            .on_hover_text(metrics::ECC.description);
+                    }
+                }
+                if let Some(vendor) = self.telemetry.vendor {
+                    result_ui
+                        .label(vendor.summary())
+                        .on_hover_text(metrics::VENDOR_TOOLS.description);
+                }
+                if let Some(duty) = duty_cycle {
+                    let mut text = format!(
+                        "Gentle mode: transferring {}% of the time, sustained {} GB/s H2D",
+                        numbers.float(duty * 100.0, 0),
+                        numbers.float(self.h2d_throughput.value * duty, 2)
+                    );
+                    if self.d2h_measured {
+                        let sustained = numbers.float(self.d2h_throughput.value * duty, 2);
+                        text.push_str(&format!(", {} GB/s D2H", sustained));
+                    }
+                    result_ui.label(text).on_hover_text(metrics::DUTY_CYCLE.description);
+                }
+                if !maximums.is_empty() {
+                    result_ui
+                        .label("Theoretical maximum:")
+                        .on_hover_text(metrics::THEORETICAL.description);
+                    for line in &maximums {
+                        result_ui.label(format!(" - {}", line));
+                    }
+                }
+                let annotation = self.environment.as_ref().and_then(Environment::annotation);
+                if let Some(annotation) = annotation {
+                    result_ui
+                        .colored_label(result_ui.visuals().warn_fg_color, annotation)
+                        .on_hover_text(metrics::VIRTUALIZATION.description);
+                }
+                if let Some(ref hybrid) = self.hybrid {
+                    result_ui
+                        .label(hybrid.annotation())
+                        .on_hover_text(metrics::HYBRID_GRAPHICS.description);
+                    for hint in hints.unwrap_or_default() {
+                        result_ui
+                            .weak(format!("Hint: {}", numbers.number(&hint)))
+                            .on_hover_text(metrics::HYBRID_GRAPHICS.description);
+                    }
+                }
+                if let Some(unified) = self.unified {
+                    result_ui
+                        .colored_label(result_ui.visuals().warn_fg_color, unified.annotation())
+                        .on_hover_text(metrics::UNIFIED_MEMORY.description);
+                    for line in unified.interpretation(self.memory.key()) {
+                        result_ui.weak(line).on_hover_text(metrics::UNIFIED_MEMORY.description);
+                    }
+                }
+
+                // Without a link there is nothing to estimate, see `unified`
+                if self.unified.is_none() {
+                    result_ui.separator();
+
+                    result_ui
+                        .label("Approximate PCIe Link Speed:")
+                        .on_hover_text(metrics::LINK_SPEED.description);
+                    result_ui.label(format!("Measured Throughput: {} GB/s", self.pcie_speed.0));
+                    for config in &self.pcie_speed.1 {
+                        result_ui.label(format!(" - {}", config));
+                    }
+                }
+
+                {
+                    // Bars for a single device would only repeat the results above
+                    let session = self.session.lock().unwrap();
+                    if session.len() > 1 {
+                        let devices: Vec<(String, f64, f64)> = session
+                            .iter()
+                            .rev()
+                            .map(|result| (result.label.clone(), result.h2d, result.d2h))
+                            .collect();
+                        result_ui.separator();
+                        result_ui
+                            .label("This session, latest first:")
+                            .on_hover_text(metrics::SESSION.description);
+                        plot::device_bars(result_ui, &devices, self.settings.palette);
+                    }
+                }
+                {
+                    let comparison = self.comparison.lock().unwrap();
+                    if !comparison.is_empty() {
+                        result_ui.separator();
+                        result_ui
+                            .label("All devices, fastest first:")
+                            .on_hover_text(metrics::DEVICES.description);
+                        plot::device_bars(result_ui, &comparison, self.settings.palette);
+                    }
+                }
+                if let Some(ref scaling) = *self.scaling.lock().unwrap() {
+                    result_ui.separator();
+                    result_ui
+                        .label(format!("Submission from {} threads:", scaling.threads))
+                        .on_hover_text(metrics::SCALING.description);
+                    for line in scaling.summary() {
+                        result_ui.label(line);
+                    }
+                }
+                if let Some(ref mapping) = *self.mapping.lock().unwrap() {
+                    result_ui.separator();
+                    result_ui
+                        .label("Thread to NUMA node mapping:")
+                        .on_hover_text(metrics::MAPPING.description);
+                    for line in mapping.summary() {
+                        result_ui.label(line);
+                    }
+                }
+                if let Some(ref patterns) = *self.patterns.lock().unwrap() {
+                    result_ui.separator();
+                    result_ui.label("Data patterns:").on_hover_text(metrics::PATTERNS.description);
+                    let mut lines = patterns.summary();
+                    let verdict = lines.pop().unwrap_or_default();
+                    for line in lines {
+                        result_ui.label(line);
+                    }
+                    if patterns.is_anomalous() {
+                        result_ui.colored_label(result_ui.visuals().warn_fg_color, verdict);
+                    } else {
+                        result_ui.label(verdict);
+                    }
+                }
+                if let Some(ref latency) = *self.latency.lock().unwrap() {
+                    result_ui.separator();
+                    result_ui.label("Latency:").on_hover_text(metrics::LATENCY.description);
+                    let mut lines = latency.summary();
+                    let verdict = lines.pop().unwrap_or_default();
+                    for line in lines {
+                        result_ui.label(line);
+                    }
+                    if latency.is_spiky() {
+                        result_ui.colored_label(result_ui.visuals().warn_fg_color, verdict);
+                    } else {
+                        result_ui.label(verdict);
+                    }
+                    let micros = |duration: Duration| duration.as_secs_f64() * 1e6;
+                    let samples: Vec<f64> = latency.samples.iter().copied().map(micros).collect();
+                    plot::latency_chart(
+                        result_ui,
+                        &samples,
+                        micros(latency.median()),
+                        micros(latency.percentile(99.0)),
+                        self.settings.palette.colors().0
+                    );
+                }
+                if let Some(completion) = *self.completion.lock().unwrap() {
+                    result_ui.separator();
+                    result_ui
+                        .label("Flush vs finish:")
+                        .on_hover_text(metrics::COMPLETION.description);
+                    let mut lines = completion.summary();
+                    let verdict = lines.pop().unwrap_or_default();
+                    for line in lines {
+                        result_ui.label(numbers.number(&line));
+                    }
+                    if completion.is_discrepant() {
+                        result_ui.colored_label(result_ui.visuals().warn_fg_color, verdict);
+                    } else {
+                        result_ui.label(verdict);
+                    }
+                }
+                if let Some(ref ramp) = *self.ramp.lock().unwrap() {
+                    result_ui.separator();
+                    result_ui.label("Size ramp:").on_hover_text(metrics::RAMP.description);
+                    let mut lines = ramp.summary();
+                    let verdict = lines.pop().unwrap_or_default();
+                    for line in lines {
+                        result_ui.label(numbers.number(&line));
+                    }
+                    result_ui.label(verdict);
+                    plot::size_curve(result_ui, &ramp.points, self.settings.palette);
+                }
+                if let Some(ref sweep) = *self.sweep.lock().unwrap() {
+                    result_ui.separator();
+                    result_ui.label("Size sweep:").on_hover_text(metrics::SWEEP.description);
+                    for line in sweep.summary() {
+                        result_ui.label(numbers.number(&line));
+                    }
+                    plot::sweep_chart(result_ui, sweep, self.settings.palette);
+                }
+                if let Some(ref offsets) = *self.offsets.lock().unwrap() {
+                    result_ui.separator();
+                    result_ui.label("Copy offsets:").on_hover_text(metrics::OFFSETS.description);
+                    for line in offsets.summary() {
+                        result_ui.label(numbers.number(&line));
+                    }
+                    plot::offset_curve(result_ui, &offsets.points, self.settings.palette);
+                }
+                if let Some(scatter) = *self.scatter.lock().unwrap() {
+                    result_ui.separator();
+                    result_ui
+                        .label("Batched small uploads:")
+                        .on_hover_text(metrics::SCATTER.description);
+                    for line in scatter.summary() {
+                        result_ui.label(numbers.number(&line));
+                    }
+                }
+                if let Some(ref inflight) = *self.inflight.lock().unwrap() {
+                    result_ui.separator();
+                    result_ui
+                        .label("In-flight depth:")
+                        .on_hover_text(metrics::INFLIGHT.description);
+                    for line in inflight.summary() {
+                        result_ui.label(numbers.number(&line));
+                    }
+                }
+                if let Some(ref contention) = *self.contention.lock().unwrap() {
+                    result_ui.separator();
+                    result_ui
+                        .label("Contention curve:")
+                        .on_hover_text(metrics::CONTENTION.description);
+                    for line in contention.summary() {
+                        result_ui.label(numbers.number(&line));
+                    }
+                }
+                if let Some(ref round_trip) = *self.round_trip.lock().unwrap() {
+                    result_ui.separator();
+                    result_ui
+                        .label("Round trip:")
+                        .on_hover_text(metrics::ROUND_TRIP.description);
+                    for line in round_trip.summary() {
+                        result_ui.label(numbers.number(&line));
+                    }
+                }
+                if let Some(ref random_sizes) = *self.random_sizes.lock().unwrap() {
+                    result_ui.separator();
+                    result_ui
+                        .label("Random sizes:")
+                        .on_hover_text(metrics::RANDOM_SIZES.description);
+                    for line in random_sizes.summary() {
+                        result_ui.label(numbers.number(&line));
+                    }
+                }
+                if let Some(ref result) = *self.mode_result.lock().unwrap() {
+                    result_ui.separator();
+                    let label = result_ui.label(format!("{}:", result.mode));
+                    if let Some(mode) = self.modes.get(&result.mode) {
+                        label.on_hover_text(mode.description());
+                    }
+                    for line in &result.lines {
+                        result_ui.label(numbers.number(line));
+                    }
+                    if pin_button(result_ui, self.pinned.len()) {
+                        self.pinned.push(PinnedCard {
+                            title: result.mode.clone(),
+                            lines: result.lines.clone(),
+                        });
+                    }
+                }
+                if let Some(ref result) = *self.matrix_result.lock().unwrap() {
+                    result_ui.separator();
+                    result_ui
+                        .label("Matrix, H2D / D2H GB/s:")
+                        .on_hover_text(metrics::MATRIX.description);
+                    let (rows, columns, text) = result.pivot();
+                    egui::Grid
+                        ::new("matrix")
+                        .striped(true)
+                        .show(result_ui, |ui| {
+                            ui.label("");
+                            for column in &columns {
+                                ui.strong(column);
+                            }
+                            ui.end_row();
+                            for (row, cells) in rows.iter().zip(&text) {
+                                ui.label(row);
+                                for cell in cells {
+                                    ui.label(cell);
+                                }
+                                ui.end_row();
+                            }
+                        });
+                    if let Some(best) = result.best() {
+                        result_ui.label(format!("Best: {}", best.cell));
+                    }
+                    for cell in &result.cells {
+                        if let Err(ref reason) = cell.outcome {
+                            result_ui.weak(format!("{}: {}", cell.cell, reason));
+                        }
+                    }
+                }
+                if let Some(peer) = *self.peer.lock().unwrap() {
+                    result_ui.separator();
+                    result_ui.label("Peer copies:").on_hover_text(metrics::PEER.description);
+                    result_ui.label(peer.summary());
+                }
+                if let Some(streaming) = *self.streaming.lock().unwrap() {
+                    result_ui.separator();
+                    result_ui
+                        .label("Streaming from disk:")
+                        .on_hover_text(metrics::STREAMING.description);
+                    result_ui.label(streaming.summary());
+                }
+                if let Some(dma_buf) = *self.dma_buf.lock().unwrap() {
+                    result_ui.separator();
+                    result_ui.label("dma-buf import:").on_hover_text(metrics::DMA_BUF.description);
+                    result_ui.label(dma_buf.summary());
+                }
+                if let Some(interop) = *self.interop.lock().unwrap() {
+                    result_ui.separator();
+                    result_ui.label("GL interop:").on_hover_text(metrics::INTEROP.description);
+                    result_ui.label(interop.summary());
+                }
+                match *self.ranking.lock().unwrap() {
+                    Some(Ok(ref ranking)) => {
+                        result_ui.separator();
+                        for line in ranking.summary() {
+                            result_ui.label(line);
+                        }
+                    }
+                    Some(Err(ref e)) => {
+                        result_ui.separator();
+                        result_ui.label(format!("Could not submit the results: {}", e));
+                    }
+                    None => {}
+                }
+            });
+        });
+    }
+}
+
+/// The stored mean throughput of each of `devices`, keyed as `App::history` is.
+fn history_from(store: &dyn ResultStore, devices: &[MyDevice]) -> HashMap<usize, Vec<f64>> {
+    let mut history = HashMap::new();
+    for device in devices {
+        match store.recent(&device.settings_key(), HISTORY_LEN) {
+            Ok(results) if !results.is_empty() => {
+                let values = results.iter().map(StoredResult::mean_throughput).collect();
+                history.insert(device.key(), values);
+            }
+            Ok(_) => (),
+            Err(e) => eprintln!("Warning: could not read the stored results: {}", e),
+        }
+    }
+    history
+}
+
+/// The modes compiled in and those of the plugins in `plugins` under the config directory.
+fn load_modes() -> Registry {
+    let mut modes = Registry::new();
+    if let Some(dir) = config::config_dir() {
+        for skipped in modes.load_plugins(&dir.join("plugins")) {
+            eprintln!("Warning: skipped plugin {}", skipped);
+        }
+    }
+    modes
+}
+
+/// The device's stored runs of `record`'s transfer size by ECC state, once `record` is kept.
+fn compare_ecc(
+    store: &Mutex<Box<dyn ResultStore>>,
+    device: &MyDevice,
+    record: &MeasurementRecord
+) -> Option<EccComparison> {
+    record.throughput.telemetry.ecc?;
+    match store.lock().unwrap().recent(&device.settings_key(), ecc::COMPARED_RUNS) {
+        Ok(history) => ecc::compare(&history, record.config.transfer_bytes()),
+        Err(e) => {
+            eprintln!("Warning: could not read the stored results: {}", e);
+            None
+        }
+    }
+}
+
+/// Color of the vendor's label in the device selector, after the vendor's own.
+fn vendor_color(vendor: Vendor) -> egui::Color32 {
+    match vendor {
+        Vendor::Nvidia => egui::Color32::from_rgb(118, 185, 0),
+        Vendor::Amd => egui::Color32::from_rgb(237, 28, 36),
+        Vendor::Intel => egui::Color32::from_rgb(0, 113, 197),
+        Vendor::Apple | Vendor::Other => egui::Color32::GRAY,
+        Vendor::Arm => egui::Color32::from_rgb(0, 145, 189),
+        Vendor::Qualcomm => egui::Color32::from_rgb(50, 83, 220),
+    }
+}
+
+/// Runs `hook` on `record`, warning on stderr rather than failing the run if it fails.
+fn run_hook(hook: &Hook, device: &MyDevice, record: &MeasurementRecord) {
+    if let Err(e) = hook.run(&StoredResult::new(device, record)) {
+        eprintln!("Warning: the after_run hook failed: {}", e);
+    }
+}
+
+/// Saves `record` to `store`, warning on stderr rather than failing the run if it cannot.
+fn keep(store: &Mutex<Box<dyn ResultStore>>, device: &MyDevice, record: &MeasurementRecord) {
+    if let Err(e) = store.lock().unwrap().save(&StoredResult::new(device, record)) {
+        eprintln!("Warning: could not keep the results: {}", e);
+    }
+}
+
+/// A button pinning a result beside the later ones, disabled once `pinned` reaches
+/// `PINNED_CARDS`. Whether it was clicked.
+fn pin_button(ui: &mut egui::Ui, pinned: usize) -> bool {
+    ui.add_enabled(pinned < PINNED_CARDS, egui::Button::new("Pin"))
+        .on_hover_text("Keeps this result beside later ones for comparison")
+        .on_disabled_hover_text(format!("At most {} results can be pinned", PINNED_CARDS))
+        .clicked()
+}
+
+/// The latest single run as a pinned card: its configuration, throughput and link health.
+fn result_card(
+    latest: &SessionResult,
+    throughput: &Throughput,
+    health: Option<HealthScore>
+) -> PinnedCard {
+    let mut lines = vec![
+        latest.configuration.clone(),
+        format!("Host to Device: {:.2} GB/s", latest.h2d)
+    ];
+    lines.push(if throughput.has_d2h() {
+        format!("Device to Host: {:.2} GB/s", latest.d2h)
+    } else {
+        "Device to Host: skipped".to_string()
+    });
+    if let Some(health) = health {
+        lines.push(format!("Link health: {} ({})", health.value(), health.grade()));
+    }
+    PinnedCard { title: latest.label.clone(), lines }
+}
+
+/// Opens the main window and runs until it is closed.
+pub fn run(config: Config) -> ExitCode {
+    run_with(config, eframe::NativeOptions::default())
+}
+
+/// Runs in the window of the NativeActivity `app`, see `android`.
+#[cfg(target_os = "android")]
+pub fn run_android(config: Config, app: AndroidApp) -> ExitCode {
+    let native_options = eframe::NativeOptions {
+        event_loop_builder: Some(
+            Box::new(move |builder| {
+                builder.with_android_app(app);
+            })
+        ),
+        ..Default::default()
+    };
+    run_with(config, native_options)
+}
+
+fn run_with(config: Config, native_options: eframe::NativeOptions) -> ExitCode {
+    let app = App::new(config, Console::install());
+    if
+        let Err(e) = eframe::run_native(
+            APP_TITLE,
+            native_options,
+            Box::new(|cc| {
+                let mut app = app;
+                app.taskbar = Taskbar::open(cc);
+                if cfg!(target_os = "android") {
+                    touch_friendly(&cc.egui_ctx);
+                }
+                Ok(Box::new(app))
+            })
+        )
+    {
+        eprintln!("Error: {}", e);
+        return ExitCode::from(error::EXIT_OTHER);
+    }
+
+    ExitCode::SUCCESS
+}
+
+/// Larger text and controls, spaced for fingers rather than a mouse pointer.
+fn touch_friendly(ctx: &egui::Context) {
+    ctx.set_zoom_factor(1.5);
+    ctx.style_mut(|style| {
+        // The minimum a control takes, in points before the zoom
+        style.spacing.interact_size = egui::vec2(48.0, 32.0);
+        style.spacing.item_spacing = egui::vec2(12.0, 10.0);
+        style.spacing.button_padding = egui::vec2(10.0, 6.0);
+        style.spacing.scroll.bar_width = 16.0;
+    });
+}
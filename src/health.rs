@@ -0,0 +1,212 @@
+//! One score from 0 to 100 for the whole run, for users who want a verdict rather than a page
+//! of numbers. It weighs throughput against what the negotiated link allows, how steady the
+//! samples were, the fixed cost of a small transfer and the PCIe errors recorded meanwhile.
+//! Components the platform cannot report are left out and the others weighted up.
+
+use crate::statistics::Statistics;
+use crate::telemetry::LinkStatus;
+use crate::Throughput;
+use std::fmt;
+
+/// Share of the link's encoded bandwidth a healthy link reaches; protocol overhead takes the
+/// rest, so reaching this scores full marks.
+const EXPECTED_EFFICIENCY: f64 = 0.8;
+/// Coefficient of variation of either direction's samples at which stability scores nothing.
+const UNSTABLE_VARIATION: f64 = 0.2;
+/// Small-write latencies in microseconds scoring full marks and nothing.
+const LATENCY_RANGE: (f64, f64) = (10.0, 100.0);
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Grade {
+    Healthy,
+    Degraded,
+    Unhealthy,
+}
+
+impl fmt::Display for Grade {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Grade::Healthy => write!(f, "Healthy"),
+            Grade::Degraded => write!(f, "Degraded"),
+            Grade::Unhealthy => write!(f, "Unhealthy"),
+        }
+    }
+}
+
+/// One input to the score.
+#[derive(Clone, Debug)]
+pub struct Component {
+    pub name: &'static str,
+    /// From 0 to 1.
+    pub score: f64,
+    /// Relative weight before the missing components are left out.
+    pub weight: f64,
+    /// What was measured, e.g. "85% of the PCIe 4.0 x16 link".
+    pub detail: String,
+}
+
+/// The composite score of a run, see `HealthScore::of`.
+#[derive(Clone, Debug)]
+pub struct HealthScore {
+    pub components: Vec<Component>,
+}
+
+impl HealthScore {
+    /// Scores `throughput`, comparing it against `link` where the link could be read. `None`
+    /// when nothing was measured or none of the components could be scored, e.g. for a single
+    /// iteration with no link information.
+    pub fn of(throughput: &Throughput, link: Option<LinkStatus>) -> Option<HealthScore> {
+        if throughput.h2d_samples.is_empty() {
+            return None;
+        }
+        let mut components = Vec::new();
+
+        if let Some(link) = link {
+            let share = throughput.slowest_throughput() / link.bandwidth();
+            components.push(Component {
+                name: "Bandwidth",
+                score: (share / EXPECTED_EFFICIENCY).min(1.0),
+                weight: 50.0,
+                detail: format!(
+                    "{:.0}% of the {} GT/s x{} link",
+                    share * 100.0,
+                    link.speed,
+                    link.width
+                ),
+            });
+        }
+
+        // Per direction, as the two differ on asymmetric links without either being unsteady
+        let variation = [&throughput.h2d_samples, &throughput.d2h_samples]
+            .into_iter()
+            .filter_map(|samples| Statistics::of(samples))
+            .map(|statistics| statistics.relative_spread())
+            .reduce(f64::max);
+        if let Some(variation) = variation {
+            components.push(Component {
+                name: "Stability",
+                score: (1.0 - variation / UNSTABLE_VARIATION).max(0.0),
+                weight: 20.0,
+                detail: format!("samples vary by {:.1}%", variation * 100.0),
+            });
+        }
+
+        if let Some(latency) = throughput.latency {
+            let micros = latency.as_secs_f64() * 1e6;
+            let (best, worst) = LATENCY_RANGE;
+            components.push(Component {
+                name: "Latency",
+                score: ((worst - micros) / (worst - best)).clamp(0.0, 1.0),
+                weight: 15.0,
+                detail: format!("{:.1} µs per small write", micros),
+            });
+        }
+
+        if let Some(errors) = throughput.telemetry.link_errors {
+            components.push(Component {
+                name: "Errors",
+                score: match errors {
+                    0 => 1.0,
+                    1..=9 => 0.5,
+                    _ => 0.0,
+                },
+                weight: 15.0,
+                detail: format!("{} PCIe errors during the run", errors),
+            });
+        }
+
+        (!components.is_empty()).then_some(HealthScore { components })
+    }
+
+    /// The weighted score from 0 to 100.
+    pub fn value(&self) -> u32 {
+        let weights: f64 = self.components
+            .iter()
+            .map(|component| component.weight)
+            .sum();
+        if weights == 0.0 {
+            return 0;
+        }
+        let weighted: f64 = self.components
+            .iter()
+            .map(|component| component.score * component.weight)
+            .sum();
+        ((weighted / weights) * 100.0).round() as u32
+    }
+
+    pub fn grade(&self) -> Grade {
+        match self.value() {
+            80.. => Grade::Healthy,
+            50..=79 => Grade::Degraded,
+            _ => Grade::Unhealthy,
+        }
+    }
+
+    /// The headline, then one line per component.
+    pub fn summary(&self) -> Vec<String> {
+        let mut lines = vec![format!("Link health: {}/100 ({})", self.value(), self.grade())];
+        lines.extend(
+            self.components.iter().map(|component| {
+                format!(
+                    "{}: {:.0}/100, {}",
+                    component.name,
+                    component.score * 100.0,
+                    component.detail
+                )
+            })
+        );
+        lines
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn throughput(h2d: &[f64], d2h: &[f64]) -> Throughput {
+        Throughput { h2d_samples: h2d.to_vec(), d2h_samples: d2h.to_vec(), ..Throughput::new() }
+    }
+
+    fn stability(score: &HealthScore) -> f64 {
+        score.components
+            .iter()
+            .find(|component| component.name == "Stability")
+            .map(|component| component.score)
+            .unwrap()
+    }
+
+    #[test]
+    fn asymmetric_link_is_stable() {
+        let throughput = throughput(&[25.0, 25.1, 24.9], &[13.0, 13.1, 12.9]);
+        let score = HealthScore::of(&throughput, None).unwrap();
+        assert!(stability(&score) > 0.9);
+        assert_eq!(score.grade(), Grade::Healthy);
+    }
+
+    #[test]
+    fn unsteady_direction_scores_stability_down() {
+        let throughput = throughput(&[25.0, 25.0, 25.0], &[13.0, 8.0, 18.0]);
+        let score = HealthScore::of(&throughput, None).unwrap();
+        assert_eq!(stability(&score), 0.0);
+        assert_eq!(score.value(), 0);
+    }
+
+    #[test]
+    fn nothing_to_score() {
+        assert!(HealthScore::of(&throughput(&[], &[]), None).is_none());
+        assert!(HealthScore::of(&throughput(&[25.0], &[]), None).is_none());
+    }
+
+    #[test]
+    fn bandwidth_against_the_link() {
+        let link = LinkStatus { speed: 16.0, width: 16 };
+        let throughput = Throughput {
+            h2d_throughput: link.bandwidth() * EXPECTED_EFFICIENCY / 2.0,
+            ..throughput(&[25.0], &[])
+        };
+        let score = HealthScore::of(&throughput, Some(link)).unwrap();
+        assert_eq!(score.components.len(), 1);
+        assert_eq!(score.value(), 50);
+        assert_eq!(score.grade(), Grade::Degraded);
+    }
+}
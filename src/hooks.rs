@@ -0,0 +1,66 @@
+//! A command run after each finished measurement, for feeding results into dashboards and
+//! scripts that gputhroughput has no integration for. The command gets the path of the result
+//! as JSON, in the format the JSON result store keeps, as its last argument, and the headline
+//! figures in the environment:
+//!
+//! - `GPUTHROUGHPUT_DEVICE`: the OpenCL device name
+//! - `GPUTHROUGHPUT_TRANSFER_BYTES`: bytes per transfer
+//! - `GPUTHROUGHPUT_H2D_GBPS`: mean host-to-device throughput
+//! - `GPUTHROUGHPUT_D2H_GBPS`: mean device-to-host throughput, unset when it was not measured
+//!
+//! The file is removed once the command exits.
+
+use crate::store::StoredResult;
+use std::path::PathBuf;
+use std::process::Command;
+use std::sync::atomic::{ AtomicU64, Ordering };
+
+/// Hook runs so far, which tell apart results of the same second, as an all-devices run has.
+static RUNS: AtomicU64 = AtomicU64::new(0);
+
+/// The command of `[hooks] after_run`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Hook {
+    pub program: String,
+    /// Passed before the result's path.
+    pub args: Vec<String>,
+}
+
+impl Hook {
+    /// Writes `result` to a temporary file and runs the command on it, waiting for it to exit.
+    /// Errors are messages for the user, as the measurement itself already succeeded.
+    pub fn run(&self, result: &StoredResult) -> Result<(), String> {
+        let path = result_path(result);
+        let json = serde_json::to_string_pretty(result).map_err(|e| e.to_string())?;
+        std::fs::write(&path, json).map_err(|e| format!("{}: {}", path.display(), e))?;
+
+        let mut command = Command::new(&self.program);
+        command
+            .args(&self.args)
+            .arg(&path)
+            .env("GPUTHROUGHPUT_DEVICE", &result.device)
+            .env("GPUTHROUGHPUT_TRANSFER_BYTES", result.transfer_bytes.to_string())
+            .env("GPUTHROUGHPUT_H2D_GBPS", result.h2d.to_string());
+        match result.d2h {
+            Some(d2h) => command.env("GPUTHROUGHPUT_D2H_GBPS", d2h.to_string()),
+            None => command.env_remove("GPUTHROUGHPUT_D2H_GBPS"),
+        };
+        let status = command.status();
+        // Left behind only if it cannot be removed, which the command itself may have done
+        let _ = std::fs::remove_file(&path);
+        let status = status.map_err(|e| format!("{}: {}", self.program, e))?;
+        if status.success() {
+            Ok(())
+        } else {
+            Err(format!("{} exited with {}", self.program, status))
+        }
+    }
+}
+
+/// A file in the temporary directory that no other run uses at the same time.
+fn result_path(result: &StoredResult) -> PathBuf {
+    let run = RUNS.fetch_add(1, Ordering::Relaxed);
+    std::env::temp_dir().join(
+        format!("gputhroughput-{}-{}-{}.json", std::process::id(), result.timestamp, run)
+    )
+}
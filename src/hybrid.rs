@@ -0,0 +1,198 @@
+//! Whether the measured GPU is one half of a hybrid graphics setup, an integrated GPU beside a
+//! discrete one as in most gaming laptops, and which of the two drives the display. With the
+//! display on the integrated GPU (NVIDIA Optimus, AMD's hybrid mode) every frame the discrete
+//! GPU renders for the screen is copied back over the same PCIe link, so its downloads compete
+//! with the desktop; a MUX switched to the discrete GPU attaches the display to it directly.
+//!
+//! Linux only: GPUs are found in `/sys/bus/pci/devices` and connected displays in
+//! `/sys/class/drm`.
+
+use crate::telemetry::{ self, LinkStatus, PciAddress };
+use crate::{ MyDevice, Throughput };
+use std::fmt;
+use std::path::Path;
+
+/// D2H below this share of H2D is called out as slower in the hints.
+const SLOWER_D2H: f64 = 0.9;
+
+/// Which GPU of a hybrid setup drives the display.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Display {
+    Integrated,
+    Discrete,
+    /// No connected display could be traced to either GPU.
+    Unknown,
+}
+
+impl fmt::Display for Display {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Display::Integrated => write!(f, "integrated GPU"),
+            Display::Discrete => write!(f, "discrete GPU"),
+            Display::Unknown => write!(f, "unknown GPU"),
+        }
+    }
+}
+
+/// What `Hybrid::detect` found.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Hybrid {
+    /// Whether the firmware says this is a laptop, where the setup is most common.
+    pub laptop: bool,
+    /// Whether the measured GPU is the integrated one.
+    pub measured_integrated: bool,
+    pub display: Display,
+}
+
+impl Hybrid {
+    /// `None` unless both an integrated and a discrete GPU are present.
+    pub fn detect(device: &MyDevice) -> Option<Hybrid> {
+        let gpus = gpus();
+        if !gpus.iter().any(|gpu| gpu.integrated) || !gpus.iter().any(|gpu| !gpu.integrated) {
+            return None;
+        }
+        let address = PciAddress::of(device.get_device());
+        let measured_integrated = device
+            .get_device()
+            .host_unified_memory()
+            .ok()
+            .or_else(|| {
+                let address = address?;
+                gpus.iter().find(|gpu| gpu.address == address).map(|gpu| gpu.integrated)
+            })?;
+        let connected = connected_displays();
+        let drives = |gpu: &&Gpu| {
+            if connected.is_empty() {
+                telemetry::drives_display(gpu.address)
+            } else {
+                connected.contains(&gpu.address)
+            }
+        };
+        let display = match gpus.iter().find(drives) {
+            Some(gpu) if gpu.integrated => Display::Integrated,
+            Some(_) => Display::Discrete,
+            None => Display::Unknown,
+        };
+        Some(Hybrid { laptop: is_laptop(), measured_integrated, display })
+    }
+
+    /// A note for the results, e.g. "Hybrid graphics (laptop): measured the discrete GPU,
+    /// display on the integrated GPU".
+    pub fn annotation(&self) -> String {
+        format!(
+            "Hybrid graphics{}: measured the {}, display on the {}",
+            if self.laptop { " (laptop)" } else { "" },
+            if self.measured_integrated { Display::Integrated } else { Display::Discrete },
+            self.display
+        )
+    }
+
+    /// What the setup means for `throughput`, most useful first.
+    pub fn hints(&self, throughput: &Throughput) -> Vec<String> {
+        if self.measured_integrated {
+            return vec![
+                "the integrated GPU shares system memory, so its transfers are memory copies \
+                 that never cross PCIe; pick the discrete GPU with --device to measure the link"
+                    .to_string()
+            ];
+        }
+        let mut hints = Vec::new();
+        match self.display {
+            Display::Integrated => {
+                let slower = throughput.has_d2h() &&
+                    throughput.d2h_throughput < throughput.h2d_throughput * SLOWER_D2H;
+                if slower {
+                    hints.push(
+                        format!(
+                            "D2H read {:.0}% below H2D, as expected with the display on the \
+                             integrated GPU",
+                            (1.0 - throughput.d2h_throughput / throughput.h2d_throughput) * 100.0
+                        )
+                    );
+                }
+                hints.push(
+                    "with the display on the integrated GPU, frames this GPU renders are copied \
+                     to it over the same PCIe link and compete with D2H transfers; close \
+                     windows running on this GPU, or switch the MUX to discrete in the firmware \
+                     or the vendor's control panel where the laptop has one"
+                        .to_string()
+                );
+            }
+            Display::Discrete => {
+                hints.push(
+                    "the display is on this GPU, as with a MUX switched to discrete, so the \
+                     desktop's own rendering shares it; --gentle keeps the desktop smooth"
+                        .to_string()
+                );
+            }
+            Display::Unknown => {}
+        }
+        hints
+    }
+}
+
+/// A display controller on the PCI bus.
+struct Gpu {
+    address: PciAddress,
+    /// Integrated GPUs sit on the root bus or have no PCIe link of their own; an APU's GPU
+    /// behind an internal bridge reads as discrete unless OpenCL says otherwise.
+    integrated: bool,
+}
+
+fn gpus() -> Vec<Gpu> {
+    let Ok(entries) = std::fs::read_dir("/sys/bus/pci/devices") else {
+        return Vec::new();
+    };
+    entries
+        .filter_map(|entry| {
+            let entry = entry.ok()?;
+            // Class 0x03 is a display controller, e.g. "0x030000"
+            let class = std::fs::read_to_string(entry.path().join("class")).ok()?;
+            if !class.trim().starts_with("0x03") {
+                return None;
+            }
+            let address = parse_address(entry.file_name().to_str()?)?;
+            let integrated = address.bus == 0 || LinkStatus::current(address).is_none();
+            Some(Gpu { address, integrated })
+        })
+        .collect()
+}
+
+/// The GPUs with at least one connected display, from DRM connectors such as `card1-eDP-1`.
+fn connected_displays() -> Vec<PciAddress> {
+    let Ok(entries) = std::fs::read_dir("/sys/class/drm") else {
+        return Vec::new();
+    };
+    entries
+        .filter_map(|entry| {
+            let entry = entry.ok()?;
+            let name = entry.file_name().into_string().ok()?;
+            let (card, _) = name.split_once('-')?;
+            let status = std::fs::read_to_string(entry.path().join("status")).ok()?;
+            if status.trim() != "connected" {
+                return None;
+            }
+            let device = Path::new("/sys/class/drm").join(card).join("device");
+            let device = std::fs::canonicalize(device).ok()?;
+            parse_address(device.file_name()?.to_str()?)
+        })
+        .collect()
+}
+
+/// Parses a sysfs device name such as "0000:01:00.0".
+fn parse_address(name: &str) -> Option<PciAddress> {
+    let mut parts = name.split([':', '.']);
+    let mut next = || u32::from_str_radix(parts.next()?, 16).ok();
+    Some(PciAddress { domain: next()?, bus: next()?, device: next()?, function: next()? })
+}
+
+/// Whether the SMBIOS chassis type is one of the portable ones.
+fn is_laptop() -> bool {
+    // Portable, laptop, notebook, sub notebook, convertible and detachable
+    let portable = [8, 9, 10, 14, 31, 32];
+    std::fs
+        ::read_to_string("/sys/class/dmi/id/chassis_type")
+        .ok()
+        .and_then(|chassis| chassis.trim().parse().ok())
+        .is_some_and(|chassis: u32| portable.contains(&chassis))
+}
@@ -0,0 +1,171 @@
+//! How many transfers need to be in flight at once to hide the latency of each. By Little's
+//! law the work in a system is its throughput times how long each piece stays in it, so
+//! keeping the link busy takes bandwidth × latency bytes queued beyond the chunk being
+//! transferred. The prediction from the measured latency and bandwidth is then checked by
+//! uploading in chunks with a capped number outstanding.
+
+use crate::device::default_queue;
+use crate::error::BenchError;
+use crate::registry::DeviceRegistry;
+use crate::MeasureConfig;
+use opencl3::device::Device;
+use opencl3::event::Event;
+use opencl3::memory::{ Buffer, CL_MEM_READ_ONLY };
+use opencl3::types::{ CL_BLOCKING, CL_NON_BLOCKING };
+use std::collections::VecDeque;
+use std::ptr;
+use std::time::{ Duration, Instant };
+
+/// Bytes of each chunk, small enough that a chunk takes about as long as the latency.
+pub const CHUNK_BYTES: usize = 64 * 1024;
+/// Depths tried, doubling up to this many chunks in flight.
+pub const MAX_DEPTH: usize = 32;
+/// Share of the best throughput at which a depth counts as having hidden the latency.
+pub const OPTIMUM_SHARE: f64 = 0.95;
+/// Single-byte writes whose median is taken as the latency.
+const LATENCY_PROBES: usize = 64;
+
+/// The predicted and measured in-flight depth, see `measure_inflight`.
+#[derive(Clone, Debug)]
+pub struct InflightResult {
+    /// Median time of a one-byte upload.
+    pub latency: Duration,
+    /// GB/s of one upload of the whole transfer.
+    pub bandwidth: f64,
+    /// Chunks in flight Little's law asks for.
+    pub predicted: usize,
+    /// GB/s at each depth tried, shallowest first.
+    pub depths: Vec<(usize, f64)>,
+}
+
+impl InflightResult {
+    /// Bytes that must be queued to cover the latency, bandwidth × latency.
+    pub fn bytes_in_flight(&self) -> f64 {
+        self.bandwidth * 1e9 * self.latency.as_secs_f64()
+    }
+
+    /// The shallowest depth reaching `OPTIMUM_SHARE` of the best throughput measured.
+    pub fn measured(&self) -> usize {
+        let best = self.depths.iter().map(|&(_, gbps)| gbps).fold(0.0, f64::max);
+        self.depths
+            .iter()
+            .find(|&&(_, gbps)| gbps >= best * OPTIMUM_SHARE)
+            .map_or(1, |&(depth, _)| depth)
+    }
+
+    /// The prediction, the throughput by depth, then how they compare.
+    pub fn summary(&self) -> Vec<String> {
+        let measured = self.measured();
+        let mut lines = vec![
+            format!(
+                "Little's law: {:.2} GB/s × {:.1} µs = {:.0} KB in flight, {} chunks of {} KB",
+                self.bandwidth,
+                self.latency.as_secs_f64() * 1e6,
+                self.bytes_in_flight() / 1024.0,
+                self.predicted,
+                CHUNK_BYTES / 1024
+            ),
+            self.depths
+                .iter()
+                .map(|(depth, gbps)| format!("{}: {:.2} GB/s", depth, gbps))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ];
+        lines.push(
+            // Depths are tried in powers of two, so the prediction is checked to within one
+            if measured / 2 < self.predicted && self.predicted <= measured {
+                format!("Measured optimum: {} in flight, as predicted", measured)
+            } else {
+                format!(
+                    "Measured optimum: {} in flight against {} predicted; the driver likely \
+                     {} transfers beyond what latency and bandwidth alone explain",
+                    measured,
+                    self.predicted,
+                    if measured > self.predicted { "serializes" } else { "overlaps" }
+                )
+            }
+        );
+        lines
+    }
+}
+
+/// Measures the latency of a one-byte upload and the bandwidth of one upload of the
+/// configured size, predicts the depth from them, then uploads the configured size in
+/// `CHUNK_BYTES` chunks at each depth from 1 to `MAX_DEPTH`, waiting on the oldest chunk
+/// whenever that many are outstanding.
+///
+/// Data is not verified in this mode; it only looks at how transfers overlap.
+pub fn measure_inflight(
+    config: &MeasureConfig,
+    device: &Device
+) -> Result<InflightResult, BenchError> {
+    let chunks = ((config.transfer_bytes() as usize) / CHUNK_BYTES).max(MAX_DEPTH);
+    let total = chunks * CHUNK_BYTES;
+    let context = DeviceRegistry::global().context(device)?;
+    let queue = default_queue(&context, 0)?;
+    let mut buffer = unsafe {
+        Buffer::<u8>::create(&context, CL_MEM_READ_ONLY, total, ptr::null_mut())?
+    };
+    let host: Vec<u8> = (0..total).map(|index| index as u8).collect();
+
+    let mut probes = Vec::with_capacity(LATENCY_PROBES);
+    for _ in 0..LATENCY_PROBES {
+        let start = Instant::now();
+        unsafe {
+            queue.enqueue_write_buffer(&mut buffer, CL_BLOCKING, 0, &host[..1], &[])?;
+        }
+        probes.push(start.elapsed());
+    }
+    probes.sort();
+    let latency = probes[LATENCY_PROBES / 2];
+
+    let iterations = config.length.fixed_iterations().max(1);
+    let mut whole = Duration::ZERO;
+    for _ in 0..iterations {
+        let start = Instant::now();
+        unsafe {
+            queue.enqueue_write_buffer(&mut buffer, CL_BLOCKING, 0, &host, &[])?;
+        }
+        whole += start.elapsed();
+    }
+    let gbps = |elapsed: Duration| {
+        (total as f64) * (iterations as f64) / elapsed.as_secs_f64() / 1e9
+    };
+    let bandwidth = gbps(whole);
+    // Each chunk spends its latency plus its own transfer time in flight
+    let predicted = ((bandwidth * 1e9 * latency.as_secs_f64()) / (CHUNK_BYTES as f64)).ceil()
+        as usize + 1;
+
+    let mut depths = Vec::new();
+    let mut depth = 1;
+    while depth <= MAX_DEPTH {
+        let mut elapsed = Duration::ZERO;
+        for _ in 0..iterations {
+            let start = Instant::now();
+            let mut outstanding: VecDeque<Event> = VecDeque::with_capacity(depth);
+            for (index, chunk) in host.chunks_exact(CHUNK_BYTES).enumerate() {
+                if outstanding.len() == depth {
+                    outstanding.pop_front().map(|event| event.wait()).transpose()?;
+                }
+                // The host data outlives the waits below, as a non-blocking write requires
+                let event = unsafe {
+                    queue.enqueue_write_buffer(
+                        &mut buffer,
+                        CL_NON_BLOCKING,
+                        index * CHUNK_BYTES,
+                        chunk,
+                        &[]
+                    )?
+                };
+                queue.flush()?;
+                outstanding.push_back(event);
+            }
+            queue.finish()?;
+            elapsed += start.elapsed();
+        }
+        depths.push((depth, gbps(elapsed)));
+        depth *= 2;
+    }
+
+    Ok(InflightResult { latency, bandwidth, predicted, depths })
+}
@@ -0,0 +1,180 @@
+//! Transfers into an OpenGL buffer shared with OpenCL through `cl_khr_gl_sharing`, the path
+//! graphics applications take when CL produces or consumes data that GL renders.
+//!
+//! The GL buffer belongs to the caller, which also needs a current GL context to create it,
+//! so only the GUI offers this mode. Every transfer is bracketed by acquiring the buffer from
+//! GL and releasing it back, as it would be between frames, and is timed with both.
+
+use crate::device::default_queue;
+use crate::error::BenchError;
+use crate::MeasureConfig;
+use cl3::context::CL_CONTEXT_PLATFORM;
+use cl3::gl::{ CL_EGL_DISPLAY_KHR, CL_GLX_DISPLAY_KHR, CL_GL_CONTEXT_KHR, CL_WGL_HDC_KHR };
+use libloading::Library;
+use opencl3::context::Context;
+use opencl3::device::Device;
+use opencl3::memory::{ Buffer, ClMem, CL_MEM_READ_WRITE };
+use opencl3::types::{ cl_context_properties, CL_BLOCKING };
+use std::ffi::c_void;
+use std::fmt;
+use std::time::{ Duration, Instant };
+
+/// The window-system binding a GL context was created through, which decides how OpenCL
+/// is told about it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GlPlatform {
+    Egl,
+    Glx,
+    Wgl,
+}
+
+/// Handles of a GL context and its display (or device context on Windows).
+#[derive(Clone, Copy, Debug)]
+pub struct GlContext {
+    pub platform: GlPlatform,
+    context: *mut c_void,
+    display: *mut c_void,
+}
+
+// The handles are only passed to the driver, which may use them from any thread
+unsafe impl Send for GlContext {}
+
+impl GlContext {
+    /// The GL context current on the calling thread, found through whichever of EGL, GLX or
+    /// WGL created it.
+    pub fn current() -> Option<GlContext> {
+        // Platform, library, and the functions returning the context and display
+        let candidates: &[(GlPlatform, &str, &[u8], &[u8])] = if cfg!(windows) {
+            &[(GlPlatform::Wgl, "opengl32.dll", b"wglGetCurrentContext\0", b"wglGetCurrentDC\0")]
+        } else {
+            &[
+                (
+                    GlPlatform::Egl,
+                    "libEGL.so.1",
+                    b"eglGetCurrentContext\0",
+                    b"eglGetCurrentDisplay\0",
+                ),
+                (
+                    GlPlatform::Glx,
+                    "libGL.so.1",
+                    b"glXGetCurrentContext\0",
+                    b"glXGetCurrentDisplay\0",
+                ),
+            ]
+        };
+        candidates.iter().find_map(|&(platform, library, context, display)| {
+            // The libraries are already loaded by the GL context, this only adds a reference
+            let library = unsafe { Library::new(library).ok()? };
+            let (context, display) = unsafe {
+                let context = library.get::<unsafe extern "C" fn() -> *mut c_void>(context).ok()?;
+                let display = library.get::<unsafe extern "C" fn() -> *mut c_void>(display).ok()?;
+                (context(), display())
+            };
+            (!context.is_null()).then_some(GlContext { platform, context, display })
+        })
+    }
+
+    fn properties(&self, device: &Device) -> Result<Vec<cl_context_properties>, BenchError> {
+        let display = match self.platform {
+            GlPlatform::Egl => CL_EGL_DISPLAY_KHR,
+            GlPlatform::Glx => CL_GLX_DISPLAY_KHR,
+            GlPlatform::Wgl => CL_WGL_HDC_KHR,
+        };
+        Ok(
+            vec![
+                CL_GL_CONTEXT_KHR,
+                self.context as cl_context_properties,
+                display,
+                self.display as cl_context_properties,
+                CL_CONTEXT_PLATFORM,
+                device.platform()? as cl_context_properties,
+                0
+            ]
+        )
+    }
+}
+
+/// Mean throughput into and out of a shared GL buffer, see `measure_gl_buffer`.
+#[derive(Clone, Copy, Debug)]
+pub struct InteropResult {
+    pub platform: GlPlatform,
+    pub h2d: f64,
+    pub d2h: f64,
+}
+
+impl InteropResult {
+    pub fn summary(&self) -> String {
+        format!(
+            "{:.2} GB/s host to device, {:.2} GB/s device to host through a {} shared GL buffer",
+            self.h2d,
+            self.d2h,
+            self.platform
+        )
+    }
+}
+
+impl fmt::Display for GlPlatform {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GlPlatform::Egl => write!(f, "EGL"),
+            GlPlatform::Glx => write!(f, "GLX"),
+            GlPlatform::Wgl => write!(f, "WGL"),
+        }
+    }
+}
+
+/// Measures transfers into the GL buffer object `gl_buffer`, which must hold at least
+/// `config.data_size` floats and must not be used by GL until this returns.
+pub fn measure_gl_buffer(
+    config: &MeasureConfig,
+    device: &Device,
+    gl: GlContext,
+    gl_buffer: u32
+) -> Result<InteropResult, BenchError> {
+    let context = Context::from_devices(
+        &[device.id()],
+        &gl.properties(device)?,
+        None,
+        std::ptr::null_mut()
+    ).map_err(|e| {
+        BenchError::Unsupported(
+            format!("the driver could not share the {} context with OpenCL: {}", gl.platform, e)
+        )
+    })?;
+    let queue = default_queue(&context, 0)?;
+    let mut shared = unsafe {
+        Buffer::<f32>::create_from_gl_buffer(&context, CL_MEM_READ_WRITE, gl_buffer)?
+    };
+    let objects = [shared.get() as *const c_void];
+
+    let mut data = vec![1.0f32; config.data_size];
+    let mut h2d = Duration::ZERO;
+    let mut d2h = Duration::ZERO;
+    let iterations = config.length.fixed_iterations();
+    for _ in 0..iterations {
+        let start = Instant::now();
+        unsafe {
+            queue.enqueue_acquire_gl_objects(&objects, &[])?;
+            queue.enqueue_write_buffer(&mut shared, CL_BLOCKING, 0, &data, &[])?;
+            queue.enqueue_release_gl_objects(&objects, &[])?;
+        }
+        queue.finish()?;
+        h2d += start.elapsed();
+
+        let start = Instant::now();
+        unsafe {
+            queue.enqueue_acquire_gl_objects(&objects, &[])?;
+            queue.enqueue_read_buffer(&shared, CL_BLOCKING, 0, &mut data, &[])?;
+            queue.enqueue_release_gl_objects(&objects, &[])?;
+        }
+        queue.finish()?;
+        d2h += start.elapsed();
+    }
+
+    let bytes = (config.transfer_bytes() as f64) * (iterations as f64);
+    Ok(InteropResult {
+        platform: gl.platform,
+        h2d: bytes / h2d.as_secs_f64() / 1e9,
+        d2h: bytes / d2h.as_secs_f64() / 1e9,
+    })
+}
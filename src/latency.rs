@@ -0,0 +1,97 @@
+//! Round trips of a single float to the device and back, timed one by one so that their
+//! spread shows rather than only their average. Latency that is fine on average but spiky
+//! is a common symptom of power management: the link or GPU drops into a low-power state
+//! between transfers and each wake-up costs a stall.
+
+use crate::device::default_queue;
+use crate::error::BenchError;
+use crate::registry::DeviceRegistry;
+use opencl3::device::Device;
+use opencl3::memory::{ Buffer, CL_MEM_READ_WRITE };
+use opencl3::types::CL_BLOCKING;
+use std::ptr;
+use std::time::{ Duration, Instant };
+
+/// Round trips measured by `measure_latency`.
+pub const ROUND_TRIPS: usize = 1000;
+/// Jitter, p99 minus p50, as a multiple of the median above which latency is reported as
+/// spiky.
+pub const SPIKY_JITTER: f64 = 1.0;
+
+/// Per-sample round-trip latency, see `measure_latency`.
+#[derive(Clone, Debug)]
+pub struct LatencyResult {
+    /// In the order they were taken.
+    pub samples: Vec<Duration>,
+}
+
+impl LatencyResult {
+    /// The latency that `percentile` percent of the samples do not exceed.
+    pub fn percentile(&self, percentile: f64) -> Duration {
+        let mut sorted = self.samples.clone();
+        sorted.sort();
+        let rank = ((percentile / 100.0) * ((sorted.len() - 1) as f64)).round() as usize;
+        sorted[rank.min(sorted.len() - 1)]
+    }
+
+    pub fn median(&self) -> Duration {
+        self.percentile(50.0)
+    }
+
+    /// p99 minus p50.
+    pub fn jitter(&self) -> Duration {
+        self.percentile(99.0).saturating_sub(self.median())
+    }
+
+    /// Whether the jitter exceeds `SPIKY_JITTER` times the median.
+    pub fn is_spiky(&self) -> bool {
+        self.jitter().as_secs_f64() > self.median().as_secs_f64() * SPIKY_JITTER
+    }
+
+    /// The percentiles, then the verdict.
+    pub fn summary(&self) -> Vec<String> {
+        let micros = |duration: Duration| duration.as_secs_f64() * 1e6;
+        let max = self.samples.iter().max().copied().unwrap_or_default();
+        let mut lines = vec![
+            format!(
+                "Round trip: p50 {:.1} µs, p99 {:.1} µs, max {:.1} µs over {} samples",
+                micros(self.median()),
+                micros(self.percentile(99.0)),
+                micros(max),
+                self.samples.len()
+            )
+        ];
+        lines.push(
+            if self.is_spiky() {
+                format!(
+                    "Jitter (p99 - p50): {:.1} µs, spiky; power management may be putting the \
+                     link or GPU to sleep between transfers",
+                    micros(self.jitter())
+                )
+            } else {
+                format!("Jitter (p99 - p50): {:.1} µs", micros(self.jitter()))
+            }
+        );
+        lines
+    }
+}
+
+/// Times `ROUND_TRIPS` blocking writes of one float, each followed by a blocking read of it.
+pub fn measure_latency(device: &Device) -> Result<LatencyResult, BenchError> {
+    let context = DeviceRegistry::global().context(device)?;
+    let queue = default_queue(&context, 0)?;
+    let mut buffer = unsafe {
+        Buffer::<f32>::create(&context, CL_MEM_READ_WRITE, 1, ptr::null_mut())?
+    };
+    let mut value = [0.0f32];
+    let mut samples = Vec::with_capacity(ROUND_TRIPS);
+    for _ in 0..ROUND_TRIPS {
+        let start = Instant::now();
+        unsafe {
+            queue.enqueue_write_buffer(&mut buffer, CL_BLOCKING, 0, &value, &[])?;
+            queue.enqueue_read_buffer(&buffer, CL_BLOCKING, 0, &mut value, &[])?;
+        }
+        samples.push(start.elapsed());
+    }
+    Ok(LatencyResult { samples })
+}
@@ -0,0 +1,188 @@
+//! Memory use of gputhroughput itself over a soak run, so that a result hours long can be told
+//! apart from one skewed by the harness leaking. The process's resident memory and the
+//! device memory in use are read every `INTERVAL`, and growth of either beyond `GROWTH_LIMIT`
+//! from the first reading after the run's own buffers are allocated is reported.
+//!
+//! Resident memory is read on Linux only. Device memory in use comes from NVML or amdgpu's
+//! sysfs and counts every process on the device, so another workload growing shows up too.
+
+use crate::nvml::{ Nvml, NvmlDevice };
+use crate::telemetry::PciAddress;
+use opencl3::device::Device;
+use serde::{ Deserialize, Serialize };
+use std::path::PathBuf;
+use std::sync::atomic::{ AtomicBool, Ordering };
+use std::sync::Arc;
+use std::thread::{ self, JoinHandle };
+use std::time::{ Duration, Instant };
+
+/// Time between readings.
+pub const INTERVAL: Duration = Duration::from_secs(30);
+/// Growth of either reading from the baseline that counts as a leak.
+pub const GROWTH_LIMIT: u64 = 64 * 1024 * 1024;
+
+/// Memory use at one point of the run.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MemoryReading {
+    /// Since the run started.
+    pub seconds: f64,
+    /// Resident bytes of this process.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rss: Option<u64>,
+    /// Bytes of device memory in use.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub device: Option<u64>,
+}
+
+/// Every reading of a run, see `LeakWatch`.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct MemoryTrack {
+    pub readings: Vec<MemoryReading>,
+}
+
+impl MemoryTrack {
+    /// Change in resident bytes from the baseline to the last reading.
+    pub fn rss_growth(&self) -> Option<i64> {
+        self.growth(|reading| reading.rss)
+    }
+
+    /// Change in device bytes in use from the baseline to the last reading.
+    pub fn device_growth(&self) -> Option<i64> {
+        self.growth(|reading| reading.device)
+    }
+
+    /// The baseline is the second reading, one `INTERVAL` in, as the first is taken before
+    /// the run allocates its buffers.
+    fn growth(&self, value: impl Fn(&MemoryReading) -> Option<u64>) -> Option<i64> {
+        let values: Vec<u64> = self.readings.iter().filter_map(value).collect();
+        if values.len() < 3 {
+            return None;
+        }
+        Some((values[values.len() - 1] as i64) - (values[1] as i64))
+    }
+
+    /// A warning for each of resident and device memory that grew beyond `GROWTH_LIMIT`.
+    pub fn warnings(&self) -> Vec<String> {
+        let minutes = self.readings.last().map_or(0.0, |reading| reading.seconds / 60.0);
+        let growths = [
+            ("gputhroughput's resident memory", self.rss_growth()),
+            ("Device memory in use", self.device_growth()),
+        ];
+        growths
+            .into_iter()
+            .filter_map(|(what, growth)| {
+                let growth = growth.filter(|&growth| growth > (GROWTH_LIMIT as i64))?;
+                Some(
+                    format!(
+                        "{} grew by {:.0} MiB over {:.0} minutes; the harness may be leaking",
+                        what,
+                        (growth as f64) / 1048576.0,
+                        minutes
+                    )
+                )
+            })
+            .collect()
+    }
+
+    /// Resident and device memory from first to last reading, e.g. for a run's summary.
+    pub fn summary(&self) -> Option<String> {
+        let (first, last) = (self.readings.first()?, self.readings.last()?);
+        let mib = |bytes: Option<u64>| {
+            bytes.map_or("-".to_string(), |bytes| format!("{:.0}", (bytes as f64) / 1048576.0))
+        };
+        Some(
+            format!(
+                "Harness memory: {} to {} MiB resident, {} to {} MiB on the device, {} readings",
+                mib(first.rss),
+                mib(last.rss),
+                mib(first.device),
+                mib(last.device),
+                self.readings.len()
+            )
+        )
+    }
+}
+
+/// Where device memory in use is read.
+enum DeviceCounter {
+    Nvml(&'static Nvml, NvmlDevice),
+    /// amdgpu's `mem_info_vram_used`.
+    Sysfs(PathBuf),
+}
+
+impl DeviceCounter {
+    fn open(device: &Device) -> Option<DeviceCounter> {
+        let address = PciAddress::of(device)?;
+        if let Some(nvml) = Nvml::get() {
+            if let Some(device) = nvml.device_by_pci(address) {
+                return Some(DeviceCounter::Nvml(nvml, device));
+            }
+        }
+        let path = PathBuf::from(format!("/sys/bus/pci/devices/{}/mem_info_vram_used", address));
+        path.exists().then_some(DeviceCounter::Sysfs(path))
+    }
+
+    fn used(&self) -> Option<u64> {
+        match self {
+            DeviceCounter::Nvml(nvml, device) => nvml.memory_used(*device),
+            DeviceCounter::Sysfs(path) => std::fs::read_to_string(path).ok()?.trim().parse().ok(),
+        }
+    }
+}
+
+/// Resident bytes of this process, from `/proc/self/status`.
+fn resident_bytes() -> Option<u64> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    // e.g. "VmRSS:     123456 kB"
+    let kilobytes: u64 = status
+        .lines()
+        .find_map(|line| line.strip_prefix("VmRSS:"))?
+        .split_whitespace()
+        .next()?
+        .parse()
+        .ok()?;
+    Some(kilobytes * 1024)
+}
+
+/// Reads memory use every `INTERVAL` on a background thread while a run goes on.
+pub struct LeakWatch {
+    stop: Arc<AtomicBool>,
+    handle: JoinHandle<Vec<MemoryReading>>,
+}
+
+impl LeakWatch {
+    pub fn start(device: &Device) -> LeakWatch {
+        let counter = DeviceCounter::open(device);
+        let started = Instant::now();
+        let read = move || MemoryReading {
+            seconds: started.elapsed().as_secs_f64(),
+            rss: resident_bytes(),
+            device: counter.as_ref().and_then(DeviceCounter::used),
+        };
+        let stop = Arc::new(AtomicBool::new(false));
+        let handle = thread::spawn({
+            let stop = Arc::clone(&stop);
+            move || {
+                let mut readings = Vec::new();
+                let mut next = Instant::now();
+                while !stop.load(Ordering::SeqCst) {
+                    if Instant::now() >= next {
+                        readings.push(read());
+                        next += INTERVAL;
+                    }
+                    // Short naps, so that stopping does not wait out a whole interval
+                    thread::sleep(Duration::from_millis(100));
+                }
+                readings.push(read());
+                readings
+            }
+        });
+        LeakWatch { stop, handle }
+    }
+
+    /// Stops reading, with a last reading as the run ends.
+    pub fn finish(self) -> MemoryTrack {
+        self.stop.store(true, Ordering::SeqCst);
+        MemoryTrack { readings: self.handle.join().unwrap_or_default() }
+    }
+}
@@ -0,0 +1,124 @@
+//! The measurement core: device discovery, transfer timing and everything that feeds it,
+//! shared by the GUI, the headless mode and the C ABI in `ffi`. It does not depend on eframe,
+//! so other tools can embed it with `default-features = false`, adding the community feature
+//! for `community`.
+//!
+//! The entry points:
+//!
+//! - `device`: the GPUs on the system, `enumerate_devices` and `MyDevice`.
+//! - `bench`: what a run measures, `MeasureConfig`, and running it,
+//!   `Throughput::measure_observed`.
+//! - `report`: reading a finished `Throughput`, e.g. the PCIe link it suggests.
+//!
+//! Their items are re-exported here, so `gputhroughput::Throughput` and
+//! `gputhroughput::bench::Throughput` are the same type. The other modules add measurements
+//! beside the main one, telemetry, and ways of storing and sharing results.
+
+pub mod alerts;
+pub mod api;
+pub mod asymmetry;
+pub mod bench;
+pub mod bursts;
+pub mod capabilities;
+pub mod checksum;
+pub mod coldstart;
+#[cfg(feature = "community")]
+pub mod community;
+pub mod completion;
+pub mod concurrency;
+pub mod contention;
+pub mod device;
+pub mod diagnostics;
+pub mod diff;
+pub mod dmabuf;
+pub mod ecc;
+pub mod error;
+pub mod ffi;
+pub mod health;
+pub mod hooks;
+pub mod hybrid;
+pub mod inflight;
+pub mod interop;
+pub mod latency;
+pub mod leaks;
+pub mod linkspeed;
+pub mod live;
+pub mod matrix;
+pub mod memory;
+pub mod metrics;
+pub mod modes;
+pub mod numa;
+pub mod offsets;
+pub mod overhead;
+mod nvml;
+pub mod paging;
+pub mod partition;
+pub mod patterns;
+pub mod peer;
+#[cfg(feature = "python")]
+mod python;
+pub mod precision;
+pub mod ramp;
+pub mod registry;
+pub mod report;
+pub mod reproducibility;
+pub mod roundtrip;
+pub mod scatter;
+pub mod simulate;
+pub mod statistics;
+pub mod store;
+pub mod streaming;
+pub mod suspend;
+pub mod sweep;
+pub mod telemetry;
+pub mod theoretical;
+pub mod trace;
+pub mod unified;
+pub mod virtualization;
+pub mod warmup;
+
+// The Android build is this library, loaded by NativeActivity, so it carries the binary's GUI
+// and the modules it uses; their `gputhroughput::` paths then name this crate
+#[cfg(all(target_os = "android", feature = "gui"))]
+extern crate self as gputhroughput;
+#[cfg(all(target_os = "android", feature = "gui"))]
+mod android;
+#[cfg(all(target_os = "android", feature = "gui"))]
+mod capabilities_tab;
+#[cfg(all(target_os = "android", feature = "gui"))]
+mod config;
+#[cfg(all(target_os = "android", feature = "gui"))]
+mod console;
+#[cfg(all(target_os = "android", feature = "gui"))]
+mod elevation;
+#[cfg(all(target_os = "android", feature = "gui"))]
+mod gui;
+#[cfg(all(target_os = "android", feature = "gui"))]
+mod locale;
+#[cfg(all(target_os = "android", feature = "gui"))]
+mod plan;
+#[cfg(all(target_os = "android", feature = "gui"))]
+mod plot;
+#[cfg(all(target_os = "android", feature = "gui"))]
+mod resume;
+#[cfg(all(target_os = "android", feature = "gui"))]
+mod settings;
+#[cfg(all(target_os = "android", feature = "gui"))]
+mod snapshots;
+#[cfg(all(target_os = "android", feature = "gui"))]
+mod taskbar;
+
+pub use bench::{
+    elements_in,
+    parse_bytes,
+    parse_size,
+    HostBuffer,
+    MeasureConfig,
+    Pacing,
+    RunLength,
+    SizeUnits,
+    Throughput,
+    Verification,
+    SOAK_RESET_LIMIT,
+};
+pub use device::{ enumerate_devices, MyDevice, Vendor };
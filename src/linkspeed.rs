@@ -0,0 +1,177 @@
+//! Temporarily retrains a GPU's PCIe link to a lower generation through the config space of
+//! its upstream port, so the estimator (and applications) can be checked on a slower link.
+//!
+//! Needs root on Linux. The original target speed is restored when the guard is dropped.
+
+use crate::error::BenchError;
+use crate::telemetry::PciAddress;
+use opencl3::device::Device;
+use std::fs::{ File, OpenOptions };
+use std::io;
+use std::path::PathBuf;
+use std::thread;
+use std::time::Duration;
+
+#[cfg(target_os = "linux")]
+use std::os::unix::fs::FileExt;
+
+const PCI_CAPABILITY_LIST: u64 = 0x34;
+const PCI_CAP_ID_EXP: u8 = 0x10;
+const PCI_EXP_LNKCAP: u64 = 0x0c;
+const PCI_EXP_LNKCTL: u64 = 0x10;
+const PCI_EXP_LNKSTA: u64 = 0x12;
+const PCI_EXP_LNKCTL2: u64 = 0x30;
+const PCI_EXP_LNKCTL_RL: u16 = 0x0020;
+const PCI_EXP_LNKSTA_LT: u16 = 0x0800;
+const PCI_EXP_LNK_SPEED_MASK: u16 = 0x000f;
+
+/// Keeps the link at the reduced speed until dropped.
+pub struct LinkSpeedGuard {
+    config: File,
+    capability: u64,
+    original_target: u16,
+    device: PathBuf,
+}
+
+impl LinkSpeedGuard {
+    /// The speed the GPU reports after retraining, e.g. "8.0 GT/s PCIe".
+    pub fn current_speed(&self) -> Option<String> {
+        std::fs
+            ::read_to_string(self.device.join("current_link_speed"))
+            .ok()
+            .map(|speed| speed.trim().to_string())
+    }
+}
+
+impl Drop for LinkSpeedGuard {
+    fn drop(&mut self) {
+        let restored = set_target_speed(&self.config, self.capability, self.original_target)
+            .and_then(|()| retrain_link(&self.config, self.capability));
+        if let Err(e) = restored {
+            eprintln!("Warning: failed to restore the original PCIe link speed: {}", e);
+        }
+    }
+}
+
+/// Retrains the link of `device` to PCIe `generation` (1 = 2.5 GT/s ... 5 = 32 GT/s).
+pub fn retrain(device: &Device, generation: u8) -> Result<LinkSpeedGuard, BenchError> {
+    if !cfg!(target_os = "linux") {
+        return Err(BenchError::Unsupported("link retraining is only available on Linux".into()));
+    }
+    let address = PciAddress::of(device).ok_or_else(|| {
+        BenchError::Unsupported("the device does not report its PCI address".into())
+    })?;
+
+    let unsupported = |e: io::Error| {
+        BenchError::Unsupported(format!("cannot retrain the link of {}: {}", address, e))
+    };
+    let device = std::fs
+        ::canonicalize(format!("/sys/bus/pci/devices/{}", address))
+        .map_err(unsupported)?;
+    let upstream = device
+        .parent()
+        .filter(|port| port.join("config").exists())
+        .ok_or_else(|| {
+            BenchError::Unsupported(format!("{} has no upstream PCIe port to retrain", address))
+        })?;
+    let config = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(upstream.join("config"))
+        .map_err(unsupported)?;
+
+    let capability = find_pcie_capability(&config)
+        .map_err(unsupported)?
+        .ok_or_else(|| BenchError::Unsupported("upstream port is not a PCIe port".into()))?;
+    let link_capabilities = read_u16(&config, capability + PCI_EXP_LNKCAP).map_err(unsupported)?;
+    let max_speed = link_capabilities & PCI_EXP_LNK_SPEED_MASK;
+    if generation == 0 || (generation as u16) > max_speed {
+        return Err(
+            BenchError::Unsupported(
+                format!("the link supports PCIe generations 1 to {}, not {}", max_speed, generation)
+            )
+        );
+    }
+    let link_control = read_u16(&config, capability + PCI_EXP_LNKCTL2).map_err(unsupported)?;
+    let original_target = link_control & PCI_EXP_LNK_SPEED_MASK;
+
+    let guard = LinkSpeedGuard {
+        config,
+        capability,
+        original_target,
+        device,
+    };
+    set_target_speed(&guard.config, capability, generation as u16)
+        .and_then(|()| retrain_link(&guard.config, capability))
+        .map_err(unsupported)?;
+    Ok(guard)
+}
+
+fn find_pcie_capability(config: &File) -> io::Result<Option<u64>> {
+    let mut pointer = read_u8(config, PCI_CAPABILITY_LIST)? & 0xfc;
+    // The list lives in the first 256 bytes, so a well-formed one has at most 48 entries
+    for _ in 0..48 {
+        if pointer == 0 {
+            break;
+        }
+        if read_u8(config, pointer as u64)? == PCI_CAP_ID_EXP {
+            return Ok(Some(pointer as u64));
+        }
+        pointer = read_u8(config, (pointer as u64) + 1)? & 0xfc;
+    }
+    Ok(None)
+}
+
+fn set_target_speed(config: &File, capability: u64, speed: u16) -> io::Result<()> {
+    let control = read_u16(config, capability + PCI_EXP_LNKCTL2)?;
+    write_u16(config, capability + PCI_EXP_LNKCTL2, (control & !PCI_EXP_LNK_SPEED_MASK) | speed)
+}
+
+fn retrain_link(config: &File, capability: u64) -> io::Result<()> {
+    let control = read_u16(config, capability + PCI_EXP_LNKCTL)?;
+    write_u16(config, capability + PCI_EXP_LNKCTL, control | PCI_EXP_LNKCTL_RL)?;
+    // Training normally finishes within milliseconds; give up waiting after a second
+    for _ in 0..100 {
+        thread::sleep(Duration::from_millis(10));
+        if read_u16(config, capability + PCI_EXP_LNKSTA)? & PCI_EXP_LNKSTA_LT == 0 {
+            break;
+        }
+    }
+    Ok(())
+}
+
+fn read_u8(config: &File, offset: u64) -> io::Result<u8> {
+    let mut buf = [0u8; 1];
+    read_at(config, &mut buf, offset)?;
+    Ok(buf[0])
+}
+
+fn read_u16(config: &File, offset: u64) -> io::Result<u16> {
+    let mut buf = [0u8; 2];
+    read_at(config, &mut buf, offset)?;
+    Ok(u16::from_le_bytes(buf))
+}
+
+fn write_u16(config: &File, offset: u64, value: u16) -> io::Result<()> {
+    write_at(config, &value.to_le_bytes(), offset)
+}
+
+#[cfg(target_os = "linux")]
+fn read_at(config: &File, buf: &mut [u8], offset: u64) -> io::Result<()> {
+    config.read_exact_at(buf, offset)
+}
+
+#[cfg(target_os = "linux")]
+fn write_at(config: &File, buf: &[u8], offset: u64) -> io::Result<()> {
+    config.write_all_at(buf, offset)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn read_at(_: &File, _: &mut [u8], _: u64) -> io::Result<()> {
+    Err(io::ErrorKind::Unsupported.into())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn write_at(_: &File, _: &[u8], _: u64) -> io::Result<()> {
+    Err(io::ErrorKind::Unsupported.into())
+}
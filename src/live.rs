@@ -0,0 +1,40 @@
+//! Running figures for a continuous run, updated after every sample so they can be shown
+//! while the run is still going.
+
+use std::collections::VecDeque;
+
+/// Samples the rolling average is taken over.
+pub const WINDOW: usize = 20;
+
+#[derive(Clone, Debug, Default)]
+pub struct LiveStats {
+    /// The most recent sample in GB/s.
+    pub current: f64,
+    /// Mean of the last `WINDOW` samples.
+    pub rolling: f64,
+    /// Lowest sample seen so far.
+    pub worst: f64,
+    pub samples: usize,
+    window: VecDeque<f64>,
+}
+
+impl LiveStats {
+    pub fn push(&mut self, sample: f64) {
+        if self.window.len() == WINDOW {
+            self.window.pop_front();
+        }
+        self.window.push_back(sample);
+
+        self.current = sample;
+        self.rolling = self.window.iter().sum::<f64>() / (self.window.len() as f64);
+        self.worst = if self.samples == 0 { sample } else { self.worst.min(sample) };
+        self.samples += 1;
+    }
+}
+
+/// Live figures for both directions of a continuous run.
+#[derive(Clone, Debug, Default)]
+pub struct LiveReadout {
+    pub h2d: LiveStats,
+    pub d2h: LiveStats,
+}
@@ -0,0 +1,164 @@
+//! Numbers in the UI written the way the user's locale writes them, e.g. "1.234,56" in German,
+//! so that screenshots shared in other communities are not misread. Only what the window
+//! shows is localized; exports, files and the command line keep the "1234.56" form that
+//! other tools parse, except for `--output table`, which is only for reading.
+
+use std::fmt;
+use std::sync::OnceLock;
+
+/// No-break space, so that a grouped number never wraps across lines.
+const SPACE: char = '\u{a0}';
+
+/// Languages writing 1.234,56 and 1 234,56; the rest write 1,234.56.
+const COMMA_LANGUAGES: &[&str] = &[
+    "da", "de", "el", "es", "hr", "id", "it", "nl", "pt", "ro", "sl", "sr", "tr", "vi",
+];
+const SPACE_LANGUAGES: &[&str] = &[
+    "bg", "cs", "et", "fi", "fr", "hu", "lt", "lv", "nb", "nn", "no", "pl", "ru", "sk", "sv", "uk",
+];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum NumberFormat {
+    /// Whichever of the others the locale in the environment uses.
+    System,
+    /// 1,234.56
+    Point,
+    /// 1.234,56
+    Comma,
+    /// 1 234,56
+    Space,
+}
+
+impl NumberFormat {
+    #[cfg(feature = "gui")]
+    pub const ALL: [NumberFormat; 4] = [
+        NumberFormat::System,
+        NumberFormat::Point,
+        NumberFormat::Comma,
+        NumberFormat::Space,
+    ];
+
+    /// Name used in the settings file.
+    #[cfg(feature = "gui")]
+    pub fn key(&self) -> &'static str {
+        match self {
+            NumberFormat::System => "system",
+            NumberFormat::Point => "point",
+            NumberFormat::Comma => "comma",
+            NumberFormat::Space => "space",
+        }
+    }
+
+    #[cfg(feature = "gui")]
+    pub fn from_key(key: &str) -> Option<NumberFormat> {
+        NumberFormat::ALL.into_iter().find(|format| format.key() == key)
+    }
+
+    /// The decimal separator and the digit group separator.
+    fn separators(&self) -> (char, char) {
+        match self {
+            NumberFormat::System => detect().separators(),
+            NumberFormat::Point => ('.', ','),
+            NumberFormat::Comma => (',', '.'),
+            NumberFormat::Space => (',', SPACE),
+        }
+    }
+
+    /// `formatted`, a number as Rust formats it, with the separators of this format. Text
+    /// between numbers is kept, so a `Measurement` such as "12.40 ± 0.05" works too.
+    pub fn number(&self, formatted: &str) -> String {
+        let (decimal, group) = self.separators();
+        let mut localized = String::with_capacity(formatted.len() + 4);
+        let mut rest = formatted;
+        while !rest.is_empty() {
+            let start = rest.find(|c: char| c.is_ascii_digit()).unwrap_or(rest.len());
+            localized.push_str(&rest[..start]);
+            rest = &rest[start..];
+            let end = rest.find(|c: char| !c.is_ascii_digit() && c != '.').unwrap_or(rest.len());
+            let (integer, fraction) = match rest[..end].split_once('.') {
+                Some((integer, fraction)) => (integer, Some(fraction)),
+                None => (&rest[..end], None),
+            };
+            // Groups of three digits from the right
+            for (index, digit) in integer.chars().enumerate() {
+                if index > 0 && (integer.len() - index) % 3 == 0 {
+                    localized.push(group);
+                }
+                localized.push(digit);
+            }
+            if let Some(fraction) = fraction {
+                localized.push(decimal);
+                localized.push_str(fraction);
+            }
+            rest = &rest[end..];
+        }
+        localized
+    }
+
+    /// `value` to `decimals` places, localized.
+    pub fn float(&self, value: f64, decimals: usize) -> String {
+        self.number(&format!("{:.*}", decimals, value))
+    }
+}
+
+impl fmt::Display for NumberFormat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            NumberFormat::System => write!(f, "System ({})", detect().float(12345.6, 1)),
+            format => write!(f, "{}", format.float(12345.6, 1)),
+        }
+    }
+}
+
+/// The format of the locale in `LC_ALL`, `LC_NUMERIC` or `LANG`, in that order as POSIX
+/// looks them up. `Point` where none is set, as is usual on Windows and macOS.
+fn detect() -> NumberFormat {
+    static DETECTED: OnceLock<NumberFormat> = OnceLock::new();
+    *DETECTED.get_or_init(|| {
+        let locale = ["LC_ALL", "LC_NUMERIC", "LANG"]
+            .iter()
+            .filter_map(|name| std::env::var(name).ok())
+            .find(|locale| !locale.is_empty())
+            .unwrap_or_default();
+        // e.g. "de_DE.UTF-8" or "pt-BR"
+        let language = locale.split(['_', '-', '.', '@']).next().unwrap_or_default();
+        if COMMA_LANGUAGES.contains(&language) {
+            NumberFormat::Comma
+        } else if SPACE_LANGUAGES.contains(&language) {
+            NumberFormat::Space
+        } else {
+            NumberFormat::Point
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn groups_and_decimal_separators() {
+        assert_eq!(NumberFormat::Point.number("1234567.89"), "1,234,567.89");
+        assert_eq!(NumberFormat::Comma.number("1234567.89"), "1.234.567,89");
+        assert_eq!(NumberFormat::Space.number("1234567.89"), "1\u{a0}234\u{a0}567,89");
+    }
+
+    #[test]
+    fn short_numbers_are_not_grouped() {
+        assert_eq!(NumberFormat::Comma.number("999"), "999");
+        assert_eq!(NumberFormat::Comma.number("0.5"), "0,5");
+        assert_eq!(NumberFormat::Point.number("1000"), "1,000");
+    }
+
+    #[test]
+    fn text_between_numbers_is_kept() {
+        assert_eq!(NumberFormat::Comma.number("12.40 ± 0.05"), "12,40 ± 0,05");
+        assert_eq!(NumberFormat::Comma.number("-1234.5 GB/s"), "-1.234,5 GB/s");
+    }
+
+    #[test]
+    fn float_rounds_then_localizes() {
+        assert_eq!(NumberFormat::Comma.float(12345.678, 1), "12.345,7");
+        assert_eq!(NumberFormat::Point.float(0.126, 2), "0.13");
+    }
+}
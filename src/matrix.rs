@@ -0,0 +1,311 @@
+//! Measures every combination of device allocation, transfer size and queue count the user
+//! picked, in place of trying them one run at a time, and pivots the results into a table
+//! alongside the fastest combination.
+//!
+//! Each combination splits its transfer between as many queues, each fed by its own host
+//! thread, and reports the aggregate throughput per direction. Data is not verified here.
+
+use crate::device::default_queue;
+use crate::error::BenchError;
+use crate::memory::{ DeviceMemory, Memory };
+use crate::registry::DeviceRegistry;
+use crate::{ elements_in, MeasureConfig, MyDevice, SizeUnits };
+use opencl3::context::Context;
+use opencl3::device::Device;
+use std::fmt;
+use std::sync::Barrier;
+use std::thread;
+use std::time::Instant;
+
+/// Most combinations one matrix may hold, so that a careless selection cannot keep the GPU
+/// busy for hours.
+pub const MAX_CELLS: usize = 64;
+/// Most queues one combination may use.
+pub const MAX_QUEUES: usize = 16;
+
+/// The values each setting takes; every combination of them is measured.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Matrix {
+    pub memories: Vec<Memory>,
+    /// Transfer sizes in MB or MiB, as the units of the run.
+    pub sizes: Vec<usize>,
+    pub queues: Vec<usize>,
+}
+
+/// One combination of a `Matrix`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Cell {
+    pub memory: Memory,
+    /// Transfer size in `units`.
+    pub size: usize,
+    pub queues: usize,
+    pub units: SizeUnits,
+}
+
+impl fmt::Display for Cell {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let unit = self.units.size_unit();
+        write!(f, "{}, {} {}, {} queue", self.memory, self.size, unit, self.queues)?;
+        if self.queues != 1 {
+            write!(f, "s")?;
+        }
+        Ok(())
+    }
+}
+
+impl Matrix {
+    /// Why the matrix cannot be run with sizes in `units`, if it cannot.
+    pub fn validate(&self, units: SizeUnits) -> Result<(), String> {
+        if self.memories.is_empty() || self.sizes.is_empty() || self.queues.is_empty() {
+            return Err("the matrix needs at least one memory kind, size and queue count".into());
+        }
+        if let Some(size) = self.sizes.iter().find(|&&size| size == 0) {
+            return Err(format!("matrix sizes must be at least 1, not {}", size));
+        }
+        for &size in &self.sizes {
+            elements_in(size, units).map_err(|e| e.to_string())?;
+        }
+        let queues = self.queues.iter().find(|&&queues| !(1..=MAX_QUEUES).contains(&queues));
+        if let Some(queues) = queues {
+            return Err(format!("matrix queue counts must be 1 to {}, not {}", MAX_QUEUES, queues));
+        }
+        let cells = self.memories.len() * self.sizes.len() * self.queues.len();
+        if cells > MAX_CELLS {
+            return Err(
+                format!(
+                    "the matrix has {} combinations, more than the {} allowed",
+                    cells,
+                    MAX_CELLS
+                )
+            );
+        }
+        Ok(())
+    }
+
+    /// Every combination with sizes in `units`, sizes varying fastest.
+    pub fn cells(&self, units: SizeUnits) -> Vec<Cell> {
+        let mut cells = Vec::new();
+        for &memory in &self.memories {
+            for &queues in &self.queues {
+                for &size in &self.sizes {
+                    cells.push(Cell { memory, size, queues, units });
+                }
+            }
+        }
+        cells
+    }
+}
+
+/// The outcome of one combination.
+#[derive(Clone, Debug)]
+pub struct CellResult {
+    pub cell: Cell,
+    /// Aggregate H2D and D2H throughput in GB/s, or why the combination failed.
+    pub outcome: Result<(f64, f64), String>,
+}
+
+impl CellResult {
+    fn mean(&self) -> Option<f64> {
+        self.outcome.as_ref().ok().map(|(h2d, d2h)| (h2d + d2h) / 2.0)
+    }
+}
+
+/// Every combination measured by `run_matrix`, in `Matrix::cells` order.
+#[derive(Clone, Debug, Default)]
+pub struct MatrixResult {
+    pub cells: Vec<CellResult>,
+    /// Units of the sizes, and in which `pivot` and `table` show throughput.
+    pub units: SizeUnits,
+}
+
+impl MatrixResult {
+    /// The combination with the highest mean of both directions.
+    pub fn best(&self) -> Option<&CellResult> {
+        self.cells
+            .iter()
+            .filter(|result| result.mean().is_some())
+            .max_by(|a, b| a.mean().partial_cmp(&b.mean()).unwrap())
+    }
+
+    /// The results pivoted with one row per memory kind and queue count and one column per
+    /// size, as the row labels, the column labels and the cells' text.
+    pub fn pivot(&self) -> (Vec<String>, Vec<String>, Vec<Vec<String>>) {
+        let mut sizes: Vec<usize> = Vec::new();
+        let mut rows: Vec<(Memory, usize)> = Vec::new();
+        for result in &self.cells {
+            if !sizes.contains(&result.cell.size) {
+                sizes.push(result.cell.size);
+            }
+            if !rows.contains(&(result.cell.memory, result.cell.queues)) {
+                rows.push((result.cell.memory, result.cell.queues));
+            }
+        }
+        let text = rows
+            .iter()
+            .map(|&(memory, queues)| {
+                sizes
+                    .iter()
+                    .map(|&size| {
+                        let result = self.cells.iter().find(|result| {
+                            result.cell == Cell { memory, size, queues, units: self.units }
+                        });
+                        match result.map(|result| &result.outcome) {
+                            Some(Ok((h2d, d2h))) => {
+                                let (h2d, d2h) = (self.units.rate(*h2d), self.units.rate(*d2h));
+                                format!("{:.2} / {:.2}", h2d, d2h)
+                            }
+                            Some(Err(_)) => "failed".to_string(),
+                            None => String::new(),
+                        }
+                    })
+                    .collect()
+            })
+            .collect();
+        (
+            rows
+                .iter()
+                .map(|(memory, queues)| format!("{} x{}", memory, queues))
+                .collect(),
+            sizes
+                .iter()
+                .map(|size| format!("{} {}", size, self.units.size_unit()))
+                .collect(),
+            text,
+        )
+    }
+
+    /// The pivoted table as aligned lines of text, H2D / D2H in `units` per cell.
+    pub fn table(&self) -> Vec<String> {
+        let (rows, columns, text) = self.pivot();
+        let header = format!("H2D / D2H {}", self.units.rate_unit());
+        let label_width = rows
+            .iter()
+            .map(String::len)
+            .max()
+            .unwrap_or(0)
+            .max(header.len());
+        let widths: Vec<usize> = columns
+            .iter()
+            .enumerate()
+            .map(|(column, label)| {
+                text.iter()
+                    .map(|row| row[column].len())
+                    .chain([label.len()])
+                    .max()
+                    .unwrap()
+            })
+            .collect();
+        let line = |label: &str, cells: &[String]| {
+            let mut line = format!("{:<width$}", label, width = label_width);
+            for (cell, width) in cells.iter().zip(&widths) {
+                line.push_str(&format!("  {:>width$}", cell, width = width));
+            }
+            line
+        };
+        let mut lines = vec![line(&header, &columns)];
+        lines.extend(rows.iter().zip(&text).map(|(label, cells)| line(label, cells)));
+        lines
+    }
+}
+
+/// Measures every cell of `matrix` on `device` with the run length of `config`, reporting
+/// each result to `on_cell` as it is taken. A combination that fails, e.g. because the
+/// device lacks the memory kind, is recorded as failed and the matrix goes on.
+pub fn run_matrix(
+    matrix: &Matrix,
+    config: &MeasureConfig,
+    device: &MyDevice,
+    on_cell: &mut dyn FnMut(&CellResult)
+) -> MatrixResult {
+    let iterations = config.length.fixed_iterations();
+    let mut result = MatrixResult { cells: Vec::new(), units: config.units };
+    for cell in matrix.cells(config.units) {
+        let outcome = if cell.memory.supported_by(device) {
+            measure_cell(device.get_device(), cell, iterations).map_err(|e| e.to_string())
+        } else {
+            Err(format!("{} is not supported by this device", cell.memory))
+        };
+        let cell_result = CellResult { cell, outcome };
+        on_cell(&cell_result);
+        result.cells.push(cell_result);
+    }
+    result
+}
+
+/// Aggregate H2D and D2H throughput of `cell`, each measured from the first thread's start to
+/// the last one's finish.
+fn measure_cell(
+    device: &Device,
+    cell: Cell,
+    iterations: usize
+) -> Result<(f64, f64), BenchError> {
+    let context = &DeviceRegistry::global().context(device)?;
+    let share = elements_in(cell.size, cell.units)?.div_ceil(cell.queues);
+    let iterations = iterations.max(1);
+    let barrier = &Barrier::new(cell.queues);
+
+    let spans = thread::scope(|scope| {
+        let handles: Vec<_> = (0..cell.queues)
+            .map(|_| {
+                scope.spawn(move || {
+                    submit(context, device, cell.memory, share, iterations, barrier)
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .map(|handle| handle.join().expect("matrix thread panicked"))
+            .collect::<Result<Vec<_>, _>>()
+    })?;
+
+    let bytes = ((share * cell.queues * std::mem::size_of::<f32>()) as f64) * (iterations as f64);
+    let throughput = |span: fn(&Spans) -> (Instant, Instant)| {
+        let start = spans.iter().map(|spans| span(spans).0).min().unwrap();
+        let end = spans.iter().map(|spans| span(spans).1).max().unwrap();
+        bytes / (end - start).as_secs_f64() / 1e9
+    };
+    Ok((throughput(|spans| spans.h2d), throughput(|spans| spans.d2h)))
+}
+
+/// When one thread started and finished each direction.
+struct Spans {
+    h2d: (Instant, Instant),
+    d2h: (Instant, Instant),
+}
+
+/// One submitting thread with its own queue and allocation. Threads start each direction
+/// together, so the directions never overlap.
+fn submit(
+    context: &Context,
+    device: &Device,
+    memory: Memory,
+    size: usize,
+    iterations: usize,
+    barrier: &Barrier
+) -> Result<Spans, BenchError> {
+    let setup = (|| -> Result<_, BenchError> {
+        let queue = default_queue(context, 0)?;
+        let allocation = DeviceMemory::create(memory, context, device, size)?;
+        Ok((queue, allocation, vec![0.0f32; size]))
+    })();
+    // Every thread reaches both barriers, even after a failure, so none are left waiting
+    barrier.wait();
+    let (queue, mut allocation, mut host) = match setup {
+        Ok(setup) => setup,
+        Err(e) => {
+            barrier.wait();
+            return Err(e);
+        }
+    };
+
+    let start = Instant::now();
+    let written = (0..iterations).try_for_each(|_| allocation.write(&queue, &host).map(drop));
+    let h2d = (start, Instant::now());
+    barrier.wait();
+    written?;
+    let start = Instant::now();
+    for _ in 0..iterations {
+        allocation.read(&queue, &mut host)?;
+    }
+    Ok(Spans { h2d, d2h: (start, Instant::now()) })
+}
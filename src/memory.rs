@@ -0,0 +1,443 @@
+//! The device-side allocation a measurement transfers into and out of.
+
+use crate::capabilities;
+use crate::error::BenchError;
+use crate::unified::UnifiedMemory;
+use crate::MyDevice;
+use cl3::ext::{
+    clDeviceMemAllocINTEL_fn,
+    clEnqueueMemcpyINTEL_fn,
+    clGetExtensionFunctionAddressForPlatform,
+    clHostMemAllocINTEL_fn,
+    clMemBlockingFreeINTEL_fn,
+    clSharedMemAllocINTEL_fn,
+};
+use opencl3::command_queue::CommandQueue;
+use opencl3::context::Context;
+use opencl3::device::Device;
+use opencl3::error_codes::{ ClError, CL_SUCCESS };
+use opencl3::event::Event;
+use opencl3::memory::{
+    Buffer,
+    ClMem,
+    CL_MAP_READ,
+    CL_MAP_WRITE_INVALIDATE_REGION,
+    CL_MEM_ALLOC_HOST_PTR,
+    CL_MEM_READ_WRITE,
+    CL_MEM_USE_HOST_PTR,
+};
+use opencl3::types::{ cl_context, cl_int, cl_mem_flags, CL_BLOCKING };
+use std::alloc::{ self, Layout };
+use std::ffi::{ c_void, CStr };
+use std::fmt;
+use std::ptr;
+use std::str::FromStr;
+
+/// Kinds of allocation from `cl_intel_unified_shared_memory`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum UsmKind {
+    /// Host memory the device can access directly.
+    Host,
+    /// Memory owned by the device.
+    Device,
+    /// Memory that migrates between host and device on demand.
+    Shared,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Memory {
+    /// A regular `cl_mem` buffer moved with read/write buffer commands.
+    Buffer,
+    /// A buffer over page-aligned host memory (`CL_MEM_USE_HOST_PTR`), moved by mapping it.
+    /// Drivers may pin the pages and let the device access them in place.
+    HostPtr,
+    /// An Intel USM allocation moved with `clEnqueueMemcpyINTEL`, the recommended path on Arc.
+    Usm(UsmKind),
+    /// A buffer in device memory the CPU can map through the PCIe BAR
+    /// (`CL_MEM_USE_PERSISTENT_MEM_AMD`), written and read by the CPU directly. Writes are
+    /// write-combined and the whole of VRAM is visible with Resizable BAR; reads are uncached
+    /// and slow.
+    Vram,
+    /// A buffer the driver allocates in host memory (`CL_MEM_ALLOC_HOST_PTR`), moved by
+    /// mapping it. On GPUs that share DRAM with the CPU, such as Jetson boards, mapping is
+    /// zero-copy, so this measures what kernels there see rather than a copy they can skip.
+    Unified,
+}
+
+impl Memory {
+    pub const ALL: [Memory; 7] = [
+        Memory::Buffer,
+        Memory::HostPtr,
+        Memory::Unified,
+        Memory::Vram,
+        Memory::Usm(UsmKind::Host),
+        Memory::Usm(UsmKind::Device),
+        Memory::Usm(UsmKind::Shared),
+    ];
+
+    /// The inverse of `from_str`.
+    pub fn key(&self) -> &'static str {
+        match self {
+            Memory::Buffer => "buffer",
+            Memory::HostPtr => "host-ptr",
+            Memory::Usm(UsmKind::Host) => "usm-host",
+            Memory::Usm(UsmKind::Device) => "usm-device",
+            Memory::Usm(UsmKind::Shared) => "usm-shared",
+            Memory::Vram => "vram",
+            Memory::Unified => "unified",
+        }
+    }
+
+    pub fn supported_by(&self, device: &MyDevice) -> bool {
+        match self {
+            Memory::Buffer | Memory::HostPtr => true,
+            Memory::Usm(_) => capabilities::USM.supported_by(device),
+            Memory::Vram => capabilities::HOST_VISIBLE_VRAM.supported_by(device),
+            Memory::Unified => UnifiedMemory::detect(device).is_some(),
+        }
+    }
+}
+
+impl fmt::Display for Memory {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Memory::Buffer => write!(f, "Buffer"),
+            Memory::HostPtr => write!(f, "Host pointer"),
+            Memory::Usm(UsmKind::Host) => write!(f, "USM host"),
+            Memory::Usm(UsmKind::Device) => write!(f, "USM device"),
+            Memory::Usm(UsmKind::Shared) => write!(f, "USM shared"),
+            Memory::Vram => write!(f, "Host-visible VRAM"),
+            Memory::Unified => write!(f, "Unified (zero-copy)"),
+        }
+    }
+}
+
+impl FromStr for Memory {
+    type Err = String;
+
+    /// Parses `buffer`, `host-ptr`, `unified`, `usm-host`, `usm-device`, `usm-shared` or
+    /// `vram`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "buffer" => Ok(Memory::Buffer),
+            "host-ptr" => Ok(Memory::HostPtr),
+            "usm-host" => Ok(Memory::Usm(UsmKind::Host)),
+            "usm-device" => Ok(Memory::Usm(UsmKind::Device)),
+            "usm-shared" => Ok(Memory::Usm(UsmKind::Shared)),
+            "vram" => Ok(Memory::Vram),
+            "unified" => Ok(Memory::Unified),
+            _ => Err(format!("unknown memory kind '{}'", s)),
+        }
+    }
+}
+
+/// An allocation of `Memory` on one device.
+pub enum DeviceMemory {
+    Buffer(Buffer<f32>),
+    HostPtr {
+        // Declared first so the buffer is released before the memory it points at
+        buffer: Buffer<f32>,
+        _host: AlignedHost,
+    },
+    Usm(UsmAllocation),
+    Vram(Buffer<f32>),
+    Unified(Buffer<f32>),
+}
+
+impl DeviceMemory {
+    /// Allocates room for `size` f32 elements.
+    pub fn create(
+        memory: Memory,
+        context: &Context,
+        device: &Device,
+        size: usize
+    ) -> Result<DeviceMemory, BenchError> {
+        match memory {
+            Memory::Buffer => {
+                let buffer = unsafe {
+                    Buffer::<f32>::create(context, CL_MEM_READ_WRITE, size, ptr::null_mut())?
+                };
+                Ok(DeviceMemory::Buffer(buffer))
+            }
+            Memory::HostPtr => {
+                let host = AlignedHost::new(size)?;
+                let buffer = unsafe {
+                    Buffer::<f32>::create(
+                        context,
+                        CL_MEM_READ_WRITE | CL_MEM_USE_HOST_PTR,
+                        size,
+                        host.ptr.cast()
+                    )?
+                };
+                Ok(DeviceMemory::HostPtr { buffer, _host: host })
+            }
+            Memory::Vram => {
+                let buffer = unsafe {
+                    Buffer::<f32>::create(
+                        context,
+                        CL_MEM_READ_WRITE | CL_MEM_USE_PERSISTENT_MEM_AMD,
+                        size,
+                        ptr::null_mut()
+                    )?
+                };
+                Ok(DeviceMemory::Vram(buffer))
+            }
+            Memory::Unified => {
+                let buffer = unsafe {
+                    Buffer::<f32>::create(
+                        context,
+                        CL_MEM_READ_WRITE | CL_MEM_ALLOC_HOST_PTR,
+                        size,
+                        ptr::null_mut()
+                    )?
+                };
+                Ok(DeviceMemory::Unified(buffer))
+            }
+            Memory::Usm(kind) => {
+                let usm = UsmFunctions::load(device)?;
+                let bytes = size * std::mem::size_of::<f32>();
+                Ok(DeviceMemory::Usm(usm.alloc(kind, context, device, bytes)?))
+            }
+        }
+    }
+
+    /// The OpenCL buffer, which USM allocations do not have.
+    pub fn buffer(&self) -> Option<&Buffer<f32>> {
+        match self {
+            DeviceMemory::Buffer(buffer) |
+            DeviceMemory::HostPtr { buffer, .. } |
+            DeviceMemory::Vram(buffer) |
+            DeviceMemory::Unified(buffer) => Some(buffer),
+            DeviceMemory::Usm(_) => None,
+        }
+    }
+
+    /// Blocking copy of `data` to the device.
+    pub fn write(&mut self, queue: &CommandQueue, data: &[f32]) -> Result<Event, BenchError> {
+        match self {
+            DeviceMemory::Buffer(buffer) =>
+                Ok(unsafe { queue.enqueue_write_buffer(buffer, CL_BLOCKING, 0, data, &[])? }),
+            DeviceMemory::HostPtr { buffer, .. } |
+            DeviceMemory::Vram(buffer) |
+            DeviceMemory::Unified(buffer) => {
+                let mut mapped = ptr::null_mut();
+                unsafe {
+                    queue.enqueue_map_buffer(
+                        buffer,
+                        CL_BLOCKING,
+                        CL_MAP_WRITE_INVALIDATE_REGION,
+                        0,
+                        size_of_val(data),
+                        &mut mapped,
+                        &[]
+                    )?;
+                    ptr::copy_nonoverlapping(data.as_ptr(), mapped.cast::<f32>(), data.len());
+                    // Unmapping is where a driver without zero-copy moves the data to the device
+                    let event = queue.enqueue_unmap_mem_object(buffer.get(), mapped, &[])?;
+                    event.wait()?;
+                    Ok(event)
+                }
+            }
+            DeviceMemory::Usm(allocation) =>
+                allocation.memcpy(queue, allocation.ptr, data.as_ptr().cast(), size_of_val(data)),
+        }
+    }
+
+    /// Blocking copy from the device into `data`.
+    pub fn read(&self, queue: &CommandQueue, data: &mut [f32]) -> Result<Event, BenchError> {
+        match self {
+            DeviceMemory::Buffer(buffer) =>
+                Ok(unsafe { queue.enqueue_read_buffer(buffer, CL_BLOCKING, 0, data, &[])? }),
+            DeviceMemory::HostPtr { buffer, .. } |
+            DeviceMemory::Vram(buffer) |
+            DeviceMemory::Unified(buffer) => {
+                let mut mapped = ptr::null_mut();
+                unsafe {
+                    let event = queue.enqueue_map_buffer(
+                        buffer,
+                        CL_BLOCKING,
+                        CL_MAP_READ,
+                        0,
+                        size_of_val(data),
+                        &mut mapped,
+                        &[]
+                    )?;
+                    ptr::copy_nonoverlapping(mapped.cast::<f32>(), data.as_mut_ptr(), data.len());
+                    queue.enqueue_unmap_mem_object(buffer.get(), mapped, &[])?.wait()?;
+                    Ok(event)
+                }
+            }
+            DeviceMemory::Usm(allocation) => {
+                let bytes = size_of_val(data);
+                allocation.memcpy(queue, data.as_mut_ptr().cast(), allocation.ptr, bytes)
+            }
+        }
+    }
+}
+
+/// From AMD's `cl_ext.h`: places the buffer in host-visible device memory, so mapping it
+/// returns a pointer into VRAM rather than a staging copy.
+const CL_MEM_USE_PERSISTENT_MEM_AMD: cl_mem_flags = 1 << 6;
+
+/// Alignment NVIDIA's driver needs to pin `CL_MEM_USE_HOST_PTR` memory instead of copying it.
+pub const PAGE_SIZE: usize = 4096;
+
+/// Zeroed, page-aligned host memory for `size` f32 elements.
+pub struct AlignedHost {
+    ptr: *mut f32,
+    layout: Layout,
+}
+
+impl AlignedHost {
+    fn new(size: usize) -> Result<AlignedHost, BenchError> {
+        let layout = Layout::array::<f32>(size.max(1))
+            .and_then(|layout| layout.align_to(PAGE_SIZE))
+            .map_err(|e| BenchError::Unsupported(format!("host allocation: {}", e)))?;
+        let ptr = unsafe { alloc::alloc_zeroed(layout) }.cast::<f32>();
+        if ptr.is_null() {
+            alloc::handle_alloc_error(layout);
+        }
+        Ok(AlignedHost { ptr, layout })
+    }
+}
+
+impl Drop for AlignedHost {
+    fn drop(&mut self) {
+        unsafe { alloc::dealloc(self.ptr.cast(), self.layout) }
+    }
+}
+
+/// USM entry points, which the ICD loader only hands out per platform.
+#[derive(Clone, Copy)]
+struct UsmFunctions {
+    host_alloc: clHostMemAllocINTEL_fn,
+    device_alloc: clDeviceMemAllocINTEL_fn,
+    shared_alloc: clSharedMemAllocINTEL_fn,
+    free: clMemBlockingFreeINTEL_fn,
+    memcpy: clEnqueueMemcpyINTEL_fn,
+}
+
+impl UsmFunctions {
+    fn load(device: &Device) -> Result<UsmFunctions, BenchError> {
+        let missing = || {
+            BenchError::Unsupported(
+                "the device does not support Intel unified shared memory".into()
+            )
+        };
+        if !device.extensions().unwrap_or_default().contains("cl_intel_unified_shared_memory") {
+            return Err(missing());
+        }
+        let platform = device.platform()?;
+        let get = |name: &CStr| unsafe {
+            clGetExtensionFunctionAddressForPlatform(platform, name.as_ptr())
+        };
+        // The `_fn` types are nullable function pointers, so a missing entry point is `None`
+        let functions = unsafe {
+            UsmFunctions {
+                host_alloc: std::mem::transmute::<*mut c_void, clHostMemAllocINTEL_fn>(
+                    get(c"clHostMemAllocINTEL")
+                ),
+                device_alloc: std::mem::transmute::<*mut c_void, clDeviceMemAllocINTEL_fn>(
+                    get(c"clDeviceMemAllocINTEL")
+                ),
+                shared_alloc: std::mem::transmute::<*mut c_void, clSharedMemAllocINTEL_fn>(
+                    get(c"clSharedMemAllocINTEL")
+                ),
+                free: std::mem::transmute::<*mut c_void, clMemBlockingFreeINTEL_fn>(
+                    get(c"clMemBlockingFreeINTEL")
+                ),
+                memcpy: std::mem::transmute::<*mut c_void, clEnqueueMemcpyINTEL_fn>(
+                    get(c"clEnqueueMemcpyINTEL")
+                ),
+            }
+        };
+        let complete =
+            functions.host_alloc.is_some() &&
+            functions.device_alloc.is_some() &&
+            functions.shared_alloc.is_some() &&
+            functions.free.is_some() &&
+            functions.memcpy.is_some();
+        if complete { Ok(functions) } else { Err(missing()) }
+    }
+
+    fn alloc(
+        self,
+        kind: UsmKind,
+        context: &Context,
+        device: &Device,
+        bytes: usize
+    ) -> Result<UsmAllocation, BenchError> {
+        let mut status: cl_int = CL_SUCCESS;
+        let ptr = unsafe {
+            match kind {
+                UsmKind::Host =>
+                    self.host_alloc.unwrap()(context.get(), ptr::null(), bytes, 0, &mut status),
+                UsmKind::Device =>
+                    self.device_alloc.unwrap()(
+                        context.get(),
+                        device.id(),
+                        ptr::null(),
+                        bytes,
+                        0,
+                        &mut status
+                    ),
+                UsmKind::Shared =>
+                    self.shared_alloc.unwrap()(
+                        context.get(),
+                        device.id(),
+                        ptr::null(),
+                        bytes,
+                        0,
+                        &mut status
+                    ),
+            }
+        };
+        if status != CL_SUCCESS || ptr.is_null() {
+            return Err(ClError(status).into());
+        }
+        Ok(UsmAllocation { functions: self, context: context.get(), ptr })
+    }
+}
+
+/// Freed on drop, so it must not outlive the context it was allocated in.
+pub struct UsmAllocation {
+    functions: UsmFunctions,
+    context: cl_context,
+    ptr: *mut c_void,
+}
+
+impl UsmAllocation {
+    fn memcpy(
+        &self,
+        queue: &CommandQueue,
+        dst: *mut c_void,
+        src: *const c_void,
+        bytes: usize
+    ) -> Result<Event, BenchError> {
+        let mut event = ptr::null_mut();
+        let status = unsafe {
+            self.functions.memcpy.unwrap()(
+                queue.get(),
+                CL_BLOCKING,
+                dst,
+                src,
+                bytes,
+                0,
+                ptr::null(),
+                &mut event
+            )
+        };
+        if status != CL_SUCCESS {
+            return Err(ClError(status).into());
+        }
+        Ok(Event::new(event))
+    }
+}
+
+impl Drop for UsmAllocation {
+    fn drop(&mut self) {
+        unsafe {
+            self.functions.free.unwrap()(self.context, self.ptr);
+        }
+    }
+}
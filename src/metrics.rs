@@ -0,0 +1,419 @@
+//! What every reported metric means, kept in one table so that the UI's tooltips and a
+//! report's glossary explain a number the same way wherever it appears.
+
+/// A reported metric and its explanation.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Metric {
+    pub name: &'static str,
+    /// Unit of the reported value, empty where it has none.
+    pub unit: &'static str,
+    pub description: &'static str,
+}
+
+pub const H2D: Metric = Metric {
+    name: "Host to device throughput",
+    unit: "GB/s",
+    description: "Uploads from host memory to the GPU, the mean over all iterations. Pinned \
+                  (page-locked) host memory is copied by DMA directly, while pageable memory is \
+                  first staged through a driver buffer, so pageable transfers read lower. The \
+                  ± is the standard error of the mean over the iterations.",
+};
+
+pub const D2H: Metric = Metric {
+    name: "Device to host throughput",
+    unit: "GB/s",
+    description: "Downloads from the GPU back to host memory, the mean over all iterations. \
+                  Often slower than uploads, since the GPU writes into host memory through \
+                  fewer outstanding requests. Skipped when uploads are verified by checksum.",
+};
+
+pub const DURATION: Metric = Metric {
+    name: "Duration",
+    unit: "s",
+    description: "Time spent transferring in one direction across all iterations, excluding \
+                  allocation, warm-up and verification.",
+};
+
+pub const DATA_SIZE: Metric = Metric {
+    name: "Data size",
+    unit: "MB",
+    description: "Bytes moved per transfer. Small transfers are dominated by the fixed cost of \
+                  submitting them and read well below what the link can carry.",
+};
+
+pub const SAMPLES: Metric = Metric {
+    name: "Samples",
+    unit: "",
+    description: "Iterations measured so far. A wide spread between them, shown as a large ± or \
+                  a low stability score, points at contention from other work on the GPU or host.",
+};
+
+pub const ROLLING: Metric = Metric {
+    name: "Rolling throughput",
+    unit: "GB/s",
+    description: "Mean of the latest samples of a running measurement, which follows changes \
+                  such as thermal throttling without waiting for the run to end.",
+};
+
+pub const COLD_START: Metric = Metric {
+    name: "Cold and warm",
+    unit: "GB/s",
+    description: "The first transfer of the run (cold) beside the mean of the ones after it \
+                  (warm). The first pays for pinning the host buffer and waking the link and \
+                  the GPU from idle, as an application's first transfer from idle does too.",
+};
+
+pub const STATISTICS: Metric = Metric {
+    name: "Per iteration",
+    unit: "GB/s",
+    description: "Mean, median, minimum, maximum and standard deviation of the throughput of \
+                  each iteration. The mean is unweighted, so it can differ from the run's \
+                  throughput, which divides all the bytes by all the time. Iterations set to \
+                  be discarded run first and are left out.",
+};
+
+pub const DRIVER_PEAK: Metric = Metric {
+    name: "Driver-reported peak",
+    unit: "GB/s",
+    description: "Link counters read from NVML or amdgpu while the benchmark ran, as a \
+                  cross-check of the measured throughput.",
+};
+
+pub const EFFICIENCY: Metric = Metric {
+    name: "Efficiency",
+    unit: "GB/s/W",
+    description: "Throughput divided by the average board power during the run.",
+};
+
+pub const HOST_CPU: Metric = Metric {
+    name: "Host CPU",
+    unit: "%",
+    description: "Load of the measuring thread, the whole system and its busiest core during \
+                  the run. A thread near 100% of a core means the core, not the link, limits \
+                  the transfers; pageable copies are the usual case.",
+};
+
+pub const PAGING: Metric = Metric {
+    name: "Paging",
+    unit: "MB",
+    description: "GPU memory moved between VRAM and system memory by the Windows video memory \
+                  manager during the run, and how many throughput dips it coincided with. \
+                  Dips that coincide with paging mean VRAM is oversubscribed.",
+};
+
+pub const ECC: Metric = Metric {
+    name: "ECC",
+    unit: "%",
+    description: "Whether the GPU memory's error correction was on, as the driver reports it. \
+                  Once stored results hold runs of the same transfer size with ECC both on and \
+                  off, the difference between their mean throughput is what ECC costs.",
+};
+
+pub const VENDOR_TOOLS: Metric = Metric {
+    name: "Vendor tool",
+    unit: "",
+    description: "The PCIe link, persistence mode and ECC as nvidia-smi or rocm-smi reported \
+                  them when the run started, for where the driver's own interfaces do not. \
+                  With persistence mode off, the driver may reload before each run and slow \
+                  its start.",
+};
+
+pub const ASYMMETRY: Metric = Metric {
+    name: "Direction asymmetry",
+    unit: "%",
+    description: "How much slower one direction was than the other, flagged above 20%. PCIe \
+                  carries as much each way, so a gap this wide comes from how the transfers are \
+                  driven: the readback path, the OS scheduling the copies, or a chipset uplink \
+                  shared with other devices.",
+};
+
+pub const DUTY_CYCLE: Metric = Metric {
+    name: "Gentle mode",
+    unit: "%",
+    description: "Share of the time spent transferring, idling after every transfer for the \
+                  rest so that the display stays smooth. Throughput per transfer is unaffected; \
+                  the sustained rate is that throughput scaled by the share.",
+};
+
+pub const VIRTUALIZATION: Metric = Metric {
+    name: "Virtualization",
+    unit: "",
+    description: "Whether the GPU is measured from a virtual machine, and whether it is passed \
+                  through whole, an SR-IOV virtual function or a mediated vGPU. Virtualized \
+                  GPUs commonly read lower, the shared kinds most of all.",
+};
+
+pub const HYBRID_GRAPHICS: Metric = Metric {
+    name: "Hybrid graphics",
+    unit: "",
+    description: "Whether the measured GPU sits beside another of the other kind, an integrated \
+                  GPU and a discrete one as in most gaming laptops, and which of them drives the \
+                  display. With the display on the integrated GPU, whatever the discrete GPU \
+                  renders for the screen is copied over its PCIe link, so D2H often reads lower.",
+};
+
+pub const UNIFIED_MEMORY: Metric = Metric {
+    name: "Unified memory",
+    unit: "",
+    description: "Whether the GPU shares DRAM with the CPU rather than having memory of its own \
+                  behind PCIe, as on Jetson boards and integrated GPUs. Transfers there are \
+                  copies within system memory, bounded by DRAM bandwidth; the unified memory \
+                  kind measures the zero-copy path instead.",
+};
+
+pub const THEORETICAL: Metric = Metric {
+    name: "Theoretical maximum",
+    unit: "GB/s",
+    description: "The fastest link the GPU and its slot both support, before protocol overhead, \
+                  and the peak bandwidth of the memory bus. Protocol overhead keeps transfers \
+                  at around 80% of the link at best.",
+};
+
+pub const LINK_HEALTH: Metric = Metric {
+    name: "Link health",
+    unit: "/100",
+    description: "One score for the run, weighing throughput against the link, the stability \
+                  of the samples, small-write latency and PCIe errors. 80 and above is healthy, \
+                  50 to 79 degraded.",
+};
+
+pub const LINK_SPEED: Metric = Metric {
+    name: "Approximate PCIe link speed",
+    unit: "GB/s",
+    description: "The PCIe generations and widths whose bandwidth the measured throughput fits, \
+                  for when the negotiated link cannot be read.",
+};
+
+pub const DEVICES: Metric = Metric {
+    name: "All devices",
+    unit: "GB/s",
+    description: "The same measurement on every device in turn, ranked by the sum of both \
+                  directions.",
+};
+
+pub const SESSION: Metric = Metric {
+    name: "This session",
+    unit: "GB/s",
+    description: "The latest single run on each device measured since the app started, kept \
+                  when switching devices so that they can be compared.",
+};
+
+pub const SCALING: Metric = Metric {
+    name: "Submission from threads",
+    unit: "GB/s",
+    description: "Aggregate throughput as more host threads submit transfers at once. Gains \
+                  that stop early mean the driver serializes submissions.",
+};
+
+pub const MAPPING: Metric = Metric {
+    name: "Thread to NUMA node mapping",
+    unit: "GB/s",
+    description: "Throughput from host memory on each NUMA node. Memory on the node far from \
+                  the GPU's root port crosses the inter-socket link and reads lower.",
+};
+
+pub const PATTERNS: Metric = Metric {
+    name: "Data patterns",
+    unit: "GB/s",
+    description: "Throughput with zeros, a constant, a ramp and random data. Throughput that \
+                  depends on the data means the link or driver compresses.",
+};
+
+pub const LATENCY: Metric = Metric {
+    name: "Round-trip latency",
+    unit: "µs",
+    description: "Time to write a single float to the device and read it back, per sample. \
+                  Jitter is p99 minus p50; a fine median with a large jitter means occasional \
+                  stalls, typically the link or GPU waking from a low-power state.",
+};
+
+pub const COMPLETION: Metric = Metric {
+    name: "Flush vs finish",
+    unit: "GB/s",
+    description: "The same upload waited on by flushing the queue and waiting on its event, and \
+                  by finishing the queue. Drivers that sleep in finish or submit flushed work \
+                  lazily make the two differ, and with them any timing built on either.",
+};
+
+pub const RAMP: Metric = Metric {
+    name: "Size ramp",
+    unit: "GB/s",
+    description: "Throughput as the transfer size doubles from 4 KB until a single transfer \
+                  takes the chosen time. Small transfers pay a fixed cost each and are timed \
+                  with a coarse clock, so throughput climbs with size until the link limits it.",
+};
+
+pub const SWEEP: Metric = Metric {
+    name: "Size sweep",
+    unit: "GB/s",
+    description: "The configured run repeated at every power of two from 1 MB to 8 GB, or the \
+                  device's largest allocation. Throughput climbs with size while per-transfer \
+                  overhead dominates and levels off where the link or memory saturates.",
+};
+
+pub const OFFSETS: Metric = Metric {
+    name: "Copy offsets",
+    unit: "GB/s",
+    description: "Throughput of the same transfer at different offsets into the device's largest \
+                  buffer. Where the host reaches device memory through a BAR aperture smaller \
+                  than the buffer, transfers that cross from one window to the next can fall \
+                  well below the rest, which shows as dips at the aperture size's multiples.",
+};
+
+pub const SCATTER: Metric = Metric {
+    name: "Batched small uploads",
+    unit: "GB/s",
+    description: "The transfer split into many small buffers uploaded as one batch, against one \
+                  buffer of the same total size. The extra time of the batch over its transfers \
+                  is the fixed cost each one pays, as when uploading many uniform buffers.",
+};
+
+pub const INFLIGHT: Metric = Metric {
+    name: "In-flight depth",
+    unit: "",
+    description: "How many chunked uploads must be queued at once to keep the link busy. By \
+                  Little's law that is the bandwidth times the latency of one upload, in chunks, \
+                  plus the one being transferred; the measured optimum is the fewest in flight \
+                  that reach 95% of the best throughput.",
+};
+
+pub const CONTENTION: Metric = Metric {
+    name: "Contention curve",
+    unit: "GB/s",
+    description: "H2D and D2H throughput while a kernel runs alongside on the same device, with \
+                  a rising share of its work-items streaming device memory instead of \
+                  computing, against the throughput without it. How fast the curve falls is \
+                  how much a transfer suffers from memory-hungry neighbors on a shared GPU.",
+};
+
+pub const ROUND_TRIP: Metric = Metric {
+    name: "Round trip",
+    unit: "GB/s",
+    description: "The transfer size over the time from queuing an upload to its download landing \
+                  back in host memory, the two queued back to back. This is the bandwidth a \
+                  readback-modify workflow such as a GPU video filter gets, and is compared with \
+                  the two transfers timed one at a time.",
+};
+
+pub const RANDOM_SIZES: Metric = Metric {
+    name: "Random sizes",
+    unit: "GB/s",
+    description: "Throughput of transfers whose size is drawn at random from a range every time, \
+                  evenly across its orders of magnitude, grouped by power-of-two size. Unlike a \
+                  loop of one size, the driver and the link never settle into a pattern, as \
+                  with bursty workloads; sizes are drawn from a fixed seed, so runs compare.",
+};
+
+pub const HARNESS_MEMORY: Metric = Metric {
+    name: "Harness memory",
+    unit: "MiB",
+    description: "gputhroughput's own resident memory and the device memory in use, read every \
+                  30 seconds of a timed or continuous run. Growth of more than 64 MiB from the \
+                  reading 30 seconds in is flagged as a possible leak of the harness, which \
+                  would make a long run's later results suspect; device memory counts every \
+                  process on the device.",
+};
+
+pub const MATRIX: Metric = Metric {
+    name: "Matrix",
+    unit: "GB/s",
+    description: "Aggregate H2D and D2H throughput of every combination of memory kind, \
+                  transfer size and queue count, with the fastest one named.",
+};
+
+pub const PEER: Metric = Metric {
+    name: "Peer copies",
+    unit: "GB/s",
+    description: "Copies directly between two GPUs without staging in host memory.",
+};
+
+pub const STREAMING: Metric = Metric {
+    name: "Streaming from disk",
+    unit: "GB/s",
+    description: "A file read from disk while it is uploaded in chunks, which the slower of \
+                  the disk and the link limits.",
+};
+
+pub const DMA_BUF: Metric = Metric {
+    name: "dma-buf import",
+    unit: "GB/s",
+    description: "Uploads into and device copies within memory imported from a DMA heap, as \
+                  camera and video pipelines share it.",
+};
+
+pub const OVERHEAD: Metric = Metric {
+    name: "Harness overhead",
+    unit: "µs",
+    description: "What enqueueing a transfer and waiting on its completed event cost on the \
+                  device's OpenCL platform, and how finely the clocks tick. Every blocking \
+                  transfer pays these on top of its data, so results from different drivers are \
+                  compared net of them.",
+};
+
+pub const INTEROP: Metric = Metric {
+    name: "GL interop",
+    unit: "GB/s",
+    description: "Transfers into an OpenGL buffer shared with OpenCL, including acquiring and \
+                  releasing it around every transfer.",
+};
+
+/// Every metric, in the order results are shown.
+pub const ALL: &[Metric] = &[
+    H2D,
+    D2H,
+    DURATION,
+    DATA_SIZE,
+    SAMPLES,
+    ROLLING,
+    STATISTICS,
+    COLD_START,
+    DRIVER_PEAK,
+    EFFICIENCY,
+    HOST_CPU,
+    PAGING,
+    ECC,
+    VENDOR_TOOLS,
+    ASYMMETRY,
+    DUTY_CYCLE,
+    VIRTUALIZATION,
+    HYBRID_GRAPHICS,
+    UNIFIED_MEMORY,
+    THEORETICAL,
+    LINK_HEALTH,
+    LINK_SPEED,
+    DEVICES,
+    SESSION,
+    SCALING,
+    MAPPING,
+    PATTERNS,
+    LATENCY,
+    COMPLETION,
+    RAMP,
+    SWEEP,
+    OFFSETS,
+    SCATTER,
+    INFLIGHT,
+    CONTENTION,
+    ROUND_TRIP,
+    RANDOM_SIZES,
+    HARNESS_MEMORY,
+    MATRIX,
+    PEER,
+    STREAMING,
+    DMA_BUF,
+    OVERHEAD,
+    INTEROP,
+];
+
+/// Every metric as "Name (unit): description", e.g. for a report's glossary.
+pub fn glossary() -> Vec<String> {
+    ALL.iter()
+        .map(|metric| {
+            if metric.unit.is_empty() {
+                format!("{}: {}", metric.name, metric.description)
+            } else {
+                format!("{} ({}): {}", metric.name, metric.unit, metric.description)
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,150 @@
+//! Benchmark modes beyond the built-in experiments, e.g. tests of a vendor's OpenCL
+//! extensions. A mode implements `BenchmarkMode` and is either compiled in, registered in
+//! `Registry::new` behind a Cargo feature of its own, or built as a plugin: a dynamic library
+//! in the plugins directory that declares its modes with `declare_plugin!`, loaded by
+//! `Registry::load_plugins`.
+//!
+//! Rust has no stable ABI, so a plugin must be built with the same compiler and the same
+//! version of this crate as the gputhroughput that loads it; the version is checked, the
+//! compiler cannot be.
+
+use crate::error::BenchError;
+use crate::{ MeasureConfig, MyDevice };
+use libloading::Library;
+use std::path::Path;
+use std::sync::Arc;
+
+/// The version of this crate a plugin was built against, which must match the one loading it.
+pub const API_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// What a plugin exports, see `declare_plugin!`.
+#[derive(Clone, Copy)]
+pub struct PluginDeclaration {
+    pub api_version: &'static str,
+    pub register: fn(&mut Registry),
+}
+
+/// A measurement that can be listed and run alongside the built-in ones.
+pub trait BenchmarkMode: Send + Sync {
+    /// Short name, shown on the mode's button and unique among the registered modes.
+    fn name(&self) -> &str;
+
+    /// What the mode measures, shown as its tooltip.
+    fn description(&self) -> &str;
+
+    /// Whether the mode can run on `device`, e.g. whether it has the extension tested.
+    fn supported_by(&self, _device: &MyDevice) -> bool {
+        true
+    }
+
+    /// Measures `device` with the configured transfer size and run length and returns the
+    /// result as lines of text.
+    fn run(&self, config: &MeasureConfig, device: &MyDevice) -> Result<Vec<String>, BenchError>;
+}
+
+/// The outcome of running a mode.
+#[derive(Clone, Debug)]
+pub struct ModeResult {
+    /// `BenchmarkMode::name` of the mode.
+    pub mode: String,
+    pub lines: Vec<String>,
+}
+
+/// The modes available, in the order they were registered.
+#[derive(Clone, Default)]
+pub struct Registry {
+    modes: Vec<Arc<dyn BenchmarkMode>>,
+}
+
+impl Registry {
+    /// The modes compiled in.
+    pub fn new() -> Registry {
+        // Modes compiled in behind features are registered here
+        Registry::default()
+    }
+
+    /// Adds `mode`, replacing an earlier one of the same name.
+    pub fn register(&mut self, mode: Arc<dyn BenchmarkMode>) {
+        self.modes.retain(|earlier| earlier.name() != mode.name());
+        self.modes.push(mode);
+    }
+
+    pub fn modes(&self) -> &[Arc<dyn BenchmarkMode>] {
+        &self.modes
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Arc<dyn BenchmarkMode>> {
+        self.modes.iter().find(|mode| mode.name() == name)
+    }
+
+    /// Registers the modes of every plugin in `dir`, returning why each plugin that could not
+    /// be loaded was skipped. A missing directory simply has no plugins.
+    pub fn load_plugins(&mut self, dir: &Path) -> Vec<String> {
+        let Ok(entries) = std::fs::read_dir(dir) else {
+            return Vec::new();
+        };
+        let mut paths: Vec<_> = entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| {
+                path.extension().is_some_and(|extension| {
+                    extension == std::env::consts::DLL_EXTENSION
+                })
+            })
+            .collect();
+        paths.sort();
+        paths
+            .iter()
+            .filter_map(|path| {
+                self.load_plugin(path).err().map(|e| format!("{}: {}", path.display(), e))
+            })
+            .collect()
+    }
+
+    fn load_plugin(&mut self, path: &Path) -> Result<(), String> {
+        // Loading runs the library's initializers, which is what installing a plugin asks for
+        let library = unsafe { Library::new(path) }.map_err(|e| e.to_string())?;
+        let declaration = unsafe {
+            let symbol = library
+                .get::<*const PluginDeclaration>(b"GPUTHROUGHPUT_PLUGIN\0")
+                .map_err(|_| "not a gputhroughput plugin, see declare_plugin!".to_string())?;
+            **symbol
+        };
+        if declaration.api_version != API_VERSION {
+            return Err(
+                format!(
+                    "built against gputhroughput {}, this is {}",
+                    declaration.api_version,
+                    API_VERSION
+                )
+            );
+        }
+        (declaration.register)(self);
+        // Plugins stay loaded for the life of the process, as their modes may still be running
+        // on a worker thread
+        std::mem::forget(library);
+        Ok(())
+    }
+}
+
+/// Declares the modes of a plugin, given a `fn(&mut Registry)` that registers them:
+///
+/// ```ignore
+/// fn register(registry: &mut gputhroughput::modes::Registry) {
+///     registry.register(std::sync::Arc::new(MyMode));
+/// }
+///
+/// gputhroughput::declare_plugin!(register);
+/// ```
+///
+/// The plugin crate is built as a `cdylib` and copied into the plugins directory.
+#[macro_export]
+macro_rules! declare_plugin {
+    ($register:path) => {
+        #[no_mangle]
+        pub static GPUTHROUGHPUT_PLUGIN: $crate::modes::PluginDeclaration =
+            $crate::modes::PluginDeclaration {
+                api_version: $crate::modes::API_VERSION,
+                register: $register,
+            };
+    };
+}
@@ -0,0 +1,264 @@
+//! Whether it matters which host thread submits a transfer relative to the one that
+//! allocated its host memory. Memory is placed on the NUMA node of the thread that first
+//! touches it, so submitting from a thread on another node moves every byte across the
+//! socket interconnect before it reaches the PCIe link.
+//!
+//! Linux only: the topology comes from `/sys/devices/system/node` and threads are pinned
+//! with `sched_setaffinity`.
+
+use crate::device::default_queue;
+use crate::error::BenchError;
+use crate::registry::DeviceRegistry;
+use crate::telemetry::PciAddress;
+use crate::MeasureConfig;
+use opencl3::context::Context;
+use opencl3::device::Device;
+use opencl3::memory::{ Buffer, CL_MEM_READ_WRITE };
+use opencl3::types::CL_BLOCKING;
+use std::fmt;
+use std::io;
+use std::ptr;
+use std::thread;
+use std::time::{ Duration, Instant };
+
+/// A NUMA node with at least one CPU.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Node {
+    pub id: usize,
+    pub cpus: Vec<usize>,
+}
+
+/// Which thread submits relative to the one that allocated and filled the host memory.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Submitter {
+    SameThread,
+    /// Another thread on the allocating thread's node.
+    SameNode,
+    /// A thread on this other node.
+    OtherNode(usize),
+}
+
+/// A node whose CPU allocates and fills the host memory, and the CPU that submits it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Pairing {
+    pub allocated_on: usize,
+    pub submitter: Submitter,
+    allocating_cpu: usize,
+    submitting_cpu: usize,
+}
+
+/// Throughput of one allocating node and submitter pairing.
+#[derive(Clone, Copy, Debug)]
+pub struct MappingSample {
+    pub allocated_on: usize,
+    pub submitter: Submitter,
+    /// Throughput in GB/s.
+    pub h2d: f64,
+    pub d2h: f64,
+}
+
+/// Every pairing measured by `measure_mapping`.
+#[derive(Clone, Debug)]
+pub struct MappingResult {
+    /// The node the device is attached to, if the platform reports it.
+    pub device_node: Option<usize>,
+    pub samples: Vec<MappingSample>,
+}
+
+impl fmt::Display for Submitter {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Submitter::SameThread => write!(f, "the same thread"),
+            Submitter::SameNode => write!(f, "another thread on that node"),
+            Submitter::OtherNode(node) => write!(f, "a thread on node {}", node),
+        }
+    }
+}
+
+impl MappingResult {
+    /// One human-readable line per pairing.
+    pub fn summary(&self) -> Vec<String> {
+        let mut lines: Vec<String> = self.samples
+            .iter()
+            .map(|sample| {
+                format!(
+                    "Allocated on node {}, submitted from {}: {:.2} GB/s H2D, {:.2} GB/s D2H",
+                    sample.allocated_on,
+                    sample.submitter,
+                    sample.h2d,
+                    sample.d2h
+                )
+            })
+            .collect();
+        if let Some(node) = self.device_node {
+            lines.push(format!("The device is attached to node {}", node));
+        }
+        lines
+    }
+}
+
+/// The NUMA nodes that have CPUs, in id order; empty where the topology cannot be read.
+pub fn nodes() -> Vec<Node> {
+    let Ok(entries) = std::fs::read_dir("/sys/devices/system/node") else {
+        return Vec::new();
+    };
+    let mut nodes: Vec<Node> = entries
+        .filter_map(|entry| {
+            let entry = entry.ok()?;
+            let id = entry.file_name().to_str()?.strip_prefix("node")?.parse().ok()?;
+            let cpus = std::fs::read_to_string(entry.path().join("cpulist")).ok()?;
+            let cpus = parse_cpu_list(&cpus)?;
+            (!cpus.is_empty()).then_some(Node { id, cpus })
+        })
+        .collect();
+    nodes.sort_by_key(|node| node.id);
+    nodes
+}
+
+/// The node a PCI device is attached to, `None` where the firmware does not say.
+pub fn device_node(address: PciAddress) -> Option<usize> {
+    let path = format!("/sys/bus/pci/devices/{}/numa_node", address);
+    // -1 when the device has no affinity
+    std::fs::read_to_string(path).ok()?.trim().parse().ok()
+}
+
+/// What `measure_mapping` pairs up on `nodes`: memory allocated on each node, submitted from
+/// the allocating thread, another thread on the same node and a thread on every other node.
+pub fn pairings(nodes: &[Node]) -> Vec<Pairing> {
+    let mut pairings = Vec::new();
+    for allocator in nodes {
+        let pairing = |submitter, submitting_cpu| Pairing {
+            allocated_on: allocator.id,
+            submitter,
+            allocating_cpu: allocator.cpus[0],
+            submitting_cpu,
+        };
+        pairings.push(pairing(Submitter::SameThread, allocator.cpus[0]));
+        if let Some(&cpu) = allocator.cpus.get(1) {
+            pairings.push(pairing(Submitter::SameNode, cpu));
+        }
+        pairings.extend(
+            nodes
+                .iter()
+                .filter(|node| node.id != allocator.id)
+                .map(|node| pairing(Submitter::OtherNode(node.id), node.cpus[0]))
+        );
+    }
+    pairings
+}
+
+/// Parses a kernel CPU list such as `0-7,16-23`.
+fn parse_cpu_list(list: &str) -> Option<Vec<usize>> {
+    let mut cpus = Vec::new();
+    for range in list.trim().split(',').filter(|range| !range.is_empty()) {
+        match range.split_once('-') {
+            Some((first, last)) => cpus.extend(first.parse::<usize>().ok()?..=last.parse().ok()?),
+            None => cpus.push(range.parse().ok()?),
+        }
+    }
+    Some(cpus)
+}
+
+/// Measures transfers of host memory allocated on each node in turn, for every pairing in
+/// `pairings`.
+pub fn measure_mapping(
+    config: &MeasureConfig,
+    device: &Device
+) -> Result<MappingResult, BenchError> {
+    let nodes = nodes();
+    if nodes.is_empty() {
+        return Err(
+            BenchError::Unsupported("the NUMA topology is only available on Linux".into())
+        );
+    }
+    let context = &DeviceRegistry::global().context(device)?;
+    let iterations = config.length.fixed_iterations();
+
+    let mut samples = Vec::new();
+    for pairing in pairings(&nodes) {
+        let (h2d, d2h) = thread::scope(|scope| {
+            let allocate = || -> Result<Vec<f32>, BenchError> {
+                pin_to(pairing.allocating_cpu)?;
+                // Written rather than zeroed, so every page is touched on this node
+                Ok(vec![1.0f32; config.data_size])
+            };
+            if pairing.submitter == Submitter::SameThread {
+                return scope
+                    .spawn(move || transfer(context, &mut allocate()?, iterations))
+                    .join()
+                    .expect("mapping thread panicked");
+            }
+            let mut host = scope.spawn(allocate).join().expect("mapping thread panicked")?;
+            scope
+                .spawn(move || {
+                    pin_to(pairing.submitting_cpu)?;
+                    transfer(context, &mut host, iterations)
+                })
+                .join()
+                .expect("mapping thread panicked")
+        })?;
+        samples.push(MappingSample {
+            allocated_on: pairing.allocated_on,
+            submitter: pairing.submitter,
+            h2d,
+            d2h,
+        });
+    }
+
+    Ok(MappingResult {
+        device_node: PciAddress::of(device).and_then(device_node),
+        samples,
+    })
+}
+
+/// H2D and D2H throughput in GB/s of `iterations` blocking transfers of `host`, on a queue
+/// created by the calling thread.
+fn transfer(
+    context: &Context,
+    host: &mut [f32],
+    iterations: usize
+) -> Result<(f64, f64), BenchError> {
+    let queue = default_queue(context, 0)?;
+    let mut buffer = unsafe {
+        Buffer::<f32>::create(context, CL_MEM_READ_WRITE, host.len(), ptr::null_mut())?
+    };
+    let mut h2d = Duration::ZERO;
+    let mut d2h = Duration::ZERO;
+    for _ in 0..iterations {
+        let start = Instant::now();
+        unsafe {
+            queue.enqueue_write_buffer(&mut buffer, CL_BLOCKING, 0, host, &[])?;
+        }
+        h2d += start.elapsed();
+        let start = Instant::now();
+        unsafe {
+            queue.enqueue_read_buffer(&buffer, CL_BLOCKING, 0, host, &[])?;
+        }
+        d2h += start.elapsed();
+    }
+    let bytes = (std::mem::size_of_val(host) as f64) * (iterations as f64);
+    Ok((bytes / h2d.as_secs_f64() / 1e9, bytes / d2h.as_secs_f64() / 1e9))
+}
+
+/// Restricts the calling thread to `cpu`.
+#[cfg(target_os = "linux")]
+fn pin_to(cpu: usize) -> Result<(), BenchError> {
+    let status = unsafe {
+        let mut set: libc::cpu_set_t = std::mem::zeroed();
+        libc::CPU_SET(cpu, &mut set);
+        libc::sched_setaffinity(0, std::mem::size_of::<libc::cpu_set_t>(), &set)
+    };
+    if status != 0 {
+        return Err(
+            BenchError::Unsupported(
+                format!("cannot pin a thread to CPU {}: {}", cpu, io::Error::last_os_error())
+            )
+        );
+    }
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn pin_to(_: usize) -> Result<(), BenchError> {
+    Err(BenchError::Io(io::ErrorKind::Unsupported.into()))
+}
@@ -0,0 +1,159 @@
+//! Just enough of NVML to read link counters, loaded at runtime so that the tool still
+//! starts on machines without an NVIDIA driver.
+
+use crate::telemetry::PciAddress;
+use libloading::{ Library, Symbol };
+use std::ffi::CString;
+use std::os::raw::{ c_char, c_int, c_uint, c_void };
+use std::sync::OnceLock;
+
+const NVML_SUCCESS: c_int = 0;
+const NVML_PCIE_UTIL_TX_BYTES: c_int = 0;
+const NVML_PCIE_UTIL_RX_BYTES: c_int = 1;
+const NVML_TEMPERATURE_GPU: c_int = 0;
+const NVML_CLOCK_GRAPHICS: c_int = 0;
+const NVML_CLOCK_MEM: c_int = 2;
+const NVML_FEATURE_ENABLED: c_int = 1;
+
+/// `nvmlUtilization_t`, percentages over the driver's last sample period.
+#[repr(C)]
+#[derive(Default)]
+struct Utilization {
+    gpu: c_uint,
+    _memory: c_uint,
+}
+
+/// `nvmlMemory_t`, in bytes.
+#[repr(C)]
+#[derive(Default)]
+struct MemoryInfo {
+    _total: u64,
+    _free: u64,
+    used: u64,
+}
+
+/// An `nvmlDevice_t`. NVML handles stay valid for the life of the library and are thread-safe.
+#[derive(Clone, Copy)]
+pub struct NvmlDevice(*mut c_void);
+
+unsafe impl Send for NvmlDevice {}
+
+pub struct Nvml {
+    lib: Library,
+}
+
+impl Nvml {
+    /// The process-wide NVML instance, or `None` if it is missing or fails to initialise.
+    pub fn get() -> Option<&'static Nvml> {
+        static NVML: OnceLock<Option<Nvml>> = OnceLock::new();
+        NVML.get_or_init(Nvml::load).as_ref()
+    }
+
+    fn load() -> Option<Nvml> {
+        let name = if cfg!(windows) { "nvml.dll" } else { "libnvidia-ml.so.1" };
+        let nvml = Nvml { lib: unsafe { Library::new(name) }.ok()? };
+        let init: Symbol<unsafe extern "C" fn() -> c_int> = nvml.symbol(b"nvmlInit_v2\0")?;
+        (unsafe { init() } == NVML_SUCCESS).then_some(nvml)
+    }
+
+    fn symbol<T>(&self, name: &[u8]) -> Option<Symbol<'_, T>> {
+        unsafe { self.lib.get(name).ok() }
+    }
+
+    pub fn device_by_pci(&self, address: PciAddress) -> Option<NvmlDevice> {
+        let get: Symbol<unsafe extern "C" fn(*const c_char, *mut *mut c_void) -> c_int> =
+            self.symbol(b"nvmlDeviceGetHandleByPciBusId_v2\0")?;
+        let bus_id = CString::new(address.to_string()).ok()?;
+        let mut handle = std::ptr::null_mut();
+        (unsafe { get(bus_id.as_ptr(), &mut handle) } == NVML_SUCCESS).then_some(NvmlDevice(handle))
+    }
+
+    /// Bytes per second received and transmitted by the GPU over the last ~20 ms.
+    pub fn pcie_throughput(&self, device: NvmlDevice) -> Option<(f64, f64)> {
+        let get: Symbol<unsafe extern "C" fn(*mut c_void, c_int, *mut c_uint) -> c_int> =
+            self.symbol(b"nvmlDeviceGetPcieThroughput\0")?;
+        let counter = |which| {
+            let mut kilobytes: c_uint = 0;
+            (unsafe { get(device.0, which, &mut kilobytes) } == NVML_SUCCESS).then_some(
+                (kilobytes as f64) * 1024.0
+            )
+        };
+        Some((counter(NVML_PCIE_UTIL_RX_BYTES)?, counter(NVML_PCIE_UTIL_TX_BYTES)?))
+    }
+
+    /// Board power draw in watts.
+    pub fn power_usage(&self, device: NvmlDevice) -> Option<f64> {
+        let get: Symbol<unsafe extern "C" fn(*mut c_void, *mut c_uint) -> c_int> =
+            self.symbol(b"nvmlDeviceGetPowerUsage\0")?;
+        let mut milliwatts: c_uint = 0;
+        (unsafe { get(device.0, &mut milliwatts) } == NVML_SUCCESS).then_some(
+            (milliwatts as f64) / 1000.0
+        )
+    }
+
+    /// GPU core temperature in degrees Celsius.
+    pub fn temperature(&self, device: NvmlDevice) -> Option<f64> {
+        let get: Symbol<unsafe extern "C" fn(*mut c_void, c_int, *mut c_uint) -> c_int> =
+            self.symbol(b"nvmlDeviceGetTemperature\0")?;
+        let mut celsius: c_uint = 0;
+        (unsafe { get(device.0, NVML_TEMPERATURE_GPU, &mut celsius) } == NVML_SUCCESS).then_some(
+            celsius as f64
+        )
+    }
+
+    /// Current and maximum graphics clock in MHz.
+    pub fn graphics_clock(&self, device: NvmlDevice) -> Option<(u32, u32)> {
+        let clock = |name: &[u8]| {
+            let get: Symbol<unsafe extern "C" fn(*mut c_void, c_int, *mut c_uint) -> c_int> =
+                self.symbol(name)?;
+            let mut mhz: c_uint = 0;
+            (unsafe { get(device.0, NVML_CLOCK_GRAPHICS, &mut mhz) } == NVML_SUCCESS).then_some(mhz)
+        };
+        Some((clock(b"nvmlDeviceGetClockInfo\0")?, clock(b"nvmlDeviceGetMaxClockInfo\0")?))
+    }
+
+    /// Maximum memory clock in MHz. Memory transfers on both clock edges, so pins carry
+    /// twice this many Mbit/s.
+    pub fn max_memory_clock(&self, device: NvmlDevice) -> Option<u32> {
+        let get: Symbol<unsafe extern "C" fn(*mut c_void, c_int, *mut c_uint) -> c_int> =
+            self.symbol(b"nvmlDeviceGetMaxClockInfo\0")?;
+        let mut mhz: c_uint = 0;
+        (unsafe { get(device.0, NVML_CLOCK_MEM, &mut mhz) } == NVML_SUCCESS).then_some(mhz)
+    }
+
+    /// Width of the memory bus in bits.
+    pub fn memory_bus_width(&self, device: NvmlDevice) -> Option<u32> {
+        let get: Symbol<unsafe extern "C" fn(*mut c_void, *mut c_uint) -> c_int> =
+            self.symbol(b"nvmlDeviceGetMemoryBusWidth\0")?;
+        let mut bits: c_uint = 0;
+        (unsafe { get(device.0, &mut bits) } == NVML_SUCCESS).then_some(bits)
+    }
+
+    /// Whether ECC is on for the device's memory now, rather than after the next reboot.
+    pub fn ecc_enabled(&self, device: NvmlDevice) -> Option<bool> {
+        let get: Symbol<unsafe extern "C" fn(*mut c_void, *mut c_int, *mut c_int) -> c_int> =
+            self.symbol(b"nvmlDeviceGetEccMode\0")?;
+        let (mut current, mut pending): (c_int, c_int) = (0, 0);
+        (unsafe { get(device.0, &mut current, &mut pending) } == NVML_SUCCESS).then_some(
+            current == NVML_FEATURE_ENABLED
+        )
+    }
+
+    /// Bytes of device memory allocated, by every process on the device.
+    pub fn memory_used(&self, device: NvmlDevice) -> Option<u64> {
+        let get: Symbol<unsafe extern "C" fn(*mut c_void, *mut MemoryInfo) -> c_int> =
+            self.symbol(b"nvmlDeviceGetMemoryInfo\0")?;
+        let mut memory = MemoryInfo::default();
+        (unsafe { get(device.0, &mut memory) } == NVML_SUCCESS).then_some(memory.used)
+    }
+
+    /// Share of the last sample period the GPU was busy, from 0 to 1.
+    pub fn utilization(&self, device: NvmlDevice) -> Option<f64> {
+        let get: Symbol<unsafe extern "C" fn(*mut c_void, *mut Utilization) -> c_int> =
+            self.symbol(b"nvmlDeviceGetUtilizationRates\0")?;
+        let mut utilization = Utilization::default();
+        (unsafe { get(device.0, &mut utilization) } == NVML_SUCCESS).then_some(
+            (utilization.gpu as f64) / 100.0
+        )
+    }
+}
@@ -0,0 +1,232 @@
+//! Throughput against where in a large device buffer a transfer lands. Where the host reaches
+//! device memory through a BAR aperture smaller than the buffer, the driver moves the window
+//! as transfers go, and some platforms fall off a cliff for transfers that cross from one
+//! window to the next. Transfers of `CHUNK_BYTES` at a range of destination offsets show
+//! where: a sweep steps by `SWEEP_STEP`, which no power of two divides, so that some transfer
+//! straddles every aperture boundary, or specific offsets are measured to look closer.
+
+use crate::device::default_queue;
+use crate::error::BenchError;
+use crate::ramp;
+use crate::registry::DeviceRegistry;
+use opencl3::device::Device;
+use opencl3::memory::{ Buffer, CL_MEM_READ_WRITE };
+use opencl3::types::CL_BLOCKING;
+use std::fmt;
+use std::ptr;
+use std::str::FromStr;
+use std::time::{ Duration, Instant };
+
+/// Bytes of every transfer.
+pub const CHUNK_BYTES: u64 = 4 * 1024 * 1024;
+/// Distance between the offsets of a sweep.
+pub const SWEEP_STEP: u64 = 3 * 1024 * 1024;
+/// Transfers each way per offset, of which the median is reported.
+pub const SAMPLES_PER_OFFSET: usize = 3;
+/// Share of the median throughput below which an offset counts as a cliff.
+const CLIFF: f64 = 0.8;
+/// Offsets up to which every one is listed in the summary, rather than only the cliffs.
+const LISTED: usize = 16;
+
+/// Which destination offsets to transfer to.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Offsets {
+    /// Every `SWEEP_STEP` through the whole buffer.
+    Sweep,
+    /// These offsets in bytes.
+    At(Vec<u64>),
+}
+
+impl FromStr for Offsets {
+    type Err = String;
+
+    /// Parses `sweep` or a comma-separated list of offsets in KB, e.g. `261120,262144`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s == "sweep" {
+            return Ok(Offsets::Sweep);
+        }
+        s.split(',')
+            .map(|item| {
+                item.trim()
+                    .parse::<u64>()
+                    .ok()
+                    .and_then(|kb| kb.checked_mul(1024))
+                    .ok_or_else(|| format!("invalid offset '{}', expected KB or sweep", item))
+            })
+            .collect::<Result<Vec<u64>, String>>()
+            .map(Offsets::At)
+    }
+}
+
+impl fmt::Display for Offsets {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Offsets::Sweep => write!(f, "sweep"),
+            Offsets::At(offsets) => {
+                let kb: Vec<String> = offsets
+                    .iter()
+                    .map(|offset| (offset / 1024).to_string())
+                    .collect();
+                write!(f, "{}", kb.join(","))
+            }
+        }
+    }
+}
+
+/// One offset of the sweep.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct OffsetPoint {
+    /// Bytes from the start of the buffer.
+    pub offset: u64,
+    /// Median time of a single transfer in each direction.
+    pub h2d: Duration,
+    pub d2h: Duration,
+}
+
+impl OffsetPoint {
+    /// GB/s host to device.
+    pub fn h2d_throughput(&self) -> f64 {
+        (CHUNK_BYTES as f64) / self.h2d.as_secs_f64() / 1e9
+    }
+
+    /// GB/s device to host.
+    pub fn d2h_throughput(&self) -> f64 {
+        (CHUNK_BYTES as f64) / self.d2h.as_secs_f64() / 1e9
+    }
+}
+
+/// Throughput at every offset, see `measure_offsets`.
+#[derive(Clone, Debug)]
+pub struct OffsetResult {
+    /// Bytes of the buffer transferred into.
+    pub buffer_bytes: u64,
+    /// In order of offset.
+    pub points: Vec<OffsetPoint>,
+}
+
+impl OffsetResult {
+    /// Every offset when there are few, otherwise the median and the offsets well below it.
+    pub fn summary(&self) -> Vec<String> {
+        let line = |point: &OffsetPoint| {
+            format!(
+                "{}: {:.2} GB/s H2D, {:.2} GB/s D2H",
+                offset_label(point.offset),
+                point.h2d_throughput(),
+                point.d2h_throughput()
+            )
+        };
+        if self.points.len() <= LISTED {
+            return self.points.iter().map(line).collect();
+        }
+        let h2d = median(self.points.iter().map(OffsetPoint::h2d_throughput));
+        let d2h = median(self.points.iter().map(OffsetPoint::d2h_throughput));
+        let mut lines = vec![
+            format!(
+                "{} offsets through {}: median {:.2} GB/s H2D, {:.2} GB/s D2H",
+                self.points.len(),
+                ramp::size_label(self.buffer_bytes),
+                h2d,
+                d2h
+            )
+        ];
+        let cliffs: Vec<&OffsetPoint> = self.points
+            .iter()
+            .filter(|point| {
+                point.h2d_throughput() < h2d * CLIFF || point.d2h_throughput() < d2h * CLIFF
+            })
+            .collect();
+        if cliffs.is_empty() {
+            lines.push(format!("No offset below {:.0}% of the median", CLIFF * 100.0));
+        } else {
+            lines.push(format!("Below {:.0}% of the median at:", CLIFF * 100.0));
+            lines.extend(cliffs.into_iter().map(line));
+        }
+        lines
+    }
+}
+
+/// `offset` in MB with up to three decimals, as offsets near a boundary are rarely whole MB.
+pub fn offset_label(offset: u64) -> String {
+    let mb = (offset as f64) / ((1024 * 1024) as f64);
+    let label = format!("{:.3}", mb);
+    format!("{} MB", label.trim_end_matches('0').trim_end_matches('.'))
+}
+
+fn median(values: impl Iterator<Item = f64>) -> f64 {
+    let mut values: Vec<f64> = values.collect();
+    values.sort_by(f64::total_cmp);
+    values[values.len() / 2]
+}
+
+/// Allocates the device's largest buffer and times uploads and downloads of `CHUNK_BYTES` at
+/// each of `offsets` within it.
+pub fn measure_offsets(device: &Device, offsets: &Offsets) -> Result<OffsetResult, BenchError> {
+    let buffer_bytes = device.max_mem_alloc_size()?;
+    if buffer_bytes < CHUNK_BYTES {
+        return Err(
+            BenchError::Unsupported(
+                format!("the largest allocation, {} bytes, is below one transfer", buffer_bytes)
+            )
+        );
+    }
+    let offsets = match offsets {
+        Offsets::Sweep => (0..=(buffer_bytes - CHUNK_BYTES) / SWEEP_STEP)
+            .map(|step| step * SWEEP_STEP)
+            .collect(),
+        Offsets::At(offsets) => {
+            let last = buffer_bytes - CHUNK_BYTES;
+            if let Some(&offset) = offsets.iter().find(|&&offset| offset > last) {
+                return Err(
+                    BenchError::Unsupported(
+                        format!(
+                            "offset {} leaves less than a transfer of the {} buffer",
+                            offset_label(offset),
+                            ramp::size_label(buffer_bytes)
+                        )
+                    )
+                );
+            }
+            let mut offsets = offsets.clone();
+            offsets.sort_unstable();
+            offsets.dedup();
+            offsets
+        }
+    };
+
+    let context = DeviceRegistry::global().context(device)?;
+    let queue = default_queue(&context, 0)?;
+    let mut buffer = unsafe {
+        Buffer::<u8>::create(&context, CL_MEM_READ_WRITE, buffer_bytes as usize, ptr::null_mut())?
+    };
+    let mut host: Vec<u8> = (0..CHUNK_BYTES).map(|index| index as u8).collect();
+
+    let median_time = |mut times: Vec<Duration>| {
+        times.sort();
+        times[times.len() / 2]
+    };
+    let mut points = Vec::with_capacity(offsets.len());
+    for offset in offsets {
+        let mut h2d = Vec::with_capacity(SAMPLES_PER_OFFSET);
+        let mut d2h = Vec::with_capacity(SAMPLES_PER_OFFSET);
+        for _ in 0..SAMPLES_PER_OFFSET {
+            let start = Instant::now();
+            unsafe {
+                queue.enqueue_write_buffer(
+                    &mut buffer,
+                    CL_BLOCKING,
+                    offset as usize,
+                    &host,
+                    &[]
+                )?;
+            }
+            h2d.push(start.elapsed());
+            let start = Instant::now();
+            unsafe {
+                queue.enqueue_read_buffer(&buffer, CL_BLOCKING, offset as usize, &mut host, &[])?;
+            }
+            d2h.push(start.elapsed());
+        }
+        points.push(OffsetPoint { offset, h2d: median_time(h2d), d2h: median_time(d2h) });
+    }
+    Ok(OffsetResult { buffer_bytes, points })
+}
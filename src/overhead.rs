@@ -0,0 +1,133 @@
+//! The harness's own fixed costs on one OpenCL platform, i.e. the driver the device is used
+//! through: how long enqueueing a transfer takes to return, how long waiting on a transfer
+//! that is already complete takes, and how finely the clocks the harness times with tick.
+//! Every blocking transfer pays the first two on top of moving its data, and they differ
+//! between drivers far more than the links do, so results from different platforms are only
+//! comparable once those costs are taken out, see `Overhead::normalize`.
+
+use crate::device::default_queue;
+use crate::error::BenchError;
+use crate::registry::DeviceRegistry;
+use opencl3::device::Device;
+use opencl3::memory::{ Buffer, CL_MEM_READ_WRITE };
+use opencl3::platform::Platform;
+use opencl3::types::CL_NON_BLOCKING;
+use serde::{ Deserialize, Serialize };
+use std::ptr;
+use std::time::{ Duration, Instant };
+
+/// Transfers enqueued and waited on by `measure_overhead`, and clock readings taken.
+pub const PROBES: usize = 256;
+
+/// Fixed costs of one platform, see the module documentation.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Overhead {
+    /// Name of the OpenCL platform measured through.
+    pub platform: String,
+    /// Its version string, which names the driver release on most platforms.
+    pub platform_version: String,
+    /// Median time a non-blocking single-float write takes to return, in nanoseconds.
+    pub enqueue_ns: u64,
+    /// Median time waiting on the event of a write that has already completed.
+    pub event_wait_ns: u64,
+    /// Smallest step of the host clock every transfer is timed with.
+    pub host_timer_ns: u64,
+    /// Resolution the device reports for its profiling timer, which traces are taken with.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub device_timer_ns: Option<u64>,
+}
+
+impl Overhead {
+    /// What every blocking transfer pays besides moving its data: an enqueue and a wait.
+    pub fn per_transfer(&self) -> Duration {
+        Duration::from_nanos(self.enqueue_ns + self.event_wait_ns)
+    }
+
+    /// `gbps` measured with transfers of `transfer_bytes`, less `per_transfer` of each
+    /// transfer's time. Left as is where the overhead would take up the whole transfer.
+    pub fn normalize(&self, gbps: f64, transfer_bytes: u64) -> f64 {
+        if gbps <= 0.0 {
+            return gbps;
+        }
+        let seconds = (transfer_bytes as f64) / (gbps * 1e9);
+        let remaining = seconds - self.per_transfer().as_secs_f64();
+        if remaining > 0.0 { (transfer_bytes as f64) / remaining / 1e9 } else { gbps }
+    }
+
+    pub fn summary(&self) -> Vec<String> {
+        let micros = |nanos: u64| (nanos as f64) / 1e3;
+        let mut lines = vec![
+            format!("Platform: {} ({})", self.platform, self.platform_version),
+            format!(
+                "Enqueue: {:.2} µs, event wait: {:.2} µs, {:.2} µs per transfer",
+                micros(self.enqueue_ns),
+                micros(self.event_wait_ns),
+                self.per_transfer().as_secs_f64() * 1e6
+            ),
+            format!("Host timer resolution: {} ns", self.host_timer_ns),
+        ];
+        if let Some(nanos) = self.device_timer_ns {
+            lines.push(format!("Device timer resolution: {} ns", nanos));
+        }
+        lines
+    }
+}
+
+/// Times `PROBES` enqueues of a single-float write and waits on their completed events, and
+/// finds the step of the host clock.
+pub fn measure_overhead(device: &Device) -> Result<Overhead, BenchError> {
+    let platform = Platform::new(device.platform()?);
+    let context = DeviceRegistry::global().context(device)?;
+    let queue = default_queue(&context, 0)?;
+    let mut buffer = unsafe {
+        Buffer::<f32>::create(&context, CL_MEM_READ_WRITE, 1, ptr::null_mut())?
+    };
+    let value = [0.0f32];
+
+    let mut enqueue = Vec::with_capacity(PROBES);
+    let mut wait = Vec::with_capacity(PROBES);
+    for _ in 0..PROBES {
+        let start = Instant::now();
+        let event = unsafe {
+            queue.enqueue_write_buffer(&mut buffer, CL_NON_BLOCKING, 0, &value, &[])?
+        };
+        enqueue.push(start.elapsed());
+        queue.finish()?;
+        let start = Instant::now();
+        event.wait()?;
+        wait.push(start.elapsed());
+    }
+
+    Ok(Overhead {
+        platform: platform.name().unwrap_or_default(),
+        platform_version: platform.version().unwrap_or_default(),
+        enqueue_ns: median(enqueue).as_nanos() as u64,
+        event_wait_ns: median(wait).as_nanos() as u64,
+        host_timer_ns: host_timer_step().as_nanos() as u64,
+        device_timer_ns: device
+            .profiling_timer_resolution()
+            .ok()
+            .map(|nanos| nanos as u64),
+    })
+}
+
+fn median(mut times: Vec<Duration>) -> Duration {
+    times.sort();
+    times[times.len() / 2]
+}
+
+/// The smallest non-zero difference between two readings of the host clock.
+fn host_timer_step() -> Duration {
+    (0..PROBES)
+        .map(|_| {
+            let start = Instant::now();
+            loop {
+                let step = start.elapsed();
+                if !step.is_zero() {
+                    return step;
+                }
+            }
+        })
+        .min()
+        .unwrap_or_default()
+}
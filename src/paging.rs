@@ -0,0 +1,281 @@
+//! Paging of GPU memory on Windows, where WDDM virtualizes VRAM: once applications ask for
+//! more than the GPU has, the video memory manager evicts allocations to system memory and
+//! pages them back in as they are used, and a transfer that waits on that runs far below the
+//! link. The adapter's memory usage counters are read through PDH, loaded at runtime like
+//! NVML, and lined up with the iterations to tell whether throughput dips coincide with
+//! paging.
+
+use crate::Throughput;
+use libloading::{ Library, Symbol };
+use opencl3::device::Device;
+use std::os::raw::c_void;
+use std::ptr;
+use std::time::Instant;
+
+const ERROR_SUCCESS: u32 = 0;
+const PDH_MORE_DATA: u32 = 0x8000_07d2;
+const PDH_FMT_LARGE: u32 = 0x0000_0400;
+
+/// Change in shared usage between two readings from which the driver is taken to have paged,
+/// well above what its own bookkeeping allocates.
+pub const PAGING_THRESHOLD: u64 = 16 * 1024 * 1024;
+/// Share of the median throughput below which an iteration counts as a dip.
+pub const DIP_SHARE: f64 = 0.8;
+
+/// The adapter's memory usage at one point in time.
+#[derive(Clone, Copy, Debug)]
+pub struct Residency {
+    pub at: Instant,
+    /// Bytes of allocations resident in VRAM.
+    pub dedicated: u64,
+    /// Bytes of allocations in system memory, those evicted from VRAM included.
+    pub shared: u64,
+}
+
+/// `PDH_FMT_COUNTERVALUE`, read through the `largeValue` member of its union.
+#[repr(C)]
+struct CounterValue {
+    status: u32,
+    large: i64,
+}
+
+/// `PDH_FMT_COUNTERVALUE_ITEM_W`.
+#[repr(C)]
+struct CounterItem {
+    name: *const u16,
+    value: CounterValue,
+}
+
+type OpenQuery = unsafe extern "system" fn(*const u16, usize, *mut *mut c_void) -> u32;
+type AddCounter = unsafe extern "system" fn(
+    *mut c_void,
+    *const u16,
+    usize,
+    *mut *mut c_void
+) -> u32;
+type CollectData = unsafe extern "system" fn(*mut c_void) -> u32;
+type CounterArray = unsafe extern "system" fn(
+    *mut c_void,
+    u32,
+    *mut u32,
+    *mut u32,
+    *mut CounterItem
+) -> u32;
+
+/// A PDH query on the "GPU Adapter Memory" counters, read for one adapter.
+pub struct ResidencyCounter {
+    lib: Library,
+    query: *mut c_void,
+    dedicated: *mut c_void,
+    shared: *mut c_void,
+    /// The adapter's counter instance, e.g. "luid_0x00000000_0x0000d1f6_phys_0".
+    instance: String,
+}
+
+// A PDH query may be used from any thread, and the poller only uses it from one
+unsafe impl Send for ResidencyCounter {}
+
+impl ResidencyCounter {
+    /// Opens the counters of `device`'s adapter, found by the LUID the driver reports for it
+    /// through `cl_khr_device_uuid`. `None` off Windows and where the LUID is not reported.
+    pub fn open(device: &Device) -> Option<ResidencyCounter> {
+        if !cfg!(windows) || !device.luid_valid_khr().unwrap_or(false) {
+            return None;
+        }
+        // A LUID is a little-endian low part followed by the high part
+        let luid = device.luid_khr().ok()?;
+        let low = u32::from_le_bytes([luid[0], luid[1], luid[2], luid[3]]);
+        let high = u32::from_le_bytes([luid[4], luid[5], luid[6], luid[7]]);
+        let instance = format!("luid_0x{:08x}_0x{:08x}_phys_0", high, low);
+
+        let lib = unsafe { Library::new("pdh.dll") }.ok()?;
+        let (query, dedicated, shared) = {
+            let open: Symbol<OpenQuery> = unsafe { lib.get(b"PdhOpenQueryW\0").ok()? };
+            let add: Symbol<AddCounter> = unsafe { lib.get(b"PdhAddEnglishCounterW\0").ok()? };
+            let mut query = ptr::null_mut();
+            if unsafe { open(ptr::null(), 0, &mut query) } != ERROR_SUCCESS {
+                return None;
+            }
+            let counter = |path: &str| {
+                let path = wide(path);
+                let mut counter = ptr::null_mut();
+                (unsafe { add(query, path.as_ptr(), 0, &mut counter) } == ERROR_SUCCESS).then_some(
+                    counter
+                )
+            };
+            (
+                query,
+                counter("\\GPU Adapter Memory(*)\\Dedicated Usage"),
+                counter("\\GPU Adapter Memory(*)\\Shared Usage"),
+            )
+        };
+        // Closes the query on drop, should either counter be missing
+        let mut counter = ResidencyCounter {
+            lib,
+            query,
+            dedicated: ptr::null_mut(),
+            shared: ptr::null_mut(),
+            instance,
+        };
+        counter.dedicated = dedicated?;
+        counter.shared = shared?;
+        // The first reading tells whether the adapter has counters at all
+        counter.sample().map(|_| counter)
+    }
+
+    pub fn sample(&mut self) -> Option<Residency> {
+        let collect: Symbol<CollectData> = unsafe {
+            self.lib.get(b"PdhCollectQueryData\0").ok()?
+        };
+        if unsafe { collect(self.query) } != ERROR_SUCCESS {
+            return None;
+        }
+        Some(Residency {
+            at: Instant::now(),
+            dedicated: self.value(self.dedicated)?,
+            shared: self.value(self.shared)?,
+        })
+    }
+
+    /// The value of `counter` for this adapter's instance, from the last collected data.
+    fn value(&self, counter: *mut c_void) -> Option<u64> {
+        let get: Symbol<CounterArray> = unsafe {
+            self.lib.get(b"PdhGetFormattedCounterArrayW\0").ok()?
+        };
+        let mut size = 0;
+        let mut count = 0;
+        let status = unsafe { get(counter, PDH_FMT_LARGE, &mut size, &mut count, ptr::null_mut()) };
+        if status != PDH_MORE_DATA {
+            return None;
+        }
+        // The items are followed by their names in the same buffer; u64s keep it aligned
+        let mut buffer = vec![0u64; (size as usize).div_ceil(8)];
+        let items = buffer.as_mut_ptr() as *mut CounterItem;
+        if unsafe { get(counter, PDH_FMT_LARGE, &mut size, &mut count, items) } != ERROR_SUCCESS {
+            return None;
+        }
+        let items = unsafe { std::slice::from_raw_parts(items, count as usize) };
+        items
+            .iter()
+            .find(|item| unsafe { narrow(item.name) }.eq_ignore_ascii_case(&self.instance))
+            .filter(|item| item.value.status == ERROR_SUCCESS)
+            .map(|item| item.value.large.max(0) as u64)
+    }
+}
+
+impl Drop for ResidencyCounter {
+    fn drop(&mut self) {
+        let close: Option<Symbol<CollectData>> = unsafe { self.lib.get(b"PdhCloseQuery\0").ok() };
+        if let Some(close) = close {
+            unsafe {
+                close(self.query);
+            }
+        }
+    }
+}
+
+/// `text` as a nul-terminated UTF-16 string.
+fn wide(text: &str) -> Vec<u16> {
+    text.encode_utf16().chain(Some(0)).collect()
+}
+
+/// The nul-terminated UTF-16 string at `text`.
+unsafe fn narrow(text: *const u16) -> String {
+    if text.is_null() {
+        return String::new();
+    }
+    let mut len = 0;
+    while *text.add(len) != 0 {
+        len += 1;
+    }
+    String::from_utf16_lossy(std::slice::from_raw_parts(text, len))
+}
+
+/// How paging during a run lines up with its throughput, see `PagingReport::correlate`.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct PagingReport {
+    /// Bytes moved between VRAM and system memory, as far as the readings show.
+    pub paged_bytes: u64,
+    pub iterations: usize,
+    /// Iterations during which the driver paged.
+    pub paging: usize,
+    /// Iterations with throughput below `DIP_SHARE` of the median in either direction.
+    pub dips: usize,
+    /// Dips during which the driver paged.
+    pub coinciding: usize,
+}
+
+impl PagingReport {
+    /// Finds the paging in `readings`, i.e. changes in shared usage of at least
+    /// `PAGING_THRESHOLD` between two of them, and the iterations of `throughput` it overlaps.
+    pub fn correlate(readings: &[Residency], throughput: &Throughput) -> Option<PagingReport> {
+        if readings.len() < 2 || throughput.timeline.is_empty() {
+            return None;
+        }
+        let paging: Vec<(Instant, Instant, u64)> = readings
+            .windows(2)
+            .map(|pair| (pair[0].at, pair[1].at, pair[0].shared.abs_diff(pair[1].shared)))
+            .filter(|&(_, _, moved)| moved >= PAGING_THRESHOLD)
+            .collect();
+        let h2d_median = median(&throughput.h2d_samples);
+        let d2h_median = median(&throughput.d2h_samples);
+        let mut report = PagingReport {
+            paged_bytes: paging.iter().map(|&(_, _, moved)| moved).sum(),
+            iterations: throughput.timeline.len(),
+            ..PagingReport::default()
+        };
+        for (iteration, &(start, end)) in throughput.timeline.iter().enumerate() {
+            let below = |samples: &[f64], median: f64| {
+                samples.get(iteration).is_some_and(|&sample| sample < median * DIP_SHARE)
+            };
+            let dip =
+                below(&throughput.h2d_samples, h2d_median) ||
+                below(&throughput.d2h_samples, d2h_median);
+            let paged = paging.iter().any(|&(from, to, _)| to >= start && from <= end);
+            report.dips += dip as usize;
+            report.paging += paged as usize;
+            report.coinciding += (dip && paged) as usize;
+        }
+        Some(report)
+    }
+
+    /// Whether at least half of the dips coincided with paging, which points at VRAM
+    /// oversubscribed by other applications or by the transfer itself.
+    pub fn explains_dips(&self) -> bool {
+        self.coinciding > 0 && self.coinciding * 2 >= self.dips
+    }
+
+    pub fn summary(&self) -> Vec<String> {
+        if self.paging == 0 {
+            return vec!["No paging between VRAM and system memory during the run".to_string()];
+        }
+        let mut lines = vec![
+            format!(
+                "Paging in {} of {} iterations, {:.0} MB moved between VRAM and system memory",
+                self.paging,
+                self.iterations,
+                (self.paged_bytes as f64) / (1024.0 * 1024.0)
+            )
+        ];
+        if self.dips > 0 {
+            let mut line = format!(
+                "{} of {} throughput dips coincided with paging",
+                self.coinciding,
+                self.dips
+            );
+            if self.explains_dips() {
+                line.push_str(
+                    "; VRAM is likely oversubscribed, close other GPU applications or transfer less"
+                );
+            }
+            lines.push(line);
+        }
+        lines
+    }
+}
+
+fn median(samples: &[f64]) -> f64 {
+    let mut sorted = samples.to_vec();
+    sorted.sort_by(f64::total_cmp);
+    sorted.get(sorted.len() / 2).copied().unwrap_or_default()
+}
@@ -0,0 +1,109 @@
+use crate::error::BenchError;
+use opencl3::device::{
+    cl_device_partition_property,
+    Device,
+    SubDevice,
+    CL_DEVICE_AFFINITY_DOMAIN_NUMA,
+    CL_DEVICE_PARTITION_BY_AFFINITY_DOMAIN,
+    CL_DEVICE_PARTITION_EQUALLY,
+};
+use std::fmt;
+use std::str::FromStr;
+
+/// How a device is split into sub-devices (OpenCL device fission) before measuring.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Partition {
+    /// Measure the whole device.
+    None,
+    /// Sub-devices with this many compute units each.
+    Equally(u32),
+    /// One sub-device per NUMA node.
+    Numa,
+}
+
+impl Partition {
+    fn properties(&self) -> Option<Vec<cl_device_partition_property>> {
+        match *self {
+            Partition::None => None,
+            Partition::Equally(units) =>
+                Some(vec![CL_DEVICE_PARTITION_EQUALLY, units as cl_device_partition_property, 0]),
+            Partition::Numa =>
+                Some(
+                    vec![
+                        CL_DEVICE_PARTITION_BY_AFFINITY_DOMAIN,
+                        CL_DEVICE_AFFINITY_DOMAIN_NUMA as cl_device_partition_property,
+                        0
+                    ]
+                ),
+        }
+    }
+}
+
+impl fmt::Display for Partition {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Partition::None => write!(f, "None"),
+            Partition::Equally(units) => write!(f, "Equally ({} CUs each)", units),
+            Partition::Numa => write!(f, "By NUMA node"),
+        }
+    }
+}
+
+impl FromStr for Partition {
+    type Err = String;
+
+    /// Parses `none`, `numa` or `equally:<compute units>`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "none" => Ok(Partition::None),
+            "numa" => Ok(Partition::Numa),
+            _ => {
+                let units = s
+                    .strip_prefix("equally:")
+                    .and_then(|n| n.parse::<u32>().ok())
+                    .filter(|&n| n > 0)
+                    .ok_or_else(|| format!("unknown partition '{}'", s))?;
+                Ok(Partition::Equally(units))
+            }
+        }
+    }
+}
+
+/// A device to measure, which may be a sub-device of the selected device.
+///
+/// Sub-devices are released when this is dropped, so it must outlive the measurement.
+pub struct Target {
+    device: Device,
+    _sub_devices: Vec<SubDevice>,
+}
+
+impl Target {
+    pub fn device(&self) -> &Device {
+        &self.device
+    }
+}
+
+/// Partitions `device` and picks sub-device `index`, or uses the device itself for
+/// `Partition::None`.
+pub fn select_target(
+    device: &Device,
+    partition: Partition,
+    index: usize
+) -> Result<Target, BenchError> {
+    let Some(properties) = partition.properties() else {
+        return Ok(Target { device: *device, _sub_devices: Vec::new() });
+    };
+
+    let sub_devices = device.create_sub_devices(&properties)?;
+    let count = sub_devices.len();
+    let sub_device = sub_devices
+        .get(index)
+        .map(|sub| Device::new(sub.id()))
+        .ok_or_else(|| {
+            BenchError::NoDevice(
+                format!("sub-device {} is out of range ({} sub-devices created)", index, count)
+            )
+        })?;
+
+    Ok(Target { device: sub_device, _sub_devices: sub_devices })
+}
@@ -0,0 +1,177 @@
+//! Whether the data being moved changes throughput. It should not, but some stacks compress
+//! in transit (PCIe links behind certain bridges, virtualized or network-attached GPUs), so
+//! zeros move faster than random data and every other result depends on what was sent.
+
+use crate::device::default_queue;
+use crate::error::BenchError;
+use crate::registry::DeviceRegistry;
+use crate::MeasureConfig;
+use opencl3::device::Device;
+use opencl3::memory::{ Buffer, CL_MEM_READ_WRITE };
+use opencl3::types::CL_BLOCKING;
+use std::fmt;
+use std::ptr;
+use std::time::{ Duration, Instant };
+
+/// Spread between the fastest and slowest pattern, as a fraction of their mean, above which
+/// the throughput is reported as depending on the data.
+pub const ANOMALY_THRESHOLD: f64 = 0.05;
+
+/// Starting state of the xorshift32 generator behind the random pattern and the random
+/// transfer sizes of `bursts`, fixed so that every run sends the same data.
+pub const PATTERN_SEED: u32 = 0x9e37_79b9;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Pattern {
+    Zeros,
+    /// The same non-zero value everywhere.
+    Constant,
+    /// The repeating ramp the main measurement sends.
+    Ramp,
+    /// Incompressible pseudo-random bits.
+    Random,
+}
+
+impl Pattern {
+    pub const ALL: [Pattern; 4] = [
+        Pattern::Zeros,
+        Pattern::Constant,
+        Pattern::Ramp,
+        Pattern::Random,
+    ];
+
+    fn fill(&self, data: &mut [f32]) {
+        match self {
+            Pattern::Zeros => data.fill(0.0),
+            Pattern::Constant => data.fill(1.0),
+            Pattern::Ramp => {
+                for (index, value) in data.iter_mut().enumerate() {
+                    *value = (index % 4096) as f32;
+                }
+            }
+            Pattern::Random => {
+                // xorshift32, reproducible and fast enough for gigabytes
+                let mut state = PATTERN_SEED;
+                for value in data {
+                    state ^= state << 13;
+                    state ^= state >> 17;
+                    state ^= state << 5;
+                    *value = f32::from_bits(state);
+                }
+            }
+        }
+    }
+}
+
+impl fmt::Display for Pattern {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Pattern::Zeros => write!(f, "Zeros"),
+            Pattern::Constant => write!(f, "Constant"),
+            Pattern::Ramp => write!(f, "Ramp"),
+            Pattern::Random => write!(f, "Random"),
+        }
+    }
+}
+
+/// Throughput per pattern, see `measure_patterns`.
+#[derive(Clone, Debug)]
+pub struct PatternResult {
+    /// Pattern and its H2D and D2H throughput in GB/s, in `Pattern::ALL` order.
+    pub samples: Vec<(Pattern, f64, f64)>,
+}
+
+impl PatternResult {
+    /// Largest difference between patterns as a fraction of the mean, per direction.
+    pub fn spread(&self) -> (f64, f64) {
+        let spread = |values: Vec<f64>| {
+            let mean = values.iter().sum::<f64>() / (values.len() as f64);
+            let max = values.iter().copied().fold(f64::MIN, f64::max);
+            let min = values.iter().copied().fold(f64::MAX, f64::min);
+            (max - min) / mean
+        };
+        (
+            spread(self.samples.iter().map(|sample| sample.1).collect()),
+            spread(self.samples.iter().map(|sample| sample.2).collect()),
+        )
+    }
+
+    /// Whether either direction varies with the data by more than `ANOMALY_THRESHOLD`.
+    pub fn is_anomalous(&self) -> bool {
+        let (h2d, d2h) = self.spread();
+        h2d > ANOMALY_THRESHOLD || d2h > ANOMALY_THRESHOLD
+    }
+
+    /// One human-readable line per pattern, then the verdict.
+    pub fn summary(&self) -> Vec<String> {
+        let mut lines: Vec<String> = self.samples
+            .iter()
+            .map(|(pattern, h2d, d2h)| {
+                format!("{}: {:.2} GB/s H2D, {:.2} GB/s D2H", pattern, h2d, d2h)
+            })
+            .collect();
+        let (h2d, d2h) = self.spread();
+        lines.push(
+            if self.is_anomalous() {
+                format!(
+                    "Throughput depends on the data ({:.1}% H2D, {:.1}% D2H apart); the link \
+                     or driver may compress in transit, so results with other data differ",
+                    h2d * 100.0,
+                    d2h * 100.0
+                )
+            } else {
+                format!(
+                    "No dependence on the data ({:.1}% H2D, {:.1}% D2H apart)",
+                    h2d * 100.0,
+                    d2h * 100.0
+                )
+            }
+        );
+        lines
+    }
+}
+
+/// Moves each of `Pattern::ALL` to the device and back `config.length.fixed_iterations()`
+/// times. Patterns take turns every iteration, so that clock or thermal drift affects them
+/// alike.
+pub fn measure_patterns(
+    config: &MeasureConfig,
+    device: &Device
+) -> Result<PatternResult, BenchError> {
+    let context = DeviceRegistry::global().context(device)?;
+    let queue = default_queue(&context, 0)?;
+    let mut buffer = unsafe {
+        Buffer::<f32>::create(&context, CL_MEM_READ_WRITE, config.data_size, ptr::null_mut())?
+    };
+    let mut host = vec![0.0f32; config.data_size];
+    let mut times = [(Duration::ZERO, Duration::ZERO); Pattern::ALL.len()];
+
+    let iterations = config.length.fixed_iterations();
+    for _ in 0..iterations {
+        for (pattern, (h2d, d2h)) in Pattern::ALL.iter().zip(&mut times) {
+            // Refilled untimed every time, as the read-back overwrites it
+            pattern.fill(&mut host);
+            let start = Instant::now();
+            unsafe {
+                queue.enqueue_write_buffer(&mut buffer, CL_BLOCKING, 0, &host, &[])?;
+            }
+            *h2d += start.elapsed();
+            let start = Instant::now();
+            unsafe {
+                queue.enqueue_read_buffer(&buffer, CL_BLOCKING, 0, &mut host, &[])?;
+            }
+            *d2h += start.elapsed();
+        }
+    }
+
+    let bytes = (config.transfer_bytes() as f64) * (iterations as f64);
+    Ok(PatternResult {
+        samples: Pattern::ALL
+            .iter()
+            .zip(times)
+            .map(|(&pattern, (h2d, d2h))| {
+                (pattern, bytes / h2d.as_secs_f64() / 1e9, bytes / d2h.as_secs_f64() / 1e9)
+            })
+            .collect(),
+    })
+}
@@ -0,0 +1,171 @@
+//! Copies from one GPU straight into another through `cl_amd_copy_buffer_p2p`, without
+//! staging in host memory, and the checks that decide whether two GPUs can do that.
+
+use crate::capabilities;
+use crate::device::default_queue;
+use crate::error::BenchError;
+use crate::{ MeasureConfig, MyDevice };
+use cl3::device::get_device_data;
+use cl3::ext::clGetExtensionFunctionAddressForPlatform;
+use opencl3::command_queue::CommandQueue;
+use opencl3::context::Context;
+use opencl3::device::Device;
+use opencl3::error_codes::{ ClError, CL_SUCCESS };
+use opencl3::memory::{ Buffer, ClMem, CL_MEM_READ_WRITE };
+use opencl3::types::{ cl_command_queue, cl_device_id, cl_device_info, cl_event, cl_int, cl_mem };
+use std::ffi::c_void;
+use std::ptr;
+use std::time::{ Duration, Instant };
+
+/// The extension whose copies are measured.
+pub const EXTENSION: &str = "cl_amd_copy_buffer_p2p";
+
+/// `CL_DEVICE_P2P_DEVICES_AMD`, the devices a device can copy to directly.
+const CL_DEVICE_P2P_DEVICES_AMD: cl_device_info = 0x4089;
+
+/// `clEnqueueCopyBufferP2PAMD`
+type CopyBufferP2P = unsafe extern "C" fn(
+    cl_command_queue,
+    cl_mem,
+    cl_mem,
+    usize,
+    usize,
+    usize,
+    u32,
+    *const cl_event,
+    *mut cl_event
+) -> cl_int;
+
+/// Throughput of direct copies between two GPUs, see `measure_peer`.
+#[derive(Clone, Copy, Debug)]
+pub struct PeerResult {
+    /// From the measured device to the peer, in GB/s.
+    pub forward: f64,
+    /// From the peer back, in GB/s.
+    pub backward: f64,
+}
+
+impl PeerResult {
+    pub fn summary(&self) -> String {
+        format!(
+            "{:.2} GB/s to the peer, {:.2} GB/s back, without staging in host memory",
+            self.forward,
+            self.backward
+        )
+    }
+}
+
+/// Whether `source` can copy directly into `destination`, and if not, why.
+pub fn peer_access(source: &MyDevice, destination: &MyDevice) -> Result<(), String> {
+    if source == destination {
+        return Err("pick two different GPUs".to_string());
+    }
+    let (Ok(source_platform), Ok(destination_platform)) = (
+        source.get_device().platform(),
+        destination.get_device().platform(),
+    ) else {
+        return Err("the driver did not report the GPUs' platforms".to_string());
+    };
+    if source_platform != destination_platform {
+        return Err(
+            "the GPUs are on different OpenCL platforms, and peer copies stay within one \
+             driver".to_string()
+        );
+    }
+    for device in [source, destination] {
+        if !device.has_extension(EXTENSION) {
+            let other = if capabilities::PEER_TO_PEER.supported_by(device) {
+                ", only its other peer extension"
+            } else {
+                ""
+            };
+            return Err(format!("{} does not expose {}{}", device.name(), EXTENSION, other));
+        }
+    }
+    // Drivers list the devices each one reaches; a peer missing there sits behind a
+    // different root complex, or the IOMMU or BIOS blocks the access
+    if let Some(peers) = peers_of(source.get_device()) {
+        if !peers.contains(&destination.get_device().id()) {
+            return Err(
+                format!(
+                    "the driver reports no direct path from {} to {}, e.g. because they are \
+                     on different PCIe root complexes",
+                    source.name(),
+                    destination.name()
+                )
+            );
+        }
+    }
+    Ok(())
+}
+
+/// The devices `device` reports direct access to, `None` where the driver does not say.
+fn peers_of(device: &Device) -> Option<Vec<cl_device_id>> {
+    let data = get_device_data(device.id(), CL_DEVICE_P2P_DEVICES_AMD).ok()?;
+    Some(
+        data
+            .chunks_exact(std::mem::size_of::<cl_device_id>())
+            .map(|bytes| usize::from_ne_bytes(bytes.try_into().unwrap()) as cl_device_id)
+            .collect()
+    )
+}
+
+/// Copies `config.data_size` floats from `source` into `destination` and back,
+/// `config.length.fixed_iterations()` times each way. Each copy is issued on the queue of
+/// the device that owns the source buffer.
+pub fn measure_peer(
+    config: &MeasureConfig,
+    source: &MyDevice,
+    destination: &MyDevice
+) -> Result<PeerResult, BenchError> {
+    peer_access(source, destination).map_err(BenchError::Unsupported)?;
+    let platform = source.get_device().platform()?;
+    let copy = unsafe {
+        std::mem::transmute::<*mut c_void, Option<CopyBufferP2P>>(
+            clGetExtensionFunctionAddressForPlatform(
+                platform,
+                c"clEnqueueCopyBufferP2PAMD".as_ptr()
+            )
+        )
+    }.ok_or_else(|| {
+        BenchError::Unsupported("the driver lacks clEnqueueCopyBufferP2PAMD".to_string())
+    })?;
+
+    let create = |device: &MyDevice| -> Result<_, BenchError> {
+        let context = Context::from_device(device.get_device())?;
+        let queue = default_queue(&context, 0)?;
+        let size = config.data_size;
+        let buffer = unsafe {
+            Buffer::<f32>::create(&context, CL_MEM_READ_WRITE, size, ptr::null_mut())?
+        };
+        Ok((context, queue, buffer))
+    };
+    let (_source_context, source_queue, source_buffer) = create(source)?;
+    let (_destination_context, destination_queue, destination_buffer) = create(destination)?;
+    let bytes = config.transfer_bytes() as usize;
+    let timed = |queue: &CommandQueue, from: &Buffer<f32>, to: &Buffer<f32>| {
+        let start = Instant::now();
+        let status = unsafe {
+            copy(queue.get(), from.get(), to.get(), 0, 0, bytes, 0, ptr::null(), ptr::null_mut())
+        };
+        if status != CL_SUCCESS {
+            return Err(BenchError::OpenCl(ClError(status)));
+        }
+        queue.finish()?;
+        Ok(start.elapsed())
+    };
+
+    let mut forward = Duration::ZERO;
+    let mut backward = Duration::ZERO;
+    let iterations = config.length.fixed_iterations();
+    for _ in 0..iterations {
+        forward += timed(&source_queue, &source_buffer, &destination_buffer)?;
+        backward += timed(&destination_queue, &destination_buffer, &source_buffer)?;
+    }
+
+    let total = (bytes as f64) * (iterations as f64);
+    Ok(PeerResult {
+        forward: total / forward.as_secs_f64() / 1e9,
+        backward: total / backward.as_secs_f64() / 1e9,
+    })
+}
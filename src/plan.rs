@@ -0,0 +1,359 @@
+//! What to measure, saved to a file that can be handed to someone else, so that e.g. a
+//! support engineer can send a customer the exact run or matrix to perform. A plan holds the
+//! configuration and the experiments but not the device, which differs between machines.
+//!
+//! ```toml
+//! [run]
+//! size_mb = 256            # or with a unit, e.g. "1GB"
+//! units = "decimal"        # or binary for size_mb in MiB, as plans without it are read
+//! run_length = "iterations:10"
+//! host_buffer = "reuse"
+//! memory = "buffer"
+//! verify = "readback"
+//! warm_up_ms = 500
+//! discard = 2              # iterations run first and left out of the results
+//! delay_ms = 0
+//! # max_temp = 60.0
+//! # gentle = 50            # percent of the time spent transferring
+//!
+//! [experiments]
+//! threads = 4
+//! thread_mapping = false
+//! patterns = true
+//! latency = true
+//! completion = false
+//! ramp_ms = 10             # double the size until a transfer takes this long
+//! offsets = "sweep"        # or KB offsets into the largest buffer, e.g. "261120,262144"
+//! scatter_buffers = 256    # small uploads batched against one large one
+//! inflight = false
+//! contention = false
+//! round_trip = false
+//! random_sizes = "4KiB..256MiB" # each transfer a random size in this range
+//!
+//! [matrix]
+//! memory = ["buffer", "host-ptr"]
+//! sizes_mb = [64, 256, 1024]
+//! queues = [1, 2, 4]
+//! ```
+
+use gputhroughput::bursts::SizeRange;
+use gputhroughput::matrix::Matrix;
+use gputhroughput::memory::Memory;
+use gputhroughput::offsets::Offsets;
+use gputhroughput::scatter;
+use gputhroughput::store::StoredResult;
+use gputhroughput::{
+    elements_in,
+    HostBuffer,
+    Pacing,
+    parse_size,
+    RunLength,
+    SizeUnits,
+    Verification,
+};
+use std::path::Path;
+use std::str::FromStr;
+use std::time::Duration;
+use toml_edit::{ value, Array, Document, Item, Table, TomlError };
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Plan {
+    /// Transfer size in `units`, which the matrix sizes are in too.
+    pub size: usize,
+    pub units: SizeUnits,
+    pub length: RunLength,
+    pub host_buffer: HostBuffer,
+    pub memory: Memory,
+    pub verification: Verification,
+    pub warm_up: Duration,
+    pub discard: usize,
+    pub pacing: Pacing,
+    pub threads: Option<usize>,
+    pub thread_mapping: bool,
+    pub patterns: bool,
+    pub latency: bool,
+    pub completion: bool,
+    pub ramp: Option<Duration>,
+    pub offsets: Option<Offsets>,
+    pub scatter: Option<usize>,
+    pub inflight: bool,
+    pub contention: bool,
+    pub round_trip: bool,
+    pub random_sizes: Option<SizeRange>,
+    /// Measured in place of the single run when set, see `matrix`.
+    pub matrix: Option<Matrix>,
+}
+
+impl Plan {
+    /// Reads a plan, failing on anything missing or invalid rather than running something
+    /// other than what was sent.
+    pub fn load(path: &Path) -> Result<Plan, String> {
+        let error = |e: &dyn std::fmt::Display| format!("{}: {}", path.display(), e);
+        let text = std::fs::read_to_string(path).map_err(|e| error(&e))?;
+        let document: Document = text.parse().map_err(|e: TomlError| error(&e))?;
+        let reader = Reader { path, document: &document };
+
+        // Plans saved before sizes had a choice of units gave them in MiB
+        let units = reader.optional("run", "units", parse)?.unwrap_or(SizeUnits::Binary);
+        let size = |item: &Item| {
+            let size: usize = match item.as_str() {
+                Some(text) => parse_size(text, units).ok()?,
+                None => item.as_integer()?.try_into().ok()?,
+            };
+            (size > 0 && elements_in(size, units).is_ok()).then_some(size)
+        };
+        let millis = |item: &Item| {
+            Some(Duration::from_millis(item.as_integer()?.try_into().ok()?))
+        };
+        let numbers = |item: &Item| -> Option<Vec<usize>> {
+            item.as_array()?
+                .iter()
+                .map(|value| value.as_integer()?.try_into().ok())
+                .collect()
+        };
+        let matrix = match document.get("matrix") {
+            Some(_) => {
+                let memories = |item: &Item| -> Option<Vec<Memory>> {
+                    item.as_array()?
+                        .iter()
+                        .map(|value| value.as_str()?.parse().ok())
+                        .collect()
+                };
+                let matrix = Matrix {
+                    memories: reader.required("matrix", "memory", memories)?,
+                    sizes: reader.required("matrix", "sizes_mb", numbers)?,
+                    queues: reader.required("matrix", "queues", numbers)?,
+                };
+                matrix.validate(units).map_err(|e| format!("{}: {}", path.display(), e))?;
+                Some(matrix)
+            }
+            None => None,
+        };
+
+        Ok(Plan {
+            size: reader.required("run", "size_mb", size)?,
+            units,
+            length: reader.required("run", "run_length", parse)?,
+            host_buffer: reader.required("run", "host_buffer", parse)?,
+            memory: reader.required("run", "memory", parse)?,
+            verification: reader.required("run", "verify", parse)?,
+            warm_up: reader.required("run", "warm_up_ms", millis)?,
+            discard: reader
+                .optional("run", "discard", |item| item.as_integer()?.try_into().ok())?
+                .unwrap_or(0),
+            pacing: Pacing {
+                delay: reader.required("run", "delay_ms", millis)?,
+                max_temperature: reader.optional("run", "max_temp", |item| {
+                    item.as_float().filter(|limit| limit.is_finite())
+                })?,
+                duty: reader.optional("run", "gentle", |item| {
+                    let percent = item.as_integer()?;
+                    (1..=99).contains(&percent).then(|| (percent as f64) / 100.0)
+                })?,
+            },
+            threads: reader.optional("experiments", "threads", |item| {
+                item.as_integer()?.try_into().ok().filter(|&threads: &usize| threads > 0)
+            })?,
+            thread_mapping: reader
+                .optional("experiments", "thread_mapping", Item::as_bool)?
+                .unwrap_or(false),
+            patterns: reader.optional("experiments", "patterns", Item::as_bool)?.unwrap_or(false),
+            latency: reader.optional("experiments", "latency", Item::as_bool)?.unwrap_or(false),
+            completion: reader
+                .optional("experiments", "completion", Item::as_bool)?
+                .unwrap_or(false),
+            ramp: reader.optional("experiments", "ramp_ms", |item| {
+                millis(item).filter(|budget| !budget.is_zero())
+            })?,
+            offsets: reader.optional("experiments", "offsets", parse)?,
+            scatter: reader.optional("experiments", "scatter_buffers", |item| {
+                item.as_integer()?
+                    .try_into()
+                    .ok()
+                    .filter(|buffers| (1..=scatter::MAX_BUFFERS).contains(buffers))
+            })?,
+            inflight: reader.optional("experiments", "inflight", Item::as_bool)?.unwrap_or(false),
+            contention: reader
+                .optional("experiments", "contention", Item::as_bool)?
+                .unwrap_or(false),
+            round_trip: reader
+                .optional("experiments", "round_trip", Item::as_bool)?
+                .unwrap_or(false),
+            random_sizes: reader.optional("experiments", "random_sizes", |item| {
+                SizeRange::parse(item.as_str()?, units).ok()
+            })?,
+            matrix,
+        })
+    }
+
+    /// The configuration `result` was measured with, rebuilt from the result and its
+    /// reproducibility block. Experiments are not part of a result, so none are planned.
+    pub fn of_result(result: &StoredResult) -> Result<Plan, String> {
+        let block = result.reproducibility
+            .as_ref()
+            .ok_or("the result was saved before its full configuration was recorded")?;
+        let units: SizeUnits = block.units.parse()?;
+        let megabyte = units.megabyte() as u64;
+        if result.transfer_bytes == 0 || !result.transfer_bytes.is_multiple_of(megabyte) {
+            return Err(
+                format!(
+                    "a transfer of {} bytes is not a whole number of {}",
+                    result.transfer_bytes,
+                    units.size_unit()
+                )
+            );
+        }
+        let millis = Duration::from_millis;
+        Ok(Plan {
+            size: usize::try_from(result.transfer_bytes / megabyte).map_err(|e| e.to_string())?,
+            units,
+            length: result.run_length.parse()?,
+            host_buffer: result.host_buffer.parse()?,
+            memory: result.memory.parse()?,
+            verification: result.verification.parse()?,
+            warm_up: millis(block.warm_up_ms),
+            discard: block.discard,
+            pacing: Pacing {
+                delay: millis(block.delay_ms),
+                max_temperature: block.max_temp,
+                duty: block.gentle,
+            },
+            threads: None,
+            thread_mapping: false,
+            patterns: false,
+            latency: false,
+            completion: false,
+            ramp: None,
+            offsets: None,
+            scatter: None,
+            inflight: false,
+            contention: false,
+            round_trip: false,
+            random_sizes: None,
+            matrix: None,
+        })
+    }
+
+    pub fn save(&self, path: &Path) -> std::io::Result<()> {
+        let mut document = Document::new();
+        let mut run = Table::new();
+        run["size_mb"] = value(self.size as i64);
+        run["units"] = value(self.units.key());
+        run["run_length"] = value(self.length.spec());
+        run["host_buffer"] = value(match self.host_buffer {
+            HostBuffer::Reuse => "reuse",
+            HostBuffer::Fresh => "fresh",
+        });
+        run["memory"] = value(self.memory.key());
+        run["verify"] = value(match self.verification {
+            Verification::ReadBack => "readback",
+            Verification::Checksum => "checksum",
+        });
+        run["warm_up_ms"] = value(self.warm_up.as_millis() as i64);
+        run["discard"] = value(self.discard as i64);
+        run["delay_ms"] = value(self.pacing.delay.as_millis() as i64);
+        if let Some(limit) = self.pacing.max_temperature {
+            run["max_temp"] = value(limit);
+        }
+        if let Some(duty) = self.pacing.duty {
+            run["gentle"] = value((duty * 100.0).round() as i64);
+        }
+        document["run"] = Item::Table(run);
+
+        let mut experiments = Table::new();
+        if let Some(threads) = self.threads {
+            experiments["threads"] = value(threads as i64);
+        }
+        experiments["thread_mapping"] = value(self.thread_mapping);
+        experiments["patterns"] = value(self.patterns);
+        experiments["latency"] = value(self.latency);
+        experiments["completion"] = value(self.completion);
+        if let Some(budget) = self.ramp {
+            experiments["ramp_ms"] = value(budget.as_millis() as i64);
+        }
+        if let Some(ref offsets) = self.offsets {
+            experiments["offsets"] = value(offsets.to_string());
+        }
+        if let Some(buffers) = self.scatter {
+            experiments["scatter_buffers"] = value(buffers as i64);
+        }
+        experiments["inflight"] = value(self.inflight);
+        experiments["contention"] = value(self.contention);
+        experiments["round_trip"] = value(self.round_trip);
+        if let Some(range) = self.random_sizes {
+            experiments["random_sizes"] = value(range.to_string());
+        }
+        document["experiments"] = Item::Table(experiments);
+
+        if let Some(ref matrix) = self.matrix {
+            let mut table = Table::new();
+            table["memory"] = value(matrix.memories.iter().map(Memory::key).collect::<Array>());
+            table["sizes_mb"] = value(
+                matrix.sizes
+                    .iter()
+                    .map(|&size| size as i64)
+                    .collect::<Array>()
+            );
+            table["queues"] = value(
+                matrix.queues
+                    .iter()
+                    .map(|&queues| queues as i64)
+                    .collect::<Array>()
+            );
+            document["matrix"] = Item::Table(table);
+        }
+
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(path, document.to_string())
+    }
+}
+
+struct Reader<'a> {
+    path: &'a Path,
+    document: &'a Document,
+}
+
+impl Reader<'_> {
+    /// `key` in `table` converted by `read`, an error when it is missing or does not convert.
+    fn required<T>(
+        &self,
+        table: &str,
+        key: &str,
+        read: impl FnOnce(&Item) -> Option<T>
+    ) -> Result<T, String> {
+        self.optional(table, key, read)?.ok_or_else(|| {
+            format!("{}: {}.{} is missing", self.path.display(), table, key)
+        })
+    }
+
+    /// Like `required`, but `None` when `key` is missing.
+    fn optional<T>(
+        &self,
+        table: &str,
+        key: &str,
+        read: impl FnOnce(&Item) -> Option<T>
+    ) -> Result<Option<T>, String> {
+        let Some(item) = self.document.get(table).and_then(|table| table.get(key)) else {
+            return Ok(None);
+        };
+        match read(item) {
+            Some(value) => Ok(Some(value)),
+            None =>
+                Err(
+                    format!(
+                        "{}: invalid {}.{} = {}",
+                        self.path.display(),
+                        table,
+                        key,
+                        item.to_string().trim()
+                    )
+                ),
+        }
+    }
+}
+
+fn parse<T: FromStr>(item: &Item) -> Option<T> {
+    item.as_str()?.parse().ok()
+}
@@ -0,0 +1,447 @@
+//! Small charts drawn straight onto the egui painter, and the size sweep's chart, which is
+//! worth zooming into, drawn with egui_plot.
+
+use eframe::egui::{ self, Color32, Pos2, Rect, Sense, Stroke, Vec2 };
+use egui_plot::{ uniform_grid_spacer, Legend, Line, LineStyle, Plot, PlotPoints, VLine };
+use gputhroughput::offsets::{ self, OffsetPoint };
+use gputhroughput::precision::significant;
+use gputhroughput::ramp::{ self, RampPoint };
+use gputhroughput::sweep::{ self, SweepResult };
+
+/// A word-sized line chart of `values`, scaled between their own minimum and maximum.
+pub fn sparkline(ui: &mut egui::Ui, values: &[f64], color: Color32) -> egui::Response {
+    let (rect, response) = ui.allocate_exact_size(Vec2::new(60.0, 16.0), Sense::hover());
+    if values.len() < 2 {
+        return response;
+    }
+
+    let min = values.iter().copied().fold(f64::INFINITY, f64::min);
+    let max = values.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+    let points = line_points(rect, values, min, max);
+    ui.painter().add(egui::Shape::line(points, Stroke::new(1.0, color)));
+    response
+}
+
+/// Maps `values` evenly across the width of `rect`, with `min` at the bottom and `max` at
+/// the top. A flat series is drawn through the middle.
+fn line_points(rect: Rect, values: &[f64], min: f64, max: f64) -> Vec<Pos2> {
+    let span = max - min;
+    let step = rect.width() / ((values.len() - 1).max(1) as f32);
+    values
+        .iter()
+        .enumerate()
+        .map(|(i, &value)| {
+            let t = if span > 0.0 { ((value - min) / span) as f32 } else { 0.5 };
+            Pos2::new(rect.left() + step * (i as f32), rect.bottom() - t * rect.height())
+        })
+        .collect()
+}
+
+/// Colors for the two transfer directions in every chart.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Palette {
+    /// Green and red, hard to tell apart with red-green color blindness.
+    Classic,
+    /// Blue and orange from the Okabe-Ito set, distinguishable with any common deficiency.
+    ColorBlindSafe,
+    Custom {
+        h2d: Color32,
+        d2h: Color32,
+    },
+}
+
+impl Palette {
+    /// Host-to-device and device-to-host colors.
+    pub fn colors(&self) -> (Color32, Color32) {
+        match *self {
+            Palette::Classic => (Color32::from_rgb(44, 160, 44), Color32::from_rgb(214, 39, 40)),
+            Palette::ColorBlindSafe =>
+                (Color32::from_rgb(0, 114, 178), Color32::from_rgb(230, 159, 0)),
+            Palette::Custom { h2d, d2h } => (h2d, d2h),
+        }
+    }
+
+    /// Name used in the settings file.
+    pub fn key(&self) -> &'static str {
+        match self {
+            Palette::Classic => "classic",
+            Palette::ColorBlindSafe => "color-blind-safe",
+            Palette::Custom { .. } => "custom",
+        }
+    }
+}
+
+impl std::fmt::Display for Palette {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Palette::Classic => write!(f, "Classic"),
+            Palette::ColorBlindSafe => write!(f, "Color-blind safe"),
+            Palette::Custom { .. } => write!(f, "Custom"),
+        }
+    }
+}
+
+/// One group of bars per device, host to device above device to host, on a shared scale
+/// starting from zero GB/s. A direction that was not measured is passed as zero and left out.
+pub fn device_bars(ui: &mut egui::Ui, devices: &[(String, f64, f64)], palette: Palette) {
+    let (h2d_color, d2h_color) = palette.colors();
+    ui.horizontal(|ui| {
+        ui.colored_label(h2d_color, "■ Host to Device");
+        ui.colored_label(d2h_color, "■ Device to Host");
+    });
+
+    let max = devices
+        .iter()
+        .flat_map(|&(_, h2d, d2h)| [h2d, d2h])
+        .fold(0.0, f64::max);
+    if max <= 0.0 {
+        return;
+    }
+    let text = ui.visuals().text_color();
+    for (name, h2d, d2h) in devices {
+        ui.label(name);
+        for (value, color) in [(*h2d, h2d_color), (*d2h, d2h_color)] {
+            if value <= 0.0 {
+                continue;
+            }
+            let size = Vec2::new(ui.available_width(), 12.0);
+            let (rect, _) = ui.allocate_exact_size(size, Sense::hover());
+            // Leaves room for the value after the longest bar
+            let width = (rect.width() - 80.0).max(0.0) * ((value / max) as f32);
+            let painter = ui.painter_at(rect);
+            painter.rect_filled(
+                Rect::from_min_size(rect.min, Vec2::new(width, rect.height())),
+                0.0,
+                color
+            );
+            painter.text(
+                Pos2::new(rect.left() + width + 4.0, rect.center().y),
+                egui::Align2::LEFT_CENTER,
+                format!("{} GB/s", significant(value, 3)),
+                egui::FontId::proportional(10.0),
+                text
+            );
+        }
+    }
+}
+
+/// Per-iteration throughput of both directions on shared axes, starting from zero GB/s.
+/// `d2h` is empty after an upload-only run. A dashed line marks each of `gaps`, the indices of
+/// the first samples after the system resumed from a suspend.
+///
+/// Clicking the chart pins the nearest iteration in `pinned`, which is then marked and its
+/// exact values shown with a button to copy them; clicking the pinned iteration again unpins it.
+pub fn samples_chart(
+    ui: &mut egui::Ui,
+    h2d: &[f64],
+    d2h: &[f64],
+    gaps: &[usize],
+    palette: Palette,
+    pinned: &mut Option<usize>
+) {
+    let (h2d_color, d2h_color) = palette.colors();
+    ui.horizontal(|ui| {
+        ui.colored_label(h2d_color, "■ Host to Device");
+        if !d2h.is_empty() {
+            ui.colored_label(d2h_color, "■ Device to Host");
+        }
+    });
+
+    let size = Vec2::new(ui.available_width(), 120.0);
+    let (rect, response) = ui.allocate_exact_size(size, Sense::click());
+    let painter = ui.painter_at(rect);
+    let axis = ui.visuals().weak_text_color();
+    painter.rect_stroke(rect, 0.0, Stroke::new(1.0, axis));
+
+    let max = h2d.iter().chain(d2h).copied().fold(0.0, f64::max);
+    let count = if d2h.is_empty() { h2d.len() } else { h2d.len().min(d2h.len()) };
+    if max <= 0.0 || count == 0 {
+        return;
+    }
+
+    let x_of = |i: usize| {
+        if count == 1 {
+            rect.center().x
+        } else {
+            rect.left() + (rect.width() * (i as f32)) / ((count - 1) as f32)
+        }
+    };
+    if let Some(click) = response.interact_pointer_pos().filter(|_| response.clicked()) {
+        let nearest = (0..count)
+            .min_by(|&a, &b| (x_of(a) - click.x).abs().total_cmp(&(x_of(b) - click.x).abs()))
+            .unwrap();
+        *pinned = if *pinned == Some(nearest) { None } else { Some(nearest) };
+    }
+    painter.text(
+        rect.left_top() + Vec2::new(4.0, 2.0),
+        egui::Align2::LEFT_TOP,
+        format!("{:.1} GB/s", max),
+        egui::FontId::proportional(10.0),
+        axis
+    );
+    for (values, color) in [(h2d, h2d_color), (d2h, d2h_color)] {
+        match values {
+            [] => {}
+            [single] => {
+                let y = rect.bottom() - ((single / max) as f32) * rect.height();
+                painter.circle_filled(Pos2::new(rect.center().x, y), 3.0, color);
+            }
+            _ => {
+                let points = line_points(rect, values, 0.0, max);
+                painter.add(egui::Shape::line(points, Stroke::new(1.5, color)));
+            }
+        }
+    }
+
+    // The system slept between the sample before a gap and the one at it
+    for &gap in gaps.iter().filter(|&&gap| gap > 0 && gap < count) {
+        let x = (x_of(gap - 1) + x_of(gap)) / 2.0;
+        let points = [Pos2::new(x, rect.top()), Pos2::new(x, rect.bottom())];
+        painter.add(egui::Shape::dashed_line(&points, Stroke::new(1.0, axis), 4.0, 4.0));
+    }
+
+    let Some(index) = pinned.filter(|&index| index < count) else {
+        return;
+    };
+    let x = x_of(index);
+    painter.vline(x, rect.y_range(), Stroke::new(1.0, axis));
+    for (value, color) in [(h2d.get(index), h2d_color), (d2h.get(index), d2h_color)] {
+        if let Some(value) = value {
+            let y = rect.bottom() - ((value / max) as f32) * rect.height();
+            painter.circle_filled(Pos2::new(x, y), 3.5, color);
+        }
+    }
+
+    let text = match d2h.get(index) {
+        Some(d2h) =>
+            format!(
+                "Iteration {}: {:.3} GB/s host to device, {:.3} GB/s device to host",
+                index + 1,
+                h2d[index],
+                d2h
+            ),
+        None => format!("Iteration {}: {:.3} GB/s host to device", index + 1, h2d[index]),
+    };
+    ui.horizontal(|ui| {
+        ui.label(&text);
+        if ui.small_button("Copy").clicked() {
+            ui.output_mut(|output| {
+                output.copied_text = text.clone();
+            });
+        }
+    });
+}
+
+/// Throughput of both directions against transfer size, one point per size of the ramp on
+/// an axis that doubles with every step, from zero GB/s.
+pub fn size_curve(ui: &mut egui::Ui, points: &[RampPoint], palette: Palette) {
+    let (h2d_color, d2h_color) = palette.colors();
+    ui.horizontal(|ui| {
+        ui.colored_label(h2d_color, "■ Host to Device");
+        ui.colored_label(d2h_color, "■ Device to Host");
+    });
+
+    let size = Vec2::new(ui.available_width(), 100.0);
+    let (rect, _) = ui.allocate_exact_size(size, Sense::hover());
+    let painter = ui.painter_at(rect);
+    let axis = ui.visuals().weak_text_color();
+    painter.rect_stroke(rect, 0.0, Stroke::new(1.0, axis));
+
+    let h2d: Vec<f64> = points.iter().map(RampPoint::h2d_throughput).collect();
+    let d2h: Vec<f64> = points.iter().map(RampPoint::d2h_throughput).collect();
+    let max = h2d.iter().chain(&d2h).copied().fold(0.0, f64::max);
+    let (Some(first), Some(last)) = (points.first(), points.last()) else {
+        return;
+    };
+    if max <= 0.0 || points.len() < 2 {
+        return;
+    }
+    for (values, color) in [(&h2d, h2d_color), (&d2h, d2h_color)] {
+        let points = line_points(rect, values, 0.0, max);
+        painter.add(egui::Shape::line(points, Stroke::new(1.5, color)));
+    }
+    let font = egui::FontId::proportional(10.0);
+    painter.text(
+        rect.left_top() + Vec2::new(4.0, 2.0),
+        egui::Align2::LEFT_TOP,
+        format!("{} GB/s", significant(max, 3)),
+        font.clone(),
+        axis
+    );
+    painter.text(
+        rect.left_bottom() + Vec2::new(4.0, -2.0),
+        egui::Align2::LEFT_BOTTOM,
+        ramp::size_label(first.bytes),
+        font.clone(),
+        axis
+    );
+    painter.text(
+        rect.right_bottom() + Vec2::new(-4.0, -2.0),
+        egui::Align2::RIGHT_BOTTOM,
+        ramp::size_label(last.bytes),
+        font,
+        axis
+    );
+}
+
+/// Throughput of both directions against transfer size as an egui_plot line chart, which can
+/// be zoomed and dragged, and reset with a double click. The X axis is in doublings of the
+/// size, labelled in bytes, so that every size of the sweep is evenly spaced; a dashed line
+/// marks the size from which each direction is saturated. Nothing is drawn before the sweep
+/// has two sizes.
+pub fn sweep_chart(ui: &mut egui::Ui, sweep: &SweepResult, palette: Palette) {
+    if sweep.points.len() < 2 {
+        return;
+    }
+    let (h2d_color, d2h_color) = palette.colors();
+    let x_of = |bytes: u64| (bytes as f64).log2();
+    let h2d: Vec<[f64; 2]> = sweep.points
+        .iter()
+        .map(|point| [x_of(point.bytes), point.h2d])
+        .collect();
+    let d2h: Vec<[f64; 2]> = sweep.points
+        .iter()
+        .filter_map(|point| point.d2h.map(|d2h| [x_of(point.bytes), d2h]))
+        .collect();
+    let (h2d_knee, d2h_knee) = sweep.saturation();
+
+    Plot::new("sweep_chart")
+        .height(180.0)
+        .legend(Legend::default())
+        .x_axis_label("Transfer size")
+        .y_axis_label("GB/s")
+        .include_y(0.0)
+        // One mark per doubling, thinned out to every second or fourth when zoomed out
+        .x_grid_spacer(uniform_grid_spacer(|_| [1.0, 2.0, 4.0]))
+        .x_axis_formatter(|mark, _| size_at(mark.value).unwrap_or_default())
+        .label_formatter(|name, value| {
+            let size = size_at(value.x.round()).unwrap_or_default();
+            if name.is_empty() {
+                size
+            } else {
+                format!("{}\n{}: {:.2} GB/s", name, size, value.y)
+            }
+        })
+        .show(ui, |plot_ui| {
+            plot_ui.line(Line::new(PlotPoints::from(h2d)).name("Host to Device").color(h2d_color));
+            if !d2h.is_empty() {
+                plot_ui.line(
+                    Line::new(PlotPoints::from(d2h)).name("Device to Host").color(d2h_color)
+                );
+            }
+            for (knee, color) in [(h2d_knee, h2d_color), (d2h_knee, d2h_color)] {
+                if let Some(bytes) = knee {
+                    let knee = VLine::new(x_of(bytes)).color(color);
+                    plot_ui.vline(knee.style(LineStyle::dashed_loose()));
+                }
+            }
+        });
+}
+
+/// The size at `log2` doublings of a byte, for whole doublings within the sweep's range.
+fn size_at(log2: f64) -> Option<String> {
+    let range = (sweep::START_BYTES as f64).log2()..=(sweep::END_BYTES as f64).log2();
+    (log2.fract() == 0.0 && range.contains(&log2)).then(|| ramp::size_label(1u64 << (log2 as u32)))
+}
+
+/// Throughput of both directions against the offset of the transfer, placed by offset so that
+/// dips line up with the aperture boundaries they fall on, from zero GB/s.
+pub fn offset_curve(ui: &mut egui::Ui, points: &[OffsetPoint], palette: Palette) {
+    let (h2d_color, d2h_color) = palette.colors();
+    ui.horizontal(|ui| {
+        ui.colored_label(h2d_color, "■ Host to Device");
+        ui.colored_label(d2h_color, "■ Device to Host");
+    });
+
+    let size = Vec2::new(ui.available_width(), 100.0);
+    let (rect, _) = ui.allocate_exact_size(size, Sense::hover());
+    let painter = ui.painter_at(rect);
+    let axis = ui.visuals().weak_text_color();
+    painter.rect_stroke(rect, 0.0, Stroke::new(1.0, axis));
+
+    let h2d: Vec<f64> = points.iter().map(OffsetPoint::h2d_throughput).collect();
+    let d2h: Vec<f64> = points.iter().map(OffsetPoint::d2h_throughput).collect();
+    let max = h2d.iter().chain(&d2h).copied().fold(0.0, f64::max);
+    let (Some(first), Some(last)) = (points.first(), points.last()) else {
+        return;
+    };
+    if max <= 0.0 || points.len() < 2 {
+        return;
+    }
+    let span = (last.offset - first.offset).max(1) as f32;
+    for (values, color) in [(&h2d, h2d_color), (&d2h, d2h_color)] {
+        let line = points
+            .iter()
+            .zip(values)
+            .map(|(point, &value)| {
+                let x = ((point.offset - first.offset) as f32) / span;
+                let y = (value / max) as f32;
+                Pos2::new(rect.left() + x * rect.width(), rect.bottom() - y * rect.height())
+            })
+            .collect();
+        painter.add(egui::Shape::line(line, Stroke::new(1.5, color)));
+    }
+    let font = egui::FontId::proportional(10.0);
+    painter.text(
+        rect.left_top() + Vec2::new(4.0, 2.0),
+        egui::Align2::LEFT_TOP,
+        format!("{} GB/s", significant(max, 3)),
+        font.clone(),
+        axis
+    );
+    painter.text(
+        rect.left_bottom() + Vec2::new(4.0, -2.0),
+        egui::Align2::LEFT_BOTTOM,
+        offsets::offset_label(first.offset),
+        font.clone(),
+        axis
+    );
+    painter.text(
+        rect.right_bottom() + Vec2::new(-4.0, -2.0),
+        egui::Align2::RIGHT_BOTTOM,
+        offsets::offset_label(last.offset),
+        font,
+        axis
+    );
+}
+
+/// Per-sample latency in µs over time, from zero to the slowest sample, with dashed guides at
+/// the median `p50` and at `p99` so that spikes stand out from the typical round trip.
+pub fn latency_chart(ui: &mut egui::Ui, micros: &[f64], p50: f64, p99: f64, color: Color32) {
+    let size = Vec2::new(ui.available_width(), 100.0);
+    let (rect, _) = ui.allocate_exact_size(size, Sense::hover());
+    let painter = ui.painter_at(rect);
+    let axis = ui.visuals().weak_text_color();
+    painter.rect_stroke(rect, 0.0, Stroke::new(1.0, axis));
+
+    let max = micros.iter().copied().fold(0.0, f64::max);
+    if max <= 0.0 || micros.len() < 2 {
+        return;
+    }
+    painter.add(egui::Shape::line(line_points(rect, micros, 0.0, max), Stroke::new(1.0, color)));
+    for (label, value) in [("p50", p50), ("p99", p99)] {
+        let y = rect.bottom() - ((value / max) as f32) * rect.height();
+        painter.add(
+            egui::Shape::dashed_line(
+                &[Pos2::new(rect.left(), y), Pos2::new(rect.right(), y)],
+                Stroke::new(1.0, axis),
+                4.0,
+                3.0
+            )
+        );
+        painter.text(
+            Pos2::new(rect.right() - 4.0, y - 1.0),
+            egui::Align2::RIGHT_BOTTOM,
+            format!("{} {} µs", label, significant(value, 3)),
+            egui::FontId::proportional(10.0),
+            axis
+        );
+    }
+    painter.text(
+        rect.left_top() + Vec2::new(4.0, 2.0),
+        egui::Align2::LEFT_TOP,
+        format!("{} µs", significant(max, 3)),
+        egui::FontId::proportional(10.0),
+        axis
+    );
+}
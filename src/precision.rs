@@ -0,0 +1,131 @@
+//! Rounding of reported values to the precision the measurement supports. A value with a
+//! known uncertainty is printed to the decimal place of that uncertainty, one without to a
+//! fixed number of significant figures.
+
+use std::fmt;
+
+/// Significant figures shown for a value whose uncertainty is unknown, such as a single run.
+const DEFAULT_FIGURES: usize = 3;
+
+/// A measured value and, when it was sampled more than once, the standard error of its mean.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Measurement {
+    pub value: f64,
+    pub uncertainty: Option<f64>,
+}
+
+impl Measurement {
+    pub fn exact(value: f64) -> Self {
+        Measurement { value, uncertainty: None }
+    }
+
+    /// `value` with the uncertainty estimated from the spread of per-iteration `samples`.
+    /// Non-finite samples are ignored and fewer than two leave the uncertainty unknown.
+    pub fn of_samples(value: f64, samples: &[f64]) -> Self {
+        let finite: Vec<f64> = samples
+            .iter()
+            .copied()
+            .filter(|sample| sample.is_finite())
+            .collect();
+        if finite.len() < 2 {
+            return Measurement::exact(value);
+        }
+        let count = finite.len() as f64;
+        let mean = finite.iter().sum::<f64>() / count;
+        let variance =
+            finite
+                .iter()
+                .map(|sample| (sample - mean).powi(2))
+                .sum::<f64>() / (count - 1.0);
+        Measurement { value, uncertainty: Some((variance / count).sqrt()) }
+    }
+
+    /// The value and its uncertainty multiplied by `factor`, as when changing units.
+    pub fn scaled(self, factor: f64) -> Self {
+        Measurement {
+            value: self.value * factor,
+            uncertainty: self.uncertainty.map(|uncertainty| uncertainty * factor),
+        }
+    }
+}
+
+impl fmt::Display for Measurement {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.uncertainty {
+            Some(uncertainty) if uncertainty > 0.0 && uncertainty.is_finite() => {
+                // Two figures when the uncertainty starts with a 1, where rounding to one
+                // would change it by up to half, otherwise one.
+                let magnitude = uncertainty.log10().floor();
+                let leading = uncertainty / (10f64).powf(magnitude);
+                let figures = if leading < 2.0 { 2 } else { 1 };
+                let decimals = decimals_for(uncertainty, figures);
+                write!(f, "{:.*} ± {:.*}", decimals, self.value, decimals, uncertainty)
+            }
+            _ => f.write_str(&significant(self.value, DEFAULT_FIGURES)),
+        }
+    }
+}
+
+/// `value` rounded to `figures` significant figures, without an exponent.
+pub fn significant(value: f64, figures: usize) -> String {
+    if value == 0.0 || !value.is_finite() {
+        return format!("{}", value);
+    }
+    format!("{:.*}", decimals_for(value, figures), value)
+}
+
+/// Decimal places that leave `figures` significant figures of `value`.
+fn decimals_for(value: f64, figures: usize) -> usize {
+    let magnitude = |value: f64| value.abs().log10().floor() as i64;
+    let decimals = (figures as i64 - 1 - magnitude(value)).max(0);
+    // Rounding can carry into a new leading digit, as 9.996 does to 10.0.
+    let scale = (10f64).powi(decimals as i32);
+    let rounded = (value * scale).round() / scale;
+    if magnitude(rounded) > magnitude(value) {
+        (decimals - 1).max(0) as usize
+    } else {
+        decimals as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn significant_figures() {
+        assert_eq!(significant(24.6123, 3), "24.6");
+        assert_eq!(significant(0.012345, 3), "0.0123");
+        assert_eq!(significant(1234.6, 3), "1235");
+        assert_eq!(significant(9.996, 3), "10.0");
+        assert_eq!(significant(0.0, 3), "0");
+    }
+
+    #[test]
+    fn rounded_to_the_uncertainty() {
+        let measurement = |uncertainty| Measurement {
+            value: 24.6123,
+            uncertainty: Some(uncertainty),
+        };
+        assert_eq!(measurement(0.3).to_string(), "24.6 ± 0.3");
+        assert_eq!(measurement(0.013).to_string(), "24.612 ± 0.013");
+        assert_eq!(measurement(2.6).to_string(), "25 ± 3");
+    }
+
+    #[test]
+    fn unknown_uncertainty() {
+        assert_eq!(Measurement::exact(24.6123).to_string(), "24.6");
+        assert_eq!(Measurement::of_samples(24.0, &[24.0]), Measurement::exact(24.0));
+        let zero = Measurement { value: 24.6123, uncertainty: Some(0.0) };
+        assert_eq!(zero.to_string(), "24.6");
+    }
+
+    #[test]
+    fn standard_error_of_the_samples() {
+        let measurement = Measurement::of_samples(2.0, &[1.0, 2.0, 3.0, f64::NAN]);
+        assert!((measurement.uncertainty.unwrap() - 1.0 / (3.0f64).sqrt()).abs() < 1e-12);
+        let scaled = measurement.scaled(2.0);
+        assert_eq!(scaled.value, 4.0);
+        assert!((scaled.uncertainty.unwrap() - 2.0 / (3.0f64).sqrt()).abs() < 1e-12);
+    }
+}
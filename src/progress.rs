@@ -0,0 +1,153 @@
+//! The headless mode's progress bar. indicatif draws it on stderr, and only when that is a
+//! terminal, so piped output stays clean even without `--quiet`. Builds without the progress
+//! feature have no bar; `CliProgress` and `MatrixProgress` then report nothing.
+
+use gputhroughput::api::ProgressSink;
+use gputhroughput::MeasureConfig;
+#[cfg(feature = "progress")]
+use gputhroughput::api::{ MeasurementRecord, Phase };
+#[cfg(feature = "progress")]
+use gputhroughput::RunLength;
+#[cfg(feature = "progress")]
+use indicatif::{ ProgressBar, ProgressFinish, ProgressStyle };
+#[cfg(feature = "progress")]
+use std::ops::ControlFlow;
+#[cfg(feature = "progress")]
+use std::time::{ Duration, Instant };
+
+#[cfg(feature = "progress")]
+pub struct CliProgress {
+    bar: ProgressBar,
+    /// Time-limited runs count milliseconds since the measurement started, not iterations.
+    timed: bool,
+    started: Option<Instant>,
+}
+
+#[cfg(feature = "progress")]
+impl CliProgress {
+    /// A bar with an ETA where the length of the run is known up front, otherwise a spinner.
+    pub fn new(config: &MeasureConfig) -> CliProgress {
+        let (bar, template, timed) = match config.length {
+            RunLength::Time(limit) =>
+                (
+                    ProgressBar::new(limit.as_millis() as u64),
+                    "{spinner} {prefix} [{bar:30}] {elapsed} of {duration} {msg}",
+                    true,
+                ),
+            length =>
+                match length.planned_bytes(config.iteration_bytes()) {
+                    Some(bytes) =>
+                        (
+                            ProgressBar::new(bytes / config.iteration_bytes()),
+                            "{spinner} {prefix} [{bar:30}] {pos}/{len} iterations, ETA {eta} {msg}",
+                            false,
+                        ),
+                    None => {
+                        let template = "{spinner} {prefix} {pos} iterations {msg}";
+                        (ProgressBar::new_spinner(), template, false)
+                    }
+                }
+        };
+        let style = ProgressStyle::with_template(template)
+            .expect("the templates are valid")
+            .progress_chars("=> ");
+        let bar = bar.with_style(style).with_finish(ProgressFinish::AndClear);
+        bar.enable_steady_tick(Duration::from_millis(100));
+        CliProgress { bar, timed, started: None }
+    }
+}
+
+#[cfg(feature = "progress")]
+impl ProgressSink for CliProgress {
+    fn on_sample(&mut self, h2d: f64, d2h: f64) -> ControlFlow<()> {
+        if !self.timed {
+            self.bar.inc(1);
+        } else if let Some(started) = self.started {
+            self.bar.set_position(started.elapsed().as_millis() as u64);
+        }
+        self.bar.set_message(if d2h.is_nan() {
+            format!("{:.2} GB/s H2D", h2d)
+        } else {
+            format!("{:.2} GB/s H2D, {:.2} GB/s D2H", h2d, d2h)
+        });
+        ControlFlow::Continue(())
+    }
+
+    fn on_phase_change(&mut self, phase: Phase) {
+        match phase {
+            Phase::Measuring => {
+                // Also reported again after every pause between iterations
+                self.started.get_or_insert_with(Instant::now);
+            }
+            Phase::Retrying => {
+                // The retry starts the run over
+                self.bar.reset();
+                self.started = None;
+            }
+            _ => {}
+        }
+        self.bar.set_prefix(phase.to_string());
+    }
+
+    fn on_complete(&mut self, _: &MeasurementRecord) {
+        self.bar.finish_and_clear();
+    }
+}
+
+#[cfg(not(feature = "progress"))]
+pub struct CliProgress;
+
+#[cfg(not(feature = "progress"))]
+impl CliProgress {
+    pub fn new(_: &MeasureConfig) -> CliProgress {
+        CliProgress
+    }
+}
+
+#[cfg(not(feature = "progress"))]
+impl ProgressSink for CliProgress {}
+
+/// A bar counting the combinations of a matrix run, see `matrix`.
+pub struct MatrixProgress {
+    #[cfg(feature = "progress")]
+    bar: ProgressBar,
+}
+
+impl MatrixProgress {
+    /// Hidden when `quiet`.
+    #[cfg(feature = "progress")]
+    pub fn new(cells: usize, quiet: bool) -> MatrixProgress {
+        if quiet {
+            return MatrixProgress { bar: ProgressBar::hidden() };
+        }
+        let style = ProgressStyle::with_template(
+            "{spinner} [{bar:30}] {pos}/{len} combinations, ETA {eta} {msg}"
+        )
+            .expect("the template is valid")
+            .progress_chars("=> ");
+        let bar = ProgressBar::new(cells as u64).with_style(style);
+        bar.enable_steady_tick(Duration::from_millis(100));
+        MatrixProgress { bar }
+    }
+
+    #[cfg(not(feature = "progress"))]
+    pub fn new(_: usize, _: bool) -> MatrixProgress {
+        MatrixProgress {}
+    }
+
+    /// Counts one more combination done, the one named by `message`.
+    pub fn advance(&self, message: String) {
+        #[cfg(feature = "progress")]
+        {
+            self.bar.inc(1);
+            self.bar.set_message(message);
+        }
+        #[cfg(not(feature = "progress"))]
+        drop(message);
+    }
+
+    pub fn finish(&self) {
+        #[cfg(feature = "progress")]
+        self.bar.finish_and_clear();
+    }
+}
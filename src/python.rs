@@ -0,0 +1,187 @@
+//! The `gputhroughput` Python module, for logging PCIe bandwidth from training scripts:
+//!
+//! ```python
+//! import gputhroughput
+//! print(gputhroughput.list_devices())
+//! result = gputhroughput.benchmark({"device": 0, "size_mb": 256, "iterations": 10})
+//! h2d = numpy.asarray(result["h2d_samples"])
+//! ```
+
+// The `#[pyfunction]` expansion in pyo3 0.22 converts `PyErr` into itself
+#![allow(clippy::useless_conversion)]
+
+use crate::api::{ self, BenchmarkRequest };
+use crate::error::BenchError;
+use crate::memory::Memory;
+use crate::partition::Partition;
+use crate::telemetry::Sensors;
+use crate::{
+    elements_in,
+    enumerate_devices,
+    HostBuffer,
+    MeasureConfig,
+    Pacing,
+    RunLength,
+    SizeUnits,
+    Verification,
+};
+use pyo3::exceptions::{ PyRuntimeError, PyValueError };
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use std::time::{ Duration, SystemTime, UNIX_EPOCH };
+
+/// One dict per OpenCL GPU device, with its `index` for `benchmark` and its `name`.
+#[pyfunction]
+fn list_devices(py: Python<'_>) -> PyResult<Vec<Bound<'_, PyDict>>> {
+    enumerate_devices()
+        .iter()
+        .enumerate()
+        .map(|(index, device)| {
+            let entry = PyDict::new_bound(py);
+            entry.set_item("index", index)?;
+            entry.set_item("name", device.name())?;
+            entry.set_item("extensions", device.extensions())?;
+            Ok(entry)
+        })
+        .collect()
+}
+
+/// Measures one device. `config` may set `device` (index, default 0), `size_mb` (default
+/// 1024), `units` ("decimal", the default, or "binary" for `size_mb` in MiB), `iterations`
+/// (default 1), `host_buffer` ("reuse" or "fresh"), `memory` (as for `--memory`), `delay_ms`,
+/// `max_temperature`, `gentle`, `verify`, `warm_up_ms` and `discard` (as for `--delay`,
+/// `--max-temp`, `--gentle`, `--verify`, `--warm-up` and `--discard`). Returns the mean
+/// throughput in GB/s, the durations in seconds and the per-iteration samples as lists, ready
+/// for `numpy.asarray`; the device-to-host samples are empty when `verify` is "checksum".
+/// `started` and `finished` are wall-clock seconds since the Unix epoch and `elapsed_seconds`
+/// the monotonic time between them. `start_clock_mhz` and `max_clock_mhz` are None where the
+/// driver does not report clocks.
+#[pyfunction]
+#[pyo3(signature = (config = None))]
+fn benchmark<'py>(
+    py: Python<'py>,
+    config: Option<&Bound<'py, PyDict>>
+) -> PyResult<Bound<'py, PyDict>> {
+    let option = |key: &str| -> PyResult<Option<Bound<'py, PyAny>>> {
+        match config {
+            Some(config) => config.get_item(key),
+            None => Ok(None),
+        }
+    };
+    let index: usize = option("device")?.map_or(Ok(0), |value| value.extract())?;
+    let size: usize = option("size_mb")?.map_or(Ok(1024), |value| value.extract())?;
+    let units = match option("units")? {
+        Some(value) => value.extract::<String>()?.parse().map_err(PyValueError::new_err)?,
+        None => SizeUnits::Decimal,
+    };
+    let iterations: usize = option("iterations")?.map_or(Ok(1), |value| value.extract())?;
+    let host_buffer = match option("host_buffer")? {
+        Some(value) => value.extract::<String>()?.parse().map_err(PyValueError::new_err)?,
+        None => HostBuffer::Reuse,
+    };
+    let memory = match option("memory")? {
+        Some(value) => value.extract::<String>()?.parse().map_err(PyValueError::new_err)?,
+        None => Memory::Buffer,
+    };
+    let pacing = Pacing {
+        delay: Duration::from_millis(option("delay_ms")?.map_or(Ok(0), |value| value.extract())?),
+        max_temperature: option("max_temperature")?.map(|value| value.extract()).transpose()?,
+        duty: option("gentle")?
+            .map(|value| value.extract::<f64>())
+            .transpose()?
+            .map(|percent| percent / 100.0),
+    };
+    if pacing.duty.is_some_and(|duty| !(0.01..=0.99).contains(&duty)) {
+        return Err(PyValueError::new_err("gentle must be a percentage from 1 to 99"));
+    }
+    let verification = match option("verify")? {
+        Some(value) => value.extract::<String>()?.parse().map_err(PyValueError::new_err)?,
+        None => Verification::ReadBack,
+    };
+    let warm_up = Duration::from_millis(
+        option("warm_up_ms")?.map_or(Ok(0), |value| value.extract())?
+    );
+    let discard: usize = option("discard")?.map_or(Ok(0), |value| value.extract())?;
+    if size == 0 || iterations == 0 {
+        return Err(PyValueError::new_err("size_mb and iterations must be at least 1"));
+    }
+
+    let device = enumerate_devices()
+        .into_iter()
+        .nth(index)
+        .ok_or_else(|| PyValueError::new_err(format!("there is no device {}", index)))?;
+    let request = BenchmarkRequest {
+        device,
+        partition: Partition::None,
+        sub_device: 0,
+        link_gen: None,
+        config: MeasureConfig {
+            data_size: elements_in(size, units)
+                .map_err(|e| PyValueError::new_err(e.to_string()))?,
+            length: RunLength::Iterations(iterations),
+            host_buffer,
+            memory,
+            pacing,
+            verification,
+            warm_up,
+            discard,
+            sensors: Sensors::default(),
+            units,
+        },
+        threads: None,
+        thread_mapping: false,
+        patterns: false,
+        latency: false,
+        completion: false,
+        ramp: None,
+        offsets: None,
+        scatter: None,
+        inflight: false,
+        contention: false,
+        round_trip: false,
+        random_sizes: None,
+        peer: None,
+        stream: None,
+        dma_buf: None,
+        overhead: false,
+    };
+    // Other Python threads keep running while the transfers do
+    let record = py
+        .allow_threads(|| api::execute(&request, &mut ()))
+        .map_err(|e: BenchError| PyRuntimeError::new_err(e.to_string()))?;
+
+    let throughput = &record.throughput;
+    let result = PyDict::new_bound(py);
+    result.set_item("device", &record.device)?;
+    let since_epoch = |time: SystemTime| {
+        time.duration_since(UNIX_EPOCH).map_or(0.0, |since| since.as_secs_f64())
+    };
+    result.set_item("started", since_epoch(record.started))?;
+    result.set_item("finished", since_epoch(record.finished))?;
+    result.set_item("elapsed_seconds", record.elapsed.as_secs_f64())?;
+    result.set_item("h2d_gbps", throughput.h2d_throughput)?;
+    result.set_item("d2h_gbps", throughput.d2h_throughput)?;
+    result.set_item("h2d_seconds", throughput.h2d_duration)?;
+    result.set_item("d2h_seconds", throughput.d2h_duration)?;
+    result.set_item("h2d_samples", &throughput.h2d_samples)?;
+    result.set_item("d2h_samples", &throughput.d2h_samples)?;
+    result.set_item("device_reset", throughput.device_reset())?;
+    let resets: Vec<u64> = throughput.resets
+        .iter()
+        .filter_map(|time| time.duration_since(UNIX_EPOCH).ok())
+        .map(|since| since.as_secs())
+        .collect();
+    result.set_item("resets", resets)?;
+    result.set_item("ecc", throughput.telemetry.ecc)?;
+    let state = throughput.start_state;
+    result.set_item("start_clock_mhz", state.map(|state| state.clock_mhz))?;
+    result.set_item("max_clock_mhz", state.map(|state| state.max_clock_mhz))?;
+    Ok(result)
+}
+
+#[pymodule]
+fn gputhroughput(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_function(wrap_pyfunction!(list_devices, module)?)?;
+    module.add_function(wrap_pyfunction!(benchmark, module)?)?;
+    Ok(())
+}
@@ -0,0 +1,151 @@
+//! Throughput as the transfer size doubles, from a few kilobytes up to the size at which a
+//! single transfer takes long enough to time reliably. Below that, the clock's resolution
+//! and the fixed cost of submitting a transfer make up much of every sample, so the curve
+//! shows both where small transfers stop being meaningful and how large they need to be.
+
+use crate::device::default_queue;
+use crate::error::BenchError;
+use crate::registry::DeviceRegistry;
+use opencl3::device::Device;
+use opencl3::memory::{ Buffer, CL_MEM_READ_WRITE };
+use opencl3::types::CL_BLOCKING;
+use std::ptr;
+use std::time::{ Duration, Instant };
+
+/// Size of the first transfer.
+pub const START_BYTES: u64 = 4 * 1024;
+/// Transfers each way per size; their median is reported, so one slow outlier does not end
+/// the ramp early.
+pub const SAMPLES_PER_SIZE: usize = 5;
+/// Time per transfer the ramp stops at, where none is given.
+pub const DEFAULT_BUDGET: Duration = Duration::from_millis(10);
+
+/// One size of the ramp.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RampPoint {
+    pub bytes: u64,
+    /// Median time of a single transfer in each direction.
+    pub h2d: Duration,
+    pub d2h: Duration,
+}
+
+impl RampPoint {
+    /// GB/s host to device.
+    pub fn h2d_throughput(&self) -> f64 {
+        (self.bytes as f64) / self.h2d.as_secs_f64() / 1e9
+    }
+
+    /// GB/s device to host.
+    pub fn d2h_throughput(&self) -> f64 {
+        (self.bytes as f64) / self.d2h.as_secs_f64() / 1e9
+    }
+
+    /// The faster direction's time, which the ramp waits on.
+    fn shortest(&self) -> Duration {
+        self.h2d.min(self.d2h)
+    }
+}
+
+/// Throughput at every size, see `measure_ramp`.
+#[derive(Clone, Debug)]
+pub struct RampResult {
+    /// Time each sample had to take for the ramp to stop.
+    pub budget: Duration,
+    /// In order of size.
+    pub points: Vec<RampPoint>,
+}
+
+impl RampResult {
+    /// Whether the last size reached `budget`, rather than the ramp stopping at the device's
+    /// largest allocation.
+    pub fn reached_budget(&self) -> bool {
+        self.points.last().is_some_and(|point| point.shortest() >= self.budget)
+    }
+
+    /// One line per size, then the verdict.
+    pub fn summary(&self) -> Vec<String> {
+        let mut lines: Vec<String> = self.points
+            .iter()
+            .map(|point| {
+                format!(
+                    "{}: {:.2} GB/s H2D, {:.2} GB/s D2H ({:.3} ms per transfer)",
+                    size_label(point.bytes),
+                    point.h2d_throughput(),
+                    point.d2h_throughput(),
+                    point.shortest().as_secs_f64() * 1e3
+                )
+            })
+            .collect();
+        let budget = self.budget.as_millis();
+        let last = self.points.last().map_or(0, |point| point.bytes);
+        lines.push(
+            if self.reached_budget() {
+                format!(
+                    "Transfers take at least {} ms from {}; timer resolution and per-transfer \
+                     overhead weigh on smaller sizes",
+                    budget,
+                    size_label(last)
+                )
+            } else {
+                format!(
+                    "Stopped at {}, the device's largest allocation, before transfers took {} ms",
+                    size_label(last),
+                    budget
+                )
+            }
+        );
+        lines
+    }
+}
+
+/// `bytes` in the largest binary unit that divides it, e.g. "4 KB" or "256 MB".
+pub fn size_label(bytes: u64) -> String {
+    match bytes {
+        bytes if bytes >= 1 << 30 && bytes % (1 << 30) == 0 => format!("{} GB", bytes >> 30),
+        bytes if bytes >= 1 << 20 && bytes % (1 << 20) == 0 => format!("{} MB", bytes >> 20),
+        bytes if bytes >= 1 << 10 && bytes % (1 << 10) == 0 => format!("{} KB", bytes >> 10),
+        bytes => format!("{} B", bytes),
+    }
+}
+
+/// Doubles the transfer size from `START_BYTES` until the median transfer in both directions
+/// takes at least `budget`, or the next size would exceed the device's largest allocation.
+pub fn measure_ramp(device: &Device, budget: Duration) -> Result<RampResult, BenchError> {
+    let context = DeviceRegistry::global().context(device)?;
+    let queue = default_queue(&context, 0)?;
+    let max_alloc = device.max_mem_alloc_size()?;
+
+    let median = |mut times: Vec<Duration>| {
+        times.sort();
+        times[times.len() / 2]
+    };
+    let mut points = Vec::new();
+    let mut bytes = START_BYTES;
+    while bytes <= max_alloc {
+        let mut buffer = unsafe {
+            Buffer::<u8>::create(&context, CL_MEM_READ_WRITE, bytes as usize, ptr::null_mut())?
+        };
+        let mut host: Vec<u8> = (0..bytes).map(|index| index as u8).collect();
+        let mut h2d = Vec::with_capacity(SAMPLES_PER_SIZE);
+        let mut d2h = Vec::with_capacity(SAMPLES_PER_SIZE);
+        for _ in 0..SAMPLES_PER_SIZE {
+            let start = Instant::now();
+            unsafe {
+                queue.enqueue_write_buffer(&mut buffer, CL_BLOCKING, 0, &host, &[])?;
+            }
+            h2d.push(start.elapsed());
+            let start = Instant::now();
+            unsafe {
+                queue.enqueue_read_buffer(&buffer, CL_BLOCKING, 0, &mut host, &[])?;
+            }
+            d2h.push(start.elapsed());
+        }
+        let point = RampPoint { bytes, h2d: median(h2d), d2h: median(d2h) };
+        points.push(point);
+        if point.shortest() >= budget {
+            break;
+        }
+        bytes *= 2;
+    }
+    Ok(RampResult { budget, points })
+}
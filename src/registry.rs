@@ -0,0 +1,102 @@
+//! The GPUs on the system, enumerated once per process, and one OpenCL context per device,
+//! created on its first measurement and reused by every one after it. The GUI, the headless
+//! mode, the C ABI and the Python module all go through the same registry, so neither the
+//! platform query nor context creation, which some drivers take hundreds of milliseconds
+//! over, is repeated from one run to the next.
+//!
+//! Some embedded stacks, e.g. for Mali and Adreno, list their GPU as a default or accelerator
+//! device rather than a GPU; where no platform reports a GPU, those are looked through for one.
+
+use crate::{ MyDevice, Vendor };
+use opencl3::context::Context;
+use opencl3::device::{
+    get_all_devices,
+    Device,
+    CL_DEVICE_TYPE_ALL,
+    CL_DEVICE_TYPE_CPU,
+    CL_DEVICE_TYPE_GPU,
+};
+use opencl3::error_codes::ClError;
+use std::collections::HashMap;
+use std::sync::{ Arc, Mutex, OnceLock };
+
+/// Parts of device names that mark a GPU listed under another device type, lowercase.
+const GPU_NAMES: [&str; 6] = ["mali", "adreno", "powervr", "vivante", "videocore", "gpu"];
+
+pub struct DeviceRegistry {
+    devices: Vec<MyDevice>,
+    /// By `MyDevice::key`, i.e. the device id.
+    contexts: Mutex<HashMap<usize, Arc<Context>>>,
+}
+
+impl DeviceRegistry {
+    /// The process-wide registry, enumerating the GPUs on first use.
+    pub fn global() -> &'static DeviceRegistry {
+        static REGISTRY: OnceLock<DeviceRegistry> = OnceLock::new();
+        REGISTRY.get_or_init(|| DeviceRegistry {
+            devices: find_devices(),
+            contexts: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// Every GPU found, in the order the platforms list them.
+    pub fn devices(&self) -> &[MyDevice] {
+        &self.devices
+    }
+
+    /// The context of `device`, created now if this is its first use. Only the devices the
+    /// registry enumerated are cached; sub-devices are partitioned afresh for every run and
+    /// their handles may be reused by the driver for other sub-devices, so each gets a context
+    /// of its own that is dropped with the run.
+    pub fn context(&self, device: &Device) -> Result<Arc<Context>, ClError> {
+        let key = device.id() as usize;
+        if !self.devices.iter().any(|known| known.key() == key) {
+            return Ok(Arc::new(Context::from_device(device)?));
+        }
+        let mut contexts = self.contexts.lock().unwrap();
+        if let Some(context) = contexts.get(&key) {
+            return Ok(Arc::clone(context));
+        }
+        let context = Arc::new(Context::from_device(device)?);
+        contexts.insert(key, Arc::clone(&context));
+        Ok(context)
+    }
+
+    /// Drops the cached context of `device`, e.g. after a reset left it unusable, so that the
+    /// next `context` creates a fresh one. Measurements still holding it keep it until done.
+    pub fn forget_context(&self, device: &Device) {
+        self.contexts.lock().unwrap().remove(&(device.id() as usize));
+    }
+}
+
+/// The GPUs of every platform, or where none reports one, the devices of other types that look
+/// like GPUs, see `looks_like_gpu`.
+fn find_devices() -> Vec<MyDevice> {
+    let gpus = get_all_devices(CL_DEVICE_TYPE_GPU).unwrap_or_default();
+    let ids = if gpus.is_empty() {
+        get_all_devices(CL_DEVICE_TYPE_ALL)
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|&id| looks_like_gpu(&Device::new(id)))
+            .collect()
+    } else {
+        gpus
+    };
+    ids.into_iter().map(MyDevice::new).collect()
+}
+
+/// Whether a device listed under some other type is a GPU: never a CPU, and otherwise made
+/// by a vendor that only ships GPUs through OpenCL or named after a GPU family.
+fn looks_like_gpu(device: &Device) -> bool {
+    let device_type = device.dev_type().unwrap_or_default();
+    if device_type & CL_DEVICE_TYPE_CPU != 0 {
+        return false;
+    }
+    if device_type & CL_DEVICE_TYPE_GPU != 0 {
+        return true;
+    }
+    let vendor = Vendor::from_id(device.vendor_id().unwrap_or_default());
+    let name = device.name().unwrap_or_default().to_lowercase();
+    matches!(vendor, Vendor::Arm | Vendor::Qualcomm) ||
+        GPU_NAMES.iter().any(|gpu| name.contains(gpu))
+}
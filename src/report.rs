@@ -0,0 +1,113 @@
+//! Reading a finished run: how its iterations spread, when it was interrupted, and which PCIe
+//! link its throughput suggests.
+
+use crate::statistics::Statistics;
+use crate::Throughput;
+use std::collections::HashMap;
+use std::time::{ SystemTime, UNIX_EPOCH };
+
+impl Throughput {
+    /// Spread of the per-iteration host to device throughput, `None` for a single iteration.
+    /// Covers the iterations kept in the samples, the latest ones of a long soak run.
+    pub fn h2d_statistics(&self) -> Option<Statistics> {
+        Statistics::of(&self.h2d_samples)
+    }
+
+    pub fn d2h_statistics(&self) -> Option<Statistics> {
+        Statistics::of(&self.d2h_samples)
+    }
+
+    /// How often and when the device was reset, e.g. "The device was reset 2 times during
+    /// the run, at 14:03:12 and 14:20:45 UTC; results are from the attempt after the last".
+    pub fn reset_summary(&self) -> Option<String> {
+        let times: Vec<String> = self.resets.iter().copied().map(clock_time).collect();
+        Some(
+            format!(
+                "The device was reset {} during the run, at {} UTC; results are from the attempt \
+                 after the last",
+                times_label(times.len()),
+                list(&times)?
+            )
+        )
+    }
+
+    /// How often and how long the system slept, e.g. "The system slept once during the run,
+    /// for 182 minutes until 07:02:11 UTC; the iteration it interrupted was dropped".
+    pub fn suspend_summary(&self) -> Option<String> {
+        let sleeps: Vec<String> = self.suspends
+            .iter()
+            .map(|suspend| {
+                let seconds = suspend.slept.as_secs();
+                let slept = if seconds < 120 {
+                    format!("{} seconds", seconds)
+                } else {
+                    format!("{} minutes", seconds / 60)
+                };
+                format!("{} until {}", slept, clock_time(suspend.resumed))
+            })
+            .collect();
+        Some(
+            format!(
+                "The system slept {} during the run, for {} UTC; the {} it interrupted {} dropped",
+                times_label(sleeps.len()),
+                list(&sleeps)?,
+                if sleeps.len() == 1 { "iteration" } else { "iterations" },
+                if sleeps.len() == 1 { "was" } else { "were" }
+            )
+        )
+    }
+
+    pub fn approximate_link_speed(&self) -> (i32, Vec<&'static str>) {
+        let rounded_avg_throughput = self.mean_throughput().round() as i32;
+
+        let pcie_speeds: HashMap<i32, Vec<&str>> = [
+            (1, vec!["PCIe 1.0 x4", "PCIe 2.0 x2", "PCIe 3.0 x1"]),
+            (2, vec!["PCIe 1.0 x8", "PCIe 2.0 x4", "PCIe 3.0 x2", "PCIe 4.0 x1"]),
+            (4, vec!["PCIe 1.0 x16", "PCIe 2.0 x8", "PCIe 3.0 x4", "PCIe 4.0 x2", "PCIe 5.0 x1"]),
+            (8, vec!["PCIe 2.0 x16", "PCIe 3.0 x8", "PCIe 4.0 x4", "PCIe 5.0 x2"]),
+            (16, vec!["PCIe 3.0 x16", "PCIe 4.0 x8", "PCIe 5.0 x4"]),
+            (32, vec!["PCIe 4.0 x16", "PCIe 5.0 x8"]),
+            (64, vec!["PCIe 5.0 x16"]),
+        ]
+            .iter()
+            .cloned()
+            .collect();
+
+        let closest_match = pcie_speeds
+            .iter()
+            .min_by(|a, b| {
+                (a.0 - rounded_avg_throughput).abs().cmp(&(b.0 - rounded_avg_throughput).abs())
+            })
+            .unwrap();
+
+        (*closest_match.0, closest_match.1.clone())
+    }
+}
+
+/// Time of day in UTC, e.g. "14:03:12".
+fn clock_time(time: SystemTime) -> String {
+    let seconds = time
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_secs());
+    format!("{:02}:{:02}:{:02}", (seconds / 3600) % 24, (seconds / 60) % 60, seconds % 60)
+}
+
+/// "once" or e.g. "3 times".
+fn times_label(count: usize) -> String {
+    match count {
+        1 => "once".to_string(),
+        count => format!("{} times", count),
+    }
+}
+
+/// `items` joined as "a, b and c", `None` if there are none.
+fn list(items: &[String]) -> Option<String> {
+    let (last, earlier) = items.split_last()?;
+    Some(
+        if earlier.is_empty() {
+            last.clone()
+        } else {
+            format!("{} and {}", earlier.join(", "), last)
+        }
+    )
+}
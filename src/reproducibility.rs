@@ -0,0 +1,171 @@
+//! What a result depends on beyond the columns of `StoredResult`: the rest of its
+//! configuration, the data it sent, where its host memory came from and the power settings
+//! of the machine. Recorded with every export, so that `--reproduce` can rebuild the exact
+//! run from the result file alone and say which of the conditions have changed since.
+//!
+//! The conditions are read from sysfs and procfs, so on other systems only the configuration
+//! is recorded.
+
+use crate::memory::{ self, Memory };
+use crate::patterns;
+use crate::MeasureConfig;
+use serde::{ Deserialize, Serialize };
+use std::fs;
+
+/// Configuration and conditions of one run, see the module documentation.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Reproducibility {
+    /// `SizeUnits::key` of the sizes the run was configured in.
+    pub units: String,
+    pub warm_up_ms: u64,
+    /// Iterations run before the measured ones, which results from before it lack.
+    #[serde(default)]
+    pub discard: usize,
+    pub delay_ms: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_temp: Option<f64>,
+    /// Share of the time spent transferring in gentle mode.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gentle: Option<f64>,
+    /// Seed of the pseudo-random data and sizes, see `patterns::PATTERN_SEED`.
+    pub pattern_seed: u32,
+    /// Where the host side of the transfers came from, see `allocator`.
+    pub allocator: String,
+    /// Alignment of the host memory in bytes, where it was allocated by gputhroughput.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub host_alignment: Option<usize>,
+    pub conditions: Conditions,
+}
+
+impl Reproducibility {
+    /// The block for a run of `config`, with the conditions as they are now.
+    pub fn new(config: &MeasureConfig) -> Reproducibility {
+        Reproducibility {
+            units: config.units.key().to_string(),
+            warm_up_ms: config.warm_up.as_millis() as u64,
+            discard: config.discard,
+            delay_ms: config.pacing.delay.as_millis() as u64,
+            max_temp: config.pacing.max_temperature,
+            gentle: config.pacing.duty,
+            pattern_seed: patterns::PATTERN_SEED,
+            allocator: allocator(config.memory).to_string(),
+            host_alignment: match config.memory {
+                Memory::HostPtr => Some(memory::PAGE_SIZE),
+                Memory::Buffer => Some(std::mem::align_of::<f32>()),
+                _ => None,
+            },
+            conditions: Conditions::detect(),
+        }
+    }
+}
+
+/// "heap" for the process allocator, "page-aligned" for `--memory host-ptr` and "driver" for
+/// kinds the OpenCL driver allocates itself.
+fn allocator(memory: Memory) -> &'static str {
+    match memory {
+        Memory::Buffer => "heap",
+        Memory::HostPtr => "page-aligned",
+        Memory::Unified | Memory::Vram | Memory::Usm(_) => "driver",
+    }
+}
+
+/// Settings of the machine that move throughput without being part of the configuration.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Conditions {
+    /// CPUs the process was allowed to run on, as a list such as "0-7,16-23".
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub affinity: Option<String>,
+    /// cpufreq governor of CPU 0, e.g. "performance" or "powersave".
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cpu_governor: Option<String>,
+    /// PCIe Active State Power Management policy, e.g. "default" or "powersupersave".
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub aspm_policy: Option<String>,
+    /// ACPI platform profile, e.g. "balanced" or "performance".
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub platform_profile: Option<String>,
+    /// Whether the machine ran from its battery.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub on_battery: Option<bool>,
+}
+
+impl Conditions {
+    pub fn detect() -> Conditions {
+        Conditions {
+            affinity: affinity(),
+            cpu_governor: read("/sys/devices/system/cpu/cpu0/cpufreq/scaling_governor"),
+            aspm_policy: aspm_policy(),
+            platform_profile: read("/sys/firmware/acpi/platform_profile"),
+            on_battery: on_battery(),
+        }
+    }
+
+    /// One line for each condition that differs between `self`, recorded with a result, and
+    /// `now`, e.g. "CPU governor: performance then, powersave now".
+    pub fn differences(&self, now: &Conditions) -> Vec<String> {
+        self.changes(now)
+            .into_iter()
+            .map(|(name, then, now)| format!("{}: {} then, {} now", name, then, now))
+            .collect()
+    }
+
+    /// Name and both values of each condition that differs between `self` and `other`.
+    /// Conditions unknown on either side are not compared.
+    pub fn changes(&self, other: &Conditions) -> Vec<(&'static str, String, String)> {
+        let mut changes = Vec::new();
+        let mut compare = |name: &'static str, this: Option<String>, other: Option<String>| {
+            if let (Some(this), Some(other)) = (this, other) {
+                if this != other {
+                    changes.push((name, this, other));
+                }
+            }
+        };
+        compare("CPU affinity", self.affinity.clone(), other.affinity.clone());
+        compare("CPU governor", self.cpu_governor.clone(), other.cpu_governor.clone());
+        compare("PCIe ASPM policy", self.aspm_policy.clone(), other.aspm_policy.clone());
+        compare("Platform profile", self.platform_profile.clone(), other.platform_profile.clone());
+        let power = |battery: Option<bool>| {
+            battery.map(|battery| (if battery { "battery" } else { "mains" }).to_string())
+        };
+        compare("Power source", power(self.on_battery), power(other.on_battery));
+        changes
+    }
+}
+
+fn read(path: &str) -> Option<String> {
+    let text = fs::read_to_string(path).ok()?;
+    Some(text.trim().to_string()).filter(|text| !text.is_empty())
+}
+
+fn affinity() -> Option<String> {
+    let status = fs::read_to_string("/proc/self/status").ok()?;
+    status
+        .lines()
+        .find_map(|line| line.strip_prefix("Cpus_allowed_list:"))
+        .map(|list| list.trim().to_string())
+}
+
+/// The policy in use, which the file lists among the others in brackets, e.g.
+/// "default [performance] powersave powersupersave".
+fn aspm_policy() -> Option<String> {
+    let policies = read("/sys/module/pcie_aspm/parameters/policy")?;
+    let (_, rest) = policies.split_once('[')?;
+    Some(rest.split_once(']')?.0.to_string())
+}
+
+/// Whether any battery reports discharging, `None` on machines without one.
+fn on_battery() -> Option<bool> {
+    let supplies = fs::read_dir("/sys/class/power_supply").ok()?;
+    let statuses: Vec<String> = supplies
+        .flatten()
+        .filter(|supply| {
+            let kind = fs::read_to_string(supply.path().join("type"));
+            kind.is_ok_and(|kind| kind.trim() == "Battery")
+        })
+        .filter_map(|supply| fs::read_to_string(supply.path().join("status")).ok())
+        .collect();
+    if statuses.is_empty() {
+        return None;
+    }
+    Some(statuses.iter().any(|status| status.trim() == "Discharging"))
+}
@@ -0,0 +1,153 @@
+//! Progress of a "Measure All Devices" run, saved to `gputhroughput/resume.toml` under the
+//! user's config directory after every device. When the app or the machine goes down
+//! mid-run the file is left behind, and the next session offers to measure the remaining
+//! devices with the same configuration.
+
+use crate::config;
+use gputhroughput::memory::Memory;
+use gputhroughput::{ HostBuffer, Pacing, RunLength, SizeUnits, Verification };
+use std::io;
+use std::path::PathBuf;
+use std::time::Duration;
+use toml_edit::{ value, ArrayOfTables, Document, Item, Table };
+
+/// What a multi-device run still has to do and what it already measured.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Checkpoint {
+    /// Transfer size in `units`.
+    pub data_size: usize,
+    pub units: SizeUnits,
+    pub run_length: RunLength,
+    pub host_buffer: HostBuffer,
+    pub memory: Memory,
+    pub verification: Verification,
+    pub pacing: Pacing,
+    pub warm_up: Duration,
+    pub discard: usize,
+    /// Index and OpenCL name of each device not yet measured, so that a device which
+    /// disappeared or moved is skipped rather than confused with another.
+    pub pending: Vec<(usize, String)>,
+    /// Label and mean H2D and D2H throughput of each device measured so far.
+    pub completed: Vec<(String, f64, f64)>,
+}
+
+impl Checkpoint {
+    /// The checkpoint an earlier session left behind, if it is readable.
+    pub fn load() -> Option<Checkpoint> {
+        let document: Document = std::fs::read_to_string(checkpoint_path()?).ok()?.parse().ok()?;
+        let millis = |key: &str| {
+            Some(Duration::from_millis(document.get(key)?.as_integer()?.try_into().ok()?))
+        };
+        let tables = |key: &str| {
+            document
+                .get(key)
+                .and_then(Item::as_array_of_tables)
+                .into_iter()
+                .flat_map(ArrayOfTables::iter)
+        };
+        Some(Checkpoint {
+            data_size: document.get("size_mb")?.as_integer()?.try_into().ok()?,
+            // Sizes were in MiB before they had a choice of units
+            units: document
+                .get("units")
+                .and_then(Item::as_str)
+                .map_or(Some(SizeUnits::Binary), |units| units.parse().ok())?,
+            run_length: document.get("run_length")?.as_str()?.parse().ok()?,
+            host_buffer: document.get("host_buffer")?.as_str()?.parse().ok()?,
+            memory: document.get("memory")?.as_str()?.parse().ok()?,
+            verification: document.get("verify")?.as_str()?.parse().ok()?,
+            pacing: Pacing {
+                delay: millis("delay_ms")?,
+                max_temperature: document.get("max_temp").and_then(Item::as_float),
+                duty: document.get("gentle_duty").and_then(Item::as_float),
+            },
+            warm_up: millis("warm_up_ms")?,
+            // Left out by checkpoints from before iterations could be discarded
+            discard: document
+                .get("discard")
+                .map_or(Some(0), |discard| discard.as_integer()?.try_into().ok())?,
+            pending: tables("pending")
+                .map(|table| {
+                    Some((
+                        table.get("index")?.as_integer()?.try_into().ok()?,
+                        table.get("name")?.as_str()?.to_string(),
+                    ))
+                })
+                .collect::<Option<_>>()?,
+            completed: tables("completed")
+                .map(|table| {
+                    Some((
+                        table.get("label")?.as_str()?.to_string(),
+                        table.get("h2d")?.as_float()?,
+                        table.get("d2h")?.as_float()?,
+                    ))
+                })
+                .collect::<Option<_>>()?,
+        })
+    }
+
+    /// Overwrites any earlier checkpoint with this one.
+    pub fn save(&self) -> io::Result<()> {
+        let path = checkpoint_path().ok_or(io::ErrorKind::NotFound)?;
+        let mut document = Document::new();
+        document["size_mb"] = value(self.data_size as i64);
+        document["units"] = value(self.units.key());
+        document["run_length"] = value(self.run_length.spec());
+        document["host_buffer"] = value(match self.host_buffer {
+            HostBuffer::Reuse => "reuse",
+            HostBuffer::Fresh => "fresh",
+        });
+        document["memory"] = value(self.memory.key());
+        document["verify"] = value(match self.verification {
+            Verification::ReadBack => "readback",
+            Verification::Checksum => "checksum",
+        });
+        document["delay_ms"] = value(self.pacing.delay.as_millis() as i64);
+        if let Some(limit) = self.pacing.max_temperature {
+            document["max_temp"] = value(limit);
+        }
+        if let Some(duty) = self.pacing.duty {
+            document["gentle_duty"] = value(duty);
+        }
+        document["warm_up_ms"] = value(self.warm_up.as_millis() as i64);
+        document["discard"] = value(self.discard as i64);
+
+        let mut pending = ArrayOfTables::new();
+        for (index, name) in &self.pending {
+            let mut table = Table::new();
+            table["index"] = value(*index as i64);
+            table["name"] = value(name);
+            pending.push(table);
+        }
+        document["pending"] = Item::ArrayOfTables(pending);
+        let mut completed = ArrayOfTables::new();
+        for (label, h2d, d2h) in &self.completed {
+            let mut table = Table::new();
+            table["label"] = value(label);
+            table["h2d"] = value(*h2d);
+            table["d2h"] = value(*d2h);
+            completed.push(table);
+        }
+        document["completed"] = Item::ArrayOfTables(completed);
+
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        // Written aside and renamed, so that a crash mid-write leaves the previous checkpoint
+        let partial = path.with_extension("toml.partial");
+        std::fs::write(&partial, document.to_string())?;
+        std::fs::rename(partial, path)
+    }
+
+    /// Removes the checkpoint once the run finished or the user discarded it.
+    pub fn clear() -> io::Result<()> {
+        match checkpoint_path().map(std::fs::remove_file) {
+            Some(Err(e)) if e.kind() != io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        }
+    }
+}
+
+fn checkpoint_path() -> Option<PathBuf> {
+    Some(config::config_dir()?.join("resume.toml"))
+}
@@ -0,0 +1,124 @@
+//! Host to device and straight back, timed end to end, as a readback-modify workflow such as a
+//! GPU video filter sees it: a frame is only done once it is back in host memory. The upload
+//! and the download are queued together so that nothing waits on the host in between, and the
+//! round trip is compared with the two transfers timed one at a time.
+
+use crate::device::default_queue;
+use crate::error::BenchError;
+use crate::registry::DeviceRegistry;
+use crate::{ ramp, MeasureConfig };
+use opencl3::device::Device;
+use opencl3::memory::{ Buffer, CL_MEM_READ_WRITE };
+use opencl3::types::{ CL_BLOCKING, CL_NON_BLOCKING };
+use std::ptr;
+use std::time::{ Duration, Instant };
+
+/// Round trips, and transfers each way, of which the median is reported with fixed run
+/// lengths of fewer iterations.
+pub const MIN_SAMPLES: usize = 5;
+
+/// Round-trip and one-way times, see `measure_round_trip`.
+#[derive(Clone, Debug)]
+pub struct RoundTripResult {
+    /// Bytes sent each way.
+    pub bytes: u64,
+    /// Median time from queuing the upload to the download landing in host memory.
+    pub round_trip: Duration,
+    /// Median times of the upload and the download on their own.
+    pub h2d: Duration,
+    pub d2h: Duration,
+}
+
+impl RoundTripResult {
+    /// GB/s of data that goes up and comes back, each byte counted once.
+    pub fn throughput(&self) -> f64 {
+        (self.bytes as f64) / self.round_trip.as_secs_f64() / 1e9
+    }
+
+    /// Time the round trip takes beyond the two transfers one at a time, negative where the
+    /// driver overlaps them.
+    pub fn overhead(&self) -> f64 {
+        self.round_trip.as_secs_f64() - (self.h2d + self.d2h).as_secs_f64()
+    }
+
+    /// The effective bandwidth, then how the round trip compares with its two halves.
+    pub fn summary(&self) -> Vec<String> {
+        let millis = |time: Duration| time.as_secs_f64() * 1e3;
+        vec![
+            format!(
+                "{} up and back in {:.2} ms: {:.2} GB/s effective",
+                ramp::size_label(self.bytes),
+                millis(self.round_trip),
+                self.throughput()
+            ),
+            format!(
+                "One at a time: {:.2} ms up + {:.2} ms down; the round trip {} {:.2} ms",
+                millis(self.h2d),
+                millis(self.d2h),
+                if self.overhead() < 0.0 { "saves" } else { "adds" },
+                self.overhead().abs() * 1e3
+            )
+        ]
+    }
+}
+
+/// Uploads the configured transfer and downloads it again into a second host buffer, queued
+/// back to back on one in-order queue, and waits for the download; then times each direction
+/// alone. Fixed run lengths give the number of samples, at least `MIN_SAMPLES`. The data that
+/// comes back is checked against what went up.
+pub fn measure_round_trip(
+    config: &MeasureConfig,
+    device: &Device
+) -> Result<RoundTripResult, BenchError> {
+    let bytes = config.transfer_bytes() as usize;
+    let context = DeviceRegistry::global().context(device)?;
+    let queue = default_queue(&context, 0)?;
+    let mut buffer = unsafe {
+        Buffer::<u8>::create(&context, CL_MEM_READ_WRITE, bytes, ptr::null_mut())?
+    };
+    let source: Vec<u8> = (0..bytes).map(|index| index as u8).collect();
+    let mut returned = vec![0u8; bytes];
+
+    let samples = config.length.fixed_iterations().max(MIN_SAMPLES);
+    let median = |mut times: Vec<Duration>| {
+        times.sort();
+        times[times.len() / 2]
+    };
+    let mut round_trips = Vec::with_capacity(samples);
+    for _ in 0..samples {
+        let start = Instant::now();
+        unsafe {
+            queue.enqueue_write_buffer(&mut buffer, CL_NON_BLOCKING, 0, &source, &[])?;
+            queue.enqueue_read_buffer(&buffer, CL_BLOCKING, 0, &mut returned, &[])?;
+        }
+        round_trips.push(start.elapsed());
+    }
+    if let Some(index) = (0..bytes).find(|&index| returned[index] != source[index]) {
+        return Err(BenchError::Verification {
+            index,
+            expected: source[index] as f32,
+            actual: returned[index] as f32,
+        });
+    }
+
+    let mut h2d = Vec::with_capacity(samples);
+    let mut d2h = Vec::with_capacity(samples);
+    for _ in 0..samples {
+        let start = Instant::now();
+        unsafe {
+            queue.enqueue_write_buffer(&mut buffer, CL_BLOCKING, 0, &source, &[])?;
+        }
+        h2d.push(start.elapsed());
+        let start = Instant::now();
+        unsafe {
+            queue.enqueue_read_buffer(&buffer, CL_BLOCKING, 0, &mut returned, &[])?;
+        }
+        d2h.push(start.elapsed());
+    }
+    Ok(RoundTripResult {
+        bytes: bytes as u64,
+        round_trip: median(round_trips),
+        h2d: median(h2d),
+        d2h: median(d2h),
+    })
+}
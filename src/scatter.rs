@@ -0,0 +1,136 @@
+//! Many small uploads submitted as one batch against a single upload of the same total size,
+//! as engines upload their uniform and constant buffers every frame. Every transfer pays a
+//! fixed cost in the driver, so the difference between the two, spread over the small
+//! transfers, is what each of them costs beyond its bytes.
+
+use crate::device::default_queue;
+use crate::error::BenchError;
+use crate::registry::DeviceRegistry;
+use crate::MeasureConfig;
+use opencl3::device::Device;
+use opencl3::memory::{ Buffer, CL_MEM_READ_ONLY };
+use opencl3::types::CL_NON_BLOCKING;
+use std::ptr;
+use std::time::{ Duration, Instant };
+
+/// Most small buffers `measure_scatter` is asked to split the transfer into.
+pub const MAX_BUFFERS: usize = 65536;
+
+/// Batched small uploads against one large upload, see `measure_scatter`.
+#[derive(Clone, Copy, Debug)]
+pub struct ScatterResult {
+    pub buffers: usize,
+    /// Bytes of each small buffer.
+    pub buffer_bytes: usize,
+    /// Mean time to upload every small buffer, and the one large buffer holding as much.
+    pub batched: Duration,
+    pub single: Duration,
+}
+
+impl ScatterResult {
+    pub fn total_bytes(&self) -> usize {
+        self.buffers * self.buffer_bytes
+    }
+
+    /// GB/s of the batch of small buffers.
+    pub fn batched_throughput(&self) -> f64 {
+        (self.total_bytes() as f64) / self.batched.as_secs_f64() / 1e9
+    }
+
+    /// GB/s of the single large buffer.
+    pub fn single_throughput(&self) -> f64 {
+        (self.total_bytes() as f64) / self.single.as_secs_f64() / 1e9
+    }
+
+    /// What each small transfer costs beyond moving its bytes: the extra time of the batch
+    /// spread over every transfer but the one the large upload also makes.
+    pub fn overhead_per_transfer(&self) -> Duration {
+        let extra = self.batched.saturating_sub(self.single);
+        extra / (self.buffers.saturating_sub(1).max(1) as u32)
+    }
+
+    /// The two throughputs, then the overhead.
+    pub fn summary(&self) -> [String; 2] {
+        let overhead = self.overhead_per_transfer();
+        [
+            format!(
+                "{} x {} bytes: {:.2} GB/s, one {} byte buffer: {:.2} GB/s ({:.0}% of it)",
+                self.buffers,
+                self.buffer_bytes,
+                self.batched_throughput(),
+                self.total_bytes(),
+                self.single_throughput(),
+                (self.batched_throughput() / self.single_throughput()) * 100.0
+            ),
+            format!(
+                "Overhead per transfer: {:.2} µs, {:.3} ms for every batch of {}",
+                overhead.as_secs_f64() * 1e6,
+                (overhead * (self.buffers as u32)).as_secs_f64() * 1e3,
+                self.buffers
+            ),
+        ]
+    }
+}
+
+/// Splits the configured transfer into `buffers` equal buffers and uploads them as one batch,
+/// enqueued without blocking and waited on once, alternating every iteration with a single
+/// upload of the same bytes into one buffer.
+///
+/// Data is not verified in this mode; it only looks at per-transfer cost.
+pub fn measure_scatter(
+    config: &MeasureConfig,
+    device: &Device,
+    buffers: usize
+) -> Result<ScatterResult, BenchError> {
+    let buffers = buffers.clamp(1, MAX_BUFFERS);
+    let buffer_bytes = (config.transfer_bytes() as usize) / buffers;
+    if buffer_bytes == 0 {
+        let reason = format!(
+            "{} bytes cannot be split into {} buffers",
+            config.transfer_bytes(),
+            buffers
+        );
+        return Err(BenchError::Unsupported(reason));
+    }
+    let context = DeviceRegistry::global().context(device)?;
+    let queue = default_queue(&context, 0)?;
+    let mut small = (0..buffers)
+        .map(|_| unsafe {
+            Buffer::<u8>::create(&context, CL_MEM_READ_ONLY, buffer_bytes, ptr::null_mut())
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let total = buffers * buffer_bytes;
+    let mut large = unsafe {
+        Buffer::<u8>::create(&context, CL_MEM_READ_ONLY, total, ptr::null_mut())?
+    };
+    let host: Vec<u8> = (0..total).map(|index| index as u8).collect();
+
+    let iterations = config.length.fixed_iterations().max(1);
+    let mut batched = Duration::ZERO;
+    let mut single = Duration::ZERO;
+    for _ in 0..iterations {
+        let start = Instant::now();
+        for (buffer, chunk) in small.iter_mut().zip(host.chunks_exact(buffer_bytes)) {
+            // The host data outlives the finish below, as a non-blocking write requires
+            unsafe {
+                queue.enqueue_write_buffer(buffer, CL_NON_BLOCKING, 0, chunk, &[])?;
+            }
+        }
+        queue.finish()?;
+        batched += start.elapsed();
+
+        let start = Instant::now();
+        unsafe {
+            queue.enqueue_write_buffer(&mut large, CL_NON_BLOCKING, 0, &host, &[])?;
+        }
+        queue.finish()?;
+        single += start.elapsed();
+    }
+
+    Ok(ScatterResult {
+        buffers,
+        buffer_bytes,
+        batched: batched / (iterations as u32),
+        single: single / (iterations as u32),
+    })
+}
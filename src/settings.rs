@@ -0,0 +1,131 @@
+//! GUI preferences kept between sessions in `gputhroughput/settings.toml` under the user's
+//! config directory. Missing or unreadable settings fall back to the defaults.
+
+use crate::config::config_dir;
+use crate::locale::NumberFormat;
+use crate::plot::Palette;
+use gputhroughput::{ HostBuffer, RunLength, SizeUnits };
+use eframe::egui::Color32;
+use std::collections::HashMap;
+use std::io;
+use std::path::PathBuf;
+use toml_edit::{ value, Document, Item, Table };
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Settings {
+    pub palette: Palette,
+    pub number_format: NumberFormat,
+    /// Last configuration measured on each device, keyed by the OpenCL device name.
+    pub devices: HashMap<String, DeviceDefaults>,
+}
+
+/// What is restored when a device is selected again.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DeviceDefaults {
+    /// Transfer size in `units`.
+    pub data_size: usize,
+    pub units: SizeUnits,
+    pub run_length: RunLength,
+    pub host_buffer: HostBuffer,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            palette: Palette::Classic,
+            number_format: NumberFormat::System,
+            devices: HashMap::new(),
+        }
+    }
+}
+
+impl Settings {
+    pub fn load() -> Settings {
+        let document = read_document().unwrap_or_default();
+        let color = |key: &str| {
+            document
+                .get(key)
+                .and_then(|item| item.as_str())
+                .and_then(|hex| Color32::from_hex(hex).ok())
+        };
+        let palette = match document.get("palette").and_then(|item| item.as_str()) {
+            Some("color-blind-safe") => Palette::ColorBlindSafe,
+            Some("custom") => {
+                let (h2d, d2h) = Palette::Classic.colors();
+                Palette::Custom {
+                    h2d: color("h2d_color").unwrap_or(h2d),
+                    d2h: color("d2h_color").unwrap_or(d2h),
+                }
+            }
+            _ => Palette::Classic,
+        };
+        let number_format = document
+            .get("number_format")
+            .and_then(|item| item.as_str())
+            .and_then(NumberFormat::from_key)
+            .unwrap_or(NumberFormat::System);
+        let devices = document
+            .get("devices")
+            .and_then(|item| item.as_table())
+            .map(|table| {
+                table
+                    .iter()
+                    .filter_map(|(name, item)| Some((name.to_string(), read_defaults(item)?)))
+                    .collect()
+            })
+            .unwrap_or_default();
+        Settings { palette, number_format, devices }
+    }
+
+    /// Writes the settings back, keeping any other keys and comments already in the file.
+    pub fn save(&self) -> io::Result<()> {
+        let path = settings_path().ok_or(io::ErrorKind::NotFound)?;
+        let mut document = read_document().unwrap_or_default();
+        let (h2d, d2h) = self.palette.colors();
+        document["palette"] = value(self.palette.key());
+        document["h2d_color"] = value(h2d.to_hex());
+        document["d2h_color"] = value(d2h.to_hex());
+        document["number_format"] = value(self.number_format.key());
+
+        let mut devices = Table::new();
+        devices.set_implicit(true);
+        for (name, defaults) in &self.devices {
+            let mut table = Table::new();
+            table["size_mb"] = value(defaults.data_size as i64);
+            table["units"] = value(defaults.units.key());
+            table["run_length"] = value(defaults.run_length.spec());
+            table["host_buffer"] = value(match defaults.host_buffer {
+                HostBuffer::Reuse => "reuse",
+                HostBuffer::Fresh => "fresh",
+            });
+            devices.insert(name, Item::Table(table));
+        }
+        document["devices"] = Item::Table(devices);
+
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(path, document.to_string())
+    }
+}
+
+fn read_defaults(item: &Item) -> Option<DeviceDefaults> {
+    Some(DeviceDefaults {
+        data_size: item.get("size_mb")?.as_integer()?.try_into().ok()?,
+        // Sizes were in MiB before they had a choice of units
+        units: item
+            .get("units")
+            .and_then(Item::as_str)
+            .map_or(Some(SizeUnits::Binary), |units| units.parse().ok())?,
+        run_length: item.get("run_length")?.as_str()?.parse().ok()?,
+        host_buffer: item.get("host_buffer")?.as_str()?.parse().ok()?,
+    })
+}
+
+fn read_document() -> Option<Document> {
+    std::fs::read_to_string(settings_path()?).ok()?.parse().ok()
+}
+
+fn settings_path() -> Option<PathBuf> {
+    Some(config_dir()?.join("settings.toml"))
+}
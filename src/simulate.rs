@@ -0,0 +1,103 @@
+//! Failures injected on purpose, so that the error display, retry and recovery paths can be
+//! exercised without broken hardware. Set with the hidden `--simulate-failure <KIND>` flag
+//! or from the GUI's debug window (Ctrl+Shift+D).
+
+use crate::error::BenchError;
+use opencl3::error_codes::{
+    ClError,
+    CL_DEVICE_NOT_AVAILABLE,
+    CL_INVALID_VALUE,
+    CL_MEM_OBJECT_ALLOCATION_FAILURE,
+};
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Mutex;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Failure {
+    NoDevice,
+    Allocation,
+    Verification,
+    /// The device resets on every attempt, so the retry fails too.
+    DeviceReset,
+    /// The device resets once and the retry succeeds.
+    TransientReset,
+    Unsupported,
+    OpenCl,
+}
+
+impl Failure {
+    pub const ALL: [Failure; 7] = [
+        Failure::NoDevice,
+        Failure::Allocation,
+        Failure::Verification,
+        Failure::DeviceReset,
+        Failure::TransientReset,
+        Failure::Unsupported,
+        Failure::OpenCl,
+    ];
+
+    fn error(&self) -> BenchError {
+        match self {
+            Failure::NoDevice => BenchError::NoDevice("simulated".to_string()),
+            Failure::Allocation =>
+                BenchError::Allocation(ClError(CL_MEM_OBJECT_ALLOCATION_FAILURE)),
+            Failure::Verification =>
+                BenchError::Verification { index: 0, expected: 1.0, actual: 0.0 },
+            Failure::DeviceReset | Failure::TransientReset =>
+                BenchError::DeviceReset(ClError(CL_DEVICE_NOT_AVAILABLE)),
+            Failure::Unsupported => BenchError::Unsupported("simulated".to_string()),
+            Failure::OpenCl => BenchError::OpenCl(ClError(CL_INVALID_VALUE)),
+        }
+    }
+}
+
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Failure::NoDevice => write!(f, "no-device"),
+            Failure::Allocation => write!(f, "allocation"),
+            Failure::Verification => write!(f, "verification"),
+            Failure::DeviceReset => write!(f, "reset"),
+            Failure::TransientReset => write!(f, "reset-once"),
+            Failure::Unsupported => write!(f, "unsupported"),
+            Failure::OpenCl => write!(f, "opencl"),
+        }
+    }
+}
+
+impl FromStr for Failure {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Failure::ALL
+            .into_iter()
+            .find(|failure| failure.to_string() == s)
+            .ok_or_else(|| format!("unknown failure '{}'", s))
+    }
+}
+
+static FAILURE: Mutex<Option<Failure>> = Mutex::new(None);
+
+/// Makes every following measurement attempt fail with `failure`, or none with `None`.
+pub fn set(failure: Option<Failure>) {
+    *FAILURE.lock().unwrap() = failure;
+}
+
+/// The failure still to come; a `TransientReset` is cleared once it has happened.
+pub fn current() -> Option<Failure> {
+    *FAILURE.lock().unwrap()
+}
+
+/// Called at the start of every measurement attempt.
+pub(crate) fn check() -> Result<(), BenchError> {
+    let mut failure = FAILURE.lock().unwrap();
+    match *failure {
+        Some(Failure::TransientReset) => {
+            *failure = None;
+            Err(Failure::TransientReset.error())
+        }
+        Some(kind) => Err(kind.error()),
+        None => Ok(()),
+    }
+}
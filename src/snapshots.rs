@@ -0,0 +1,89 @@
+//! Capability snapshots of every device seen, kept in `gputhroughput/capabilities.toml` next
+//! to the settings so that a session can tell what the last driver update changed.
+
+use crate::config;
+use gputhroughput::capabilities::Snapshot;
+use gputhroughput::MyDevice;
+use std::io;
+use std::path::PathBuf;
+use toml_edit::{ value, Array, Document, Item, Table };
+
+/// A device whose driver version differs from the last session's.
+pub struct DriverUpdate {
+    pub device: String,
+    /// Everything that changed with it, the driver version first.
+    pub changes: Vec<String>,
+}
+
+/// Compares `devices` against the snapshots saved by the previous session, then saves theirs
+/// in place of the old ones. Devices not present keep their old snapshot.
+pub fn update(devices: &[MyDevice]) -> Vec<DriverUpdate> {
+    let mut document = read_document().unwrap_or_default();
+    if document.get("devices").and_then(Item::as_table).is_none() {
+        let mut table = Table::new();
+        table.set_implicit(true);
+        document["devices"] = Item::Table(table);
+    }
+
+    let mut updates = Vec::new();
+    for device in devices {
+        let key = device.settings_key();
+        let current = Snapshot::of(device);
+        let previous = document["devices"].get(&key).and_then(read_snapshot);
+        if let Some(previous) = previous.filter(|p| p.driver_version != current.driver_version) {
+            updates.push(DriverUpdate {
+                device: device.name().to_string(),
+                changes: current.changes_since(&previous),
+            });
+        }
+        document["devices"][&key] = Item::Table(write_snapshot(&current));
+    }
+
+    if let Err(e) = save(&document) {
+        eprintln!("Warning: failed to save the capability snapshot: {}", e);
+    }
+    updates
+}
+
+fn read_snapshot(item: &Item) -> Option<Snapshot> {
+    Some(Snapshot {
+        driver_version: item.get("driver_version")?.as_str()?.to_string(),
+        opencl_version: item.get("opencl_version")?.as_str()?.to_string(),
+        max_alloc: item.get("max_alloc")?.as_integer()?.try_into().ok()?,
+        global_memory: item.get("global_memory")?.as_integer()?.try_into().ok()?,
+        compute_units: item.get("compute_units")?.as_integer()?.try_into().ok()?,
+        extensions: item
+            .get("extensions")?
+            .as_array()?
+            .iter()
+            .filter_map(|extension| extension.as_str().map(str::to_string))
+            .collect(),
+    })
+}
+
+fn write_snapshot(snapshot: &Snapshot) -> Table {
+    let mut table = Table::new();
+    table["driver_version"] = value(&snapshot.driver_version);
+    table["opencl_version"] = value(&snapshot.opencl_version);
+    table["max_alloc"] = value(snapshot.max_alloc as i64);
+    table["global_memory"] = value(snapshot.global_memory as i64);
+    table["compute_units"] = value(snapshot.compute_units as i64);
+    table["extensions"] = value(snapshot.extensions.iter().collect::<Array>());
+    table
+}
+
+fn read_document() -> Option<Document> {
+    std::fs::read_to_string(snapshot_path()?).ok()?.parse().ok()
+}
+
+fn save(document: &Document) -> io::Result<()> {
+    let path = snapshot_path().ok_or(io::ErrorKind::NotFound)?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    std::fs::write(path, document.to_string())
+}
+
+fn snapshot_path() -> Option<PathBuf> {
+    Some(config::config_dir()?.join("capabilities.toml"))
+}
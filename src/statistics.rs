@@ -0,0 +1,115 @@
+//! Spread of a run's per-iteration throughput. A single blocking transfer is at the mercy of
+//! the scheduler and the driver's batching, so runs of several iterations report how far the
+//! iterations ranged beside the mean the results lead with.
+
+use crate::SizeUnits;
+use serde::{ Deserialize, Serialize };
+
+/// Statistics of one direction's per-iteration throughput, in GB/s.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Statistics {
+    pub iterations: usize,
+    /// Of the iterations, unweighted, unlike the run's throughput, which is all the bytes
+    /// over all the time; the two differ when iterations vary in length.
+    pub mean: f64,
+    pub median: f64,
+    pub min: f64,
+    pub max: f64,
+    /// Sample standard deviation.
+    pub std_dev: f64,
+}
+
+impl Statistics {
+    /// `None` for fewer than two finite samples, which have no spread.
+    pub fn of(samples: &[f64]) -> Option<Statistics> {
+        let mut sorted: Vec<f64> = samples
+            .iter()
+            .copied()
+            .filter(|sample| sample.is_finite())
+            .collect();
+        if sorted.len() < 2 {
+            return None;
+        }
+        sorted.sort_by(f64::total_cmp);
+        let count = sorted.len();
+        let mean = sorted.iter().sum::<f64>() / (count as f64);
+        let median = if count.is_multiple_of(2) {
+            (sorted[count / 2 - 1] + sorted[count / 2]) / 2.0
+        } else {
+            sorted[count / 2]
+        };
+        let variance =
+            sorted
+                .iter()
+                .map(|sample| (sample - mean).powi(2))
+                .sum::<f64>() / ((count - 1) as f64);
+        Some(Statistics {
+            iterations: count,
+            mean,
+            median,
+            min: sorted[0],
+            max: sorted[count - 1],
+            std_dev: variance.sqrt(),
+        })
+    }
+
+    /// The standard deviation as a fraction of the mean.
+    pub fn relative_spread(&self) -> f64 {
+        if self.mean > 0.0 { self.std_dev / self.mean } else { 0.0 }
+    }
+
+    /// e.g. "mean 24.61, median 24.70, min 22.03, max 25.12, std dev 0.71 GB/s (2.9%)", in
+    /// the rate unit of `units`.
+    pub fn summary(&self, units: SizeUnits) -> String {
+        let factor = units.rate_factor();
+        format!(
+            "mean {:.2}, median {:.2}, min {:.2}, max {:.2}, std dev {:.2} {} ({:.1}%)",
+            self.mean * factor,
+            self.median * factor,
+            self.min * factor,
+            self.max * factor,
+            self.std_dev * factor,
+            units.rate_unit(),
+            self.relative_spread() * 100.0
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn needs_two_finite_samples() {
+        assert_eq!(Statistics::of(&[]), None);
+        assert_eq!(Statistics::of(&[24.0]), None);
+        assert_eq!(Statistics::of(&[24.0, f64::NAN, f64::INFINITY]), None);
+    }
+
+    #[test]
+    fn odd_count() {
+        let statistics = Statistics::of(&[3.0, 1.0, 2.0]).unwrap();
+        assert_eq!(statistics.iterations, 3);
+        assert_eq!(statistics.mean, 2.0);
+        assert_eq!(statistics.median, 2.0);
+        assert_eq!((statistics.min, statistics.max), (1.0, 3.0));
+        assert_eq!(statistics.std_dev, 1.0);
+    }
+
+    #[test]
+    fn even_count_and_non_finite_samples() {
+        let statistics = Statistics::of(&[4.0, f64::NAN, 1.0, 2.0, 3.0]).unwrap();
+        assert_eq!(statistics.iterations, 4);
+        assert_eq!(statistics.mean, 2.5);
+        assert_eq!(statistics.median, 2.5);
+        assert!((statistics.std_dev - (5.0f64 / 3.0).sqrt()).abs() < 1e-12);
+        assert!((statistics.relative_spread() - statistics.std_dev / 2.5).abs() < 1e-12);
+    }
+
+    #[test]
+    fn no_spread() {
+        let statistics = Statistics::of(&[24.0, 24.0]).unwrap();
+        assert_eq!(statistics.std_dev, 0.0);
+        assert_eq!(statistics.relative_spread(), 0.0);
+    }
+}
@@ -0,0 +1,570 @@
+//! Keeping finished results between runs, behind `ResultStore` so that the UI and the
+//! command line never depend on where they end up. Results go to a JSON Lines file by
+//! default, or to an SQLite database when built with the `sqlite` feature; a fleet that
+//! collects them centrally, e.g. in Postgres, implements the trait for its own store.
+
+use crate::api::MeasurementRecord;
+use crate::leaks::MemoryTrack;
+use crate::overhead::Overhead;
+use crate::reproducibility::Reproducibility;
+use crate::statistics::Statistics;
+use crate::telemetry::{ LinkStatus, PciAddress };
+use crate::{ HostBuffer, MyDevice, Verification };
+use serde::{ Deserialize, Serialize };
+use std::fmt;
+use std::fs::{ File, OpenOptions };
+use std::io::{ BufRead, BufReader, Write };
+use std::path::{ Path, PathBuf };
+use std::str::FromStr;
+use std::time::{ SystemTime, UNIX_EPOCH };
+
+/// One finished result, as stored.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct StoredResult {
+    /// When the run finished, in seconds since the Unix epoch.
+    pub timestamp: u64,
+    /// The OpenCL device name, the same key the per-device settings use.
+    pub device: String,
+    pub transfer_bytes: u64,
+    pub run_length: String,
+    pub host_buffer: String,
+    pub memory: String,
+    pub verification: String,
+    /// Mean throughput in GB/s.
+    pub h2d: f64,
+    /// Left out when uploads were verified by checksum instead of read back.
+    pub d2h: Option<f64>,
+    /// When the device was reset during the run, in seconds since the Unix epoch.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub resets: Vec<u64>,
+    /// Whether ECC was on, where the driver says.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ecc: Option<bool>,
+    /// Memory use of the harness over a soak run.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub harness_memory: Option<MemoryTrack>,
+    /// Width of the PCIe link, where it could be read. Read when the result is kept, right
+    /// after the run, so a link that retrained to fewer lanes under load reads as it ended.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lanes: Option<u32>,
+    /// `h2d` in other units, see `Figures`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub h2d_figures: Option<Figures>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub d2h_figures: Option<Figures>,
+    /// Spread of the per-iteration throughput, for runs of more than one iteration. Not kept
+    /// by the SQLite store.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub h2d_statistics: Option<Statistics>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub d2h_statistics: Option<Statistics>,
+    /// The rest of the configuration and the conditions of the run, which results saved
+    /// before it was recorded lack.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reproducibility: Option<Reproducibility>,
+    /// The harness's fixed costs on the platform, for runs that measured them.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub overhead: Option<Overhead>,
+}
+
+/// One direction's mean throughput in the units people compare it with, so that a result
+/// read next to a network link's rating needs no converting. Derived from the GB/s figure and
+/// the link width.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Figures {
+    /// Gbit/s of 10^9 bits, as network links are rated.
+    pub gbit_per_s: f64,
+    /// GB/s carried per PCIe lane, where the link width is known.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub per_lane_gbps: Option<f64>,
+    /// Gbit/s carried per PCIe lane.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub per_lane_gbit_per_s: Option<f64>,
+}
+
+impl Figures {
+    /// The figures of `gbps` over a link of `lanes`.
+    pub fn of(gbps: f64, lanes: Option<u32>) -> Figures {
+        let per_lane = lanes.filter(|&lanes| lanes > 0).map(|lanes| gbps / (lanes as f64));
+        Figures {
+            gbit_per_s: gbps * 8.0,
+            per_lane_gbps: per_lane,
+            per_lane_gbit_per_s: per_lane.map(|gbps| gbps * 8.0),
+        }
+    }
+}
+
+impl StoredResult {
+    pub fn new(device: &MyDevice, record: &MeasurementRecord) -> StoredResult {
+        let config = &record.config;
+        let throughput = &record.throughput;
+        let seconds = |time: SystemTime| {
+            time.duration_since(UNIX_EPOCH).map_or(0, |since| since.as_secs())
+        };
+        let lanes = PciAddress::of(device.get_device())
+            .and_then(LinkStatus::current)
+            .map(|link| link.width);
+        let d2h = throughput.has_d2h().then_some(throughput.d2h_throughput);
+        StoredResult {
+            timestamp: seconds(record.finished),
+            device: device.settings_key(),
+            transfer_bytes: config.transfer_bytes(),
+            run_length: config.length.spec(),
+            host_buffer: (
+                match config.host_buffer {
+                    HostBuffer::Reuse => "reuse",
+                    HostBuffer::Fresh => "fresh",
+                }
+            ).to_string(),
+            memory: config.memory.key().to_string(),
+            verification: (
+                match config.verification {
+                    Verification::ReadBack => "readback",
+                    Verification::Checksum => "checksum",
+                }
+            ).to_string(),
+            h2d: throughput.h2d_throughput,
+            d2h,
+            resets: throughput.resets.iter().copied().map(seconds).collect(),
+            ecc: throughput.telemetry.ecc,
+            harness_memory: throughput.harness_memory.clone(),
+            lanes,
+            h2d_figures: Some(Figures::of(throughput.h2d_throughput, lanes)),
+            d2h_figures: d2h.map(|d2h| Figures::of(d2h, lanes)),
+            h2d_statistics: throughput.h2d_statistics(),
+            d2h_statistics: throughput.d2h_statistics(),
+            reproducibility: Some(Reproducibility::new(config)),
+            overhead: record.overhead.clone(),
+        }
+    }
+
+    /// Reads a result file: one result as `--output json` prints it, or a JSON store, whose
+    /// latest result is taken.
+    pub fn load(path: &Path) -> Result<StoredResult, String> {
+        let error = |e: &dyn fmt::Display| format!("{}: {}", path.display(), e);
+        let text = std::fs::read_to_string(path).map_err(|e| error(&e))?;
+        match serde_json::from_str(&text) {
+            Ok(result) => Ok(result),
+            Err(e) => {
+                let last = text.lines().rev().find(|line| !line.trim().is_empty());
+                match last.map(serde_json::from_str) {
+                    Some(Ok(result)) => Ok(result),
+                    _ => Err(error(&e)),
+                }
+            }
+        }
+    }
+
+    /// The columns of `csv_row`, as a CSV header line.
+    pub const CSV_HEADER: &'static str =
+        "timestamp,device,transfer_bytes,run_length,host_buffer,memory,verification,lanes,\
+        h2d_gbps,h2d_gbit_per_s,h2d_per_lane_gbps,h2d_per_lane_gbit_per_s,\
+        d2h_gbps,d2h_gbit_per_s,d2h_per_lane_gbps,d2h_per_lane_gbit_per_s";
+
+    /// The result as one line of comma-separated values under `CSV_HEADER`: the
+    /// configuration, then each direction in GB/s and in `Figures`. Figures that were not
+    /// measured or are unknown are left empty.
+    pub fn csv_row(&self) -> String {
+        let number = |value: Option<f64>| value.map(|value| value.to_string()).unwrap_or_default();
+        let direction = |gbps: Option<f64>, figures: Option<Figures>| {
+            [
+                number(gbps),
+                number(figures.map(|figures| figures.gbit_per_s)),
+                number(figures.and_then(|figures| figures.per_lane_gbps)),
+                number(figures.and_then(|figures| figures.per_lane_gbit_per_s)),
+            ]
+        };
+        let mut cells = vec![
+            self.timestamp.to_string(),
+            csv_text(&self.device),
+            self.transfer_bytes.to_string(),
+            csv_text(&self.run_length),
+            self.host_buffer.clone(),
+            self.memory.clone(),
+            self.verification.clone(),
+            self.lanes.map(|lanes| lanes.to_string()).unwrap_or_default(),
+        ];
+        cells.extend(direction(Some(self.h2d), self.h2d_figures));
+        cells.extend(direction(self.d2h, self.d2h_figures));
+        cells.join(",")
+    }
+
+    /// Mean of both directions, or H2D alone where D2H was not measured.
+    pub fn mean_throughput(&self) -> f64 {
+        match self.d2h {
+            Some(d2h) => (self.h2d + d2h) / 2.0,
+            None => self.h2d,
+        }
+    }
+}
+
+/// `text` as a CSV field, quoted where it holds a comma, a quote or a line break.
+fn csv_text(text: &str) -> String {
+    if text.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_string()
+    }
+}
+
+/// Somewhere results are kept. Errors are messages for the user, since a store that cannot
+/// be written should never fail the measurement itself.
+pub trait ResultStore: Send {
+    fn save(&mut self, result: &StoredResult) -> Result<(), String>;
+
+    /// The latest `limit` results for `device`, oldest first.
+    fn recent(&self, device: &str, limit: usize) -> Result<Vec<StoredResult>, String>;
+}
+
+/// The stores built into gputhroughput.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Backend {
+    Json,
+    Sqlite,
+}
+
+impl Backend {
+    /// The file the store uses when none is configured.
+    pub fn default_file_name(&self) -> &'static str {
+        match self {
+            Backend::Json => "results.jsonl",
+            Backend::Sqlite => "results.db",
+        }
+    }
+}
+
+impl fmt::Display for Backend {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Backend::Json => write!(f, "JSON"),
+            Backend::Sqlite => write!(f, "SQLite"),
+        }
+    }
+}
+
+impl FromStr for Backend {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "json" => Ok(Backend::Json),
+            "sqlite" => Ok(Backend::Sqlite),
+            _ => Err(format!("unknown result store '{}', expected json or sqlite", s)),
+        }
+    }
+}
+
+/// Opens the `backend` store at `path`, creating it if needed.
+pub fn open(backend: Backend, path: &Path) -> Result<Box<dyn ResultStore>, String> {
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        std::fs::create_dir_all(dir).map_err(|e| format!("{}: {}", dir.display(), e))?;
+    }
+    match backend {
+        Backend::Json => Ok(Box::new(JsonStore::new(path.to_path_buf()))),
+        #[cfg(feature = "sqlite")]
+        Backend::Sqlite => Ok(Box::new(SqliteStore::open(path)?)),
+        #[cfg(not(feature = "sqlite"))]
+        Backend::Sqlite => Err("this build has no SQLite support, see the sqlite feature".into()),
+    }
+}
+
+/// One JSON object per line, appended to as results come in.
+pub struct JsonStore {
+    path: PathBuf,
+}
+
+impl JsonStore {
+    pub fn new(path: PathBuf) -> JsonStore {
+        JsonStore { path }
+    }
+}
+
+impl ResultStore for JsonStore {
+    fn save(&mut self, result: &StoredResult) -> Result<(), String> {
+        let error = |e: &dyn fmt::Display| format!("{}: {}", self.path.display(), e);
+        let line = serde_json::to_string(result).map_err(|e| error(&e))?;
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .map_err(|e| error(&e))?;
+        writeln!(file, "{}", line).map_err(|e| error(&e))
+    }
+
+    fn recent(&self, device: &str, limit: usize) -> Result<Vec<StoredResult>, String> {
+        let file = match File::open(&self.path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                return Ok(Vec::new());
+            }
+            Err(e) => {
+                return Err(format!("{}: {}", self.path.display(), e));
+            }
+        };
+        // A line cut short by a crash mid-write is skipped rather than failing the whole file
+        let mut results: Vec<StoredResult> = BufReader::new(file)
+            .lines()
+            .map_while(Result::ok)
+            .filter_map(|line| serde_json::from_str::<StoredResult>(&line).ok())
+            .filter(|result| result.device == device)
+            .collect();
+        results.drain(..results.len().saturating_sub(limit));
+        Ok(results)
+    }
+}
+
+/// A `results` table in an SQLite database, for histories too long to rescan as a file, and
+/// a `resets` table with the device resets of each result and a `harness_memory` table with
+/// the readings of each soak run.
+#[cfg(feature = "sqlite")]
+pub struct SqliteStore {
+    connection: rusqlite::Connection,
+}
+
+#[cfg(feature = "sqlite")]
+impl SqliteStore {
+    pub fn open(path: &Path) -> Result<SqliteStore, String> {
+        let error = |e: rusqlite::Error| format!("{}: {}", path.display(), e);
+        let connection = rusqlite::Connection::open(path).map_err(error)?;
+        connection
+            .execute_batch(
+                "CREATE TABLE IF NOT EXISTS results (
+                    id INTEGER PRIMARY KEY,
+                    timestamp INTEGER NOT NULL,
+                    device TEXT NOT NULL,
+                    transfer_bytes INTEGER NOT NULL,
+                    run_length TEXT NOT NULL,
+                    host_buffer TEXT NOT NULL,
+                    memory TEXT NOT NULL,
+                    verification TEXT NOT NULL,
+                    h2d REAL NOT NULL,
+                    d2h REAL,
+                    ecc INTEGER,
+                    lanes INTEGER,
+                    reproducibility TEXT,
+                    overhead TEXT
+                );
+                CREATE INDEX IF NOT EXISTS results_device ON results (device, id);
+                CREATE TABLE IF NOT EXISTS resets (
+                    result INTEGER NOT NULL REFERENCES results (id),
+                    timestamp INTEGER NOT NULL
+                );
+                CREATE INDEX IF NOT EXISTS resets_result ON resets (result);
+                CREATE TABLE IF NOT EXISTS harness_memory (
+                    result INTEGER NOT NULL REFERENCES results (id),
+                    seconds REAL NOT NULL,
+                    rss INTEGER,
+                    device INTEGER
+                );
+                CREATE INDEX IF NOT EXISTS harness_memory_result ON harness_memory (result);"
+            )
+            .map_err(error)?;
+        // Databases from before ECC, the link width, the reproducibility block and the
+        // harness overhead were recorded lack their columns
+        let columns = [
+            ("ecc", "INTEGER"),
+            ("lanes", "INTEGER"),
+            ("reproducibility", "TEXT"),
+            ("overhead", "TEXT"),
+        ];
+        for (column, kind) in columns {
+            let has_column: bool = connection
+                .query_row(
+                    "SELECT count(*) > 0 FROM pragma_table_info('results') WHERE name = ?1",
+                    [column],
+                    |row| row.get(0)
+                )
+                .map_err(error)?;
+            if !has_column {
+                connection
+                    .execute(&format!("ALTER TABLE results ADD COLUMN {} {}", column, kind), [])
+                    .map_err(error)?;
+            }
+        }
+        Ok(SqliteStore { connection })
+    }
+}
+
+#[cfg(feature = "sqlite")]
+impl ResultStore for SqliteStore {
+    fn save(&mut self, result: &StoredResult) -> Result<(), String> {
+        // Kept as JSON, being only ever read back whole
+        let reproducibility = result.reproducibility
+            .as_ref()
+            .map(serde_json::to_string)
+            .transpose()
+            .map_err(|e| e.to_string())?;
+        let overhead = result.overhead
+            .as_ref()
+            .map(serde_json::to_string)
+            .transpose()
+            .map_err(|e| e.to_string())?;
+        let transaction = self.connection.transaction().map_err(|e| e.to_string())?;
+        transaction
+            .execute(
+                "INSERT INTO results (timestamp, device, transfer_bytes, run_length, host_buffer,
+                    memory, verification, h2d, d2h, ecc, lanes, reproducibility, overhead)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)",
+                rusqlite::params![
+                    result.timestamp as i64,
+                    result.device,
+                    result.transfer_bytes as i64,
+                    result.run_length,
+                    result.host_buffer,
+                    result.memory,
+                    result.verification,
+                    result.h2d,
+                    result.d2h,
+                    result.ecc,
+                    result.lanes,
+                    reproducibility,
+                    overhead
+                ]
+            )
+            .map_err(|e| e.to_string())?;
+        let id = transaction.last_insert_rowid();
+        for &reset in &result.resets {
+            transaction
+                .execute(
+                    "INSERT INTO resets (result, timestamp) VALUES (?1, ?2)",
+                    rusqlite::params![id, reset as i64]
+                )
+                .map_err(|e| e.to_string())?;
+        }
+        let readings = result.harness_memory.iter().flat_map(|track| &track.readings);
+        for reading in readings {
+            transaction
+                .execute(
+                    "INSERT INTO harness_memory (result, seconds, rss, device)
+                     VALUES (?1, ?2, ?3, ?4)",
+                    rusqlite::params![
+                        id,
+                        reading.seconds,
+                        reading.rss.map(|rss| rss as i64),
+                        reading.device.map(|device| device as i64)
+                    ]
+                )
+                .map_err(|e| e.to_string())?;
+        }
+        transaction.commit().map_err(|e| e.to_string())
+    }
+
+    fn recent(&self, device: &str, limit: usize) -> Result<Vec<StoredResult>, String> {
+        let mut statement = self.connection
+            .prepare(
+                "SELECT timestamp, device, transfer_bytes, run_length, host_buffer, memory,
+                    verification, h2d, d2h, ecc, id,
+                    (SELECT group_concat(timestamp) FROM resets WHERE result = results.id),
+                    lanes, reproducibility, overhead
+                 FROM results WHERE device = ?1 ORDER BY id DESC LIMIT ?2"
+            )
+            .map_err(|e| e.to_string())?;
+        let mut readings = self.connection
+            .prepare(
+                "SELECT seconds, rss, device FROM harness_memory WHERE result = ?1
+                 ORDER BY seconds"
+            )
+            .map_err(|e| e.to_string())?;
+        let rows = statement
+            .query_map(rusqlite::params![device, limit as i64], |row| {
+                let track = MemoryTrack {
+                    readings: readings
+                        .query_map([row.get::<_, i64>(10)?], |reading| {
+                            Ok(crate::leaks::MemoryReading {
+                                seconds: reading.get(0)?,
+                                rss: reading.get::<_, Option<i64>>(1)?.map(|rss| rss as u64),
+                                device: reading
+                                    .get::<_, Option<i64>>(2)?
+                                    .map(|device| device as u64),
+                            })
+                        })?
+                        .collect::<Result<Vec<_>, _>>()?,
+                };
+                // Not stored, as they follow from the throughput and the link width
+                let h2d: f64 = row.get(7)?;
+                let d2h: Option<f64> = row.get(8)?;
+                let lanes: Option<u32> = row.get(12)?;
+                Ok(StoredResult {
+                    timestamp: row.get::<_, i64>(0)? as u64,
+                    device: row.get(1)?,
+                    transfer_bytes: row.get::<_, i64>(2)? as u64,
+                    run_length: row.get(3)?,
+                    host_buffer: row.get(4)?,
+                    memory: row.get(5)?,
+                    verification: row.get(6)?,
+                    h2d,
+                    d2h,
+                    ecc: row.get(9)?,
+                    resets: row
+                        .get::<_, Option<String>>(11)?
+                        .unwrap_or_default()
+                        .split(',')
+                        .filter_map(|reset| reset.parse().ok())
+                        .collect(),
+                    harness_memory: (!track.readings.is_empty()).then_some(track),
+                    lanes,
+                    h2d_figures: Some(Figures::of(h2d, lanes)),
+                    d2h_figures: d2h.map(|d2h| Figures::of(d2h, lanes)),
+                    h2d_statistics: None,
+                    d2h_statistics: None,
+                    reproducibility: row
+                        .get::<_, Option<String>>(13)?
+                        .and_then(|json| serde_json::from_str(&json).ok()),
+                    overhead: row
+                        .get::<_, Option<String>>(14)?
+                        .and_then(|json| serde_json::from_str(&json).ok()),
+                })
+            })
+            .map_err(|e| e.to_string())?;
+        let mut results = rows.collect::<Result<Vec<_>, _>>().map_err(|e| e.to_string())?;
+        results.reverse();
+        Ok(results)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(device: &str, d2h: Option<f64>, lanes: Option<u32>) -> StoredResult {
+        let mut result: StoredResult = serde_json::from_value(
+            serde_json::json!({
+                "timestamp": 1700000000,
+                "device": device,
+                "transfer_bytes": 1073741824,
+                "run_length": "10",
+                "host_buffer": "reuse",
+                "memory": "device",
+                "verification": "readback",
+                "h2d": 24.0,
+                "d2h": d2h,
+            })
+        ).unwrap();
+        result.lanes = lanes;
+        result.h2d_figures = Some(Figures::of(result.h2d, lanes));
+        result.d2h_figures = d2h.map(|d2h| Figures::of(d2h, lanes));
+        result
+    }
+
+    #[test]
+    fn csv_row_has_every_figure() {
+        let row = result("GPU", Some(20.0), Some(16)).csv_row();
+        assert_eq!(
+            row,
+            "1700000000,GPU,1073741824,10,reuse,device,readback,16,24,192,1.5,12,20,160,1.25,10"
+        );
+        assert_eq!(row.split(',').count(), StoredResult::CSV_HEADER.split(',').count());
+    }
+
+    #[test]
+    fn csv_row_leaves_unknown_figures_empty() {
+        let row = result("GPU", None, None).csv_row();
+        assert_eq!(row, "1700000000,GPU,1073741824,10,reuse,device,readback,,24,192,,,,,,");
+    }
+
+    #[test]
+    fn csv_row_quotes_device_names() {
+        let row = result("GPU \"Pro\", 2", None, None).csv_row();
+        assert!(row.starts_with("1700000000,\"GPU \"\"Pro\"\", 2\",1073741824,"));
+    }
+}
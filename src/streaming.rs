@@ -0,0 +1,147 @@
+//! End-to-end streaming: a file read from disk and uploaded to the device chunk by chunk,
+//! with the next chunk read while the current one uploads, as asset streaming does.
+
+use crate::device::default_queue;
+use crate::error::BenchError;
+use crate::registry::DeviceRegistry;
+use opencl3::device::Device;
+use opencl3::memory::{ Buffer, CL_MEM_READ_ONLY };
+use opencl3::types::CL_BLOCKING;
+use std::fs::File;
+use std::io::{ self, Read };
+use std::path::Path;
+use std::ptr;
+use std::sync::mpsc;
+use std::thread;
+use std::time::{ Duration, Instant };
+
+/// One pass over a file, see `measure_streaming`.
+#[derive(Clone, Copy, Debug)]
+pub struct StreamResult {
+    pub bytes: u64,
+    /// From the first read to the last upload finishing.
+    pub elapsed: Duration,
+    /// Time spent in reads, overlapped with the uploads.
+    pub reading: Duration,
+    pub uploading: Duration,
+}
+
+impl StreamResult {
+    /// The rate at which data got from the file onto the device, in GB/s.
+    pub fn throughput(&self) -> f64 {
+        gbps(self.bytes, self.elapsed)
+    }
+
+    pub fn summary(&self) -> String {
+        format!(
+            "{:.2} GB/s end to end over {:.2} GB (disk {:.2} GB/s, upload {:.2} GB/s)",
+            self.throughput(),
+            (self.bytes as f64) / 1e9,
+            gbps(self.bytes, self.reading),
+            gbps(self.bytes, self.uploading)
+        )
+    }
+}
+
+fn gbps(bytes: u64, time: Duration) -> f64 {
+    (bytes as f64) / time.as_secs_f64() / 1e9
+}
+
+/// Streams the whole file at `path` onto `device` in chunks of `chunk_size` bytes. Files
+/// the page cache already holds come back at memory speed, so drop caches first for cold
+/// disk numbers.
+pub fn measure_streaming(
+    path: &Path,
+    chunk_size: usize,
+    device: &Device
+) -> Result<StreamResult, BenchError> {
+    let mut file = File::open(path)?;
+    let context = DeviceRegistry::global().context(device)?;
+    let queue = default_queue(&context, 0)?;
+    let mut buffer = unsafe {
+        Buffer::<u8>::create(&context, CL_MEM_READ_ONLY, chunk_size, ptr::null_mut())?
+    };
+
+    // Two host chunks take turns: one is read into while the other uploads
+    let (full_sender, full) = mpsc::sync_channel::<io::Result<(Vec<u8>, usize)>>(1);
+    let (empty_sender, empty) = mpsc::channel::<Vec<u8>>();
+    for _ in 0..2 {
+        empty_sender.send(vec![0u8; chunk_size]).unwrap();
+    }
+
+    let started = Instant::now();
+    let reader = thread::spawn(move || {
+        let mut reading = Duration::ZERO;
+        while let Ok(mut chunk) = empty.recv() {
+            let start = Instant::now();
+            let read = read_chunk(&mut file, &mut chunk);
+            reading += start.elapsed();
+            match read {
+                Ok(0) => break,
+                Ok(length) => {
+                    if full_sender.send(Ok((chunk, length))).is_err() {
+                        break;
+                    }
+                }
+                Err(e) => {
+                    full_sender.send(Err(e)).ok();
+                    break;
+                }
+            }
+        }
+        reading
+    });
+
+    let mut bytes = 0;
+    let mut uploading = Duration::ZERO;
+    let mut outcome = Ok(());
+    for message in &full {
+        let (chunk, length) = match message {
+            Ok(chunk) => chunk,
+            Err(e) => {
+                outcome = Err(BenchError::from(e));
+                break;
+            }
+        };
+        let start = Instant::now();
+        let written = unsafe {
+            queue.enqueue_write_buffer(&mut buffer, CL_BLOCKING, 0, &chunk[..length], &[])
+        };
+        if let Err(e) = written {
+            outcome = Err(BenchError::from(e));
+            break;
+        }
+        uploading += start.elapsed();
+        bytes += length as u64;
+        empty_sender.send(chunk).ok();
+    }
+    let elapsed = started.elapsed();
+    // Closing both channels stops the reader wherever it is waiting
+    drop(empty_sender);
+    drop(full);
+    let reading = reader.join().unwrap_or_default();
+    outcome?;
+
+    if bytes == 0 {
+        return Err(BenchError::Unsupported(format!("{} is empty", path.display())));
+    }
+    Ok(StreamResult { bytes, elapsed, reading, uploading })
+}
+
+/// Fills `chunk` from `file`, short only at the end of the file.
+fn read_chunk(file: &mut File, chunk: &mut [u8]) -> io::Result<usize> {
+    let mut length = 0;
+    while length < chunk.len() {
+        match file.read(&mut chunk[length..]) {
+            Ok(0) => break,
+            Ok(read) => {
+                length += read;
+            }
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => {
+                return Err(e);
+            }
+        }
+    }
+    Ok(length)
+}
@@ -0,0 +1,46 @@
+//! Noticing that the system was suspended during a run, as a laptop left on a soak run
+//! overnight may well be. The monotonic clock that times the transfers stops while the system
+//! sleeps but the wall clock keeps going, so the two drifting apart by more than `THRESHOLD`
+//! between two checks means the system slept in between.
+//!
+//! The monotonic clock stops during sleep on Linux and macOS; where it keeps counting, as on
+//! Windows, a suspend is not noticed.
+
+use std::time::{ Duration, Instant, SystemTime };
+
+/// Drift between the clocks that counts as a suspend, well beyond what NTP corrects at once.
+pub const THRESHOLD: Duration = Duration::from_secs(5);
+
+/// A suspend of the system noticed during a run.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Suspend {
+    /// When the run noticed the resume.
+    pub resumed: SystemTime,
+    /// How long the system slept, to the precision of the wall clock.
+    pub slept: Duration,
+    /// Iteration of the first sample taken after the resume, counting any `trimmed` from the
+    /// samples; the iteration the system slept through is dropped.
+    pub iteration: usize,
+}
+
+/// Compares the two clocks from one check to the next.
+pub struct SuspendWatch {
+    wall: SystemTime,
+    monotonic: Instant,
+}
+
+impl SuspendWatch {
+    pub fn start() -> Self {
+        SuspendWatch { wall: SystemTime::now(), monotonic: Instant::now() }
+    }
+
+    /// How long the system slept since the previous check, if it did.
+    pub fn check(&mut self) -> Option<Duration> {
+        let (wall, monotonic) = (SystemTime::now(), Instant::now());
+        let wall_elapsed = wall.duration_since(self.wall).unwrap_or_default();
+        let slept = wall_elapsed.saturating_sub(monotonic - self.monotonic);
+        self.wall = wall;
+        self.monotonic = monotonic;
+        (slept > THRESHOLD).then_some(slept)
+    }
+}
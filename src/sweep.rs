@@ -0,0 +1,128 @@
+//! The full measurement repeated at every power-of-two transfer size from `START_BYTES` to
+//! `END_BYTES`, or to the device's largest allocation where that is smaller. Unlike the size
+//! ramp, which times bare blocking transfers until they are long enough to trust, every size
+//! here is measured as a run of the configured memory kind, host buffer and verification, so
+//! the curve shows where the run's own throughput saturates.
+
+use crate::error::BenchError;
+use crate::ramp;
+use crate::{ MeasureConfig, RunLength, Throughput };
+use opencl3::device::Device;
+use std::time::Duration;
+
+/// Size of the first transfer.
+pub const START_BYTES: u64 = 1024 * 1024;
+/// Size of the last transfer, where the device can allocate it.
+pub const END_BYTES: u64 = 8 * 1024 * 1024 * 1024;
+/// Share of the peak a size has to reach to count as saturated.
+const SATURATION: f64 = 0.9;
+
+/// One size of the sweep, in GB/s.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SweepPoint {
+    pub bytes: u64,
+    pub h2d: f64,
+    /// `None` for upload-only runs, see `Verification::Checksum`.
+    pub d2h: Option<f64>,
+}
+
+/// Throughput at every size, see `measure_sweep`.
+#[derive(Clone, Debug, Default)]
+pub struct SweepResult {
+    /// In order of size.
+    pub points: Vec<SweepPoint>,
+}
+
+impl SweepResult {
+    /// The smallest size reaching `SATURATION` of the sweep's peak in each direction.
+    pub fn saturation(&self) -> (Option<u64>, Option<u64>) {
+        let first_near_peak = |values: Vec<(u64, f64)>| {
+            let peak = values.iter().map(|&(_, value)| value).fold(0.0, f64::max);
+            values
+                .into_iter()
+                .find(|&(_, value)| peak > 0.0 && value >= peak * SATURATION)
+                .map(|(bytes, _)| bytes)
+        };
+        let h2d = first_near_peak(
+            self.points
+                .iter()
+                .map(|point| (point.bytes, point.h2d))
+                .collect()
+        );
+        let d2h = first_near_peak(
+            self.points
+                .iter()
+                .filter_map(|point| point.d2h.map(|d2h| (point.bytes, d2h)))
+                .collect()
+        );
+        (h2d, d2h)
+    }
+
+    /// One line per size, then where each direction saturates.
+    pub fn summary(&self) -> Vec<String> {
+        let mut lines: Vec<String> = self.points
+            .iter()
+            .map(|point| {
+                match point.d2h {
+                    Some(d2h) =>
+                        format!(
+                            "{}: {:.2} GB/s H2D, {:.2} GB/s D2H",
+                            ramp::size_label(point.bytes),
+                            point.h2d,
+                            d2h
+                        ),
+                    None => format!("{}: {:.2} GB/s H2D", ramp::size_label(point.bytes), point.h2d),
+                }
+            })
+            .collect();
+        let (h2d, d2h) = self.saturation();
+        for (direction, bytes) in [("H2D", h2d), ("D2H", d2h)] {
+            if let Some(bytes) = bytes {
+                lines.push(
+                    format!(
+                        "{} reaches {:.0}% of its peak from {}",
+                        direction,
+                        SATURATION * 100.0,
+                        ramp::size_label(bytes)
+                    )
+                );
+            }
+        }
+        lines
+    }
+}
+
+/// Runs `config` at every size of the sweep, reporting each size to `on_point` as it is done.
+/// Open-ended run lengths are measured for a single iteration per size, and the warm-up is
+/// only run before the first size.
+pub fn measure_sweep(
+    config: &MeasureConfig,
+    device: &Device,
+    on_point: &mut dyn FnMut(&SweepPoint)
+) -> Result<SweepResult, BenchError> {
+    let max_alloc = device.max_mem_alloc_size()?;
+    let mut config = MeasureConfig {
+        length: RunLength::Iterations(config.length.fixed_iterations()),
+        ..*config
+    };
+    let mut result = SweepResult::default();
+    let mut bytes = START_BYTES;
+    while bytes <= END_BYTES.min(max_alloc) {
+        let Ok(data_size) = usize::try_from(bytes / (std::mem::size_of::<f32>() as u64)) else {
+            break;
+        };
+        config.data_size = data_size;
+        let mut throughput = Throughput::new();
+        throughput.measure_observed(&config, device, &mut ())?;
+        let point = SweepPoint {
+            bytes,
+            h2d: throughput.h2d_throughput,
+            d2h: throughput.has_d2h().then_some(throughput.d2h_throughput),
+        };
+        on_point(&point);
+        result.points.push(point);
+        config.warm_up = Duration::ZERO;
+        bytes *= 2;
+    }
+    Ok(result)
+}
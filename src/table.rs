@@ -0,0 +1,184 @@
+//! Results as an aligned table for reading in a terminal, `--output table`. Values are scaled
+//! to a readable unit, e.g. 850 MB/s rather than 0.85 GB/s or 120 µs rather than 0.00012 s,
+//! and written in the locale's number format, so the table is for people rather than for
+//! parsing; the default text output keeps fixed units.
+
+use crate::locale::NumberFormat;
+use gputhroughput::precision::Measurement;
+use gputhroughput::SizeUnits;
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Output {
+    /// One line per figure, in fixed units.
+    Text,
+    /// The main figures in an aligned table, see `table`.
+    Table,
+    /// The result as JSON, in the format of the JSON result store, for scripts.
+    Json,
+    /// The result as a CSV header and one row, see `StoredResult::csv_row`, for spreadsheets.
+    Csv,
+}
+
+impl Output {
+    /// Whether the output is for other programs, so that nothing but the result is printed.
+    pub fn is_data(self) -> bool {
+        matches!(self, Output::Json | Output::Csv)
+    }
+}
+
+impl FromStr for Output {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(Output::Text),
+            "table" => Ok(Output::Table),
+            "json" => Ok(Output::Json),
+            "csv" => Ok(Output::Csv),
+            _ => Err(format!("unknown output format '{}'", s)),
+        }
+    }
+}
+
+impl fmt::Display for Output {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Output::Text => write!(f, "text"),
+            Output::Table => write!(f, "table"),
+            Output::Json => write!(f, "json"),
+            Output::Csv => write!(f, "csv"),
+        }
+    }
+}
+
+/// Rows of cells under a header, the first column aligned left and the rest right.
+pub struct Table {
+    rows: Vec<Vec<String>>,
+}
+
+impl Table {
+    pub fn new(header: &[&str]) -> Table {
+        Table { rows: vec![header.iter().map(|cell| cell.to_string()).collect()] }
+    }
+
+    pub fn row(&mut self, cells: Vec<String>) {
+        self.rows.push(cells);
+    }
+
+    /// The table as lines, with a rule under the header.
+    pub fn render(&self) -> Vec<String> {
+        // Counted in characters, as "µ" and the no-break space of grouped digits are wider
+        // in bytes than on screen
+        let width = |cell: &String| cell.chars().count();
+        let columns = self.rows.iter().map(Vec::len).max().unwrap_or(0);
+        let widths: Vec<usize> = (0..columns)
+            .map(|column| {
+                self.rows
+                    .iter()
+                    .filter_map(|row| row.get(column))
+                    .map(width)
+                    .max()
+                    .unwrap_or(0)
+            })
+            .collect();
+        let line = |row: &Vec<String>| {
+            let cells: Vec<String> = row
+                .iter()
+                .zip(&widths)
+                .enumerate()
+                .map(|(column, (cell, &width))| {
+                    let padding = " ".repeat(width - cell.chars().count());
+                    if column == 0 {
+                        format!("{}{}", cell, padding)
+                    } else {
+                        format!("{}{}", padding, cell)
+                    }
+                })
+                .collect();
+            cells.join("   ").trim_end().to_string()
+        };
+        let mut lines = vec![line(&self.rows[0])];
+        lines.push("-".repeat(widths.iter().sum::<usize>() + 3 * columns.saturating_sub(1)));
+        lines.extend(self.rows[1..].iter().map(line));
+        lines
+    }
+}
+
+/// `measurement` in GB/s, shown in `units` and scaled to MB/s or MiB/s below 1 GB/s or GiB/s.
+pub fn throughput(measurement: Measurement, units: SizeUnits, numbers: NumberFormat) -> String {
+    let rate = measurement.scaled(units.rate_factor());
+    let (scale, unit) = if rate.value.abs() < 1.0 {
+        let per_gigabyte = if units == SizeUnits::Binary { 1024.0 } else { 1000.0 };
+        (per_gigabyte, format!("{}/s", units.size_unit()))
+    } else {
+        (1.0, units.rate_unit().to_string())
+    };
+    format!("{} {}", numbers.number(&rate.scaled(scale).to_string()), unit)
+}
+
+/// `seconds` in s, ms or µs, whichever keeps the value at 1 or above.
+pub fn duration(seconds: f64, numbers: NumberFormat) -> String {
+    let (value, unit) = if seconds >= 1.0 {
+        (seconds, "s")
+    } else if seconds >= 1e-3 {
+        (seconds * 1e3, "ms")
+    } else {
+        (seconds * 1e6, "µs")
+    };
+    format!("{} {}", numbers.float(value, 2), unit)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn first_column_left_and_the_rest_right() {
+        let mut table = Table::new(&["", "Host to device", "Device to host"]);
+        table.row(vec!["Throughput".to_string(), "24.6 GB/s".to_string(), "850 MB/s".to_string()]);
+        table.row(vec!["Duration".to_string(), "43.6 ms".to_string(), "1.26 s".to_string()]);
+        assert_eq!(
+            table.render(),
+            [
+                "             Host to device   Device to host",
+                "--------------------------------------------",
+                "Throughput        24.6 GB/s         850 MB/s",
+                "Duration            43.6 ms           1.26 s",
+            ]
+        );
+    }
+
+    #[test]
+    fn width_counted_in_characters() {
+        let mut table = Table::new(&["", "Latency"]);
+        table.row(vec!["Write".to_string(), "12.5 µs".to_string()]);
+        table.row(vec!["Read".to_string(), "1\u{a0}250 µs".to_string()]);
+        let lines = table.render();
+        assert!(lines.iter().all(|line| line.chars().count() == 16), "{:?}", lines);
+    }
+
+    #[test]
+    fn throughput_scaled_below_a_gigabyte() {
+        let format = |gbps, units| throughput(Measurement::exact(gbps), units, NumberFormat::Point);
+        assert_eq!(format(24.6, SizeUnits::Decimal), "24.6 GB/s");
+        assert_eq!(format(0.85, SizeUnits::Decimal), "850 MB/s");
+        assert_eq!(format(0.5, SizeUnits::Binary), "477 MiB/s");
+    }
+
+    #[test]
+    fn duration_units() {
+        assert_eq!(duration(1.5, NumberFormat::Point), "1.50 s");
+        assert_eq!(duration(0.0436, NumberFormat::Point), "43.60 ms");
+        assert_eq!(duration(0.000012, NumberFormat::Comma), "12,00 µs");
+    }
+
+    #[test]
+    fn output_names() {
+        for output in [Output::Text, Output::Table, Output::Json, Output::Csv] {
+            assert_eq!(output.to_string().parse::<Output>(), Ok(output));
+        }
+        assert!("xml".parse::<Output>().is_err());
+    }
+}
@@ -0,0 +1,156 @@
+//! Run progress on the window's taskbar button on Windows, through the shell's
+//! `ITaskbarList3`. COM is loaded at runtime like PDH and NVML, so that other platforms build
+//! and start without it.
+
+use libloading::{ Library, Symbol };
+use raw_window_handle::{ HasWindowHandle, RawWindowHandle };
+use std::os::raw::c_void;
+use std::ptr;
+
+const CLSCTX_INPROC_SERVER: u32 = 0x1;
+const TBPF_NOPROGRESS: u32 = 0;
+const TBPF_INDETERMINATE: u32 = 0x1;
+const TBPF_NORMAL: u32 = 0x2;
+/// Steps of the progress value, fine enough that the bar moves smoothly.
+const PROGRESS_STEPS: u64 = 1000;
+
+/// `CLSID_TaskbarList`.
+const CLSID_TASKBAR_LIST: Guid = Guid {
+    data1: 0x56fd_f344,
+    data2: 0xfd6d,
+    data3: 0x11d0,
+    data4: [0x95, 0x8a, 0x00, 0x60, 0x97, 0xc9, 0xa0, 0x90],
+};
+/// `IID_ITaskbarList3`.
+const IID_TASKBAR_LIST3: Guid = Guid {
+    data1: 0xea1a_fb91,
+    data2: 0x9e28,
+    data3: 0x4b86,
+    data4: [0x90, 0xe9, 0x9e, 0x9f, 0x8a, 0x5e, 0xef, 0xaf],
+};
+
+#[repr(C)]
+struct Guid {
+    data1: u32,
+    data2: u16,
+    data3: u16,
+    data4: [u8; 8],
+}
+
+type CreateInstance = unsafe extern "system" fn(
+    *const Guid,
+    *mut c_void,
+    u32,
+    *const Guid,
+    *mut *mut c_void
+) -> i32;
+type Release = unsafe extern "system" fn(*mut c_void) -> u32;
+type HrInit = unsafe extern "system" fn(*mut c_void) -> i32;
+type SetProgressValue = unsafe extern "system" fn(*mut c_void, isize, u64, u64) -> i32;
+type SetProgressState = unsafe extern "system" fn(*mut c_void, isize, u32) -> i32;
+
+/// Slots of the `ITaskbarList3` methods used, after `IUnknown`'s three, `ITaskbarList`'s five
+/// and `ITaskbarList2`'s one.
+const RELEASE: usize = 2;
+const HR_INIT: usize = 3;
+const SET_PROGRESS_VALUE: usize = 9;
+const SET_PROGRESS_STATE: usize = 10;
+
+/// What the taskbar button shows.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Progress {
+    None,
+    /// Running for an unknown length, e.g. a time-limited or continuous run.
+    Indeterminate,
+    /// Done from 0 to 1.
+    Fraction(f64),
+}
+
+/// The taskbar button of one window.
+pub struct Taskbar {
+    // Kept loaded for as long as the interface is used
+    _ole: Library,
+    list: *mut c_void,
+    window: isize,
+    shown: Progress,
+}
+
+impl Taskbar {
+    /// The taskbar button of `window`, or `None` off Windows and where the shell has none.
+    /// COM must already be initialized on the calling thread, as the window's event loop does.
+    pub fn open(window: &impl HasWindowHandle) -> Option<Taskbar> {
+        if !cfg!(windows) {
+            return None;
+        }
+        let RawWindowHandle::Win32(handle) = window.window_handle().ok()?.as_raw() else {
+            return None;
+        };
+        let ole = unsafe { Library::new("ole32.dll") }.ok()?;
+        let list = {
+            let create: Symbol<CreateInstance> = unsafe { ole.get(b"CoCreateInstance\0").ok()? };
+            let mut list = ptr::null_mut();
+            let result = unsafe {
+                create(
+                    &CLSID_TASKBAR_LIST,
+                    ptr::null_mut(),
+                    CLSCTX_INPROC_SERVER,
+                    &IID_TASKBAR_LIST3,
+                    &mut list
+                )
+            };
+            (result >= 0 && !list.is_null()).then_some(list)?
+        };
+        // Released on drop, should initialization fail
+        let taskbar = Taskbar {
+            _ole: ole,
+            list,
+            window: handle.hwnd.get(),
+            shown: Progress::None,
+        };
+        let init: HrInit = unsafe { taskbar.method(HR_INIT) };
+        (unsafe { init(taskbar.list) } >= 0).then_some(taskbar)
+    }
+
+    /// Shows `progress`, calling into the shell only when it changed.
+    pub fn show(&mut self, progress: Progress) {
+        if progress == self.shown {
+            return;
+        }
+        let state: SetProgressState = unsafe { self.method(SET_PROGRESS_STATE) };
+        match progress {
+            Progress::None => unsafe {
+                state(self.list, self.window, TBPF_NOPROGRESS);
+            }
+            Progress::Indeterminate => unsafe {
+                state(self.list, self.window, TBPF_INDETERMINATE);
+            }
+            Progress::Fraction(done) => {
+                let value: SetProgressValue = unsafe { self.method(SET_PROGRESS_VALUE) };
+                let completed = (done.clamp(0.0, 1.0) * (PROGRESS_STEPS as f64)) as u64;
+                unsafe {
+                    state(self.list, self.window, TBPF_NORMAL);
+                    value(self.list, self.window, completed, PROGRESS_STEPS);
+                }
+            }
+        }
+        self.shown = progress;
+    }
+
+    /// The method in `slot` of the interface's vtable.
+    ///
+    /// # Safety
+    /// `F` must be the signature of the method in that slot.
+    unsafe fn method<F: Copy>(&self, slot: usize) -> F {
+        let vtable = *(self.list as *const *const usize);
+        std::mem::transmute_copy(&*vtable.add(slot))
+    }
+}
+
+impl Drop for Taskbar {
+    fn drop(&mut self) {
+        let release: Release = unsafe { self.method(RELEASE) };
+        unsafe {
+            release(self.list);
+        }
+    }
+}
@@ -0,0 +1,591 @@
+//! Readings taken from the driver rather than from our own timing, used to cross-check
+//! what the benchmark measured.
+
+use crate::diagnostics::{ self, VendorDiagnostics };
+use crate::nvml::{ Nvml, NvmlDevice };
+use crate::paging::{ PagingReport, Residency, ResidencyCounter };
+use crate::Throughput;
+use opencl3::device::Device;
+use std::fmt;
+use std::path::PathBuf;
+use std::sync::atomic::{ AtomicBool, Ordering };
+use std::sync::Arc;
+use std::thread::{ self, JoinHandle };
+use std::time::{ Duration, Instant };
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PciAddress {
+    pub domain: u32,
+    pub bus: u32,
+    pub device: u32,
+    pub function: u32,
+}
+
+impl PciAddress {
+    /// Looks the address up through whichever vendor query the driver answers.
+    pub fn of(device: &Device) -> Option<PciAddress> {
+        if device.extensions().unwrap_or_default().contains("cl_khr_pci_bus_info") {
+            if let Ok(info) = device.pcibusinfokhr_intel() {
+                return Some(PciAddress {
+                    domain: info.pci_domain,
+                    bus: info.pci_bus,
+                    device: info.pci_device,
+                    function: info.pci_function,
+                });
+            }
+        }
+        if let (Ok(bus), Ok(slot)) = (device.pci_bus_id_nv(), device.pci_slot_id_nv()) {
+            return Some(PciAddress {
+                domain: 0,
+                bus,
+                device: slot >> 3,
+                function: slot & 0x7,
+            });
+        }
+        if let Ok(topology) = device.topology_amd() {
+            return Some(PciAddress {
+                domain: 0,
+                bus: topology.bus as u32,
+                device: topology.device as u32,
+                function: topology.function as u32,
+            });
+        }
+        None
+    }
+}
+
+impl fmt::Display for PciAddress {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:04x}:{:02x}:{:02x}.{:x}", self.domain, self.bus, self.device, self.function)
+    }
+}
+
+/// Negotiated PCIe link as reported by the OS.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LinkStatus {
+    /// Transfer rate per lane in GT/s.
+    pub speed: f64,
+    pub width: u32,
+}
+
+impl LinkStatus {
+    /// Reads the current link from sysfs; only available on Linux.
+    pub fn current(address: PciAddress) -> Option<LinkStatus> {
+        let read = |file: &str| {
+            std::fs::read_to_string(format!("/sys/bus/pci/devices/{}/{}", address, file)).ok()
+        };
+        // e.g. "16.0 GT/s PCIe"
+        let speed = read("current_link_speed")?.split_whitespace().next()?.parse().ok()?;
+        let width = read("current_link_width")?.trim().parse().ok()?;
+        Some(LinkStatus { speed, width })
+    }
+
+    /// The fastest link the GPU and the port above it both support, which the link may run
+    /// below while idle to save power; only available on Linux.
+    pub fn maximum(address: PciAddress) -> Option<LinkStatus> {
+        let device = std::fs::canonicalize(format!("/sys/bus/pci/devices/{}", address)).ok()?;
+        let read = |dir: &std::path::Path| {
+            let read = |file: &str| std::fs::read_to_string(dir.join(file)).ok();
+            // e.g. "16.0 GT/s PCIe"
+            let speed: f64 = read("max_link_speed")?.split_whitespace().next()?.parse().ok()?;
+            let width: u32 = read("max_link_width")?.trim().parse().ok()?;
+            Some(LinkStatus { speed, width })
+        };
+        let gpu = read(&device)?;
+        Some(match device.parent().and_then(read) {
+            Some(port) =>
+                LinkStatus { speed: gpu.speed.min(port.speed), width: gpu.width.min(port.width) },
+            None => gpu,
+        })
+    }
+
+    /// The PCIe generation running at this speed, e.g. 4 at 16 GT/s.
+    pub fn generation(&self) -> Option<u8> {
+        [2.5, 5.0, 8.0, 16.0, 32.0, 64.0]
+            .iter()
+            .position(|&speed| (self.speed - speed).abs() < 0.1)
+            .map(|index| (index as u8) + 1)
+    }
+
+    /// Payload bandwidth in GB/s per direction after line encoding, before protocol overhead.
+    pub fn bandwidth(&self) -> f64 {
+        // 8b/10b up to 5 GT/s (gen 1 and 2), 128b/130b from gen 3 on
+        let encoding = if self.speed <= 5.0 { 8.0 / 10.0 } else { 128.0 / 130.0 };
+        (self.speed * encoding * (self.width as f64)) / 8.0
+    }
+}
+
+/// Whether the firmware set the device up as the primary display adapter, i.e. it most
+/// likely drives the desktop; only known on Linux.
+pub fn drives_display(address: PciAddress) -> bool {
+    std::fs
+        ::read_to_string(format!("/sys/bus/pci/devices/{}/boot_vga", address))
+        .is_ok_and(|flag| flag.trim() == "1")
+}
+
+/// Whether the device's memory is protected by ECC, from NVML where it answers and otherwise
+/// from OpenCL, whose drivers report it as the current setting rather than the capability.
+pub fn ecc_enabled(device: &Device) -> Option<bool> {
+    let nvml = PciAddress::of(device).and_then(|address| {
+        let nvml = Nvml::get()?;
+        nvml.ecc_enabled(nvml.device_by_pci(address)?)
+    });
+    nvml.or_else(|| device.error_correction_support().ok())
+}
+
+/// PCIe errors the device's AER counters recorded since boot, correctable, non-fatal and
+/// fatal together; only available on Linux with AER enabled.
+pub fn link_errors(address: PciAddress) -> Option<u64> {
+    ["aer_dev_correctable", "aer_dev_nonfatal", "aer_dev_fatal"]
+        .iter()
+        .map(|file| {
+            let path = format!("/sys/bus/pci/devices/{}/{}", address, file);
+            // Each file ends with a line like "TOTAL_ERR_COR 0"
+            std::fs
+                ::read_to_string(path)
+                .ok()?
+                .lines()
+                .find(|line| line.starts_with("TOTAL_ERR_"))?
+                .split_whitespace()
+                .nth(1)?
+                .parse::<u64>()
+                .ok()
+        })
+        .sum()
+}
+
+/// Driver-reported PCIe traffic in GB/s, from the GPU's side of the link.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct LinkSample {
+    /// Received by the GPU, i.e. host to device.
+    pub rx: f64,
+    /// Sent by the GPU, i.e. device to host.
+    pub tx: f64,
+}
+
+pub trait LinkCounter: Send {
+    fn sample(&mut self) -> Option<LinkSample>;
+}
+
+struct NvmlCounter {
+    nvml: &'static Nvml,
+    device: NvmlDevice,
+}
+
+impl LinkCounter for NvmlCounter {
+    fn sample(&mut self) -> Option<LinkSample> {
+        let (rx, tx) = self.nvml.pcie_throughput(self.device)?;
+        Some(LinkSample { rx: rx / 1e9, tx: tx / 1e9 })
+    }
+}
+
+/// amdgpu's `pcie_bw` file: packets received, packets sent and max payload size over
+/// the last second. Packets times payload size is an upper bound on the bytes moved.
+struct AmdgpuCounter {
+    path: PathBuf,
+}
+
+impl LinkCounter for AmdgpuCounter {
+    fn sample(&mut self) -> Option<LinkSample> {
+        let contents = std::fs::read_to_string(&self.path).ok()?;
+        let values: Vec<f64> = contents
+            .split_whitespace()
+            .filter_map(|v| v.parse().ok())
+            .collect();
+        let [received, sent, payload] = values[..] else {
+            return None;
+        };
+        Some(LinkSample { rx: (received * payload) / 1e9, tx: (sent * payload) / 1e9 })
+    }
+}
+
+/// Finds a source of link counters for the device at `address`, if the platform has one.
+pub fn link_counter(address: PciAddress) -> Option<Box<dyn LinkCounter>> {
+    if let Some(nvml) = Nvml::get() {
+        if let Some(device) = nvml.device_by_pci(address) {
+            return Some(Box::new(NvmlCounter { nvml, device }));
+        }
+    }
+    let path = PathBuf::from(format!("/sys/bus/pci/devices/{}/pcie_bw", address));
+    if path.exists() {
+        return Some(Box::new(AmdgpuCounter { path }));
+    }
+    None
+}
+
+pub trait PowerMeter: Send {
+    /// Current board power draw in watts.
+    fn watts(&mut self) -> Option<f64>;
+}
+
+struct NvmlPower {
+    nvml: &'static Nvml,
+    device: NvmlDevice,
+}
+
+impl PowerMeter for NvmlPower {
+    fn watts(&mut self) -> Option<f64> {
+        self.nvml.power_usage(self.device)
+    }
+}
+
+/// hwmon power readings as exposed by amdgpu (and other drivers) in microwatts.
+struct HwmonPower {
+    path: PathBuf,
+}
+
+impl PowerMeter for HwmonPower {
+    fn watts(&mut self) -> Option<f64> {
+        let microwatts: f64 = std::fs::read_to_string(&self.path).ok()?.trim().parse().ok()?;
+        Some(microwatts / 1e6)
+    }
+}
+
+/// Finds a power sensor for the device at `address`, if the platform has one.
+pub fn power_meter(address: PciAddress) -> Option<Box<dyn PowerMeter>> {
+    if let Some(nvml) = Nvml::get() {
+        if let Some(device) = nvml.device_by_pci(address) {
+            return Some(Box::new(NvmlPower { nvml, device }));
+        }
+    }
+    let hwmon = std::fs::read_dir(format!("/sys/bus/pci/devices/{}/hwmon", address)).ok()?;
+    hwmon
+        .filter_map(|entry| entry.ok())
+        .flat_map(|entry| ["power1_average", "power1_input"].map(|file| entry.path().join(file)))
+        .find(|path| path.exists())
+        .map(|path| Box::new(HwmonPower { path }) as Box<dyn PowerMeter>)
+}
+
+pub trait Thermometer: Send {
+    /// Current GPU temperature in degrees Celsius.
+    fn celsius(&mut self) -> Option<f64>;
+}
+
+struct NvmlTemperature {
+    nvml: &'static Nvml,
+    device: NvmlDevice,
+}
+
+impl Thermometer for NvmlTemperature {
+    fn celsius(&mut self) -> Option<f64> {
+        self.nvml.temperature(self.device)
+    }
+}
+
+/// hwmon temperature readings in millidegrees Celsius.
+struct HwmonTemperature {
+    path: PathBuf,
+}
+
+impl Thermometer for HwmonTemperature {
+    fn celsius(&mut self) -> Option<f64> {
+        let millidegrees: f64 = std::fs::read_to_string(&self.path).ok()?.trim().parse().ok()?;
+        Some(millidegrees / 1000.0)
+    }
+}
+
+/// Finds a temperature sensor for the device at `address`, if the platform has one.
+pub fn thermometer(address: PciAddress) -> Option<Box<dyn Thermometer>> {
+    if let Some(nvml) = Nvml::get() {
+        if let Some(device) = nvml.device_by_pci(address) {
+            return Some(Box::new(NvmlTemperature { nvml, device }));
+        }
+    }
+    let hwmon = std::fs::read_dir(format!("/sys/bus/pci/devices/{}/hwmon", address)).ok()?;
+    hwmon
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path().join("temp1_input"))
+        .find(|path| path.exists())
+        .map(|path| Box::new(HwmonTemperature { path }) as Box<dyn Thermometer>)
+}
+
+/// Clock and load of the GPU core at one moment, to tell whether it is still in an idle
+/// power state.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GpuState {
+    pub clock_mhz: u32,
+    pub max_clock_mhz: u32,
+    /// Share of recent time the GPU was busy, from 0 to 1, where the driver reports it.
+    pub utilization: Option<f64>,
+}
+
+impl GpuState {
+    /// Whether the core still runs below half its top clock, as power-managed GPUs do until
+    /// work has kept them busy for a while.
+    pub fn is_idle(&self) -> bool {
+        self.clock_mhz * 2 < self.max_clock_mhz
+    }
+}
+
+impl fmt::Display for GpuState {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} of {} MHz", self.clock_mhz, self.max_clock_mhz)?;
+        if let Some(utilization) = self.utilization {
+            write!(f, ", {:.0}% busy", utilization * 100.0)?;
+        }
+        Ok(())
+    }
+}
+
+/// Reads the clock state of the device at `address` from NVML, or from amdgpu's
+/// `pp_dpm_sclk`, which lists the clock levels and marks the current one with `*`.
+pub fn gpu_state(address: PciAddress) -> Option<GpuState> {
+    if let Some(nvml) = Nvml::get() {
+        if let Some(device) = nvml.device_by_pci(address) {
+            let (clock_mhz, max_clock_mhz) = nvml.graphics_clock(device)?;
+            let utilization = nvml.utilization(device);
+            return Some(GpuState { clock_mhz, max_clock_mhz, utilization });
+        }
+    }
+    let sysfs = PathBuf::from(format!("/sys/bus/pci/devices/{}", address));
+    let levels = std::fs::read_to_string(sysfs.join("pp_dpm_sclk")).ok()?;
+    // Lines look like "1: 1800Mhz *"
+    let mhz = |line: &str| -> Option<u32> {
+        line.split_whitespace().nth(1)?.trim_end_matches(|c: char| c.is_alphabetic()).parse().ok()
+    };
+    let clock_mhz = levels
+        .lines()
+        .find(|line| line.trim_end().ends_with('*'))
+        .and_then(mhz)?;
+    let max_clock_mhz = levels.lines().filter_map(mhz).max()?;
+    let utilization = std::fs
+        ::read_to_string(sysfs.join("gpu_busy_percent"))
+        .ok()
+        .and_then(|percent| percent.trim().parse::<f64>().ok())
+        .map(|percent| percent / 100.0);
+    Some(GpuState { clock_mhz, max_clock_mhz, utilization })
+}
+
+/// Calls `read` on a background thread until stopped, collecting every reading.
+struct Poller<T> {
+    stop: Arc<AtomicBool>,
+    handle: JoinHandle<Vec<T>>,
+}
+
+impl<T: Send + 'static> Poller<T> {
+    fn start(mut read: impl FnMut() -> Option<T> + Send + 'static) -> Poller<T> {
+        let stop = Arc::new(AtomicBool::new(false));
+        let handle = thread::spawn({
+            let stop = Arc::clone(&stop);
+            move || {
+                let mut readings = Vec::new();
+                while !stop.load(Ordering::SeqCst) {
+                    let Some(reading) = read() else {
+                        break;
+                    };
+                    readings.push(reading);
+                    thread::sleep(Duration::from_millis(10));
+                }
+                readings
+            }
+        });
+        Poller { stop, handle }
+    }
+
+    fn finish(self) -> Vec<T> {
+        self.stop.store(true, Ordering::SeqCst);
+        self.handle.join().unwrap_or_default()
+    }
+}
+
+/// Host CPU use over a measurement, each as a share from 0 to 1.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CpuUsage {
+    /// Of one core, by the thread that ran the measurement.
+    pub thread: f64,
+    /// Of all cores, system-wide.
+    pub system: f64,
+    pub busiest_core: f64,
+}
+
+impl fmt::Display for CpuUsage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "measuring thread {:.0}% of a core, system {:.0}%, busiest core {:.0}%",
+            self.thread * 100.0,
+            self.system * 100.0,
+            self.busiest_core * 100.0
+        )
+    }
+}
+
+/// Busy and total jiffies of the whole system followed by each core, from `/proc/stat`.
+fn cpu_jiffies() -> Option<Vec<(u64, u64)>> {
+    let stat = std::fs::read_to_string("/proc/stat").ok()?;
+    let jiffies = stat
+        .lines()
+        .filter(|line| line.starts_with("cpu"))
+        .map(|line| {
+            // user nice system idle iowait irq softirq steal, guest time is counted in user
+            let fields: Vec<u64> = line
+                .split_whitespace()
+                .skip(1)
+                .take(8)
+                .filter_map(|field| field.parse().ok())
+                .collect();
+            let total: u64 = fields.iter().sum();
+            let idle = fields.get(3).copied().unwrap_or_default() +
+                       fields.get(4).copied().unwrap_or_default();
+            (total - idle, total)
+        })
+        .collect::<Vec<_>>();
+    (!jiffies.is_empty()).then_some(jiffies)
+}
+
+/// User and system time of one thread in clock ticks, from its `/proc` stat file.
+fn thread_ticks(path: &PathBuf) -> Option<u64> {
+    let stat = std::fs::read_to_string(path).ok()?;
+    // The command name in parentheses may contain spaces, so count fields after it
+    let mut fields = stat[stat.rfind(')')? + 1..].split_whitespace().skip(11);
+    let user: u64 = fields.next()?.parse().ok()?;
+    let system: u64 = fields.next()?.parse().ok()?;
+    Some(user + system)
+}
+
+/// CPU counters at the start of a run, compared against at the end. Linux only; reading
+/// `/proc` twice per run keeps the overhead out of the measurement.
+struct CpuCounters {
+    started: Instant,
+    thread_stat: PathBuf,
+    thread_ticks: u64,
+    jiffies: Vec<(u64, u64)>,
+}
+
+impl CpuCounters {
+    /// Must be called on the thread whose usage is wanted.
+    fn start() -> Option<CpuCounters> {
+        // `/proc/thread-self` would resolve to whichever thread reads it, so keep the target
+        let thread_stat = PathBuf::from("/proc")
+            .join(std::fs::read_link("/proc/thread-self").ok()?)
+            .join("stat");
+        Some(CpuCounters {
+            started: Instant::now(),
+            thread_ticks: thread_ticks(&thread_stat)?,
+            thread_stat,
+            jiffies: cpu_jiffies()?,
+        })
+    }
+
+    fn finish(self) -> Option<CpuUsage> {
+        // /proc reports thread times in USER_HZ, which is 100 on every Linux architecture
+        const TICKS_PER_SECOND: f64 = 100.0;
+        let elapsed = self.started.elapsed().as_secs_f64();
+        let ticks = thread_ticks(&self.thread_stat)?.saturating_sub(self.thread_ticks);
+        let share = |(busy, total): &(u64, u64), (busy_before, total_before): &(u64, u64)| {
+            let busy = busy.saturating_sub(*busy_before) as f64;
+            let total = total.saturating_sub(*total_before) as f64;
+            if total > 0.0 { busy / total } else { 0.0 }
+        };
+        let jiffies = cpu_jiffies()?;
+        let mut shares = jiffies
+            .iter()
+            .zip(&self.jiffies)
+            .map(|(now, before)| share(now, before));
+        Some(CpuUsage {
+            thread: ((ticks as f64) / TICKS_PER_SECOND / elapsed).min(1.0),
+            system: shares.next()?,
+            busiest_core: shares.fold(0.0, f64::max),
+        })
+    }
+}
+
+/// What the driver reported while a measurement ran.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Telemetry {
+    /// Peak link traffic in each direction.
+    pub link: Option<LinkSample>,
+    /// Mean board power in watts.
+    pub power: Option<f64>,
+    pub cpu: Option<CpuUsage>,
+    /// PCIe errors recorded while the measurement ran.
+    pub link_errors: Option<u64>,
+    /// Paging of GPU memory and the throughput dips it coincided with, on Windows.
+    pub paging: Option<PagingReport>,
+    /// Whether ECC was on, where the driver or else the vendor tool says.
+    pub ecc: Option<bool>,
+    /// What `nvidia-smi` or `rocm-smi` reported as the measurement started, see `diagnostics`.
+    pub vendor: Option<VendorDiagnostics>,
+}
+
+/// Which sensors `Monitor` samples during a measurement.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Sensors {
+    pub link: bool,
+    pub power: bool,
+    pub cpu: bool,
+    /// WDDM memory usage counters, see `paging`.
+    pub paging: bool,
+    /// Asking `nvidia-smi` or `rocm-smi`, see `diagnostics`.
+    pub vendor_tools: bool,
+}
+
+impl Default for Sensors {
+    fn default() -> Self {
+        Sensors { link: true, power: true, cpu: true, paging: true, vendor_tools: false }
+    }
+}
+
+/// Samples every enabled sensor available for a device while a measurement runs.
+pub struct Monitor {
+    link: Option<Poller<LinkSample>>,
+    power: Option<Poller<f64>>,
+    cpu: Option<CpuCounters>,
+    paging: Option<Poller<Residency>>,
+    /// Device address and its error count as monitoring started.
+    errors: Option<(PciAddress, u64)>,
+    ecc: Option<bool>,
+    vendor: Option<VendorDiagnostics>,
+}
+
+impl Monitor {
+    /// Starts monitoring `device`, and the CPU use of the calling thread.
+    pub fn start(device: &Device, sensors: Sensors) -> Monitor {
+        let address = PciAddress::of(device);
+        let vendor = address.filter(|_| sensors.vendor_tools).and_then(diagnostics::query);
+        Monitor {
+            link: address
+                .filter(|_| sensors.link)
+                .and_then(link_counter)
+                .map(|mut counter| Poller::start(move || counter.sample())),
+            power: address
+                .filter(|_| sensors.power)
+                .and_then(power_meter)
+                .map(|mut meter| Poller::start(move || meter.watts())),
+            cpu: if sensors.cpu { CpuCounters::start() } else { None },
+            paging: (if sensors.paging { ResidencyCounter::open(device) } else { None }).map(
+                |mut counter| Poller::start(move || counter.sample())
+            ),
+            errors: address
+                .filter(|_| sensors.link)
+                .and_then(|address| Some((address, link_errors(address)?))),
+            ecc: ecc_enabled(device).or(vendor.and_then(|vendor| vendor.ecc)),
+            vendor,
+        }
+    }
+
+    /// Stops monitoring, lining paging up with the iterations of `throughput`.
+    pub fn finish(self, throughput: &Throughput) -> Telemetry {
+        let link = self.link
+            .map(Poller::finish)
+            .filter(|samples| !samples.is_empty())
+            .map(|samples| {
+                samples.iter().fold(LinkSample::default(), |peak, sample| LinkSample {
+                    rx: peak.rx.max(sample.rx),
+                    tx: peak.tx.max(sample.tx),
+                })
+            });
+        let power = self.power
+            .map(Poller::finish)
+            .filter(|watts| !watts.is_empty())
+            .map(|watts| watts.iter().sum::<f64>() / (watts.len() as f64));
+        let cpu = self.cpu.and_then(CpuCounters::finish);
+        let link_errors = self.errors.and_then(|(address, before)| {
+            Some(link_errors(address)?.saturating_sub(before))
+        });
+        let paging = self.paging.and_then(|readings| {
+            PagingReport::correlate(&readings.finish(), throughput)
+        });
+        Telemetry { link, power, cpu, link_errors, paging, ecc: self.ecc, vendor: self.vendor }
+    }
+}
@@ -0,0 +1,97 @@
+//! The best the detected hardware could do, shown next to the measured numbers so that they
+//! read as a share of what is possible rather than as bare GB/s. The PCIe maximum comes
+//! from the link the GPU and its slot both support, read from sysfs; the VRAM maximum from
+//! the memory bus width and clock, read through NVML and so only for NVIDIA GPUs.
+
+use crate::nvml::Nvml;
+use crate::telemetry::{ LinkStatus, PciAddress };
+use crate::Throughput;
+use opencl3::device::Device;
+
+/// Theoretical bandwidth of the device's memory.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Vram {
+    /// Bus width in bits.
+    pub bus_width: u32,
+    /// Maximum memory clock in MHz.
+    pub clock: u32,
+}
+
+impl Vram {
+    /// Gbit/s per pin, two transfers per clock.
+    pub fn data_rate(&self) -> f64 {
+        ((self.clock as f64) * 2.0) / 1000.0
+    }
+
+    /// GB/s across the whole bus.
+    pub fn bandwidth(&self) -> f64 {
+        (self.data_rate() * (self.bus_width as f64)) / 8.0
+    }
+}
+
+/// What could be read of the device's theoretical maximums, see `Maximums::of`.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Maximums {
+    pub link: Option<LinkStatus>,
+    pub vram: Option<Vram>,
+}
+
+impl Maximums {
+    pub fn of(device: &Device) -> Maximums {
+        let Some(address) = PciAddress::of(device) else {
+            return Maximums::default();
+        };
+        let vram = Nvml::get().and_then(|nvml| {
+            let device = nvml.device_by_pci(address)?;
+            Some(Vram {
+                bus_width: nvml.memory_bus_width(device)?,
+                clock: nvml.max_memory_clock(device)?,
+            }).filter(|vram| vram.bus_width > 0 && vram.clock > 0)
+        });
+        Maximums {
+            link: LinkStatus::maximum(address).or_else(|| LinkStatus::current(address)),
+            vram,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.link.is_none() && self.vram.is_none()
+    }
+
+    /// One line per maximum, the PCIe one with the share `throughput` reached of it.
+    pub fn summary(&self, throughput: &Throughput) -> Vec<String> {
+        let mut lines = Vec::new();
+        if let Some(link) = self.link {
+            let generation = link
+                .generation()
+                .map_or_else(|| format!("{} GT/s", link.speed), |gen| format!("{}.0", gen));
+            let share = |measured: f64| (measured / link.bandwidth()) * 100.0;
+            let mut line = format!(
+                "PCIe {} x{}: {:.2} GB/s per direction",
+                generation,
+                link.width,
+                link.bandwidth()
+            );
+            if !throughput.h2d_samples.is_empty() {
+                line.push_str(&format!(", measured {:.0}% H2D", share(throughput.h2d_throughput)));
+                if throughput.has_d2h() {
+                    line.push_str(
+                        &format!(", {:.0}% D2H", share(throughput.d2h_throughput))
+                    );
+                }
+            }
+            lines.push(line);
+        }
+        if let Some(vram) = self.vram {
+            lines.push(
+                format!(
+                    "VRAM: {:.0} GB/s ({}-bit bus at {:.1} Gbit/s per pin)",
+                    vram.bandwidth(),
+                    vram.bus_width,
+                    vram.data_rate()
+                )
+            );
+        }
+        lines
+    }
+}
@@ -0,0 +1,101 @@
+//! Device-side timestamps of every transfer, exported in the Chrome `trace_event` format so
+//! that runs can be inspected in Perfetto or chrome://tracing.
+
+use opencl3::event::Event;
+use std::io;
+use std::path::Path;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Direction {
+    HostToDevice,
+    DeviceToHost,
+}
+
+/// One enqueued transfer, with OpenCL profiling timestamps in nanoseconds.
+#[derive(Clone, Copy, Debug)]
+pub struct TransferEvent {
+    pub direction: Direction,
+    pub iteration: usize,
+    pub queued: u64,
+    pub submitted: u64,
+    pub start: u64,
+    pub end: u64,
+}
+
+impl TransferEvent {
+    /// Reads the profiling timestamps of a completed `event`. `None` if the queue was created
+    /// without profiling or the driver does not report them.
+    pub fn from_event(event: &Event, direction: Direction, iteration: usize) -> Option<Self> {
+        Some(TransferEvent {
+            direction,
+            iteration,
+            queued: event.profiling_command_queued().ok()?,
+            submitted: event.profiling_command_submit().ok()?,
+            start: event.profiling_command_start().ok()?,
+            end: event.profiling_command_end().ok()?,
+        })
+    }
+}
+
+/// Writes `events` as a Chrome trace: one track per direction for the transfers themselves,
+/// and one for the time each spent queued on the host and submitted to the device before
+/// it started.
+pub fn write_chrome_trace(path: &Path, events: &[TransferEvent]) -> io::Result<()> {
+    let origin = events
+        .iter()
+        .map(|event| event.queued)
+        .min()
+        .unwrap_or_default();
+    // Trace timestamps are in microseconds
+    let micros = |ns: u64| ((ns.saturating_sub(origin)) as f64) / 1000.0;
+
+    let mut entries = Vec::new();
+    for (track, name) in [(1, "Host to Device"), (2, "Device to Host"), (3, "Queue wait")] {
+        entries.push(
+            format!(
+                concat!(
+                    "{{\"name\":\"thread_name\",\"ph\":\"M\",\"pid\":1,\"tid\":{},",
+                    "\"args\":{{\"name\":\"{}\"}}}}"
+                ),
+                track,
+                name
+            )
+        );
+    }
+    for event in events {
+        let (label, track) = match event.direction {
+            Direction::HostToDevice => ("H2D", 1),
+            Direction::DeviceToHost => ("D2H", 2),
+        };
+        let name = format!("{} #{}", label, event.iteration + 1);
+        let (queued, submitted, start) = (
+            micros(event.queued),
+            micros(event.submitted),
+            micros(event.start),
+        );
+        entries.push(span(&name, "transfer", track, start, micros(event.end)));
+        entries.push(span(&format!("{} queued", name), "queue", 3, queued, submitted));
+        entries.push(span(&format!("{} submitted", name), "queue", 3, submitted, start));
+    }
+
+    let json = format!(
+        "{{\"traceEvents\":[\n{}\n],\"displayTimeUnit\":\"ns\"}}\n",
+        entries.join(",\n")
+    );
+    std::fs::write(path, json)
+}
+
+/// A complete ("X") event between `start` and `end` microseconds.
+fn span(name: &str, category: &str, track: u32, start: f64, end: f64) -> String {
+    format!(
+        concat!(
+            "{{\"name\":\"{}\",\"cat\":\"{}\",\"ph\":\"X\",\"pid\":1,\"tid\":{},",
+            "\"ts\":{:.3},\"dur\":{:.3}}}"
+        ),
+        name,
+        category,
+        track,
+        start,
+        end - start
+    )
+}
@@ -0,0 +1,93 @@
+//! GPUs that share DRAM with the CPU instead of sitting behind a PCIe link, NVIDIA's Jetson
+//! boards (Tegra, Xavier, Orin) above all, and integrated GPUs generally. A "transfer" there
+//! is a memcpy within the same memory, so reading the results as link throughput, or
+//! estimating a PCIe generation from them, gives nonsense; `--memory unified` measures the
+//! zero-copy path such boards are meant to be programmed with instead.
+
+use crate::{ MyDevice, Vendor };
+use std::fs;
+
+/// Parts of device names that mark an NVIDIA Tegra SoC, lowercase.
+const TEGRA_NAMES: [&str; 4] = ["tegra", "xavier", "orin", "jetson"];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UnifiedKind {
+    /// An NVIDIA Jetson board, whose GPU and CPU share LPDDR on one SoC.
+    Tegra,
+    /// Any other GPU reporting `CL_DEVICE_HOST_UNIFIED_MEMORY`.
+    Integrated,
+}
+
+/// What `UnifiedMemory::detect` found.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct UnifiedMemory {
+    pub kind: UnifiedKind,
+}
+
+impl UnifiedMemory {
+    /// `None` for GPUs with memory of their own.
+    pub fn detect(device: &MyDevice) -> Option<UnifiedMemory> {
+        let name = device.name().to_lowercase();
+        let tegra =
+            device.vendor() == Vendor::Nvidia &&
+            (TEGRA_NAMES.iter().any(|tegra| name.contains(tegra)) || is_tegra_board());
+        if tegra {
+            // Jetson drivers do not always set the flag, but the memory is shared regardless
+            return Some(UnifiedMemory { kind: UnifiedKind::Tegra });
+        }
+        device
+            .get_device()
+            .host_unified_memory()
+            .unwrap_or(false)
+            .then_some(UnifiedMemory { kind: UnifiedKind::Integrated })
+    }
+
+    /// A note for the results, e.g. "Unified memory (Tegra): GPU and CPU share DRAM, no PCIe
+    /// link".
+    pub fn annotation(&self) -> String {
+        let kind = match self.kind {
+            UnifiedKind::Tegra => "Tegra",
+            UnifiedKind::Integrated => "integrated GPU",
+        };
+        format!("Unified memory ({}): GPU and CPU share DRAM, no PCIe link", kind)
+    }
+
+    /// How to read the results of a run with `memory_key`, the `Memory::key` it used.
+    pub fn interpretation(&self, memory_key: &str) -> Vec<String> {
+        let mut lines = vec![
+            "Both directions copy within system memory, so they are bounded by DRAM bandwidth \
+             shared with the CPU, not by a link; the PCIe link estimate does not apply."
+                .to_string(),
+        ];
+        if memory_key == "unified" {
+            lines.push(
+                "Zero-copy: the device reads and writes the host's allocation in place, which \
+                 is what kernels on this board see."
+                    .to_string()
+            );
+        } else {
+            lines.push(
+                "The copies here are ones a program on this board can skip; --memory unified \
+                 measures the zero-copy path instead."
+                    .to_string()
+            );
+        }
+        if self.kind == UnifiedKind::Tegra {
+            lines.push(
+                "Jetson clocks scale with the power mode; `sudo jetson_clocks` pins them at \
+                 their maximum for repeatable numbers."
+                    .to_string()
+            );
+        }
+        lines
+    }
+}
+
+/// Whether the device tree names the board a Tegra, for drivers whose device name does not.
+fn is_tegra_board() -> bool {
+    fs::read("/proc/device-tree/compatible").is_ok_and(|compatible| {
+        compatible
+            .split(|&byte| byte == 0)
+            .any(|entry| entry.starts_with(b"nvidia,tegra"))
+    })
+}
@@ -0,0 +1,163 @@
+//! Whether the measurement runs inside a virtual machine or on a virtualized GPU, since that
+//! commonly explains throughput below what the hardware should reach: an IOMMU translates
+//! every DMA, a shared GPU is time-sliced between guests, and a mediated vGPU may route
+//! transfers through the host driver. Reporting it up front saves a support thread from
+//! discovering it.
+
+use crate::telemetry::PciAddress;
+use crate::MyDevice;
+use std::fmt;
+use std::path::Path;
+
+/// How the GPU reaches the code measuring it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GpuAccess {
+    /// No hypervisor was detected.
+    BareMetal,
+    /// A whole GPU handed to a virtual machine.
+    Passthrough,
+    /// An SR-IOV virtual function, a hardware slice of a GPU shared with others.
+    VirtualFunction,
+    /// A mediated vGPU profile such as NVIDIA GRID, time-sliced by the host driver.
+    MediatedVgpu,
+}
+
+impl fmt::Display for GpuAccess {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GpuAccess::BareMetal => write!(f, "bare metal"),
+            GpuAccess::Passthrough => write!(f, "GPU passthrough"),
+            GpuAccess::VirtualFunction => write!(f, "SR-IOV virtual function"),
+            GpuAccess::MediatedVgpu => write!(f, "mediated vGPU"),
+        }
+    }
+}
+
+/// What `Environment::detect` found.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Environment {
+    /// The hypervisor's name, e.g. "KVM", or "unknown hypervisor" where it does not say.
+    pub hypervisor: Option<String>,
+    pub access: GpuAccess,
+}
+
+impl Environment {
+    pub fn detect(device: &MyDevice) -> Environment {
+        let hypervisor = hypervisor();
+        let address = PciAddress::of(device.get_device());
+        let is_virtual_function = address.is_some_and(|address| {
+            Path::new(&format!("/sys/bus/pci/devices/{}/physfn", address)).exists()
+        });
+        let access = if is_vgpu_profile(device.name()) {
+            GpuAccess::MediatedVgpu
+        } else if is_virtual_function {
+            GpuAccess::VirtualFunction
+        } else if hypervisor.is_some() {
+            GpuAccess::Passthrough
+        } else {
+            GpuAccess::BareMetal
+        };
+        Environment { hypervisor, access }
+    }
+
+    pub fn is_virtualized(&self) -> bool {
+        self.hypervisor.is_some() || self.access != GpuAccess::BareMetal
+    }
+
+    /// A note for the results, `None` on bare metal.
+    pub fn annotation(&self) -> Option<String> {
+        if !self.is_virtualized() {
+            return None;
+        }
+        let setting = match (&self.hypervisor, self.access) {
+            (Some(hypervisor), GpuAccess::BareMetal) =>
+                format!("virtual machine on {}", hypervisor),
+            (Some(hypervisor), access) => format!("{} on {}", access, hypervisor),
+            (None, access) => access.to_string(),
+        };
+        let consequence = match self.access {
+            GpuAccess::VirtualFunction | GpuAccess::MediatedVgpu =>
+                "the GPU is shared with other guests, so throughput is often well below the \
+                 hardware's",
+            _ => "IOMMU translation and interrupt remapping often lower throughput",
+        };
+        Some(format!("Virtualized ({}); {}", setting, consequence))
+    }
+}
+
+/// Device names of NVIDIA vGPU profiles, e.g. "GRID A100-4C" or "NVIDIA A10-8Q", whose suffix
+/// is the framebuffer in GB and the profile class.
+fn is_vgpu_profile(name: &str) -> bool {
+    if name.contains("GRID") {
+        return true;
+    }
+    let Some((_, profile)) = name.rsplit_once('-') else {
+        return false;
+    };
+    let (size, class) = profile.split_at(profile.len().saturating_sub(1));
+    !size.is_empty() &&
+        size.chars().all(|c| c.is_ascii_digit()) &&
+        ["A", "B", "C", "Q"].contains(&class)
+}
+
+/// The hypervisor the CPU reports running under, if any.
+fn hypervisor() -> Option<String> {
+    cpuid_hypervisor().or_else(|| {
+        // Hypervisors that hide from CPUID still tend to name themselves in the firmware tables
+        let vendor = std::fs::read_to_string("/sys/class/dmi/id/sys_vendor").ok()?;
+        let product = std::fs::read_to_string("/sys/class/dmi/id/product_name").unwrap_or_default();
+        let known = [
+            ("QEMU", "QEMU"),
+            ("VMware", "VMware"),
+            ("innotek", "VirtualBox"),
+            ("Xen", "Xen"),
+            ("Parallels", "Parallels"),
+            ("Amazon EC2", "Amazon EC2"),
+            ("Google", "Google Compute Engine"),
+        ];
+        if vendor.contains("Microsoft") && product.contains("Virtual Machine") {
+            return Some("Hyper-V".to_string());
+        }
+        known
+            .iter()
+            .find(|(marker, _)| vendor.contains(marker) || product.contains(marker))
+            .map(|(_, name)| name.to_string())
+    })
+}
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+fn cpuid_hypervisor() -> Option<String> {
+    #[cfg(target_arch = "x86")]
+    use std::arch::x86::__cpuid;
+    #[cfg(target_arch = "x86_64")]
+    use std::arch::x86_64::__cpuid;
+
+    // Safe on newer toolchains, unsafe on older ones
+    #[allow(unused_unsafe)]
+    let (features, vendor) = unsafe { (__cpuid(1), __cpuid(0x4000_0000)) };
+    // Bit 31 of ECX is reserved for hypervisors to announce themselves
+    if features.ecx & (1 << 31) == 0 {
+        return None;
+    }
+    let signature: Vec<u8> = [vendor.ebx, vendor.ecx, vendor.edx]
+        .iter()
+        .flat_map(|register| register.to_le_bytes())
+        .collect();
+    let name = match &signature[..] {
+        b"KVMKVMKVM\0\0\0" => "KVM",
+        b"Microsoft Hv" => "Hyper-V",
+        b"VMwareVMware" => "VMware",
+        b"XenVMMXenVMM" => "Xen",
+        b"VBoxVBoxVBox" => "VirtualBox",
+        b"TCGTCGTCGTCG" => "QEMU",
+        b" lrpepyh  vr" => "Parallels",
+        b"ACRNACRNACRN" => "ACRN",
+        _ => "unknown hypervisor",
+    };
+    Some(name.to_string())
+}
+
+#[cfg(not(any(target_arch = "x86", target_arch = "x86_64")))]
+fn cpuid_hypervisor() -> Option<String> {
+    None
+}
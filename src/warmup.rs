@@ -0,0 +1,63 @@
+//! A busy kernel run before measuring, so that power-managed GPUs leave their idle clocks
+//! before the first timed transfer instead of reporting a low first iteration.
+
+use crate::error::BenchError;
+use opencl3::command_queue::CommandQueue;
+use opencl3::context::Context;
+use opencl3::kernel::Kernel;
+use opencl3::memory::{ Buffer, ClMem, CL_MEM_WRITE_ONLY };
+use opencl3::program::Program;
+use opencl3::types::cl_uint;
+use std::ptr;
+use std::time::{ Duration, Instant };
+
+const SOURCE: &str =
+    "\
+__kernel void spin(__global float *out, uint rounds) {
+    float x = (float)get_global_id(0);
+    for (uint i = 0; i < rounds; i++) {
+        x = x * 0.999999f + 1.0f;
+    }
+    out[get_global_id(0)] = x;
+}
+";
+
+const WORK_ITEMS: usize = 65536;
+
+/// Loop rounds per launch, a few milliseconds of work on most GPUs so the loop below can
+/// stop close to the requested duration.
+const ROUNDS: cl_uint = 4096;
+
+/// Keeps the device busy with back-to-back launches for at least `duration`.
+pub fn warm_up(
+    context: &Context,
+    queue: &CommandQueue,
+    duration: Duration
+) -> Result<(), BenchError> {
+    let program = Program::create_and_build_from_source(context, SOURCE, "").map_err(|log| {
+        BenchError::Unsupported(format!("the warm-up kernel failed to build: {}", log))
+    })?;
+    let kernel = Kernel::create(&program, "spin")?;
+    let out = unsafe {
+        Buffer::<f32>::create(context, CL_MEM_WRITE_ONLY, WORK_ITEMS, ptr::null_mut())?
+    };
+    unsafe {
+        kernel.set_arg(0, &out.get())?;
+        kernel.set_arg(1, &ROUNDS)?;
+    }
+    let start = Instant::now();
+    while start.elapsed() < duration {
+        unsafe {
+            queue.enqueue_nd_range_kernel(
+                kernel.get(),
+                1,
+                ptr::null(),
+                [WORK_ITEMS].as_ptr(),
+                ptr::null(),
+                &[]
+            )?;
+        }
+        queue.finish()?;
+    }
+    Ok(())
+}
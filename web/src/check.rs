@@ -0,0 +1,242 @@
+//! Times uploads with `Queue::write_buffer` and readbacks through a mapped staging buffer,
+//! one after the other, on the device eframe already renders with.
+
+use eframe::egui;
+use eframe::egui_wgpu::RenderState;
+use eframe::wgpu;
+use std::sync::{ Arc, Mutex };
+
+/// Largest transfer offered, to stay well inside browser memory limits.
+const MAX_SIZE_MB: u64 = 256;
+
+/// Set by a wgpu callback with the time the work finished, read back in `update`.
+type Finished = Arc<Mutex<Option<Result<f64, String>>>>;
+
+/// Milliseconds since the page loaded.
+fn now() -> f64 {
+    web_sys
+        ::window()
+        .and_then(|window| window.performance())
+        .map_or(0.0, |performance| performance.now())
+}
+
+enum Stage {
+    Idle,
+    Uploading {
+        started: f64,
+    },
+    Reading {
+        started: f64,
+    },
+}
+
+struct Run {
+    bytes: u64,
+    iterations: usize,
+    upload: wgpu::Buffer,
+    staging: wgpu::Buffer,
+    data: Vec<u8>,
+    stage: Stage,
+    finished: Finished,
+}
+
+pub struct BrowserCheck {
+    render_state: Option<RenderState>,
+    size_mb: u64,
+    iterations: usize,
+    run: Option<Run>,
+    /// Per-iteration throughput in GB/s.
+    upload_samples: Vec<f64>,
+    readback_samples: Vec<f64>,
+    error_message: Option<String>,
+}
+
+impl BrowserCheck {
+    pub fn new(cc: &eframe::CreationContext<'_>) -> Self {
+        BrowserCheck {
+            render_state: cc.wgpu_render_state.clone(),
+            size_mb: 64,
+            iterations: 10,
+            run: None,
+            upload_samples: Vec::new(),
+            readback_samples: Vec::new(),
+            error_message: None,
+        }
+    }
+
+    fn start(&mut self, render_state: &RenderState) {
+        let device = &render_state.device;
+        let limit = device.limits().max_buffer_size;
+        let bytes = (self.size_mb * 1024 * 1024).min(limit);
+        let buffer = |label: &str, usage: wgpu::BufferUsages| {
+            device.create_buffer(
+                &(wgpu::BufferDescriptor {
+                    label: Some(label),
+                    size: bytes,
+                    usage,
+                    mapped_at_creation: false,
+                })
+            )
+        };
+        let upload = buffer("upload", wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::COPY_SRC);
+        let staging = buffer(
+            "staging",
+            wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ
+        );
+
+        self.upload_samples.clear();
+        self.readback_samples.clear();
+        self.error_message = None;
+        let mut run = Run {
+            bytes,
+            iterations: self.iterations,
+            upload,
+            staging,
+            data: (0..bytes).map(|i| i as u8).collect(),
+            stage: Stage::Idle,
+            finished: Arc::new(Mutex::new(None)),
+        };
+        run.upload(render_state);
+        self.run = Some(run);
+    }
+
+    /// Moves the running check on once its last submission has finished.
+    fn advance(&mut self, render_state: &RenderState) {
+        let Some(run) = self.run.as_mut() else {
+            return;
+        };
+        let Some(finished) = run.finished.lock().unwrap().take() else {
+            return;
+        };
+        let finished = match finished {
+            Ok(finished) => finished,
+            Err(e) => {
+                self.error_message = Some(e);
+                self.run = None;
+                return;
+            }
+        };
+        match run.stage {
+            Stage::Uploading { started } => {
+                self.upload_samples.push(gbps(run.bytes, finished - started));
+                run.read_back(render_state);
+            }
+            Stage::Reading { started } => {
+                // Copying out of the mapping is part of getting the data back to the page
+                let copied = run.staging.slice(..).get_mapped_range().to_vec();
+                run.staging.unmap();
+                self.readback_samples.push(gbps(run.bytes, now() - started));
+                if copied != run.data {
+                    self.error_message = Some(
+                        "Data read back differs from what was written".to_string()
+                    );
+                    self.run = None;
+                } else if self.readback_samples.len() < run.iterations {
+                    run.upload(render_state);
+                } else {
+                    self.run = None;
+                }
+            }
+            Stage::Idle => {}
+        }
+    }
+}
+
+/// `bytes` moved in `millis` milliseconds, in GB/s.
+fn gbps(bytes: u64, millis: f64) -> f64 {
+    (bytes as f64) / (millis / 1000.0) / 1e9
+}
+
+impl Run {
+    fn upload(&mut self, render_state: &RenderState) {
+        let started = now();
+        render_state.queue.write_buffer(&self.upload, 0, &self.data);
+        render_state.queue.submit(std::iter::empty());
+        let finished = Arc::clone(&self.finished);
+        render_state.queue.on_submitted_work_done(move || {
+            *finished.lock().unwrap() = Some(Ok(now()));
+        });
+        self.stage = Stage::Uploading { started };
+    }
+
+    fn read_back(&mut self, render_state: &RenderState) {
+        let started = now();
+        let mut encoder = render_state.device.create_command_encoder(&Default::default());
+        encoder.copy_buffer_to_buffer(&self.upload, 0, &self.staging, 0, self.bytes);
+        render_state.queue.submit(Some(encoder.finish()));
+        let finished = Arc::clone(&self.finished);
+        self.staging.slice(..).map_async(wgpu::MapMode::Read, move |result| {
+            *finished.lock().unwrap() = Some(
+                result.map(|()| now()).map_err(|e| format!("Mapping the readback failed: {}", e))
+            );
+        });
+        self.stage = Stage::Reading { started };
+    }
+}
+
+fn mean(samples: &[f64]) -> f64 {
+    samples.iter().sum::<f64>() / (samples.len().max(1) as f64)
+}
+
+impl eframe::App for BrowserCheck {
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        let Some(render_state) = self.render_state.clone() else {
+            egui::CentralPanel::default().show(ctx, |ui| {
+                ui.label("This browser did not provide a WebGPU device.");
+            });
+            return;
+        };
+        self.advance(&render_state);
+        if self.run.is_some() {
+            // wgpu callbacks arrive between frames, so keep polling until the run is over
+            render_state.device.poll(wgpu::Maintain::Poll);
+            ctx.request_repaint();
+        }
+
+        egui::CentralPanel::default().show(ctx, |ui| {
+            ui.heading("GPU Throughput (browser check)");
+            ui.colored_label(
+                ui.visuals().warn_fg_color,
+                "Not PCIe-accurate: the browser copies and schedules every transfer itself. \
+                 Use the desktop app for real measurements."
+            );
+            let info = render_state.adapter.get_info();
+            ui.label(format!("Adapter: {} ({:?})", info.name, info.backend));
+            ui.separator();
+
+            let running = self.run.is_some();
+            ui.add_enabled_ui(!running, |ui| {
+                ui.add(egui::Slider::new(&mut self.size_mb, 1..=MAX_SIZE_MB).text("MB"));
+                ui.add(egui::Slider::new(&mut self.iterations, 1..=50).text("iterations"));
+            });
+            if ui.add_enabled(!running, egui::Button::new("Run check")).clicked() {
+                self.start(&render_state);
+            }
+            if running {
+                ui.spinner();
+            }
+
+            if let Some(error) = &self.error_message {
+                ui.colored_label(ui.visuals().error_fg_color, error);
+            }
+            if !self.upload_samples.is_empty() {
+                ui.label(
+                    format!(
+                        "Upload: {:.2} GB/s over {} iterations",
+                        mean(&self.upload_samples),
+                        self.upload_samples.len()
+                    )
+                );
+            }
+            if !self.readback_samples.is_empty() {
+                ui.label(
+                    format!(
+                        "Readback: {:.2} GB/s over {} iterations",
+                        mean(&self.readback_samples),
+                        self.readback_samples.len()
+                    )
+                );
+            }
+        });
+    }
+}
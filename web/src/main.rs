@@ -0,0 +1,31 @@
+//! A rough upload and readback bandwidth check that runs in a browser over WebGPU, for a
+//! quick look at a machine without installing anything. Browsers add their own copies and
+//! scheduling around every transfer, so the numbers are not PCIe-accurate; the OpenCL
+//! benchmark in the parent directory is the one to trust.
+
+#[cfg(target_arch = "wasm32")]
+mod check;
+
+#[cfg(target_arch = "wasm32")]
+fn main() {
+    // Log to the browser console
+    eframe::WebLogger::init(log::LevelFilter::Info).ok();
+
+    wasm_bindgen_futures::spawn_local(async {
+        let started = eframe::WebRunner
+            ::new()
+            .start(
+                "gputhroughput",
+                eframe::WebOptions::default(),
+                Box::new(|cc| Ok(Box::new(check::BrowserCheck::new(cc))))
+            ).await;
+        if let Err(e) = started {
+            log::error!("Failed to start: {:?}", e);
+        }
+    });
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn main() {
+    eprintln!("This is the browser build; run `trunk serve` in this directory to try it.");
+}